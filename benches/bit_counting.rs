@@ -0,0 +1,41 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use wasmer::*;
+
+/// Sums `op(x)` for every `x` in `0..n`.
+fn bit_counting_loop(op: &str) -> String {
+    format!(
+        r#"(module
+            (func (export "main") (param $n i64) (result i64)
+                (local $x i64) (local $sum i64)
+                (block $done
+                    (loop $loop
+                        (br_if $done (i64.ge_u (local.get $x) (local.get $n)))
+                        (local.set $sum (i64.add (local.get $sum) ({op} (local.get $x))))
+                        (local.set $x (i64.add (local.get $x) (i64.const 1)))
+                        (br $loop)))
+                (local.get $sum)))"#,
+        op = op
+    )
+}
+
+fn bit_counting(c: &mut Criterion) {
+    // Without LZCNT and BMI1, `clz` and `ctz` use `bsr` and `bsf`.
+    let fallback = Target::new(
+        Target::default().triple().clone(),
+        CpuFeature::AVX | CpuFeature::SSE2,
+    );
+    for (name, target) in [("native", Target::default()), ("fallback", fallback)] {
+        let store = Store::new(&Universal::new(Singlepass::new()).target(target).engine());
+        let mut group = c.benchmark_group(name);
+        for op in ["i64.clz", "i64.ctz", "i64.popcnt"] {
+            let module = Module::new(&store, bit_counting_loop(op)).unwrap();
+            let instance = Instance::new(&module, &imports! {}).unwrap();
+            let main: NativeFunc<i64, i64> = instance.get_native_function("main").unwrap();
+            group.bench_function(op, |b| b.iter(|| black_box(main.call(black_box(10_000)))));
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bit_counting);
+criterion_main!(benches);
@@ -0,0 +1,21 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use wasmer_compiler::FunctionBodyRef;
+
+fn function_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("function_hash");
+    for &size in &[1024usize, 10 * 1024, 100 * 1024] {
+        let body = (0..size).map(|i| (i * 7 + 3) as u8).collect::<Vec<u8>>();
+        let body = FunctionBodyRef {
+            body: &body,
+            unwind_info: None,
+        };
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(format!("{}KB", size / 1024), |b| {
+            b.iter(|| black_box(black_box(&body).compute_hash()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, function_hash);
+criterion_main!(benches);
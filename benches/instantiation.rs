@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use wasmer::*;
+use wasmer_types::InstanceConfig;
+
+const INSTANCES: usize = 10_000;
+
+fn many_imports(n: usize) -> String {
+    let imports = (0..n)
+        .map(|idx| format!(r#"(import "env" "f{idx}" (func $f{idx}))"#, idx = idx))
+        .collect::<String>();
+    format!(
+        r#"(module {imports} (memory (export "memory") 1) (func (export "main") call $f0))"#,
+        imports = imports
+    )
+}
+
+#[derive(WasmerEnv, Clone, Default)]
+struct Env {
+    #[wasmer(export)]
+    memory: LazyInit<Memory>,
+}
+
+fn instantiation(c: &mut Criterion) {
+    let store = Store::new(&Universal::new(Singlepass::new()).engine());
+    let module = Module::new(&store, many_imports(32)).unwrap();
+    let mut imports = ImportObject::new();
+    let mut namespace = Exports::new();
+    for idx in 0..32 {
+        namespace.insert(
+            format!("f{}", idx),
+            Function::new_native_with_env(&store, Env::default(), |_: &Env| {}),
+        );
+    }
+    imports.register("env", namespace);
+
+    let mut group = c.benchmark_group("instantiate_10k");
+    group.sample_size(10);
+    group.bench_function("resolve_each_time", |b| {
+        b.iter(|| {
+            for _ in 0..INSTANCES {
+                black_box(Instance::new(&module, &imports).unwrap());
+            }
+        })
+    });
+    group.bench_function("prepared", |b| {
+        b.iter(|| {
+            let prepared = module.prepare(&imports).unwrap();
+            for _ in 0..INSTANCES {
+                black_box(prepared.instantiate(InstanceConfig::default()).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group!(benches, instantiation);
+criterion_main!(benches);
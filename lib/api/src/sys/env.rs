@@ -9,6 +9,14 @@ pub enum HostEnvInitError {
     Export(ExportError),
     /// Incorrect gas metering config
     IncorrectGasMeteringConfig,
+    /// The module updates a gas counter but none was provided
+    MissingGasCounter,
+    /// A maximum function call depth was configured for a module whose code
+    /// doesn't limit the call depth
+    CallDepthNotLimited,
+    /// An interrupt flag was configured for a module whose code doesn't check
+    /// for interrupts
+    InterruptsNotChecked,
 }
 
 impl From<ExportError> for HostEnvInitError {
@@ -12,11 +12,12 @@ use std::cmp::max;
 use std::ffi::c_void;
 use std::fmt;
 use std::sync::Arc;
+use wasmer_types::FunctionIndex;
 use wasmer_vm::{
     raise_user_trap, resume_panic, wasmer_call_trampoline, Export, ExportFunction,
     ExportFunctionMetadata, ImportInitializerFuncPtr, VMCallerCheckedAnyfunc,
     VMDynamicFunctionContext, VMFuncRef, VMFunction, VMFunctionBody, VMFunctionEnvironment,
-    VMFunctionKind, VMTrampoline,
+    VMFunctionKind, VMTrampoline, WeakOrStrongInstanceRef,
 };
 
 /// A WebAssembly `function` instance.
@@ -390,6 +391,28 @@ impl Function {
         &self.store
     }
 
+    /// Returns the index of this function in the module of the instance
+    /// defining it, if it is a WebAssembly function of a live instance.
+    ///
+    /// This works for functions received as a `funcref` too, so that host
+    /// functions can log which function they were given, for example with
+    /// the names of the module's functions. Host functions have no index.
+    pub fn function_index(&self) -> Option<FunctionIndex> {
+        let (_, index) = self.exported.vm_function.defining_instance()?;
+        Some(index)
+    }
+
+    /// Whether `instance` is the instance defining this function.
+    ///
+    /// Functions imported by `instance` are defined by another instance, or
+    /// by the host.
+    pub fn is_defined_by(&self, instance: &crate::Instance) -> bool {
+        match self.exported.vm_function.defining_instance() {
+            Some((vmctx, _)) => std::ptr::eq(vmctx, instance.vmctx_ptr()),
+            None => false,
+        }
+    }
+
     fn call_wasm(
         &self,
         trampoline: VMTrampoline,
@@ -419,6 +442,7 @@ impl Function {
             )));
         }
 
+        let _instance = upgrade_for_call(&self.exported.vm_function)?;
         let mut values_vec = vec![0; max(params.len(), results.len())];
 
         // Store the argument values into `values_vec`.
@@ -437,14 +461,15 @@ impl Function {
         }
 
         // Call the trampoline.
-        if let Err(error) = unsafe {
+        let vm_function = &self.exported.vm_function;
+        if let Err(error) = vm_function.with_call_depth_restored(|| unsafe {
             wasmer_call_trampoline(
-                self.exported.vm_function.vmctx,
+                vm_function.vmctx,
                 trampoline,
-                self.exported.vm_function.address,
+                vm_function.address,
                 values_vec.as_mut_ptr() as *mut u8,
             )
-        } {
+        }) {
             return Err(RuntimeError::from_trap(error));
         }
 
@@ -678,6 +703,35 @@ impl Function {
         unimplemented!("Closures (functions with captured environments) are currently unsupported with native functions. See: https://github.com/wasmerio/wasmer/issues/1840")
     }
 
+    /// Returns whether this function keeps the instance it was exported from alive.
+    ///
+    /// Returns `Some(true)` if it holds a strong reference to the instance,
+    /// `Some(false)` if it holds a weak one and `None` if it does not belong to an
+    /// instance at all, e.g. because it was created by the host.
+    pub fn holds_instance_strongly(&self) -> Option<bool> {
+        self.exported
+            .vm_function
+            .instance_ref
+            .as_ref()
+            .map(WeakOrStrongInstanceRef::is_strong)
+    }
+
+    /// Returns whether the instance this function was exported from is still
+    /// alive, which is always the case if the function holds it strongly.
+    ///
+    /// Use it to check whether a function cached after
+    /// [`Exportable::into_weak_instance_ref`] can still be used. Returns `None`
+    /// if the function does not belong to an instance at all.
+    ///
+    /// [`Exportable::into_weak_instance_ref`]: crate::Exportable::into_weak_instance_ref
+    pub fn instance_is_alive(&self) -> Option<bool> {
+        self.exported
+            .vm_function
+            .instance_ref
+            .as_ref()
+            .map(WeakOrStrongInstanceRef::is_alive)
+    }
+
     /// Get access to the backing VM value for this extern. This function is for
     /// tests it should not be called by users of the Wasmer API.
     ///
@@ -712,10 +766,27 @@ impl<'a> Exportable<'a> for Function {
     }
 }
 
+/// Upgrades the reference `vm_function` holds on its instance, so that the
+/// instance stays alive while the function is being called.
+///
+/// Functions looked up with weak exports may outlive their instance, whose
+/// `vmctx` must not be passed to the function once it is dropped.
+pub(crate) fn upgrade_for_call(
+    vm_function: &VMFunction,
+) -> Result<Option<WeakOrStrongInstanceRef>, RuntimeError> {
+    match &vm_function.instance_ref {
+        Some(instance_ref) => instance_ref.upgrade().map(Some).ok_or_else(|| {
+            RuntimeError::new("the instance this function was exported from was dropped")
+        }),
+        None => Ok(None),
+    }
+}
+
 impl Clone for Function {
     fn clone(&self) -> Self {
         let mut exported = self.exported.clone();
-        exported.vm_function.upgrade_instance_ref().unwrap();
+        // A function whose instance was dropped stays weak, calling it fails.
+        let _ = exported.vm_function.upgrade_instance_ref();
 
         Self {
             store: self.store.clone(),
@@ -7,7 +7,7 @@ use crate::sys::Mutability;
 use crate::sys::RuntimeError;
 use std::fmt;
 use std::sync::Arc;
-use wasmer_vm::{Export, Global as RuntimeGlobal, VMGlobal};
+use wasmer_vm::{Export, Global as RuntimeGlobal, VMGlobal, WeakOrStrongInstanceRef};
 
 /// A WebAssembly `global` instance.
 ///
@@ -206,6 +206,33 @@ impl Global {
         Arc::ptr_eq(&self.vm_global.from, &other.vm_global.from)
     }
 
+    /// Returns whether this global keeps the instance it was exported from alive.
+    ///
+    /// Returns `Some(true)` if it holds a strong reference to the instance,
+    /// `Some(false)` if it holds a weak one and `None` if it does not belong to an
+    /// instance at all, e.g. because it was created by the host.
+    pub fn holds_instance_strongly(&self) -> Option<bool> {
+        self.vm_global
+            .instance_ref
+            .as_ref()
+            .map(WeakOrStrongInstanceRef::is_strong)
+    }
+
+    /// Returns whether the instance this global was exported from is still
+    /// alive, which is always the case if the global holds it strongly.
+    ///
+    /// Use it to check whether a global cached after
+    /// [`Exportable::into_weak_instance_ref`] can still be used. Returns `None`
+    /// if the global does not belong to an instance at all.
+    ///
+    /// [`Exportable::into_weak_instance_ref`]: crate::Exportable::into_weak_instance_ref
+    pub fn instance_is_alive(&self) -> Option<bool> {
+        self.vm_global
+            .instance_ref
+            .as_ref()
+            .map(WeakOrStrongInstanceRef::is_alive)
+    }
+
     /// Get access to the backing VM value for this extern. This function is for
     /// tests it should not be called by users of the Wasmer API.
     ///
@@ -222,7 +249,8 @@ impl Global {
 impl Clone for Global {
     fn clone(&self) -> Self {
         let mut vm_global = self.vm_global.clone();
-        vm_global.upgrade_instance_ref().unwrap();
+        // An extern whose instance was dropped stays weak.
+        let _ = vm_global.upgrade_instance_ref();
 
         Self {
             store: self.store.clone(),
@@ -241,6 +269,15 @@ impl fmt::Debug for Global {
     }
 }
 
+/// Two `Global`s are equal if they refer to the same global, see [`Global::same`].
+impl PartialEq for Global {
+    fn eq(&self, other: &Self) -> bool {
+        self.same(other)
+    }
+}
+
+impl Eq for Global {}
+
 impl<'a> Exportable<'a> for Global {
     fn to_export(&self) -> Export {
         self.vm_global.clone().into()
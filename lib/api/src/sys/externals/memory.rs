@@ -6,7 +6,7 @@ use std::convert::TryInto;
 use std::slice;
 use std::sync::Arc;
 use wasmer_types::{Pages, ValueType};
-use wasmer_vm::{Export, MemoryError, VMMemory};
+use wasmer_vm::{Export, MemoryError, VMMemory, WeakOrStrongInstanceRef};
 
 /// A WebAssembly `memory` instance.
 ///
@@ -132,6 +132,60 @@ impl Memory {
         def.current_length.try_into().unwrap()
     }
 
+    /// Returns the address of the `len` bytes at `offset`, along with `len`.
+    ///
+    /// The address stays valid until the memory is grown, so it should not
+    /// be kept across calls into wasm.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError::AccessViolation`] if the range isn't within the
+    /// current size of the memory.
+    pub fn data_range(&self, offset: u32, len: usize) -> Result<(*mut u8, usize), MemoryError> {
+        let definition = self.vm_memory.from.vmmemory();
+        let def = unsafe { definition.as_ref() };
+        let offset = offset as usize;
+        match offset.checked_add(len) {
+            // SAFETY: `offset` is within the `current_length` bytes at `base`.
+            Some(end) if end <= def.current_length => Ok((unsafe { def.base.add(offset) }, len)),
+            _ => Err(MemoryError::AccessViolation),
+        }
+    }
+
+    /// Copy the contents of the memory at `offset` into `buf`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write(8, b"hello").unwrap();
+    ///
+    /// let mut buf = [0; 5];
+    /// m.read(8, &mut buf).unwrap();
+    /// assert_eq!(&buf, b"hello");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError::AccessViolation`] if the range isn't within the
+    /// current size of the memory.
+    pub fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), MemoryError> {
+        self.vm_memory.from.read(offset, buf)
+    }
+
+    /// Copy `data` into the memory at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError::AccessViolation`] if the range isn't within the
+    /// current size of the memory.
+    pub fn write(&self, offset: u32, data: &[u8]) -> Result<(), MemoryError> {
+        self.vm_memory.from.write(offset, data)
+    }
+
     /// Returns the size (in [`Pages`]) of the `Memory`.
     ///
     /// # Example
@@ -145,7 +199,7 @@ impl Memory {
     /// assert_eq!(m.size(), Pages(1));
     /// ```
     pub fn size(&self) -> Pages {
-        self.vm_memory.from.size()
+        self.vm_memory.from.current_pages()
     }
 
     /// Grow memory by the specified amount of WebAssembly [`Pages`] and return
@@ -255,6 +309,33 @@ impl Memory {
         Arc::ptr_eq(&self.vm_memory.from, &other.vm_memory.from)
     }
 
+    /// Returns whether this memory keeps the instance it was exported from alive.
+    ///
+    /// Returns `Some(true)` if it holds a strong reference to the instance,
+    /// `Some(false)` if it holds a weak one and `None` if it does not belong to an
+    /// instance at all, e.g. because it was created by the host.
+    pub fn holds_instance_strongly(&self) -> Option<bool> {
+        self.vm_memory
+            .instance_ref
+            .as_ref()
+            .map(WeakOrStrongInstanceRef::is_strong)
+    }
+
+    /// Returns whether the instance this memory was exported from is still
+    /// alive, which is always the case if the memory holds it strongly.
+    ///
+    /// Use it to check whether a memory cached after
+    /// [`Exportable::into_weak_instance_ref`] can still be used. Returns `None`
+    /// if the memory does not belong to an instance at all.
+    ///
+    /// [`Exportable::into_weak_instance_ref`]: crate::Exportable::into_weak_instance_ref
+    pub fn instance_is_alive(&self) -> Option<bool> {
+        self.vm_memory
+            .instance_ref
+            .as_ref()
+            .map(WeakOrStrongInstanceRef::is_alive)
+    }
+
     /// Get access to the backing VM value for this extern. This function is for
     /// tests it should not be called by users of the Wasmer API.
     ///
@@ -268,10 +349,48 @@ impl Memory {
     }
 }
 
+macro_rules! impl_primitive_access {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        impl Memory {
+            $(
+                #[doc = concat!("Read the little-endian `", stringify!($ty), "` stored at `offset`.")]
+                ///
+                /// Like [`Memory::read`], fails with [`MemoryError::AccessViolation`]
+                /// if the value isn't within the current size of the memory.
+                pub fn $read(&self, offset: u32) -> Result<$ty, MemoryError> {
+                    let mut bytes = [0; std::mem::size_of::<$ty>()];
+                    self.read(offset, &mut bytes)?;
+                    Ok(<$ty>::from_le_bytes(bytes))
+                }
+
+                #[doc = concat!("Store `value` as a little-endian `", stringify!($ty), "` at `offset`.")]
+                ///
+                /// Like [`Memory::write`], fails with [`MemoryError::AccessViolation`]
+                /// if the value isn't within the current size of the memory.
+                pub fn $write(&self, offset: u32, value: $ty) -> Result<(), MemoryError> {
+                    self.write(offset, &value.to_le_bytes())
+                }
+            )*
+        }
+    };
+}
+
+impl_primitive_access! {
+    u8 => read_u8, write_u8;
+    u16 => read_u16, write_u16;
+    u32 => read_u32, write_u32;
+    u64 => read_u64, write_u64;
+    i32 => read_i32, write_i32;
+    i64 => read_i64, write_i64;
+    f32 => read_f32, write_f32;
+    f64 => read_f64, write_f64;
+}
+
 impl Clone for Memory {
     fn clone(&self) -> Self {
         let mut vm_memory = self.vm_memory.clone();
-        vm_memory.upgrade_instance_ref().unwrap();
+        // An extern whose instance was dropped stays weak.
+        let _ = vm_memory.upgrade_instance_ref();
 
         Self {
             store: self.store.clone(),
@@ -280,6 +399,15 @@ impl Clone for Memory {
     }
 }
 
+/// Two `Memory`s are equal if they refer to the same memory, see [`Memory::same`].
+impl PartialEq for Memory {
+    fn eq(&self, other: &Self) -> bool {
+        self.same(other)
+    }
+}
+
+impl Eq for Memory {}
+
 impl<'a> Exportable<'a> for Memory {
     fn to_export(&self) -> Export {
         self.vm_memory.clone().into()
@@ -5,7 +5,11 @@ use crate::sys::types::{Val, ValFuncRef};
 use crate::sys::RuntimeError;
 use crate::sys::TableType;
 use std::sync::Arc;
-use wasmer_vm::{Export, Table as RuntimeTable, TableElement, VMTable};
+#[cfg(feature = "experimental-reference-types-extern-ref")]
+use wasmer_types::ExternRef;
+use wasmer_vm::{
+    Export, Table as RuntimeTable, TableElement, VMFuncRef, VMTable, WeakOrStrongInstanceRef,
+};
 
 /// A WebAssembly `table` instance.
 ///
@@ -102,6 +106,23 @@ impl Table {
             .ok_or_else(|| RuntimeError::new(format!("failed to grow table by `{}`", delta)))
     }
 
+    /// Sets the `len` elements of the `Table` starting at `start` to `val`,
+    /// like the `table.fill` instruction does.
+    ///
+    /// This can be used to populate a table before it is imported into an
+    /// instance, without a `set` call for every element.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is out of bounds of the table.
+    pub fn fill(&self, start: u32, len: u32, val: Val) -> Result<(), RuntimeError> {
+        let item = val.into_table_reference(&self.store)?;
+        self.vm_table
+            .from
+            .fill(start, len, item)
+            .map_err(RuntimeError::from_trap)
+    }
+
     /// Copies the `len` elements of `src_table` starting at `src_index`
     /// to the destination table `dst_table` at index `dst_index`.
     ///
@@ -132,6 +153,27 @@ impl Table {
         Ok(())
     }
 
+    /// Copies out the elements of a `funcref` table, with `None` standing
+    /// for null references.
+    ///
+    /// Unlike going through [`Table::get`] for every element, no [`Function`]
+    /// is created along the way.
+    ///
+    /// Returns `None` if this is not a `funcref` table.
+    ///
+    /// [`Function`]: crate::Function
+    pub fn funcref_snapshot(&self) -> Option<Vec<Option<VMFuncRef>>> {
+        self.vm_table.from.funcref_snapshot()
+    }
+
+    /// Copies out the elements of an `externref` table.
+    ///
+    /// Returns `None` if this is not an `externref` table.
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    pub fn externref_snapshot(&self) -> Option<Vec<ExternRef>> {
+        self.vm_table.from.externref_snapshot()
+    }
+
     pub(crate) fn from_vm_export(store: &Store, vm_table: VMTable) -> Self {
         Self {
             store: store.clone(),
@@ -144,6 +186,33 @@ impl Table {
         Arc::ptr_eq(&self.vm_table.from, &other.vm_table.from)
     }
 
+    /// Returns whether this table keeps the instance it was exported from alive.
+    ///
+    /// Returns `Some(true)` if it holds a strong reference to the instance,
+    /// `Some(false)` if it holds a weak one and `None` if it does not belong to an
+    /// instance at all, e.g. because it was created by the host.
+    pub fn holds_instance_strongly(&self) -> Option<bool> {
+        self.vm_table
+            .instance_ref
+            .as_ref()
+            .map(WeakOrStrongInstanceRef::is_strong)
+    }
+
+    /// Returns whether the instance this table was exported from is still
+    /// alive, which is always the case if the table holds it strongly.
+    ///
+    /// Use it to check whether a table cached after
+    /// [`Exportable::into_weak_instance_ref`] can still be used. Returns `None`
+    /// if the table does not belong to an instance at all.
+    ///
+    /// [`Exportable::into_weak_instance_ref`]: crate::Exportable::into_weak_instance_ref
+    pub fn instance_is_alive(&self) -> Option<bool> {
+        self.vm_table
+            .instance_ref
+            .as_ref()
+            .map(WeakOrStrongInstanceRef::is_alive)
+    }
+
     /// Get access to the backing VM value for this extern. This function is for
     /// tests it should not be called by users of the Wasmer API.
     ///
@@ -160,7 +229,8 @@ impl Table {
 impl Clone for Table {
     fn clone(&self) -> Self {
         let mut vm_table = self.vm_table.clone();
-        vm_table.upgrade_instance_ref().unwrap();
+        // An extern whose instance was dropped stays weak.
+        let _ = vm_table.upgrade_instance_ref();
 
         Self {
             store: self.store.clone(),
@@ -169,6 +239,15 @@ impl Clone for Table {
     }
 }
 
+/// Two `Table`s are equal if they refer to the same table, see [`Table::same`].
+impl PartialEq for Table {
+    fn eq(&self, other: &Self) -> bool {
+        self.same(other)
+    }
+}
+
+impl Eq for Table {}
+
 impl<'a> Exportable<'a> for Table {
     fn to_export(&self) -> Export {
         self.vm_table.clone().into()
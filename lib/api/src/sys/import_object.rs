@@ -134,6 +134,10 @@ impl NamedResolver for ImportObject {
     fn resolve_by_name(&self, module: &str, name: &str) -> Option<Export> {
         self.get_export(module, name)
     }
+
+    fn import_names(&self) -> Vec<(String, String)> {
+        self.get_objects().into_iter().map(|(key, _)| key).collect()
+    }
 }
 
 /// Iterator for an `ImportObject`'s exports.
@@ -298,6 +302,40 @@ mod test {
         assert!(small.is_some());
     }
 
+    #[test]
+    fn resolution_failures_suggest_close_names() {
+        let store = Store::default();
+        let g = Global::new(&store, Val::I32(0));
+        let imports = imports! {
+            "env" => {
+                "memory_grow" => g.clone(),
+                "abort" => g.clone(),
+            },
+        };
+        assert_eq!(
+            imports.explain_resolution_failure("env", "memory_grwo"),
+            "import `env`.`memory_grwo` could not be resolved; did you mean `env`.`memory_grow`?"
+        );
+        assert_eq!(
+            imports.explain_resolution_failure("emv", "Abort"),
+            "import `emv`.`Abort` could not be resolved; did you mean `env`.`abort`?"
+        );
+        assert_eq!(
+            imports.explain_resolution_failure("wasi", "fd_write"),
+            "import `wasi`.`fd_write` could not be resolved"
+        );
+        assert_eq!(
+            imports.explain_resolution_failure("env", "abort"),
+            "import `env`.`abort` is provided, but with an incompatible type"
+        );
+
+        let chained = ImportObject::new().chain_back(imports);
+        assert_eq!(
+            chained.explain_resolution_failure("env", "memory_grwo"),
+            "import `env`.`memory_grwo` could not be resolved; did you mean `env`.`memory_grow`?"
+        );
+    }
+
     #[test]
     fn extending_conflict_overwrites() {
         let store = Store::default();
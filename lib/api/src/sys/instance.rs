@@ -2,11 +2,18 @@ use crate::sys::module::Module;
 use crate::sys::store::Store;
 use crate::sys::{HostEnvInitError, LinkError, RuntimeError};
 use crate::{ExportError, NativeFunc, WasmTypeList};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::ffi::c_void;
 use std::fmt;
+use std::mem;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
-use wasmer_types::InstanceConfig;
-use wasmer_vm::{InstanceHandle, Resolver, VMContext};
+use wasmer_types::{ExportIndex, FunctionIndex, InstanceConfig, WasmClock};
+use wasmer_vm::{
+    ExportFunction, ImportFunctionEnv, ImportInitializerFuncPtr, InstanceHandle,
+    InstantiationProfile, Resolver, VMContext, VMFunctionBody,
+};
 
 use super::exports::ExportableWithGenerics;
 
@@ -24,6 +31,20 @@ pub struct Instance {
     module: Module,
 }
 
+/// A function of an [`Instance`] that can be referred to by name.
+///
+/// See [`Instance::named_functions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedFunction {
+    /// The index of the function in the module.
+    pub index: FunctionIndex,
+    /// The name under which the function is exported, if any. A function
+    /// exported under several names gets the first one in name order.
+    pub export_name: Option<String>,
+    /// The name given to the function by the `name` custom section, if any.
+    pub debug_name: Option<String>,
+}
+
 #[cfg(test)]
 mod send_test {
     use super::*;
@@ -126,15 +147,64 @@ impl Instance {
         config: InstanceConfig,
         resolver: &dyn Resolver,
     ) -> Result<Self, InstantiationError> {
-        unsafe {
-            if (*config.gas_counter).opcode_cost > i32::MAX as u64 {
-                // Fast gas counter logic assumes that individual opcode cost is not too big.
+        Self::instantiate_with(module, config, |config| {
+            module.instantiate(resolver, config, &mut InstantiationProfile::default())
+        })
+    }
+
+    /// Like [`Instance::new_with_config`], also returning the time spent in
+    /// each phase of the instantiation.
+    ///
+    /// `config` is set to profile the instantiation. Initializing the host
+    /// environments isn't part of the profile.
+    pub fn new_with_profile(
+        module: &Module,
+        config: InstanceConfig,
+        resolver: &dyn Resolver,
+    ) -> Result<(Self, InstantiationProfile), InstantiationError> {
+        let config = config.with_profile_instantiation(true);
+        let mut profile = InstantiationProfile::default();
+        let instance = Self::instantiate_with(module, config, |config| {
+            module.instantiate(resolver, config, &mut profile)
+        })?;
+        Ok((instance, profile))
+    }
+
+    /// Shared tail of instantiation, `instantiate` creates the `InstanceHandle`
+    /// of `module` once `config` has been validated.
+    pub(crate) fn instantiate_with(
+        module: &Module,
+        config: InstanceConfig,
+        instantiate: impl FnOnce(InstanceConfig) -> Result<InstanceHandle, InstantiationError>,
+    ) -> Result<Self, InstantiationError> {
+        if config.gas_counter.is_null() {
+            // Code with intrinsified gas imports updates the counter directly.
+            if module.metering_info().uses_gas_counter() {
                 return Err(InstantiationError::HostEnvInitialization(
-                    HostEnvInitError::IncorrectGasMeteringConfig,
+                    HostEnvInitError::MissingGasCounter,
                 ));
             }
+        } else {
+            unsafe {
+                if (*config.gas_counter).opcode_cost > i32::MAX as u64 {
+                    // Fast gas counter logic assumes that individual opcode cost is not too big.
+                    return Err(InstantiationError::HostEnvInitialization(
+                        HostEnvInitError::IncorrectGasMeteringConfig,
+                    ));
+                }
+            }
+        }
+        if config.max_function_call_depth.is_some() && !module.metering_info().limits_call_depth {
+            return Err(InstantiationError::HostEnvInitialization(
+                HostEnvInitError::CallDepthNotLimited,
+            ));
         }
-        let handle = module.instantiate(resolver, config)?;
+        if config.interrupt_flag.is_some() && !module.metering_info().checks_interrupts {
+            return Err(InstantiationError::HostEnvInitialization(
+                HostEnvInitError::InterruptsNotChecked,
+            ));
+        }
+        let handle = instantiate(config)?;
         let instance = Self {
             handle: Arc::new(Mutex::new(handle)),
             module: module.clone(),
@@ -158,6 +228,62 @@ impl Instance {
         Ok(instance)
     }
 
+    /// Returns the state attached to this instance with
+    /// [`InstanceConfig::with_host_state`], if it is a `T`.
+    ///
+    /// Host functions reach it through their environment, which can take
+    /// what it needs when it is initialized:
+    ///
+    /// ```
+    /// # use std::sync::atomic::AtomicU32;
+    /// # use std::sync::Arc;
+    /// # use wasmer::{HostEnvInitError, Instance, WasmerEnv};
+    /// #[derive(Clone, Default)]
+    /// struct CounterEnv {
+    ///     counter: Option<Arc<AtomicU32>>,
+    /// }
+    ///
+    /// impl WasmerEnv for CounterEnv {
+    ///     fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+    ///         self.counter = instance.host_state_ref::<Arc<AtomicU32>>().cloned();
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// Holding on to the `Instance` itself instead would leak it.
+    pub fn host_state_ref<T: Any>(&self) -> Option<&T> {
+        let state = self.handle.lock().unwrap().host_state_ref::<T>()? as *const T;
+        // The state is owned by the instance, which `self` keeps alive, and is
+        // never replaced.
+        Some(unsafe { &*state })
+    }
+
+    /// Returns the clock set with [`InstanceConfig::with_clock`].
+    ///
+    /// Host functions exposing the current time to WebAssembly code should
+    /// read it from this clock, typically keeping the returned handle in their
+    /// environment when it is initialized.
+    pub fn clock(&self) -> Arc<dyn WasmClock> {
+        self.handle.lock().unwrap().clock().clone()
+    }
+
+    /// Runs the start function of the module, if it has one.
+    ///
+    /// This is meant for instances created with
+    /// [`InstanceConfig::with_run_start`]`(false)`, whose start function did
+    /// not run during instantiation. It is called like it would have been
+    /// then, consuming gas from the same counter and trapping the same way.
+    pub fn run_start(&self) -> Result<(), RuntimeError> {
+        let handle = &*self.handle.lock().unwrap() as *const InstanceHandle;
+        // The handle is owned by the instance, which `self` keeps alive, and
+        // is never replaced. The lock is not held during the call, so that
+        // the start function can call host functions using the instance.
+        unsafe { &*handle }
+            .run_start()
+            .map_err(RuntimeError::from_trap)
+    }
+
     /// Gets the [`Module`] associated with this instance.
     pub fn module(&self) -> &Module {
         &self.module
@@ -168,6 +294,34 @@ impl Instance {
         self.module.store()
     }
 
+    /// Returns the functions of this instance that are exported or named by
+    /// the `name` custom section of its module, in index order.
+    pub fn named_functions(&self) -> Vec<NamedFunction> {
+        let artifact = self.module.artifact();
+        let mut functions = artifact
+            .function_names()
+            .map(|(index, name)| {
+                let function = NamedFunction {
+                    index,
+                    export_name: None,
+                    debug_name: Some(name.to_string()),
+                };
+                (index, function)
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (name, export) in artifact.exports() {
+            if let ExportIndex::Function(index) = *export {
+                let function = functions.entry(index).or_insert(NamedFunction {
+                    index,
+                    export_name: None,
+                    debug_name: None,
+                });
+                function.export_name.get_or_insert_with(|| name.to_string());
+            }
+        }
+        functions.into_values().collect()
+    }
+
     /// Lookup an exported entity by its name.
     pub fn lookup(&self, field: &str) -> Option<crate::Export> {
         let vmextern = self.handle.lock().unwrap().lookup(field)?;
@@ -177,12 +331,72 @@ impl Instance {
     /// Lookup an exported function by its name.
     pub fn lookup_function(&self, field: &str) -> Option<crate::Function> {
         if let crate::Export::Function(f) = self.lookup(field)? {
-            Some(crate::Function::from_vm_export(self.store(), f))
+            Some(self.exported_function(f))
         } else {
             None
         }
     }
 
+    /// Wrap the exported function `f`, holding this instance weakly if the
+    /// store says so.
+    fn exported_function(&self, f: ExportFunction) -> crate::Function {
+        let mut function = crate::Function::from_vm_export(self.store(), f);
+        if self.store().weak_exports() {
+            crate::Exportable::into_weak_instance_ref(&mut function);
+        }
+        function
+    }
+
+    /// Makes the function this instance imports as `module`.`field` refer to
+    /// `new_fn` instead, without instantiating the module again.
+    ///
+    /// The host env of `new_fn` is cloned and initialized with this instance,
+    /// just like on instantiation, and the one of the previous function is
+    /// dropped. Function references to the import, in tables for example,
+    /// see `new_fn` as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the module has no such function import, if the
+    /// type of `new_fn` differs from the one of the import or if the host
+    /// env of `new_fn` fails to initialize. The import is unchanged then.
+    ///
+    /// # Safety
+    ///
+    /// The import is not patched atomically: no code of this instance may be
+    /// running while this is called, on this thread or on any other one. If
+    /// `new_fn` is exported by another instance, that instance must outlive
+    /// this one.
+    pub unsafe fn replace_function_import(
+        &self,
+        module: &str,
+        field: &str,
+        new_fn: &crate::Function,
+    ) -> Result<(), LinkError> {
+        let (index, import, mut env) =
+            self.module
+                .link_function_import(module, field, &new_fn.exported)?;
+        if let ImportFunctionEnv::Env {
+            env, initializer, ..
+        } = &mut env
+        {
+            if let Some(init) = initializer.take() {
+                let init = mem::transmute::<
+                    ImportInitializerFuncPtr,
+                    ImportInitializerFuncPtr<HostEnvInitError>,
+                >(init);
+                init(*env, self as *const _ as *const _).map_err(|e| {
+                    LinkError::Resource(format!("failed to initialize the host env: {}", e))
+                })?;
+            }
+        }
+        self.handle
+            .lock()
+            .unwrap()
+            .replace_function_import(index, import, env);
+        Ok(())
+    }
+
     /// Get an export as a `NativeFunc`.
     pub fn get_native_function<Args, Rets>(
         &self,
@@ -193,7 +407,8 @@ impl Instance {
         Rets: WasmTypeList,
     {
         match self.lookup(name) {
-            Some(crate::Export::Function(f)) => crate::Function::from_vm_export(self.store(), f)
+            Some(crate::Export::Function(f)) => self
+                .exported_function(f)
                 .native()
                 .map_err(|_| ExportError::IncompatibleType),
             Some(_) => Err(ExportError::IncompatibleType),
@@ -212,7 +427,11 @@ impl Instance {
             .lookup(name)
             .ok_or_else(|| ExportError::Missing(name.to_string()))?;
         let ext = crate::Extern::from_vm_export(self.store(), export);
-        T::get_self_from_extern_with_generics(ext)
+        let mut out = T::get_self_from_extern_with_generics(ext)?;
+        if self.store().weak_exports() {
+            out.into_weak_instance_ref();
+        }
+        Ok(out)
     }
 
     /// Like `get_with_generics` but with a WeakReference to the `InstanceRef` internally.
@@ -228,6 +447,46 @@ impl Instance {
         Ok(out)
     }
 
+    /// Returns the address of the native code entry point of the exported function
+    /// `name`, e.g. to map it in an external profiler or debugger.
+    ///
+    /// Returns `None` if there is no such export, or if it is not a function defined
+    /// by the module itself.
+    pub fn get_function_address(&self, name: &str) -> Option<usize> {
+        let extent = self
+            .handle
+            .lock()
+            .unwrap()
+            .function_extent_by_export_field(name)?;
+        Some(*extent.address as usize)
+    }
+
+    /// Returns the module and field names of every imported function, along
+    /// with the address of the code the import has been resolved to.
+    ///
+    /// Dynamic host functions are reached through a reverse trampoline of
+    /// the module, see [`UniversalArtifact::dynamic_function_trampoline`].
+    ///
+    /// [`UniversalArtifact::dynamic_function_trampoline`]: crate::UniversalArtifact::dynamic_function_trampoline
+    pub fn imported_functions(&self) -> Vec<(String, String, *const VMFunctionBody)> {
+        self.handle.lock().unwrap().imported_functions()
+    }
+
+    /// Returns a pointer to the host env of the function imported as
+    /// `module`.`field`, once initialized with [`WasmerEnv::init_with_instance`].
+    ///
+    /// Every instance gets its own clone of the env its imports were created
+    /// with, so this is the way to reach what was set up during
+    /// initialization. For functions created with
+    /// [`Function::new_native_with_env`], it points to the `Env` itself.
+    /// Returns `None` if there is no such function import or if it has no env.
+    ///
+    /// [`WasmerEnv::init_with_instance`]: crate::WasmerEnv::init_with_instance
+    /// [`Function::new_native_with_env`]: crate::Function::new_native_with_env
+    pub fn get_function_env_ptr(&self, module: &str, field: &str) -> Option<*mut c_void> {
+        self.handle.lock().unwrap().function_env_ptr(module, field)
+    }
+
     #[doc(hidden)]
     pub fn vmctx_ptr(&self) -> *mut VMContext {
         self.handle.lock().unwrap().vmctx_ptr()
@@ -34,8 +34,8 @@ pub use crate::sys::externals::{
     Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
 };
 pub use crate::sys::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
-pub use crate::sys::instance::{Instance, InstantiationError};
-pub use crate::sys::module::Module;
+pub use crate::sys::instance::{Instance, InstantiationError, NamedFunction};
+pub use crate::sys::module::{Module, PreparedModule};
 pub use crate::sys::native::NativeFunc;
 pub use crate::sys::ptr::{Array, Item, WasmPtr};
 pub use crate::sys::store::{Store, StoreObject};
@@ -48,19 +48,24 @@ pub use crate::sys::types::{Val as Value, ValType as Type};
 pub use crate::sys::utils::is_wasm;
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
 #[cfg(feature = "compiler")]
-pub use wasmer_compiler::{wasmparser, CompilerConfig};
+pub use wasmer_compiler::{wasmparser, CompilerConfig, ModuleEnvironment};
 pub use wasmer_compiler::{
-    CompileError, CpuFeature, Features, ParseCpuFeatureError, Target, WasmError, WasmResult,
+    CompilationProfile, CompileError, CpuFeature, Features, FeaturesDiff, ImportAllowList,
+    MeteringInfo, ModuleLimit, ModuleLimits, ParseCpuFeatureError, Target, UnknownFeatureError,
+    WasmError, WasmResult,
+};
+pub use wasmer_engine::{
+    DeserializeError, Engine, FrameInfo, LinkError, RuntimeError, TrapOrHostError,
 };
-pub use wasmer_engine::{DeserializeError, Engine, FrameInfo, LinkError, RuntimeError};
 #[cfg(feature = "experimental-reference-types-extern-ref")]
 pub use wasmer_types::ExternRef;
 pub use wasmer_types::{
-    Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
-    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    Atomically, Bytes, ExportIndex, FunctionIndex, GlobalInit, LocalFunctionIndex, MemoryView,
+    Pages, ValueType, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 pub use wasmer_vm::{
-    ChainableNamedResolver, Export, NamedResolver, NamedResolverChain, Resolver, Tunables,
+    ChainableNamedResolver, Export, InstantiationProfile, NamedResolver, NamedResolverChain,
+    RemappingResolver, Resolver, Tunables,
 };
 
 // TODO: should those be moved into wasmer::vm as well?
@@ -69,8 +74,9 @@ pub mod vm {
     //! The `vm` module re-exports wasmer-vm types.
 
     pub use wasmer_vm::{
-        Memory, MemoryError, MemoryStyle, Table, TableStyle, VMExtern, VMMemoryDefinition,
-        VMTableDefinition,
+        AllocatedMemory, Memory, MemoryAllocator, MemoryError, MemoryStyle,
+        PooledInstanceAllocator, Table, TableStyle, TrapCode, VMExtern, VMFuncRef,
+        VMMemoryDefinition, VMTableDefinition,
     };
 }
 
@@ -107,7 +113,9 @@ pub use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
 pub use wasmer_compiler_llvm::{LLVMOptLevel, LLVM};
 
 #[cfg(feature = "universal")]
-pub use wasmer_engine_universal::{Universal, UniversalArtifact, UniversalEngine};
+pub use wasmer_engine_universal::{
+    Universal, UniversalArtifact, UniversalEngine, UniversalExecutable,
+};
 
 #[cfg(feature = "dylib")]
 pub use wasmer_engine_dylib::{Dylib, DylibArtifact, DylibEngine};
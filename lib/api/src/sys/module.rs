@@ -1,17 +1,29 @@
 use crate::sys::store::Store;
-use crate::sys::InstantiationError;
+#[cfg(feature = "compiler")]
+use crate::sys::BaseTunables;
+use crate::sys::MeteringInfo;
+use crate::sys::{Instance, InstantiationError};
 use std::fmt;
 use std::io;
 use std::path::Path;
 use std::sync::Arc;
 use thiserror::Error;
 use wasmer_compiler::CompileError;
+#[cfg(feature = "compiler")]
+use wasmer_compiler::Target;
 #[cfg(feature = "wat")]
 use wasmer_compiler::WasmError;
-use wasmer_engine::RuntimeError;
+#[cfg(feature = "compiler")]
+use wasmer_engine::Engine;
+use wasmer_engine::{LinkError, ResolvedImports, RuntimeError};
 use wasmer_engine_universal::UniversalArtifact;
-use wasmer_types::InstanceConfig;
-use wasmer_vm::{InstanceHandle, Instantiatable, Resolver};
+#[cfg(feature = "compiler")]
+use wasmer_engine_universal::{UniversalEngine, UniversalExecutable};
+use wasmer_types::{FunctionIndex, InstanceConfig};
+use wasmer_vm::{
+    ExportFunction, ImportFunctionEnv, InstanceHandle, InstantiationProfile, Resolver,
+    VMFunctionImport,
+};
 
 #[derive(Error, Debug)]
 pub enum IoCompileError {
@@ -155,6 +167,40 @@ impl Module {
         store.engine().validate(binary)
     }
 
+    /// Compiles a WebAssembly module for `target` instead of the host, for
+    /// example for a different CPU feature set.
+    ///
+    /// The module is validated with the features of the store's engine, and
+    /// compiled with [`BaseTunables`](crate::BaseTunables) for `target`. The
+    /// resulting executable is neither loaded nor executed: it is meant to be
+    /// serialized and shipped to hardware matching `target`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the module is invalid or can't be compiled for
+    /// `target`, or if the store doesn't use the universal engine.
+    #[cfg(feature = "compiler")]
+    pub fn compile_for(
+        store: &Store,
+        bytes: impl AsRef<[u8]>,
+        target: &Target,
+    ) -> Result<UniversalExecutable, CompileError> {
+        #[cfg(feature = "wat")]
+        let bytes = wat::parse_bytes(bytes.as_ref()).map_err(|e| {
+            CompileError::Wasm(WasmError::Generic(format!(
+                "Error when converting wat: {}",
+                e
+            )))
+        })?;
+        let binary = bytes.as_ref();
+        Self::validate(store, binary)?;
+        let engine: &dyn Engine = &**store.engine();
+        let engine = engine.downcast_ref::<UniversalEngine>().ok_or_else(|| {
+            CompileError::Codegen("cross-compilation requires the universal engine".to_string())
+        })?;
+        engine.compile_universal_for_target(binary, &BaseTunables::for_target(target), target)
+    }
+
     fn compile(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
         let executable = store.engine().compile(binary, store.tunables())?;
         let artifact = store.engine().load(&*executable)?;
@@ -178,36 +224,171 @@ impl Module {
         }
     }
 
+    /// Resolves the imports of this module once, so that it can be instantiated
+    /// any number of times without resolving them again.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # use wasmer_types::InstanceConfig;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, "(module)")?;
+    /// let prepared = module.prepare(&imports! {})?;
+    /// for _ in 0..10 {
+    ///     let _instance = prepared.instantiate(InstanceConfig::default())?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn prepare(&self, resolver: &dyn Resolver) -> Result<PreparedModule, InstantiationError> {
+        let imports = self.artifact.resolve_imports(resolver)?;
+        Ok(PreparedModule {
+            module: self.clone(),
+            imports,
+        })
+    }
+
     pub(crate) fn instantiate(
         &self,
         resolver: &dyn Resolver,
         config: InstanceConfig,
+        profile: &mut InstantiationProfile,
     ) -> Result<InstanceHandle, InstantiationError> {
         unsafe {
-            let instance_handle = Arc::clone(&self.artifact).instantiate(
+            let instance_handle = Arc::clone(&self.artifact).instantiate_with_profile(
                 self.store.tunables(),
                 resolver,
                 Box::new((self.store.clone(), Arc::clone(&self.artifact))),
                 config,
+                profile,
             )?;
+            Self::finish_instantiation(instance_handle, profile)
+        }
+    }
 
-            // After the instance handle is created, we need to initialize
-            // the data, call the start function and so. However, if any
-            // of this steps traps, we still need to keep the instance alive
-            // as some of the Instance elements may have placed in other
-            // instance tables.
-            instance_handle
-                .finish_instantiation()
-                .map_err(|t| InstantiationError::Start(RuntimeError::from_trap(t)))?;
-
-            Ok(instance_handle)
+    fn instantiate_resolved(
+        &self,
+        imports: &ResolvedImports,
+        config: InstanceConfig,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        unsafe {
+            let instance_handle = Arc::clone(&self.artifact).instantiate_resolved(
+                self.store.tunables(),
+                imports,
+                Box::new((self.store.clone(), Arc::clone(&self.artifact))),
+                config,
+            )?;
+            Self::finish_instantiation(instance_handle, &mut InstantiationProfile::default())
         }
     }
 
+    unsafe fn finish_instantiation(
+        instance_handle: InstanceHandle,
+        profile: &mut InstantiationProfile,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        // After the instance handle is created, we need to initialize
+        // the data, call the start function and so. However, if any
+        // of this steps traps, we still need to keep the instance alive
+        // as some of the Instance elements may have placed in other
+        // instance tables.
+        instance_handle
+            .finish_instantiation_with_profile(profile)
+            .map_err(|t| InstantiationError::Start(RuntimeError::from_trap(t)))?;
+
+        Ok(instance_handle)
+    }
+
     /// Returns the [`Store`] where the `Instance` belongs.
     pub fn store(&self) -> &Store {
         &self.store
     }
+
+    /// Returns the artifact this module has been compiled to.
+    ///
+    /// This gives tooling access to the compiled code, for instance to
+    /// inspect its exports or to warm it up before the first call.
+    pub fn artifact(&self) -> &UniversalArtifact {
+        &self.artifact
+    }
+
+    /// Link `export` as the function this module imports as `module`.`field`.
+    pub(crate) fn link_function_import(
+        &self,
+        module: &str,
+        field: &str,
+        export: &ExportFunction,
+    ) -> Result<(FunctionIndex, VMFunctionImport, ImportFunctionEnv), LinkError> {
+        self.artifact.link_function_import(module, field, export)
+    }
+
+    /// Returns how the compiled code of this module meters gas.
+    ///
+    /// Modules that use a gas counter must be instantiated with an
+    /// [`InstanceConfig`] providing one.
+    pub fn metering_info(&self) -> &MeteringInfo {
+        self.artifact.metering_info()
+    }
+
+    /// Returns the BLAKE3 hash of the wasm binary this module has been
+    /// compiled from, or converted to if it was given in the text format.
+    ///
+    /// Modules compiled from the same binary have the same hash, whatever
+    /// the engine or host that compiled them.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wasm = wat2wasm(b"(module)")?;
+    /// let module = Module::new(&store, &wasm)?;
+    /// assert_eq!(module.hash(), Module::new(&store, &wasm)?.hash());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn hash(&self) -> [u8; 32] {
+        self.artifact.module_hash()
+    }
+
+    /// Returns the functions named by the `name` custom section of this
+    /// module, in index order.
+    pub fn function_names(&self) -> impl Iterator<Item = (FunctionIndex, &str)> + '_ {
+        self.artifact.function_names()
+    }
+}
+
+/// A [`Module`] whose imports have already been resolved, see [`Module::prepare`].
+///
+/// Every instance created from it gets its own clones of the host function
+/// environments, exactly as if it was instantiated with the original resolver.
+#[derive(Clone)]
+pub struct PreparedModule {
+    module: Module,
+    imports: ResolvedImports,
+}
+
+impl PreparedModule {
+    /// Creates a new instance of the prepared module.
+    ///
+    /// This behaves like [`Instance::new_with_config`] called with the resolver
+    /// the module was prepared with.
+    pub fn instantiate(&self, config: InstanceConfig) -> Result<Instance, InstantiationError> {
+        Instance::instantiate_with(&self.module, config, |config| {
+            self.module.instantiate_resolved(&self.imports, config)
+        })
+    }
+
+    /// Gets the [`Module`] that was prepared.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+}
+
+impl fmt::Debug for PreparedModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreparedModule").finish()
+    }
 }
 
 impl fmt::Debug for Module {
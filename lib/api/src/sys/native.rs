@@ -9,8 +9,10 @@
 //! ```
 use std::marker::PhantomData;
 
-use crate::sys::externals::function::{DynamicFunction, VMDynamicFunction};
-use crate::sys::{FromToNativeWasmType, Function, RuntimeError, Store, WasmTypeList};
+use crate::sys::externals::function::{upgrade_for_call, DynamicFunction, VMDynamicFunction};
+use crate::sys::{
+    FromToNativeWasmType, Function, RuntimeError, Store, TrapOrHostError, WasmTypeList,
+};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use wasmer_types::NativeWasmType;
 use wasmer_vm::{
@@ -91,7 +93,8 @@ where
 impl<Args: WasmTypeList, Rets: WasmTypeList> Clone for NativeFunc<Args, Rets> {
     fn clone(&self) -> Self {
         let mut exported = self.exported.clone();
-        exported.vm_function.upgrade_instance_ref().unwrap();
+        // A function whose instance was dropped stays weak, calling it fails.
+        let _ = exported.vm_function.upgrade_instance_ref();
 
         Self {
             store: self.store.clone(),
@@ -138,6 +141,7 @@ macro_rules! impl_native_traits {
                     // We assume the trampoline is always going to be present for
                     // Wasm functions
                     let trampoline = self.exported.vm_function.call_trampoline.expect("Call trampoline not found in wasm function");
+                    let _instance = upgrade_for_call(&self.exported.vm_function)?;
                     // TODO: when `const fn` related features mature more, we can declare a single array
                     // of the correct size here.
                     let mut params_list = [ $( $x.to_native().to_binary() ),* ];
@@ -154,14 +158,14 @@ macro_rules! impl_native_traits {
                         }
                         rets_list.as_mut()
                     };
-                    unsafe {
+                    self.exported.vm_function.with_call_depth_restored(|| unsafe {
                         wasmer_vm::wasmer_call_trampoline(
                             self.vmctx(),
                             trampoline,
                             self.address(),
                             args_rets.as_mut_ptr() as *mut u8,
                         )
-                    }?;
+                    })?;
                     let num_rets = rets_list.len();
                     if !using_rets_array && num_rets > 0 {
                         let src_pointer = params_list.as_ptr();
@@ -220,6 +224,12 @@ macro_rules! impl_native_traits {
                 }
             }
 
+            /// Call the typed func, telling traps of the WebAssembly code apart
+            /// from other errors, such as the ones returned by host functions.
+            #[allow(clippy::too_many_arguments)]
+            pub fn call_and_catch_trap(&self, $( $x: $x, )* ) -> Result<Rets, TrapOrHostError> {
+                self.call($( $x, )*).map_err(TrapOrHostError::from)
+            }
         }
 
         #[allow(unused_parens)]
@@ -20,6 +20,7 @@ use wasmer_vm::Tunables;
 pub struct Store {
     engine: Arc<dyn Engine + Send + Sync>,
     tunables: Arc<dyn Tunables + Send + Sync>,
+    weak_exports: bool,
 }
 
 impl Store {
@@ -39,9 +40,36 @@ impl Store {
         Self {
             engine: engine.cloned(),
             tunables: Arc::new(tunables),
+            weak_exports: false,
         }
     }
 
+    /// Makes the exports looked up on the instances of this store, with
+    /// `Instance::lookup_function`, `get_native_function` or
+    /// `get_with_generics`, hold their instance weakly, as if
+    /// [`Exportable::into_weak_instance_ref`] was called on them.
+    ///
+    /// By default these exports keep their instance alive, so caching one
+    /// keeps the whole instance around. With weak exports, the instance is
+    /// dropped along with its last `Instance` handle, and
+    /// [`Function::instance_is_alive`] tells whether a cached function can
+    /// still be called; calling it after its instance is dropped returns an
+    /// error. Clones of an export made while the instance is alive hold it
+    /// strongly, e.g. while a host function uses it.
+    ///
+    /// [`Exportable::into_weak_instance_ref`]: crate::Exportable::into_weak_instance_ref
+    /// [`Function::instance_is_alive`]: crate::Function::instance_is_alive
+    pub fn with_weak_exports(mut self, enable: bool) -> Self {
+        self.weak_exports = enable;
+        self
+    }
+
+    /// Returns whether exports looked up on the instances of this store hold
+    /// their instance weakly, see [`Store::with_weak_exports`].
+    pub fn weak_exports(&self) -> bool {
+        self.weak_exports
+    }
+
     /// Returns the [`Tunables`].
     pub fn tunables(&self) -> &dyn Tunables {
         self.tunables.as_ref()
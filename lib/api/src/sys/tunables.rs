@@ -91,6 +91,17 @@ impl Tunables for BaseTunables {
         TableStyle::CallerChecksSignature
     }
 
+    /// Describe the bounds and guard sizes these tunables give memories.
+    fn describe(&self) -> String {
+        format!(
+            "static memory bound: {} pages, static memory offset guard: {:#x} bytes, \
+             dynamic memory offset guard: {:#x} bytes",
+            self.static_memory_bound.0,
+            self.static_memory_offset_guard_size,
+            self.dynamic_memory_offset_guard_size,
+        )
+    }
+
     /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
     fn create_host_memory(
         &self,
@@ -188,4 +199,25 @@ mod tests {
             s => panic!("Unexpected memory style: {:?}", s),
         }
     }
+
+    #[test]
+    fn describe_for_64_bit_target() {
+        let target = Target::new(
+            "x86_64-unknown-linux-gnu".parse().unwrap(),
+            Default::default(),
+        );
+        let tunables = BaseTunables::for_target(&target);
+        let style = tunables.memory_style(&MemoryType::new(1, Some(16), false));
+        assert_eq!(
+            style,
+            MemoryStyle::Static {
+                bound: Pages(0x1_0000),
+                offset_guard_size: 0x8000_0000,
+            }
+        );
+        // The dynamic guard size depends on the host OS.
+        assert!(tunables.describe().starts_with(
+            "static memory bound: 65536 pages, static memory offset guard: 0x80000000 bytes, "
+        ));
+    }
 }
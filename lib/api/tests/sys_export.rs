@@ -363,4 +363,124 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn holds_instance_strongly_works() -> Result<()> {
+        const WAT: &str = "
+        (module
+          (func $host_fn (import \"env\" \"host_fn\") (param) (result))
+          (func (export \"call_host_fn\") (param) (result)
+              (call $host_fn))
+
+          (memory $mem 0)
+          (export \"memory\" (memory $mem))
+          (global $global i32 (i32.const 11))
+          (export \"global\" (global $global))
+          (table $table 4 4 funcref)
+          (export \"table\" (table $table))
+          )
+";
+
+        #[derive(Clone, WasmerEnv, Default)]
+        struct ExportsEnv {
+            #[wasmer(export)]
+            memory: LazyInit<Memory>,
+            #[wasmer(export)]
+            global: LazyInit<Global>,
+            #[wasmer(export)]
+            table: LazyInit<Table>,
+            #[wasmer(export)]
+            call_host_fn: LazyInit<Function>,
+        }
+
+        let host_fn = |env: &ExportsEnv| {
+            let memory = env.memory_ref().unwrap();
+            assert_eq!(memory.holds_instance_strongly(), Some(false));
+            assert_eq!(memory.clone().holds_instance_strongly(), Some(true));
+
+            let global = env.global_ref().unwrap();
+            assert_eq!(global.holds_instance_strongly(), Some(false));
+            assert_eq!(global.clone().holds_instance_strongly(), Some(true));
+
+            let table = env.table_ref().unwrap();
+            assert_eq!(table.holds_instance_strongly(), Some(false));
+            assert_eq!(table.clone().holds_instance_strongly(), Some(true));
+
+            let function = env.call_host_fn_ref().unwrap();
+            assert_eq!(function.holds_instance_strongly(), Some(false));
+            assert_eq!(function.clone().holds_instance_strongly(), Some(true));
+        };
+
+        let store = Store::default();
+        let host_memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        assert_eq!(host_memory.holds_instance_strongly(), None);
+        let host_global = Global::new(&store, Value::I32(1));
+        assert_eq!(host_global.holds_instance_strongly(), None);
+        let host_function = Function::new_native_with_env(&store, ExportsEnv::default(), host_fn);
+        assert_eq!(host_function.holds_instance_strongly(), None);
+
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(
+            &module,
+            &imports! {
+                "env" => {
+                    "host_fn" => host_function
+                }
+            },
+        )?;
+        let function = instance.lookup_function("call_host_fn").unwrap();
+        assert_eq!(function.holds_instance_strongly(), Some(true));
+        function.call(&[])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn weak_exports_let_instance_drop() -> Result<()> {
+        const WAT: &str = "
+        (module
+          (func (export \"sum\") (param i32 i32) (result i32)
+              local.get 0
+              local.get 1
+              i32.add)
+          )
+";
+
+        // By default, a cached export keeps the instance alive.
+        let store = Store::default();
+        assert!(!store.weak_exports());
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let sum = instance.lookup_function("sum").unwrap();
+        drop(instance);
+        assert_eq!(sum.holds_instance_strongly(), Some(true));
+        assert_eq!(sum.instance_is_alive(), Some(true));
+        assert_eq!(sum.call(&[Value::I32(1), Value::I32(2)])?[0], Value::I32(3));
+
+        let store = Store::default().with_weak_exports(true);
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let sum = instance.lookup_function("sum").unwrap();
+        let native: NativeFunc<(i32, i32), i32> = instance.get_native_function("sum")?;
+        assert_eq!(sum.holds_instance_strongly(), Some(false));
+        assert_eq!(sum.instance_is_alive(), Some(true));
+        assert_eq!(sum.call(&[Value::I32(1), Value::I32(2)])?[0], Value::I32(3));
+        assert_eq!(native.call(1, 2)?, 3);
+        // Clones keep the instance alive while they are used.
+        assert_eq!(sum.clone().holds_instance_strongly(), Some(true));
+
+        drop(instance);
+        assert_eq!(sum.instance_is_alive(), Some(false));
+        // Calling into a dropped instance is an error rather than a use of
+        // its freed vmctx, and cloning keeps the reference weak.
+        assert!(sum.call(&[Value::I32(1), Value::I32(2)]).is_err());
+        assert!(native.call(1, 2).is_err());
+        assert_eq!(sum.clone().holds_instance_strongly(), Some(false));
+        assert!(sum.clone().call(&[Value::I32(1), Value::I32(2)]).is_err());
+
+        let host = Function::new_native(&store, || {});
+        assert_eq!(host.instance_is_alive(), None);
+
+        Ok(())
+    }
 }
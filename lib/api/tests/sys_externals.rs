@@ -130,9 +130,149 @@ mod sys {
     }
 
     #[test]
-    #[ignore]
+    fn table_fill() -> Result<()> {
+        let store = Store::default();
+        let table_type = TableType {
+            ty: Type::FuncRef,
+            minimum: 4,
+            maximum: None,
+        };
+        let f = Function::new_native(&store, |num: i32| num + 1);
+        let table = Table::new(&store, table_type, Value::FuncRef(None))?;
+
+        table.fill(1, 2, Value::FuncRef(Some(f.clone())))?;
+        assert!(matches!(table.get(0), Some(Value::FuncRef(None))));
+        assert!(matches!(table.get(1), Some(Value::FuncRef(Some(_)))));
+        assert!(matches!(table.get(2), Some(Value::FuncRef(Some(_)))));
+        assert!(matches!(table.get(3), Some(Value::FuncRef(None))));
+
+        // Filling nothing at the end of the table is fine, past it is not.
+        table.fill(4, 0, Value::FuncRef(Some(f.clone())))?;
+        assert!(table.fill(3, 2, Value::FuncRef(Some(f.clone()))).is_err());
+        assert!(table.fill(u32::MAX, 2, Value::FuncRef(Some(f))).is_err());
+        assert!(matches!(table.get(3), Some(Value::FuncRef(None))));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
     fn table_copy() -> Result<()> {
-        // TODO: table copy test not yet implemented
+        let store = Store::default();
+        let table_type = TableType {
+            ty: Type::ExternRef,
+            minimum: 6,
+            maximum: None,
+        };
+        let new_table = |values: &[u32]| -> Result<Table> {
+            let table = Table::new(&store, table_type, Value::ExternRef(ExternRef::null()))?;
+            for (index, value) in values.iter().enumerate() {
+                table.set(index as u32, Value::ExternRef(ExternRef::new(*value)))?;
+            }
+            Ok(table)
+        };
+        let contents = |table: &Table| -> Vec<Option<u32>> {
+            (0..table.size())
+                .map(|index| match table.get(index) {
+                    Some(Value::ExternRef(r)) => r.downcast::<u32>().copied(),
+                    _ => panic!("expected an externref"),
+                })
+                .collect()
+        };
+
+        // Between different tables.
+        let src = new_table(&[1, 2, 3, 4, 5, 6])?;
+        let dst = new_table(&[])?;
+        Table::copy(&dst, 1, &src, 3, 2)?;
+        assert_eq!(contents(&dst), [None, Some(4), Some(5), None, None, None]);
+
+        // Overlapping ranges of the same table, in both directions.
+        let table = new_table(&[1, 2, 3, 4, 5, 6])?;
+        Table::copy(&table, 2, &table, 0, 4)?;
+        assert_eq!(
+            contents(&table),
+            [Some(1), Some(2), Some(1), Some(2), Some(3), Some(4)]
+        );
+        let table = new_table(&[1, 2, 3, 4, 5, 6])?;
+        Table::copy(&table, 0, &table, 2, 4)?;
+        assert_eq!(
+            contents(&table),
+            [Some(3), Some(4), Some(5), Some(6), Some(5), Some(6)]
+        );
+
+        // Out of bounds ranges copy nothing.
+        assert!(Table::copy(&dst, 5, &src, 0, 2).is_err());
+        assert!(Table::copy(&dst, 0, &src, 5, 2).is_err());
+        assert!(Table::copy(&dst, 0, &src, u32::MAX, 2).is_err());
+        assert_eq!(contents(&dst), [None, Some(4), Some(5), None, None, None]);
+        Table::copy(&dst, 6, &src, 6, 0)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn table_funcref_snapshot() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+            (func $a (export "a"))
+            (func $b (export "b"))
+            (table (export "table") 4 funcref)
+            (elem (i32.const 1) $b $a))"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let address = |name: &str| match instance.lookup(name) {
+            Some(Export::Function(f)) => f.vm_function.address,
+            _ => panic!("`{}` is not an exported function", name),
+        };
+        let table = match Extern::from_vm_export(&store, instance.lookup("table").unwrap()) {
+            Extern::Table(table) => table,
+            _ => panic!("`table` is not an exported table"),
+        };
+
+        let snapshot = table.funcref_snapshot().unwrap();
+        let addresses: Vec<_> = snapshot
+            .iter()
+            .map(|f| f.map(|f| unsafe { (**f).func_ptr }))
+            .collect();
+        assert_eq!(
+            addresses,
+            [None, Some(address("b")), Some(address("a")), None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    fn table_externref_snapshot() -> Result<()> {
+        let store = Store::default();
+        let table_type = TableType {
+            ty: Type::ExternRef,
+            minimum: 2,
+            maximum: None,
+        };
+        let value = ExternRef::new(7u32);
+        let table = Table::new(&store, table_type, Value::ExternRef(ExternRef::null()))?;
+        table.set(1, Value::ExternRef(value.clone()))?;
+        assert_eq!(value.strong_count(), 2);
+
+        let snapshot = table.externref_snapshot().unwrap();
+        assert!(snapshot[0].is_null());
+        assert_eq!(snapshot[1].downcast::<u32>(), Some(&7));
+        assert_eq!(value.strong_count(), 3);
+        drop(snapshot);
+        assert_eq!(value.strong_count(), 2);
+
+        // Snapshots of the other element type are refused.
+        assert!(table.funcref_snapshot().is_none());
+        let funcref_table = Table::new(
+            &store,
+            TableType {
+                ty: Type::FuncRef,
+                ..table_type
+            },
+            Value::FuncRef(None),
+        )?;
+        assert!(funcref_table.externref_snapshot().is_none());
         Ok(())
     }
 
@@ -143,6 +283,7 @@ mod sys {
             shared: false,
             minimum: Pages(0),
             maximum: Some(Pages(10)),
+            memory64: false,
         };
         let memory = Memory::new(&store, memory_type)?;
         assert_eq!(memory.size(), Pages(0));
@@ -179,6 +320,139 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn memory_read_write() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(Pages(1), Some(Pages(2)), false))?;
+        let end = memory.data_size() as u32;
+
+        memory.write(end - 5, b"hello")?;
+        let mut buf = [0; 5];
+        memory.read(end - 5, &mut buf)?;
+        assert_eq!(&buf, b"hello");
+        memory.read(end, &mut [])?;
+
+        assert_eq!(
+            memory.write(end - 4, b"hello"),
+            Err(MemoryError::AccessViolation)
+        );
+        assert_eq!(
+            memory.read(u32::MAX, &mut buf),
+            Err(MemoryError::AccessViolation)
+        );
+        assert_eq!(&buf, b"hello");
+
+        // The bounds follow the memory as it grows.
+        memory.grow(Pages(1))?;
+        memory.write(end - 4, b"hello")?;
+        memory.read(end - 4, &mut buf)?;
+        assert_eq!(&buf, b"hello");
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_read_write_primitives() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(Pages(1), None, false))?;
+        let end = memory.data_size() as u32;
+
+        memory.write_u32(0, 0x0403_0201)?;
+        let mut bytes = [0; 4];
+        memory.read(0, &mut bytes)?;
+        assert_eq!(bytes, [1, 2, 3, 4]);
+        assert_eq!(memory.read_u8(3)?, 4);
+        assert_eq!(memory.read_u16(1)?, 0x0302);
+        assert_eq!(memory.read_u32(0)?, 0x0403_0201);
+
+        memory.write_i64(end - 8, -2)?;
+        assert_eq!(memory.read_i64(end - 8)?, -2);
+        assert_eq!(memory.read_u64(end - 8)?, u64::MAX - 1);
+        memory.write_f64(16, 1.5)?;
+        assert_eq!(memory.read_f64(16)?, 1.5);
+        memory.write_f32(end - 4, -0.25)?;
+        assert_eq!(memory.read_f32(end - 4)?, -0.25);
+
+        assert_eq!(memory.read_u32(end - 3), Err(MemoryError::AccessViolation));
+        assert_eq!(
+            memory.write_u64(end - 7, 1),
+            Err(MemoryError::AccessViolation)
+        );
+        assert_eq!(memory.read_u8(end), Err(MemoryError::AccessViolation));
+        assert_eq!(memory.read_i32(u32::MAX), Err(MemoryError::AccessViolation));
+        assert_eq!(memory.read_f32(end - 4)?, -0.25);
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_data_range() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(Pages(1), None, false))?;
+        let end = memory.data_size() as u32;
+
+        memory.write(8, b"hello")?;
+        let (base, len) = memory.data_range(8, 5)?;
+        assert_eq!(len, 5);
+        assert_eq!(unsafe { std::slice::from_raw_parts(base, len) }, b"hello");
+        assert_eq!(base, unsafe { memory.data_ptr().add(8) });
+
+        assert!(memory.data_range(end - 4, 4).is_ok());
+        assert!(memory.data_range(end, 0).is_ok());
+        assert_eq!(
+            memory.data_range(end - 4, 5),
+            Err(MemoryError::AccessViolation)
+        );
+        assert_eq!(
+            memory.data_range(u32::MAX, usize::MAX),
+            Err(MemoryError::AccessViolation)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_identity() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (import "env" "memory" (memory 1))
+      (import "env" "global" (global i32))
+      (export "memory" (memory 0))
+      (export "global" (global 0)))
+"#,
+        )?;
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        let global = Global::new(&store, Value::I32(1));
+        let import_object = imports! {
+            "env" => {
+                "memory" => memory.clone(),
+                "global" => global.clone(),
+            },
+        };
+        let first = Instance::new(&module, &import_object)?;
+        let second = Instance::new(&module, &import_object)?;
+
+        for instance in &[first, second] {
+            match Extern::from_vm_export(&store, instance.lookup("memory").unwrap()) {
+                Extern::Memory(exported) => assert_eq!(exported, memory),
+                _ => panic!("expected a memory export"),
+            }
+            match Extern::from_vm_export(&store, instance.lookup("global").unwrap()) {
+                Extern::Global(exported) => assert_eq!(exported, global),
+                _ => panic!("expected a global export"),
+            }
+        }
+
+        // Identity, not contents, is compared.
+        let other = Memory::new(&store, MemoryType::new(1, None, false))?;
+        assert_ne!(other, memory);
+        assert_ne!(Global::new(&store, Value::I32(1)), global);
+        Ok(())
+    }
+
     #[test]
     fn function_new() -> Result<()> {
         let store = Store::default();
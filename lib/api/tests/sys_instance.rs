@@ -1,7 +1,11 @@
 #[cfg(feature = "sys")]
 mod sys {
     use anyhow::Result;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::Arc;
     use wasmer::*;
+    use wasmer_types::{FakeClock, InstanceConfig, WasmClock};
 
     #[test]
     fn exports_work_after_multiple_instances_have_been_freed() -> Result<()> {
@@ -67,4 +71,420 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn get_function_address_works() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (func $host (import \"env\" \"host\"))
+      (func $one (result i32) i32.const 1)
+      (func $two (result i32) i32.const 2)
+      (memory $mem 1)
+      (export \"one\" (func $one))
+      (export \"two\" (func $two))
+      (export \"host\" (func $host))
+      (export \"memory\" (memory $mem)))
+",
+        )?;
+        let import_object = imports! {
+            "env" => {
+                "host" => Function::new_native(&store, || {}),
+            },
+        };
+        let instance = Instance::new(&module, &import_object)?;
+
+        let one = instance.get_function_address("one").unwrap();
+        let two = instance.get_function_address("two").unwrap();
+        assert_ne!(one, 0);
+        assert_ne!(two, 0);
+        assert_ne!(one, two);
+
+        assert_eq!(instance.get_function_address("host"), None);
+        assert_eq!(instance.get_function_address("memory"), None);
+        assert_eq!(instance.get_function_address("missing"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn prepared_module_instantiates_with_own_host_envs() -> Result<()> {
+        #[derive(Clone, WasmerEnv, Default)]
+        struct MemEnv {
+            #[wasmer(export)]
+            memory: LazyInit<Memory>,
+        }
+
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (func $mark (import \"env\" \"mark\") (param i32))
+      (memory $mem 1)
+      (export \"memory\" (memory $mem))
+      (func $peek (export \"peek\") (result i32)
+        i32.const 0
+        i32.load8_u)
+      (func (export \"run\") (param $value i32) (result i32)
+        local.get $value
+        call $mark
+        call $peek))
+",
+        )?;
+        let import_object = imports! {
+            "env" => {
+                "mark" => Function::new_native_with_env(
+                    &store,
+                    MemEnv::default(),
+                    |env: &MemEnv, value: i32| {
+                        env.memory_ref().unwrap().view::<u8>()[0].set(value as u8);
+                    },
+                ),
+            },
+        };
+        let prepared = module.prepare(&import_object)?;
+
+        let instances = (0..3)
+            .map(|_| prepared.instantiate(InstanceConfig::default()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (i, instance) in instances.iter().enumerate() {
+            let run: NativeFunc<i32, i32> = instance.get_native_function("run")?;
+            assert_eq!(run.call(i as i32 + 1)?, i as i32 + 1);
+        }
+        // Each host env was bound to the memory of its own instance.
+        for (i, instance) in instances.iter().enumerate() {
+            let peek: NativeFunc<(), i32> = instance.get_native_function("peek")?;
+            assert_eq!(peek.call()?, i as i32 + 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn pooled_allocator_reuses_instance_memory() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (global $g (mut i32) (i32.const 0))
+      (func (export \"bump\") (result i32)
+        global.get $g
+        i32.const 1
+        i32.add
+        global.set $g
+        global.get $g))
+",
+        )?;
+        let pool = Arc::new(vm::PooledInstanceAllocator::with_pool_size(2));
+        let config = || InstanceConfig::default().with_allocator(pool.clone());
+
+        let first = Instance::new_with_config(&module, config(), &imports! {})?;
+        let second = Instance::new_with_config(&module, config(), &imports! {})?;
+        assert_eq!(pool.free_regions(), 0);
+        drop(first);
+        drop(second);
+        assert_eq!(pool.free_regions(), 2);
+
+        // Reused regions start out from a fresh instance state.
+        for _ in 0..3 {
+            let instance = Instance::new_with_config(&module, config(), &imports! {})?;
+            assert_eq!(pool.free_regions(), 1);
+            let bump: NativeFunc<(), i32> = instance.get_native_function("bump")?;
+            assert_eq!(bump.call()?, 1);
+            assert_eq!(bump.call()?, 2);
+        }
+        assert_eq!(pool.free_regions(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn host_state_is_reachable_from_host_functions() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (func $tick (import \"env\" \"tick\"))
+      (func (export \"run\")
+        call $tick
+        call $tick))
+",
+        )?;
+
+        #[derive(Clone, Default)]
+        struct CounterEnv {
+            counter: Option<Arc<AtomicU32>>,
+        }
+
+        impl WasmerEnv for CounterEnv {
+            fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+                self.counter = instance.host_state_ref::<Arc<AtomicU32>>().cloned();
+                Ok(())
+            }
+        }
+
+        fn tick(env: &CounterEnv) {
+            env.counter.as_ref().unwrap().fetch_add(1, SeqCst);
+        }
+
+        let counter = Arc::new(AtomicU32::new(0));
+        let instance = Instance::new_with_config(
+            &module,
+            InstanceConfig::default().with_host_state(Arc::new(counter.clone())),
+            &imports! {
+                "env" => {
+                    "tick" => Function::new_native_with_env(&store, CounterEnv::default(), tick),
+                },
+            },
+        )?;
+        assert!(instance.host_state_ref::<u32>().is_none());
+        let run: NativeFunc<(), ()> = instance.get_native_function("run")?;
+        run.call()?;
+        run.call()?;
+        assert_eq!(counter.load(SeqCst), 4);
+        assert_eq!(
+            instance
+                .host_state_ref::<Arc<AtomicU32>>()
+                .unwrap()
+                .load(SeqCst),
+            4
+        );
+
+        let instance = Instance::new(
+            &module,
+            &imports! {
+                "env" => {
+                    "tick" => Function::new_native_with_env(&store, CounterEnv::default(), |_: &CounterEnv| {}),
+                },
+            },
+        )?;
+        assert!(instance.host_state_ref::<Arc<AtomicU32>>().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn host_functions_read_the_instance_clock() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (func $now (import \"env\" \"now\") (result i64))
+      (func (export \"now\") (result i64)
+        call $now))
+",
+        )?;
+
+        #[derive(Clone, Default)]
+        struct ClockEnv {
+            clock: Option<Arc<dyn WasmClock>>,
+        }
+
+        impl WasmerEnv for ClockEnv {
+            fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+                self.clock = Some(instance.clock());
+                Ok(())
+            }
+        }
+
+        fn now(env: &ClockEnv) -> i64 {
+            env.clock.as_ref().unwrap().now_ns() as i64
+        }
+
+        let clock = Arc::new(FakeClock::new(1_000));
+        let instance = Instance::new_with_config(
+            &module,
+            InstanceConfig::default().with_clock(clock.clone()),
+            &imports! {
+                "env" => {
+                    "now" => Function::new_native_with_env(&store, ClockEnv::default(), now),
+                },
+            },
+        )?;
+        let wasm_now: NativeFunc<(), i64> = instance.get_native_function("now")?;
+        assert_eq!(wasm_now.call()?, 1_000);
+        assert_eq!(wasm_now.call()?, 1_000);
+        clock.set_time(5_000);
+        assert_eq!(wasm_now.call()?, 5_000);
+        assert_eq!(instance.clock().now_ns(), 5_000);
+
+        let instance = Instance::new(
+            &module,
+            &imports! {
+                "env" => {
+                    "now" => Function::new_native_with_env(&store, ClockEnv::default(), now),
+                },
+            },
+        )?;
+        let wasm_now: NativeFunc<(), i64> = instance.get_native_function("now")?;
+        assert!(wasm_now.call()? > 1_000_000_000_000_000_000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn start_function_run_explicitly() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (global $initialized (mut i32) (i32.const 0))
+      (func $start
+        global.get $initialized
+        if
+          unreachable
+        end
+        i32.const 1
+        global.set $initialized)
+      (func (export "initialized") (result i32)
+        global.get $initialized)
+      (start $start))
+"#,
+        )?;
+        let initialized = |instance: &Instance| -> Result<i32> {
+            let get: NativeFunc<(), i32> = instance.get_native_function("initialized")?;
+            Ok(get.call()?)
+        };
+
+        let instance = Instance::new(&module, &imports! {})?;
+        assert_eq!(initialized(&instance)?, 1);
+
+        let config = InstanceConfig::default().with_run_start(false);
+        let instance = Instance::new_with_config(&module, config, &imports! {})?;
+        assert_eq!(initialized(&instance)?, 0);
+        instance.run_start()?;
+        assert_eq!(initialized(&instance)?, 1);
+
+        // A trap in the start function is reported as a call would.
+        let trap = instance.run_start().unwrap_err();
+        assert_eq!(trap.to_trap(), Some(vm::TrapCode::UnreachableCodeReached));
+        Ok(())
+    }
+
+    #[test]
+    fn shared_resolvers() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (import "env" "base" (global $base i32))
+      (func (export "get") (result i32)
+        global.get $base))
+"#,
+        )?;
+        let imports = Arc::new(imports! {
+            "env" => {
+                "base" => Global::new(&store, Value::I32(42)),
+            },
+        });
+        let shared: Arc<dyn NamedResolver + Send + Sync> = imports.clone();
+        for resolver in [&imports as &dyn Resolver, &shared, &shared.clone()] {
+            let instance = Instance::new(&module, resolver)?;
+            let get: NativeFunc<(), i32> = instance.get_native_function("get")?;
+            assert_eq!(get.call()?, 42);
+        }
+        let chained = shared.chain_back(imports! {});
+        assert!(Instance::new(&module, &chained).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn remapped_imports_resolve_to_new_names() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (import "env" "log_v1" (func $log (param i32)))
+      (import "env" "base" (global $base i32))
+      (func (export "run")
+        global.get $base
+        call $log))
+"#,
+        )?;
+
+        #[derive(WasmerEnv, Clone, Default)]
+        struct LogEnv {
+            logged: Arc<AtomicU32>,
+        }
+        let env = LogEnv::default();
+        let host = imports! {
+            "env" => {
+                "log" => Function::new_native_with_env(&store, env.clone(), |env: &LogEnv, value: u32| {
+                    env.logged.store(value, SeqCst);
+                }),
+            },
+        };
+        let globals = imports! {
+            "env" => {
+                "base" => Global::new(&store, Value::I32(42)),
+            },
+        };
+
+        // The import isn't found under its own name.
+        assert!(Instance::new(&module, &host.clone().chain_back(globals.clone())).is_err());
+
+        let resolver = RemappingResolver::new(host)
+            .remap("env", "log_v1", "env", "log")
+            .chain_back(globals);
+        let instance = Instance::new(&module, &resolver)?;
+        let run: NativeFunc<(), ()> = instance.get_native_function("run")?;
+        run.call()?;
+        assert_eq!(env.logged.load(SeqCst), 42);
+
+        Ok(())
+    }
+
+    #[test]
+    fn initialized_function_envs_are_reachable() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (import "env" "tick" (func))
+      (import "env" "plain" (func))
+      (import "env" "base" (global i32)))
+"#,
+        )?;
+
+        #[derive(Clone, Default)]
+        struct InitEnv {
+            initialized: bool,
+        }
+
+        impl WasmerEnv for InitEnv {
+            fn init_with_instance(&mut self, _: &Instance) -> Result<(), HostEnvInitError> {
+                self.initialized = true;
+                Ok(())
+            }
+        }
+
+        let instance = Instance::new(
+            &module,
+            &imports! {
+                "env" => {
+                    "tick" => Function::new_native_with_env(&store, InitEnv::default(), |_: &InitEnv| {}),
+                    "plain" => Function::new_native(&store, || {}),
+                    "base" => Global::new(&store, Value::I32(0)),
+                },
+            },
+        )?;
+        let env = instance.get_function_env_ptr("env", "tick").unwrap();
+        assert!(unsafe { &*env.cast::<InitEnv>() }.initialized);
+        assert!(instance.get_function_env_ptr("env", "plain").is_none());
+        assert!(instance.get_function_env_ptr("env", "base").is_none());
+        assert!(instance.get_function_env_ptr("env", "missing").is_none());
+
+        Ok(())
+    }
 }
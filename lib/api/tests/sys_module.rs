@@ -92,4 +92,120 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn named_functions_come_from_the_name_section() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+            (module
+              (import "env" "log" (func $log (param i32)))
+              (func $helper (result i32) i32.const 1)
+              (func (export "run") (result i32) call $helper)
+              (func $square (param i32) (result i32)
+                local.get 0
+                local.get 0
+                i32.mul))
+            "#,
+        )?;
+        let imports = imports! {
+            "env" => {
+                "log" => Function::new_native(&store, |_: i32| {}),
+            },
+        };
+        let instance = Instance::new(&module, &imports)?;
+        let names = instance
+            .named_functions()
+            .into_iter()
+            .map(|f| (f.index.as_u32(), f.export_name, f.debug_name))
+            .collect::<Vec<_>>();
+        let name = |name: &str| Some(name.to_string());
+        assert_eq!(
+            names,
+            [
+                (0, None, name("log")),
+                (1, None, name("helper")),
+                (2, name("run"), None),
+                (3, None, name("square")),
+            ]
+        );
+
+        let module = Module::new(&store, "(module (func (nop)))")?;
+        assert_eq!(module.function_names().count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_names_are_unique() -> Result<()> {
+        let store = Store::default();
+        // Two functions, both exported as `f`.
+        let duplicate = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // types: () -> ()
+            0x03, 0x03, 0x02, 0x00, 0x00, // functions: 2 of type 0
+            0x07, 0x09, 0x02, 0x01, 0x66, 0x00, 0x00, 0x01, 0x66, 0x00, 0x01, // exports
+            0x0a, 0x07, 0x02, 0x02, 0x00, 0x0b, 0x02, 0x00, 0x0b, // code
+        ];
+        assert!(Module::validate(&store, &duplicate).is_err());
+        // Translation rejects them too, for modules that skipped validation.
+        assert!(matches!(
+            ModuleEnvironment::new().translate(&duplicate),
+            Err(WasmError::Generic(message)) if message.contains("`f`")
+        ));
+
+        // A single function exported under several names is fine, and every
+        // name resolves to it.
+        let module = Module::new(
+            &store,
+            r#"(module
+                (func $f (result i32) (i32.const 7))
+                (export "b" (func $f))
+                (export "a" (func $f))
+                (export "c" (func $f)))"#,
+        )?;
+        let names = module
+            .artifact()
+            .exports()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"]);
+        let instance = Instance::new(&module, &imports! {})?;
+        let address = instance.get_function_address("a");
+        assert!(address.is_some());
+        for name in names {
+            assert_eq!(instance.get_function_address(name), address);
+            let f: NativeFunc<(), i32> = instance.get_native_function(name)?;
+            assert_eq!(f.call()?, 7);
+        }
+        let functions = instance.named_functions();
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].export_name.as_deref(), Some("a"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn warmed_up_artifacts_run() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+                (func $double (param i32) (result i32)
+                    (i32.add (local.get 0) (local.get 0)))
+                (func (export "run") (param i32) (result i32)
+                    (call $double (call $double (local.get 0)))))"#,
+        )?;
+        module.artifact().warm_up();
+        module.artifact().warm_up();
+        let instance = Instance::new(&module, &imports! {})?;
+        let run: NativeFunc<i32, i32> = instance.get_native_function("run")?;
+        assert_eq!(run.call(3)?, 12);
+
+        let module = Module::new(&store, "(module)")?;
+        module.artifact().warm_up();
+
+        Ok(())
+    }
 }
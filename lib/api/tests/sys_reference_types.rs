@@ -6,6 +6,31 @@ mod sys {
     use std::sync::Arc;
     use wasmer::*;
 
+    #[test]
+    fn ref_func_called_indirectly() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (type $ret_i32_ty (func (result i32)))
+    (table $table 2 2 funcref)
+    (elem declare func $one $two)
+
+    (func $one (result i32) (i32.const 1))
+    (func $two (result i32) (i32.const 2))
+    (func (export "call") (param $idx i32) (result i32)
+          (table.set $table (i32.const 0) (ref.func $one))
+          (table.set $table (i32.const 1) (ref.func $two))
+          (call_indirect $table (type $ret_i32_ty) (local.get $idx)))
+)"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let call: NativeFunc<i32, i32> = instance.get_native_function("call")?;
+        assert_eq!(call.call(0)?, 1);
+        assert_eq!(call.call(1)?, 2);
+        assert_eq!(call.call(0)?, 1);
+
+        Ok(())
+    }
+
     #[test]
     #[cfg_attr(feature = "singlepass", ignore)] // singlepass does not support funcref args.
     fn func_ref_passed_and_returned() -> Result<()> {
@@ -24,7 +49,7 @@ mod sys {
         let module = Module::new(&store, wat)?;
         let func_ref_identity = Function::new(
             &store,
-            FunctionType::new(vec![Type::FuncRef], vec![Type::FuncRef]),
+            FunctionType::parse("(param funcref) (result funcref)")?,
             |values| -> Result<Vec<_>, _> { Ok(vec![values[0].clone()]) },
         );
         let imports = imports! {
@@ -130,6 +155,85 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn func_ref_index_recovered_by_host() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (func $log (import "env" "log") (param funcref))
+    (func $first)
+    (func $second)
+    (elem declare func $log $first $second)
+    (func (export "log_second")
+          (call $log (ref.func $second)))
+    (func (export "log_import")
+          (call $log (ref.func $log)))
+)"#;
+        let module = Module::new(&store, wat)?;
+
+        let logged = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = {
+            let logged = Arc::clone(&logged);
+            Function::new(
+                &store,
+                FunctionType::new(vec![Type::FuncRef], vec![]),
+                move |values| {
+                    let f = values[0].unwrap_funcref().as_ref().unwrap();
+                    logged.lock().unwrap().push(f.function_index());
+                    Ok(vec![])
+                },
+            )
+        };
+        let instance = Instance::new(&module, &imports! { "env" => { "log" => log } })?;
+
+        let log_second: NativeFunc<(), ()> = instance.get_native_function("log_second")?;
+        log_second.call()?;
+        let log_import: NativeFunc<(), ()> = instance.get_native_function("log_import")?;
+        log_import.call()?;
+        // `log` is a host function, it has no index.
+        assert_eq!(
+            *logged.lock().unwrap(),
+            [Some(FunctionIndex::from_u32(2)), None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn func_ref_index_and_instance() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (func $host (import "env" "host"))
+    (func $first)
+    (func $second)
+    (table (export "table") 3 funcref)
+    (elem (i32.const 0) $second $host $first)
+)"#;
+        let module = Module::new(&store, wat)?;
+        let imports = imports! { "env" => { "host" => Function::new_native(&store, || {}) } };
+        let instance = Instance::new(&module, &imports)?;
+        let other = Instance::new(&module, &imports)?;
+        let table = match Extern::from_vm_export(&store, instance.lookup("table").unwrap()) {
+            Extern::Table(table) => table,
+            _ => panic!("not a table"),
+        };
+        let function = |index| match table.get(index) {
+            Some(Value::FuncRef(Some(f))) => f,
+            _ => panic!("not a function"),
+        };
+
+        let second = function(0);
+        assert_eq!(second.function_index(), Some(FunctionIndex::from_u32(2)));
+        assert!(second.is_defined_by(&instance));
+        assert!(!second.is_defined_by(&other));
+        let host = function(1);
+        assert_eq!(host.function_index(), None);
+        assert!(!host.is_defined_by(&instance));
+        assert_eq!(
+            function(2).function_index(),
+            Some(FunctionIndex::from_u32(1))
+        );
+        Ok(())
+    }
+
     #[cfg(feature = "experimental-reference-types-extern-ref")]
     #[test]
     #[cfg_attr(feature = "singlepass", ignore)] // singlepass does not support funcref args.
@@ -11,6 +11,12 @@ pub struct Validate {
     #[structopt(name = "FILE", parse(from_os_str))]
     path: PathBuf,
 
+    /// Validate against exactly these features, comma separated
+    /// (e.g. `bulk_memory,multi_value`), instead of the ones of the engine.
+    #[cfg(feature = "compiler")]
+    #[structopt(long, use_delimiter = true)]
+    features: Option<Vec<String>>,
+
     #[structopt(flatten)]
     store: StoreOptions,
 }
@@ -22,12 +28,37 @@ impl Validate {
             .context(format!("failed to validate `{}`", self.path.display()))
     }
     fn inner_execute(&self) -> Result<()> {
-        let (store, _engine_type, _compiler_type) = self.store.get_store()?;
         let module_contents = std::fs::read(&self.path)?;
         if !is_wasm(&module_contents) {
             bail!("`wasmer validate` only validates WebAssembly files");
         }
+        #[cfg(feature = "compiler")]
+        if let Some(names) = &self.features {
+            let features = Features::from_names(names.iter().map(String::as_str))?;
+            wasmer_compiler::validate_module(&features, &module_contents)?;
+            self.report(&module_contents)?;
+            return Ok(());
+        }
+        let (store, _engine_type, _compiler_type) = self.store.get_store()?;
         Module::validate(&store, &module_contents)?;
+        self.report(&module_contents)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "compiler")]
+    fn report(&self, module_contents: &[u8]) -> Result<()> {
+        eprintln!("Validation passed for `{}`.", self.path.display());
+        let features = wasmer_compiler::required_features(module_contents)?.enabled();
+        if features.is_empty() {
+            eprintln!("No feature is used.");
+        } else {
+            eprintln!("Features used: {}.", features.join(", "));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "compiler"))]
+    fn report(&self, _module_contents: &[u8]) -> Result<()> {
         eprintln!("Validation passed for `{}`.", self.path.display());
         Ok(())
     }
@@ -25,6 +25,7 @@ use wasmer_compiler::{CallingConvention, ModuleTranslationState, Target};
 use wasmer_compiler::{
     Compilation, CompileModuleInfo, CompiledFunction, CompiledFunctionFrameInfo,
     CompiledFunctionUnwindInfo, Compiler, Dwarf, FunctionBody, FunctionBodyData, SectionIndex,
+    TraceInfo,
 };
 use wasmer_compiler::{
     CustomSection, CustomSectionProtection, Relocation, RelocationKind, RelocationTarget,
@@ -53,6 +54,10 @@ impl CraneliftCompiler {
 }
 
 impl Compiler for CraneliftCompiler {
+    fn name(&self) -> &str {
+        "cranelift"
+    }
+
     /// Compile the module using Cranelift, producing a compilation result with
     /// associated relocations.
     fn compile_module(
@@ -219,6 +224,7 @@ impl Compiler for CraneliftCompiler {
                             address_map,
                             traps: trap_sink.traps,
                         },
+                        trace_info: TraceInfo::default(),
                     },
                     fde,
                 ))
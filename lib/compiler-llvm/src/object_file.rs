@@ -7,7 +7,7 @@ use std::num::TryFromIntError;
 use wasmer_compiler::{
     CompileError, CompiledFunctionFrameInfo, CustomSection, CustomSectionProtection,
     CustomSections, FunctionAddressMap, FunctionBody, InstructionAddressMap, Relocation,
-    RelocationKind, RelocationTarget, SectionBody, SectionIndex, SourceLoc,
+    RelocationKind, RelocationTarget, SectionBody, SectionIndex, SourceLoc, TraceInfo,
 };
 use wasmer_types::entity::{PrimaryMap, SecondaryMap};
 use wasmer_vm::libcalls::LibCall;
@@ -348,6 +348,7 @@ where
                 address_map,
                 traps: vec![],
             },
+            trace_info: TraceInfo::default(),
         },
         custom_sections,
         eh_frame_section_indices,
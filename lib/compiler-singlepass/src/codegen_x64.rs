@@ -10,10 +10,10 @@ use wasmer_compiler::wasmparser::{
     MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
 };
 use wasmer_compiler::{
-    CallingConvention, CompiledFunction, CompiledFunctionFrameInfo, CustomSection,
+    CallingConvention, CompiledFunction, CompiledFunctionFrameInfo, CpuFeature, CustomSection,
     CustomSectionProtection, FunctionBody, FunctionBodyData, InstructionAddressMap,
     ModuleTranslationState, Relocation, RelocationKind, RelocationTarget, SectionBody,
-    SectionIndex, SourceLoc,
+    SectionIndex, SourceLoc, Target, TraceInfo, TrapInformation,
 };
 use wasmer_types::{
     entity::{EntityRef, PrimaryMap, SecondaryMap},
@@ -98,8 +98,20 @@ pub(crate) struct FuncGen<'a> {
     // Ordered by increasing InstructionAddressMap::srcloc.
     instructions_address_map: Vec<InstructionAddressMap>,
 
+    /// Native offset at which the code of every operator starts.
+    trace_info: TraceInfo,
+
+    /// Trap codes whose special label is jumped to.
+    used_traps: Vec<TrapCode>,
+
+    /// The trap sites of the function, in ascending code offset order.
+    trap_sites: Vec<TrapInformation>,
+
     /// Calling convention to use.
     calling_convention: CallingConvention,
+
+    /// The target the code is generated for.
+    target: &'a Target,
 }
 
 struct SpecialLabelSet {
@@ -112,6 +124,9 @@ struct SpecialLabelSet {
     bad_signature: DynamicLabel,
     gas_limit_exceeded: DynamicLabel,
     stack_overflow: DynamicLabel,
+    interrupted: DynamicLabel,
+    #[cfg(debug_assertions)]
+    stack_mismatch: DynamicLabel,
 }
 
 /// Metadata about a floating-point value.
@@ -263,6 +278,8 @@ impl<'a> FuncGen<'a> {
     /// Set the source location of the Wasm to the given offset.
     pub(crate) fn set_srcloc(&mut self, offset: u32) {
         self.src_loc = offset;
+        self.trace_info
+            .push(self.assembler.get_offset().0 as u32, offset);
     }
 
     fn get_location_released(&mut self, loc: Location) -> Location {
@@ -405,14 +422,14 @@ impl<'a> FuncGen<'a> {
         let signature_index = self.module.functions[function_index];
         let signature = &self.module.signatures[signature_index];
         // Returns None if not imported.
-        let import_name = self
+        let (module_name, field) = self
             .module_translation_state
             .import_map
             .get(&function_index)?;
         // TODO: can keep intrinsics in above map, but not sure if we'll have
         //   significant amount of them to make it important.
         for intrinsic in &self.config.intrinsics {
-            if intrinsic.name == *import_name
+            if intrinsic.is_named(module_name, field)
                 && intrinsic.signature == *signature
                 && intrinsic.is_params_ok(params)
             {
@@ -449,32 +466,57 @@ impl<'a> FuncGen<'a> {
                     Location::GPR(base_reg),
                 );
                 let current_burnt_reg = self.machine.acquire_temp_gpr().unwrap();
-                // Read current gas counter.
-                self.assembler.emit_mov(
-                    Size::S64,
-                    Location::Memory(base_reg, counter_offset),
-                    Location::GPR(current_burnt_reg),
-                );
-                // Read opcode cost.
+                // Read opcode cost. It isn't inlined, so that it can be changed
+                // through `FastGasCounter::set_cost_multiplier`.
                 let count_reg = self.machine.acquire_temp_gpr().unwrap();
                 self.assembler.emit_mov(
                     Size::S64,
                     Location::Memory(base_reg, opcode_cost_offset),
                     Location::GPR(count_reg),
                 );
-                // Multiply instruction count by opcode cost.
-                match count_location {
-                    Location::Imm32(imm) => self.assembler.emit_imul_imm32_gpr64(imm, count_reg),
-                    _ => assert!(false),
+                let saturate = self.assembler.get_label();
+                let count = match count_location {
+                    Location::Imm32(imm) => imm,
+                    _ => unreachable!(),
+                };
+                if self.config.saturate_gas && count > 1 {
+                    // The unsigned product overflows iff the cost exceeds
+                    // `u64::MAX / count`. No temporary is left for the bound,
+                    // so it goes through the counter register before the
+                    // counter is loaded.
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Imm64(u64::MAX / u64::from(count)),
+                        Location::GPR(current_burnt_reg),
+                    );
+                    self.assembler.emit_cmp(
+                        Size::S64,
+                        Location::GPR(current_burnt_reg),
+                        Location::GPR(count_reg),
+                    );
+                    self.assembler.emit_jmp(Condition::Above, saturate);
                 }
+                // Read current gas counter.
+                self.assembler.emit_mov(
+                    Size::S64,
+                    Location::Memory(base_reg, counter_offset),
+                    Location::GPR(current_burnt_reg),
+                );
+                // Multiply instruction count by opcode cost.
+                self.assembler.emit_imul_imm32_gpr64(count, count_reg);
                 // Compute new cost.
                 self.assembler.emit_add(
                     Size::S64,
                     Location::GPR(count_reg),
                     Location::GPR(current_burnt_reg),
                 );
-                self.assembler
-                    .emit_jmp(Condition::Overflow, self.special_labels.integer_overflow);
+                if self.config.saturate_gas {
+                    // The counter is unsigned, so it overflows on carry.
+                    self.assembler.emit_jmp(Condition::Carry, saturate);
+                } else {
+                    let label = self.trap_label(TrapCode::IntegerOverflow);
+                    self.assembler.emit_jmp(Condition::Overflow, label);
+                }
                 // Compare with the limit.
                 self.assembler.emit_cmp(
                     Size::S64,
@@ -487,10 +529,26 @@ impl<'a> FuncGen<'a> {
                     Location::GPR(current_burnt_reg),
                     Location::Memory(base_reg, counter_offset),
                 );
-                self.assembler.emit_jmp(
-                    Condition::BelowEqual,
-                    self.special_labels.gas_limit_exceeded,
-                );
+                let label = self.trap_label(TrapCode::GasExceeded);
+                self.assembler.emit_jmp(Condition::BelowEqual, label);
+                if self.config.saturate_gas {
+                    // Clamp the counter to the limit on overflow, so that the
+                    // next charge traps with `GasExceeded`.
+                    let done = self.assembler.get_label();
+                    self.assembler.emit_jmp(Condition::None, done);
+                    self.assembler.emit_label(saturate);
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(base_reg, gas_limit_offset),
+                        Location::GPR(current_burnt_reg),
+                    );
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::GPR(current_burnt_reg),
+                        Location::Memory(base_reg, counter_offset),
+                    );
+                    self.assembler.emit_label(done);
+                }
                 self.machine.release_temp_gpr(base_reg);
                 self.machine.release_temp_gpr(current_burnt_reg);
                 self.machine.release_temp_gpr(count_reg);
@@ -499,6 +557,35 @@ impl<'a> FuncGen<'a> {
         Ok(())
     }
 
+    /// Return the special label raising `code`, for jumping to it.
+    fn trap_label(&mut self, code: TrapCode) -> DynamicLabel {
+        if !self.used_traps.contains(&code) {
+            self.used_traps.push(code);
+        }
+        let labels = &self.special_labels;
+        match code {
+            TrapCode::IntegerDivisionByZero => labels.integer_division_by_zero,
+            TrapCode::IntegerOverflow => labels.integer_overflow,
+            TrapCode::BadConversionToInteger => labels.bad_conversion_to_integer,
+            TrapCode::HeapAccessOutOfBounds => labels.heap_access_oob,
+            TrapCode::TableAccessOutOfBounds => labels.table_access_oob,
+            TrapCode::IndirectCallToNull => labels.indirect_call_null,
+            TrapCode::BadSignature => labels.bad_signature,
+            TrapCode::GasExceeded => labels.gas_limit_exceeded,
+            TrapCode::WasmStackOverflow => labels.stack_overflow,
+            TrapCode::Interrupted => labels.interrupted,
+            _ => unreachable!("no special label for {:?}", code),
+        }
+    }
+
+    /// Record a trap site raising `code` at the current offset.
+    fn record_trap(&mut self, code: TrapCode) {
+        self.trap_sites.push(TrapInformation {
+            code_offset: self.assembler.get_offset().0 as u32,
+            trap_code: code,
+        });
+    }
+
     fn emit_trap(&mut self, code: TrapCode) {
         let label = self.assembler.get_label();
         self.assembler.emit_label(label);
@@ -511,6 +598,18 @@ impl<'a> FuncGen<'a> {
             Location::Imm32(code as u32),
             Machine::get_param_location(1, self.calling_convention),
         );
+        // Pass the frame pointer, so that the handler can walk the wasm frames.
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::GPR(GPR::RBP),
+            Machine::get_param_location(2, self.calling_convention),
+        );
+        // And the vmctx, so that it can tell which instance trapped.
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::GPR(Machine::get_vmctx_reg()),
+            Machine::get_param_location(3, self.calling_convention),
+        );
         // Align stack.
         self.assembler.emit_and(
             Size::S64,
@@ -572,10 +671,8 @@ impl<'a> FuncGen<'a> {
     /// Moves `loc` to a valid location for `div`/`idiv`.
     fn emit_relaxed_xdiv(&mut self, signed: bool, sz: Size, loc: Location) {
         self.assembler.emit_cmp(sz, Location::Imm32(0), loc);
-        self.assembler.emit_jmp(
-            Condition::Equal,
-            self.special_labels.integer_division_by_zero,
-        );
+        let label = self.trap_label(TrapCode::IntegerDivisionByZero);
+        self.assembler.emit_jmp(Condition::Equal, label);
 
         // Boundary checks for integer overflow. It clearly doesn't make sense for
         // unsigned division, as numerator is of same size as the actual result, and divisor is
@@ -609,8 +706,8 @@ impl<'a> FuncGen<'a> {
                 _ => assert!(false),
             }
             self.assembler.emit_jmp(Condition::NotEqual, end);
-            self.assembler
-                .emit_jmp(Condition::None, self.special_labels.integer_overflow);
+            let label = self.trap_label(TrapCode::IntegerOverflow);
+            self.assembler.emit_jmp(Condition::None, label);
             self.assembler.emit_label(end);
         }
 
@@ -711,6 +808,12 @@ impl<'a> FuncGen<'a> {
                 RelaxMode::Direct
             }
             _ if (op as *const u8 == Assembler::emit_imul as *const u8) => RelaxMode::BothToGPR,
+            // `xchg` writes back to its source, which can't be an immediate.
+            (Location::Imm32(_), _) | (Location::Imm64(_), _)
+                if (op as *const u8 == Assembler::emit_xchg as *const u8) =>
+            {
+                RelaxMode::SrcToGPR
+            }
 
             (Location::Memory(_, _), Location::Memory(_, _)) => RelaxMode::SrcToGPR,
             (Location::Imm64(_), Location::Imm64(_)) | (Location::Imm64(_), Location::Imm32(_)) => {
@@ -1098,6 +1201,106 @@ impl<'a> FuncGen<'a> {
         Ok(())
     }
 
+    /// `clz` with operand popped from the virtual stack.
+    fn emit_clz(&mut self, sz: Size) -> Result<(), CodegenError> {
+        self.emit_xzcnt(sz, true)
+    }
+
+    /// `ctz` with operand popped from the virtual stack.
+    fn emit_ctz(&mut self, sz: Size) -> Result<(), CodegenError> {
+        self.emit_xzcnt(sz, false)
+    }
+
+    /// Count the leading or trailing zeros of the operand popped from the
+    /// virtual stack.
+    ///
+    /// `lzcnt` and `tzcnt` are only used if the target supports them: on
+    /// CPUs without LZCNT or BMI1 they silently decode as `bsr` and `bsf`,
+    /// which count from the other end and leave their destination undefined
+    /// for a zero input. `bsr` and `bsf` with an explicit zero check are
+    /// used otherwise.
+    fn emit_xzcnt(&mut self, sz: Size, leading: bool) -> Result<(), CodegenError> {
+        let (ty, bits) = match sz {
+            Size::S32 => (WpType::I32, 32),
+            Size::S64 => (WpType::I64, 64),
+            _ => {
+                return Err(CodegenError {
+                    message: "emit_xzcnt sz: unreachable code".to_string(),
+                })
+            }
+        };
+        let loc = self.pop_value_released();
+        let src = match loc {
+            Location::Imm64(_) | Location::Imm32(_) | Location::Memory(_, _) => {
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                self.assembler.emit_mov(sz, loc, Location::GPR(tmp));
+                tmp
+            }
+            Location::GPR(reg) => reg,
+            _ => {
+                return Err(CodegenError {
+                    message: "emit_xzcnt src: unreachable code".to_string(),
+                })
+            }
+        };
+
+        let ret = self
+            .machine
+            .acquire_locations(&mut self.assembler, &[(ty)], false)[0];
+        self.value_stack.push(ret);
+
+        let dst = match ret {
+            Location::Memory(_, _) => self.machine.acquire_temp_gpr().unwrap(),
+            Location::GPR(reg) => reg,
+            _ => {
+                return Err(CodegenError {
+                    message: "emit_xzcnt dst: unreachable code".to_string(),
+                })
+            }
+        };
+
+        let cpu_features = self.target.cpu_features();
+        if leading && cpu_features.contains(CpuFeature::LZCNT) {
+            self.assembler
+                .emit_lzcnt(sz, Location::GPR(src), Location::GPR(dst));
+        } else if !leading && cpu_features.contains(CpuFeature::BMI1) {
+            self.assembler
+                .emit_tzcnt(sz, Location::GPR(src), Location::GPR(dst));
+        } else {
+            let zero_path = self.assembler.get_label();
+            let end = self.assembler.get_label();
+
+            self.assembler.emit_test_gpr_64(src);
+            self.assembler.emit_jmp(Condition::Equal, zero_path);
+            if leading {
+                self.assembler
+                    .emit_bsr(sz, Location::GPR(src), Location::GPR(dst));
+                self.assembler
+                    .emit_xor(sz, Location::Imm32(bits - 1), Location::GPR(dst));
+            } else {
+                self.assembler
+                    .emit_bsf(sz, Location::GPR(src), Location::GPR(dst));
+            }
+            self.assembler.emit_jmp(Condition::None, end);
+            self.assembler.emit_label(zero_path);
+            self.assembler
+                .emit_mov(sz, Location::Imm32(bits), Location::GPR(dst));
+            self.assembler.emit_label(end);
+        }
+
+        match loc {
+            Location::Imm64(_) | Location::Imm32(_) | Location::Memory(_, _) => {
+                self.machine.release_temp_gpr(src);
+            }
+            _ => {}
+        };
+        if let Location::Memory(_, _) = ret {
+            self.assembler.emit_mov(sz, Location::GPR(dst), ret);
+            self.machine.release_temp_gpr(dst);
+        };
+        Ok(())
+    }
+
     /// I32 shift with both operands popped from the virtual stack.
     fn emit_shift_i32(&mut self, f: fn(&mut Assembler, Size, Location, Location)) {
         let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
@@ -1372,6 +1575,17 @@ impl<'a> FuncGen<'a> {
         Ok(())
     }
 
+    /// The instruction atomic stores are lowered to. Only a shared memory
+    /// can be observed by other threads, so only those need the implicitly
+    /// locked `xchg`, a plain `mov` is enough for the others.
+    fn atomic_store_op(&self) -> fn(&mut Assembler, Size, Location, Location) {
+        if self.module.memories[MemoryIndex::new(0)].shared {
+            Assembler::emit_xchg
+        } else {
+            Assembler::emit_mov
+        }
+    }
+
     /// Emits a memory operation.
     fn emit_memory_op<F: FnOnce(&mut Self, GPR) -> Result<(), CodegenError>>(
         &mut self,
@@ -1449,8 +1663,8 @@ impl<'a> FuncGen<'a> {
             );
 
             // Trap if offset calculation overflowed.
-            self.assembler
-                .emit_jmp(Condition::Carry, self.special_labels.heap_access_oob);
+            let label = self.trap_label(TrapCode::HeapAccessOutOfBounds);
+            self.assembler.emit_jmp(Condition::Carry, label);
         }
 
         // Wasm linear memory -> real memory
@@ -1463,8 +1677,8 @@ impl<'a> FuncGen<'a> {
                 .emit_cmp(Size::S64, Location::GPR(tmp_bound), Location::GPR(tmp_addr));
 
             // `tmp_bound` is inclusive. So trap only if `tmp_addr > tmp_bound`.
-            self.assembler
-                .emit_jmp(Condition::Above, self.special_labels.heap_access_oob);
+            let label = self.trap_label(TrapCode::HeapAccessOutOfBounds);
+            self.assembler.emit_jmp(Condition::Above, label);
         }
 
         self.machine.release_temp_gpr(tmp_bound);
@@ -1483,8 +1697,8 @@ impl<'a> FuncGen<'a> {
                 Location::Imm32((align - 1).into()),
                 Location::GPR(tmp_aligncheck),
             );
-            self.assembler
-                .emit_jmp(Condition::NotEqual, self.special_labels.heap_access_oob);
+            let label = self.trap_label(TrapCode::HeapAccessOutOfBounds);
+            self.assembler.emit_jmp(Condition::NotEqual, label);
             self.machine.release_temp_gpr(tmp_aligncheck);
         }
 
@@ -1612,8 +1826,8 @@ impl<'a> FuncGen<'a> {
 
     // Checks for underflow/overflow/nan before IxxTrunc{U/S}F32.
     fn emit_f32_int_conv_check_trap(&mut self, reg: XMM, lower_bound: f32, upper_bound: f32) {
-        let trap_overflow = self.special_labels.integer_overflow;
-        let trap_badconv = self.special_labels.bad_conversion_to_integer;
+        let trap_overflow = self.trap_label(TrapCode::IntegerOverflow);
+        let trap_badconv = self.trap_label(TrapCode::BadConversionToInteger);
         let end = self.assembler.get_label();
 
         self.emit_f32_int_conv_check(
@@ -1748,8 +1962,8 @@ impl<'a> FuncGen<'a> {
 
     // Checks for underflow/overflow/nan before IxxTrunc{U/S}F64.
     fn emit_f64_int_conv_check_trap(&mut self, reg: XMM, lower_bound: f64, upper_bound: f64) {
-        let trap_overflow = self.special_labels.integer_overflow;
-        let trap_badconv = self.special_labels.bad_conversion_to_integer;
+        let trap_overflow = self.trap_label(TrapCode::IntegerOverflow);
+        let trap_badconv = self.trap_label(TrapCode::BadConversionToInteger);
         let end = self.assembler.get_label();
 
         self.emit_f64_int_conv_check(
@@ -1837,8 +2051,8 @@ impl<'a> FuncGen<'a> {
             // TODO: make it cleaner, now we assume instruction with 32-bit immediate at the end.
             // Recheck offsets, if change above instruction to anything else.
             self.stack_check_offset = AssemblyOffset(self.assembler.offset().0 - 4);
-            self.assembler
-                .emit_jmp(Condition::Signed, self.special_labels.stack_overflow);
+            let label = self.trap_label(TrapCode::WasmStackOverflow);
+            self.assembler.emit_jmp(Condition::Signed, label);
         } else {
             {
                 // Patch earlier stack checker with now known max stack depth.
@@ -1859,6 +2073,24 @@ impl<'a> FuncGen<'a> {
         }
     }
 
+    /// Trap with `TrapCode::Interrupted` if the interrupt flag of the instance is set.
+    fn emit_interrupt_check(&mut self) {
+        let flag = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_interrupt_flag_pointer() as i32,
+            ),
+            Location::GPR(flag),
+        );
+        self.assembler
+            .emit_cmp(Size::S8, Location::Imm32(0), Location::Memory(flag, 0));
+        let label = self.trap_label(TrapCode::Interrupted);
+        self.assembler.emit_jmp(Condition::NotEqual, label);
+        self.machine.release_temp_gpr(flag);
+    }
+
     fn emit_function_stack_check(&mut self, enter: bool) {
         // `local_types` include parameters as well.
         let depth = self.local_types.len()
@@ -1868,6 +2100,46 @@ impl<'a> FuncGen<'a> {
             // along with spill area for callee-saved registers.
             + 4;
         self.emit_stack_check(enter, depth);
+        if self.config.limit_call_depth {
+            self.emit_call_depth_check(enter);
+        }
+    }
+
+    /// Count the frame of the function in the remaining call depth of the
+    /// instance, trapping once it is exhausted.
+    fn emit_call_depth_check(&mut self, enter: bool) {
+        let call_depth = Location::Memory(
+            Machine::get_vmctx_reg(),
+            self.vmoffsets.vmctx_call_depth_begin() as i32,
+        );
+        if enter {
+            self.assembler
+                .emit_sub(Size::S32, Location::Imm32(1), call_depth);
+            let label = self.trap_label(TrapCode::WasmStackOverflow);
+            self.assembler.emit_jmp(Condition::Signed, label);
+        } else {
+            self.assembler
+                .emit_add(Size::S32, Location::Imm32(1), call_depth);
+        }
+    }
+
+    /// Traps if `RSP` is not where the machine state expects it to be, that
+    /// is just below the slots acquired so far and the 32 bytes reserved by
+    /// `emit_head`.
+    ///
+    /// Clobbers RCX, RAX being possibly taken by the return value.
+    #[cfg(debug_assertions)]
+    fn emit_stack_state_check(&mut self) {
+        let expected = -((self.machine.get_stack_offset() + 32) as i32);
+        self.assembler.emit_lea(
+            Size::S64,
+            Location::Memory(GPR::RBP, expected),
+            Location::GPR(GPR::RCX),
+        );
+        self.assembler
+            .emit_cmp(Size::S64, Location::GPR(GPR::RCX), Location::GPR(GPR::RSP));
+        self.assembler
+            .emit_jmp(Condition::NotEqual, self.special_labels.stack_mismatch);
     }
 
     fn emit_head(&mut self) -> Result<(), CodegenError> {
@@ -1926,6 +2198,7 @@ impl<'a> FuncGen<'a> {
         local_func_index: LocalFunctionIndex,
         local_types_excluding_arguments: &[WpType],
         calling_convention: CallingConvention,
+        target: &'a Target,
     ) -> Result<FuncGen<'a>, CodegenError> {
         let func_index = module.func_index(local_func_index);
         let sig_index = module.functions[func_index];
@@ -1949,6 +2222,9 @@ impl<'a> FuncGen<'a> {
             bad_signature: assembler.get_label(),
             gas_limit_exceeded: assembler.get_label(),
             stack_overflow: assembler.get_label(),
+            interrupted: assembler.get_label(),
+            #[cfg(debug_assertions)]
+            stack_mismatch: assembler.get_label(),
         };
 
         let mut fg = FuncGen {
@@ -1972,7 +2248,11 @@ impl<'a> FuncGen<'a> {
             special_labels,
             src_loc: 0,
             instructions_address_map: vec![],
+            trace_info: TraceInfo::default(),
+            used_traps: vec![],
+            trap_sites: vec![],
             calling_convention,
+            target,
         };
         fg.emit_head()?;
         Ok(fg)
@@ -1985,6 +2265,18 @@ impl<'a> FuncGen<'a> {
     pub(crate) fn feed_operator(&mut self, op: Operator) -> Result<(), CodegenError> {
         assert!(self.fp_stack.len() <= self.value_stack.len());
 
+        if let Some(limit) = self.config.max_operand_stack_depth {
+            if self.value_stack.len() > limit {
+                return Err(CodegenError {
+                    message: format!(
+                        "operand stack depth {} exceeds the configured limit of {}",
+                        self.value_stack.len(),
+                        limit
+                    ),
+                });
+            }
+        }
+
         let was_unreachable;
 
         if self.unreachable_depth > 0 {
@@ -2287,134 +2579,8 @@ impl<'a> FuncGen<'a> {
             Operator::I32Eqz => {
                 self.emit_cmpop_i32_dynamic_b(Condition::Equal, Location::Imm32(0))?
             }
-            Operator::I32Clz => {
-                let loc = self.pop_value_released();
-                let src = match loc {
-                    Location::Imm32(_) | Location::Memory(_, _) => {
-                        let tmp = self.machine.acquire_temp_gpr().unwrap();
-                        self.assembler.emit_mov(Size::S32, loc, Location::GPR(tmp));
-                        tmp
-                    }
-                    Location::GPR(reg) => reg,
-                    _ => {
-                        return Err(CodegenError {
-                            message: "I32Clz src: unreachable code".to_string(),
-                        })
-                    }
-                };
-
-                let ret =
-                    self.machine
-                        .acquire_locations(&mut self.assembler, &[(WpType::I32)], false)[0];
-                self.value_stack.push(ret);
-
-                let dst = match ret {
-                    Location::Memory(_, _) => self.machine.acquire_temp_gpr().unwrap(),
-                    Location::GPR(reg) => reg,
-                    _ => {
-                        return Err(CodegenError {
-                            message: "I32Clz dst: unreachable code".to_string(),
-                        })
-                    }
-                };
-
-                if self.assembler.arch_has_xzcnt() {
-                    self.assembler.arch_emit_lzcnt(
-                        Size::S32,
-                        Location::GPR(src),
-                        Location::GPR(dst),
-                    );
-                } else {
-                    let zero_path = self.assembler.get_label();
-                    let end = self.assembler.get_label();
-
-                    self.assembler.emit_test_gpr_64(src);
-                    self.assembler.emit_jmp(Condition::Equal, zero_path);
-                    self.assembler
-                        .emit_bsr(Size::S32, Location::GPR(src), Location::GPR(dst));
-                    self.assembler
-                        .emit_xor(Size::S32, Location::Imm32(31), Location::GPR(dst));
-                    self.assembler.emit_jmp(Condition::None, end);
-                    self.assembler.emit_label(zero_path);
-                    self.assembler
-                        .emit_mov(Size::S32, Location::Imm32(32), Location::GPR(dst));
-                    self.assembler.emit_label(end);
-                }
-
-                match loc {
-                    Location::Imm32(_) | Location::Memory(_, _) => {
-                        self.machine.release_temp_gpr(src);
-                    }
-                    _ => {}
-                };
-                if let Location::Memory(_, _) = ret {
-                    self.assembler.emit_mov(Size::S32, Location::GPR(dst), ret);
-                    self.machine.release_temp_gpr(dst);
-                };
-            }
-            Operator::I32Ctz => {
-                let loc = self.pop_value_released();
-                let src = match loc {
-                    Location::Imm32(_) | Location::Memory(_, _) => {
-                        let tmp = self.machine.acquire_temp_gpr().unwrap();
-                        self.assembler.emit_mov(Size::S32, loc, Location::GPR(tmp));
-                        tmp
-                    }
-                    Location::GPR(reg) => reg,
-                    _ => {
-                        return Err(CodegenError {
-                            message: "I32Ctz src: unreachable code".to_string(),
-                        })
-                    }
-                };
-
-                let ret =
-                    self.machine
-                        .acquire_locations(&mut self.assembler, &[(WpType::I32)], false)[0];
-                self.value_stack.push(ret);
-
-                let dst = match ret {
-                    Location::Memory(_, _) => self.machine.acquire_temp_gpr().unwrap(),
-                    Location::GPR(reg) => reg,
-                    _ => {
-                        return Err(CodegenError {
-                            message: "I32Ctz dst: unreachable code".to_string(),
-                        })
-                    }
-                };
-
-                if self.assembler.arch_has_xzcnt() {
-                    self.assembler.arch_emit_tzcnt(
-                        Size::S32,
-                        Location::GPR(src),
-                        Location::GPR(dst),
-                    );
-                } else {
-                    let zero_path = self.assembler.get_label();
-                    let end = self.assembler.get_label();
-
-                    self.assembler.emit_test_gpr_64(src);
-                    self.assembler.emit_jmp(Condition::Equal, zero_path);
-                    self.assembler
-                        .emit_bsf(Size::S32, Location::GPR(src), Location::GPR(dst));
-                    self.assembler.emit_jmp(Condition::None, end);
-                    self.assembler.emit_label(zero_path);
-                    self.assembler
-                        .emit_mov(Size::S32, Location::Imm32(32), Location::GPR(dst));
-                    self.assembler.emit_label(end);
-                }
-
-                match loc {
-                    Location::Imm32(_) | Location::Memory(_, _) => {
-                        self.machine.release_temp_gpr(src);
-                    }
-                    _ => {}
-                };
-                if let Location::Memory(_, _) = ret {
-                    self.assembler.emit_mov(Size::S32, Location::GPR(dst), ret);
-                    self.machine.release_temp_gpr(dst);
-                };
-            }
+            Operator::I32Clz => self.emit_clz(Size::S32)?,
+            Operator::I32Ctz => self.emit_ctz(Size::S32)?,
             Operator::I32Popcnt => self.emit_xcnt_i32(Assembler::emit_popcnt)?,
             Operator::I32Shl => self.emit_shift_i32(Assembler::emit_shl),
             Operator::I32ShrU => self.emit_shift_i32(Assembler::emit_shr),
@@ -2518,134 +2684,8 @@ impl<'a> FuncGen<'a> {
             Operator::I64Eqz => {
                 self.emit_cmpop_i64_dynamic_b(Condition::Equal, Location::Imm64(0))?
             }
-            Operator::I64Clz => {
-                let loc = self.pop_value_released();
-                let src = match loc {
-                    Location::Imm64(_) | Location::Imm32(_) | Location::Memory(_, _) => {
-                        let tmp = self.machine.acquire_temp_gpr().unwrap();
-                        self.assembler.emit_mov(Size::S64, loc, Location::GPR(tmp));
-                        tmp
-                    }
-                    Location::GPR(reg) => reg,
-                    _ => {
-                        return Err(CodegenError {
-                            message: "I64Clz src: unreachable code".to_string(),
-                        })
-                    }
-                };
-
-                let ret =
-                    self.machine
-                        .acquire_locations(&mut self.assembler, &[(WpType::I64)], false)[0];
-                self.value_stack.push(ret);
-
-                let dst = match ret {
-                    Location::Memory(_, _) => self.machine.acquire_temp_gpr().unwrap(),
-                    Location::GPR(reg) => reg,
-                    _ => {
-                        return Err(CodegenError {
-                            message: "I64Clz dst: unreachable code".to_string(),
-                        })
-                    }
-                };
-
-                if self.assembler.arch_has_xzcnt() {
-                    self.assembler.arch_emit_lzcnt(
-                        Size::S64,
-                        Location::GPR(src),
-                        Location::GPR(dst),
-                    );
-                } else {
-                    let zero_path = self.assembler.get_label();
-                    let end = self.assembler.get_label();
-
-                    self.assembler.emit_test_gpr_64(src);
-                    self.assembler.emit_jmp(Condition::Equal, zero_path);
-                    self.assembler
-                        .emit_bsr(Size::S64, Location::GPR(src), Location::GPR(dst));
-                    self.assembler
-                        .emit_xor(Size::S64, Location::Imm32(63), Location::GPR(dst));
-                    self.assembler.emit_jmp(Condition::None, end);
-                    self.assembler.emit_label(zero_path);
-                    self.assembler
-                        .emit_mov(Size::S64, Location::Imm32(64), Location::GPR(dst));
-                    self.assembler.emit_label(end);
-                }
-
-                match loc {
-                    Location::Imm64(_) | Location::Imm32(_) | Location::Memory(_, _) => {
-                        self.machine.release_temp_gpr(src);
-                    }
-                    _ => {}
-                };
-                if let Location::Memory(_, _) = ret {
-                    self.assembler.emit_mov(Size::S64, Location::GPR(dst), ret);
-                    self.machine.release_temp_gpr(dst);
-                };
-            }
-            Operator::I64Ctz => {
-                let loc = self.pop_value_released();
-                let src = match loc {
-                    Location::Imm64(_) | Location::Imm32(_) | Location::Memory(_, _) => {
-                        let tmp = self.machine.acquire_temp_gpr().unwrap();
-                        self.assembler.emit_mov(Size::S64, loc, Location::GPR(tmp));
-                        tmp
-                    }
-                    Location::GPR(reg) => reg,
-                    _ => {
-                        return Err(CodegenError {
-                            message: "I64Ctz src: unreachable code".to_string(),
-                        })
-                    }
-                };
-
-                let ret =
-                    self.machine
-                        .acquire_locations(&mut self.assembler, &[(WpType::I64)], false)[0];
-                self.value_stack.push(ret);
-
-                let dst = match ret {
-                    Location::Memory(_, _) => self.machine.acquire_temp_gpr().unwrap(),
-                    Location::GPR(reg) => reg,
-                    _ => {
-                        return Err(CodegenError {
-                            message: "I64Ctz dst: unreachable code".to_string(),
-                        })
-                    }
-                };
-
-                if self.assembler.arch_has_xzcnt() {
-                    self.assembler.arch_emit_tzcnt(
-                        Size::S64,
-                        Location::GPR(src),
-                        Location::GPR(dst),
-                    );
-                } else {
-                    let zero_path = self.assembler.get_label();
-                    let end = self.assembler.get_label();
-
-                    self.assembler.emit_test_gpr_64(src);
-                    self.assembler.emit_jmp(Condition::Equal, zero_path);
-                    self.assembler
-                        .emit_bsf(Size::S64, Location::GPR(src), Location::GPR(dst));
-                    self.assembler.emit_jmp(Condition::None, end);
-                    self.assembler.emit_label(zero_path);
-                    self.assembler
-                        .emit_mov(Size::S64, Location::Imm32(64), Location::GPR(dst));
-                    self.assembler.emit_label(end);
-                }
-
-                match loc {
-                    Location::Imm64(_) | Location::Imm32(_) | Location::Memory(_, _) => {
-                        self.machine.release_temp_gpr(src);
-                    }
-                    _ => {}
-                };
-                if let Location::Memory(_, _) = ret {
-                    self.assembler.emit_mov(Size::S64, Location::GPR(dst), ret);
-                    self.machine.release_temp_gpr(dst);
-                };
-            }
+            Operator::I64Clz => self.emit_clz(Size::S64)?,
+            Operator::I64Ctz => self.emit_ctz(Size::S64)?,
             Operator::I64Popcnt => self.emit_xcnt_i64(Assembler::emit_popcnt)?,
             Operator::I64Shl => self.emit_shift_i64(Assembler::emit_shl),
             Operator::I64ShrU => self.emit_shift_i64(Assembler::emit_shr),
@@ -5228,8 +5268,8 @@ impl<'a> FuncGen<'a> {
 
                 self.assembler
                     .emit_cmp(Size::S32, func_index, Location::GPR(table_count));
-                self.assembler
-                    .emit_jmp(Condition::BelowEqual, self.special_labels.table_access_oob);
+                let label = self.trap_label(TrapCode::TableAccessOutOfBounds);
+                self.assembler.emit_jmp(Condition::BelowEqual, label);
                 self.assembler
                     .emit_mov(Size::S32, func_index, Location::GPR(table_count));
                 self.assembler
@@ -5249,8 +5289,8 @@ impl<'a> FuncGen<'a> {
                 // Trap if the FuncRef is null
                 self.assembler
                     .emit_cmp(Size::S64, Location::Imm32(0), Location::GPR(table_count));
-                self.assembler
-                    .emit_jmp(Condition::Equal, self.special_labels.indirect_call_null);
+                let label = self.trap_label(TrapCode::IndirectCallToNull);
+                self.assembler.emit_jmp(Condition::Equal, label);
                 self.assembler.emit_mov(
                     Size::S64,
                     Location::Memory(
@@ -5269,8 +5309,8 @@ impl<'a> FuncGen<'a> {
                         (self.vmoffsets.vmcaller_checked_anyfunc_type_index() as usize) as i32,
                     ),
                 );
-                self.assembler
-                    .emit_jmp(Condition::NotEqual, self.special_labels.bad_signature);
+                let label = self.trap_label(TrapCode::BadSignature);
+                self.assembler.emit_jmp(Condition::NotEqual, label);
 
                 self.machine.release_temp_gpr(sigidx);
                 self.machine.release_temp_gpr(table_count);
@@ -5536,8 +5576,9 @@ impl<'a> FuncGen<'a> {
                     fp_stack_depth: self.fp_stack.len(),
                 });
                 self.assembler.emit_label(label);
-
-                // TODO: Re-enable interrupt signal check without branching
+                if self.config.check_interrupts {
+                    self.emit_interrupt_check();
+                }
             }
             Operator::Nop => {}
             Operator::MemorySize { mem, mem_byte: _ } => {
@@ -6168,6 +6209,7 @@ impl<'a> FuncGen<'a> {
             }
             Operator::Unreachable => {
                 let offset = self.assembler.get_offset().0;
+                self.record_trap(TrapCode::UnreachableCodeReached);
                 self.emit_trap(TrapCode::UnreachableCodeReached);
                 self.mark_instruction_address_end(offset);
                 self.unreachable_depth = 1;
@@ -6515,7 +6557,22 @@ impl<'a> FuncGen<'a> {
                 }
 
                 if self.control_stack.is_empty() {
+                    // Branches to the function end release their values before
+                    // jumping, so do the same when falling through for the
+                    // stack checks below. Release builds leave it out, so that
+                    // their code and stack limit accounting stay unchanged.
+                    #[cfg(debug_assertions)]
+                    {
+                        let released = &self.value_stack[frame.value_stack_depth..];
+                        self.machine
+                            .release_locations(&mut self.assembler, released);
+                        self.value_stack.truncate(frame.value_stack_depth);
+                        self.fp_stack.truncate(frame.fp_stack_depth);
+                    }
+
                     self.assembler.emit_label(frame.label);
+                    #[cfg(debug_assertions)]
+                    self.emit_stack_state_check();
                     self.update_max_stack_depth();
                     self.emit_function_stack_check(false);
                     self.machine.finalize_locals(
@@ -6523,6 +6580,7 @@ impl<'a> FuncGen<'a> {
                         &self.locals,
                         self.calling_convention,
                     );
+                    self.machine.verify_stack_state();
                     self.assembler.emit_mov(
                         Size::S64,
                         Location::GPR(GPR::RBP),
@@ -6648,7 +6706,7 @@ impl<'a> FuncGen<'a> {
 
                 self.emit_memory_op(target_addr, memarg, true, 4, |this, addr| {
                     this.emit_relaxed_binop(
-                        Assembler::emit_xchg,
+                        this.atomic_store_op(),
                         Size::S32,
                         target_value,
                         Location::Memory(addr, 0),
@@ -6662,7 +6720,7 @@ impl<'a> FuncGen<'a> {
 
                 self.emit_memory_op(target_addr, memarg, true, 1, |this, addr| {
                     this.emit_relaxed_binop(
-                        Assembler::emit_xchg,
+                        this.atomic_store_op(),
                         Size::S8,
                         target_value,
                         Location::Memory(addr, 0),
@@ -6676,7 +6734,7 @@ impl<'a> FuncGen<'a> {
 
                 self.emit_memory_op(target_addr, memarg, true, 2, |this, addr| {
                     this.emit_relaxed_binop(
-                        Assembler::emit_xchg,
+                        this.atomic_store_op(),
                         Size::S16,
                         target_value,
                         Location::Memory(addr, 0),
@@ -6775,7 +6833,7 @@ impl<'a> FuncGen<'a> {
 
                 self.emit_memory_op(target_addr, memarg, true, 8, |this, addr| {
                     this.emit_relaxed_binop(
-                        Assembler::emit_xchg,
+                        this.atomic_store_op(),
                         Size::S64,
                         target_value,
                         Location::Memory(addr, 0),
@@ -6789,7 +6847,7 @@ impl<'a> FuncGen<'a> {
 
                 self.emit_memory_op(target_addr, memarg, true, 1, |this, addr| {
                     this.emit_relaxed_binop(
-                        Assembler::emit_xchg,
+                        this.atomic_store_op(),
                         Size::S8,
                         target_value,
                         Location::Memory(addr, 0),
@@ -6803,7 +6861,7 @@ impl<'a> FuncGen<'a> {
 
                 self.emit_memory_op(target_addr, memarg, true, 2, |this, addr| {
                     this.emit_relaxed_binop(
-                        Assembler::emit_xchg,
+                        this.atomic_store_op(),
                         Size::S16,
                         target_value,
                         Location::Memory(addr, 0),
@@ -6817,7 +6875,7 @@ impl<'a> FuncGen<'a> {
 
                 self.emit_memory_op(target_addr, memarg, true, 4, |this, addr| {
                     this.emit_relaxed_binop(
-                        Assembler::emit_xchg,
+                        this.atomic_store_op(),
                         Size::S32,
                         target_value,
                         Location::Memory(addr, 0),
@@ -8375,40 +8433,57 @@ impl<'a> FuncGen<'a> {
 
     pub(crate) fn finalize(mut self, data: &FunctionBodyData) -> CompiledFunction {
         // Generate actual code for special labels.
-        self.assembler
-            .emit_label(self.special_labels.integer_division_by_zero);
-        self.emit_trap(TrapCode::IntegerDivisionByZero);
-
-        self.assembler
-            .emit_label(self.special_labels.integer_overflow);
-        self.emit_trap(TrapCode::IntegerOverflow);
-
-        self.assembler
-            .emit_label(self.special_labels.bad_conversion_to_integer);
-        self.emit_trap(TrapCode::BadConversionToInteger);
-
-        self.assembler
-            .emit_label(self.special_labels.heap_access_oob);
-        self.emit_trap(TrapCode::HeapAccessOutOfBounds);
-
-        self.assembler
-            .emit_label(self.special_labels.table_access_oob);
-        self.emit_trap(TrapCode::TableAccessOutOfBounds);
-
-        self.assembler
-            .emit_label(self.special_labels.indirect_call_null);
-        self.emit_trap(TrapCode::IndirectCallToNull);
-
-        self.assembler.emit_label(self.special_labels.bad_signature);
-        self.emit_trap(TrapCode::BadSignature);
-
-        self.assembler
-            .emit_label(self.special_labels.gas_limit_exceeded);
-        self.emit_trap(TrapCode::GasExceeded);
+        let special_labels = [
+            (
+                self.special_labels.integer_division_by_zero,
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                self.special_labels.integer_overflow,
+                TrapCode::IntegerOverflow,
+            ),
+            (
+                self.special_labels.bad_conversion_to_integer,
+                TrapCode::BadConversionToInteger,
+            ),
+            (
+                self.special_labels.heap_access_oob,
+                TrapCode::HeapAccessOutOfBounds,
+            ),
+            (
+                self.special_labels.table_access_oob,
+                TrapCode::TableAccessOutOfBounds,
+            ),
+            (
+                self.special_labels.indirect_call_null,
+                TrapCode::IndirectCallToNull,
+            ),
+            (self.special_labels.bad_signature, TrapCode::BadSignature),
+            (
+                self.special_labels.gas_limit_exceeded,
+                TrapCode::GasExceeded,
+            ),
+            (
+                self.special_labels.stack_overflow,
+                TrapCode::WasmStackOverflow,
+            ),
+            (self.special_labels.interrupted, TrapCode::Interrupted),
+        ];
+        for &(label, code) in special_labels.iter() {
+            self.assembler.emit_label(label);
+            if self.used_traps.contains(&code) {
+                self.record_trap(code);
+            }
+            self.emit_trap(code);
+        }
 
-        self.assembler
-            .emit_label(self.special_labels.stack_overflow);
-        self.emit_trap(TrapCode::StackOverflow);
+        #[cfg(debug_assertions)]
+        {
+            self.assembler
+                .emit_label(self.special_labels.stack_mismatch);
+            self.record_trap(TrapCode::UnreachableCodeReached);
+            self.emit_trap(TrapCode::UnreachableCodeReached);
+        }
 
         // Notify the assembler backend to generate necessary code at end of function.
         self.assembler.finalize_function();
@@ -8425,9 +8500,10 @@ impl<'a> FuncGen<'a> {
             relocations: self.relocations,
             jt_offsets: SecondaryMap::new(),
             frame_info: CompiledFunctionFrameInfo {
-                traps: vec![],
+                traps: self.trap_sites,
                 address_map,
             },
+            trace_info: self.trace_info,
         }
     }
 }
@@ -8723,6 +8799,148 @@ pub(crate) fn gen_std_dynamic_import_trampoline(
     }
 }
 
+// Calls the import function from its trampoline through a regular frame, reporting entry
+// and exit to the host call tracer. Expects the arguments to be already placed according
+// to the native calling convention.
+fn emit_traced_import_call(
+    a: &mut Assembler,
+    vmoffsets: &VMOffsets,
+    index: FunctionIndex,
+    sig: &FunctionType,
+    calling_convention: CallingConvention,
+) {
+    let (arg_gprs, arg_xmms, shadow_space): (&[GPR], &[XMM], i32) = match calling_convention {
+        CallingConvention::WindowsFastcall => (
+            &[GPR::RCX, GPR::RDX, GPR::R8, GPR::R9],
+            &[XMM::XMM0, XMM::XMM1, XMM::XMM2, XMM::XMM3],
+            32,
+        ),
+        _ => (
+            &[GPR::RDI, GPR::RSI, GPR::RDX, GPR::RCX, GPR::R8, GPR::R9],
+            &[
+                XMM::XMM0,
+                XMM::XMM1,
+                XMM::XMM2,
+                XMM::XMM3,
+                XMM::XMM4,
+                XMM::XMM5,
+                XMM::XMM6,
+                XMM::XMM7,
+            ],
+            0,
+        ),
+    };
+    let vmctx = arg_gprs[0];
+    let index_arg = arg_gprs[1];
+
+    let mut argalloc = ArgumentRegisterAllocator::default();
+    argalloc.next(Type::I64, calling_convention).unwrap(); // skip VMContext
+    let stack_args = sig
+        .params()
+        .iter()
+        .filter(|&&ty| argalloc.next(ty, calling_convention).is_none())
+        .count() as i32;
+
+    // Argument registers are spilled right below the saved RBP. Together with the return
+    // address and RBP, a 16-byte multiple keeps RSP aligned for the calls below.
+    let save_area = ((arg_gprs.len() + arg_xmms.len()) as i32 * 8 + 15) / 16 * 16;
+    let gpr_slot = |i: usize| Location::Memory(GPR::RBP, -save_area + i as i32 * 8);
+    let xmm_slot = |i: usize| gpr_slot(arg_gprs.len() + i);
+
+    a.emit_push(Size::S64, Location::GPR(GPR::RBP));
+    a.emit_mov(Size::S64, Location::GPR(GPR::RSP), Location::GPR(GPR::RBP));
+    a.emit_sub(
+        Size::S64,
+        Location::Imm32((save_area + shadow_space) as u32),
+        Location::GPR(GPR::RSP),
+    );
+    for (i, gpr) in arg_gprs.iter().enumerate() {
+        a.emit_mov(Size::S64, Location::GPR(*gpr), gpr_slot(i));
+    }
+    for (i, xmm) in arg_xmms.iter().enumerate() {
+        a.emit_mov(Size::S64, Location::XMM(*xmm), xmm_slot(i));
+    }
+
+    a.emit_mov(
+        Size::S32,
+        Location::Imm32(index.as_u32()),
+        Location::GPR(index_arg),
+    );
+    a.emit_call_location(Location::Memory(
+        vmctx,
+        vmoffsets.vmctx_builtin_function(VMBuiltinFunctionIndex::get_host_call_enter_index())
+            as i32,
+    ));
+
+    for (i, gpr) in arg_gprs.iter().enumerate() {
+        a.emit_mov(Size::S64, gpr_slot(i), Location::GPR(*gpr));
+    }
+    for (i, xmm) in arg_xmms.iter().enumerate() {
+        a.emit_mov(Size::S64, xmm_slot(i), Location::XMM(*xmm));
+    }
+
+    // Copy the stack arguments into the new frame.
+    let stack_args_size = (stack_args * 8 + 15) / 16 * 16;
+    if stack_args_size > 0 {
+        a.emit_sub(
+            Size::S64,
+            Location::Imm32(stack_args_size as u32),
+            Location::GPR(GPR::RSP),
+        );
+    }
+    for i in 0..stack_args {
+        a.emit_mov(
+            Size::S64,
+            Location::Memory(GPR::RBP, 16 + shadow_space + i * 8),
+            Location::GPR(GPR::RAX),
+        );
+        a.emit_mov(
+            Size::S64,
+            Location::GPR(GPR::RAX),
+            Location::Memory(GPR::RSP, shadow_space + i * 8),
+        );
+    }
+
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(vmctx, vmoffsets.vmctx_vmfunction_import_body(index) as i32),
+        Location::GPR(GPR::RAX),
+    );
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(vmctx, vmoffsets.vmctx_vmfunction_import_vmctx(index) as i32),
+        Location::GPR(vmctx),
+    );
+    a.emit_call_register(GPR::RAX);
+    if stack_args_size > 0 {
+        a.emit_add(
+            Size::S64,
+            Location::Imm32(stack_args_size as u32),
+            Location::GPR(GPR::RSP),
+        );
+    }
+
+    // Keep the return value in the argument slots, which are no longer needed.
+    a.emit_mov(Size::S64, Location::GPR(GPR::RAX), gpr_slot(1));
+    a.emit_mov(Size::S64, Location::XMM(XMM::XMM0), xmm_slot(0));
+    a.emit_mov(Size::S64, gpr_slot(0), Location::GPR(vmctx));
+    a.emit_mov(
+        Size::S32,
+        Location::Imm32(index.as_u32()),
+        Location::GPR(index_arg),
+    );
+    a.emit_call_location(Location::Memory(
+        vmctx,
+        vmoffsets.vmctx_builtin_function(VMBuiltinFunctionIndex::get_host_call_exit_index()) as i32,
+    ));
+    a.emit_mov(Size::S64, gpr_slot(1), Location::GPR(GPR::RAX));
+    a.emit_mov(Size::S64, xmm_slot(0), Location::XMM(XMM::XMM0));
+
+    a.emit_mov(Size::S64, Location::GPR(GPR::RBP), Location::GPR(GPR::RSP));
+    a.emit_pop(Size::S64, Location::GPR(GPR::RBP));
+    a.emit_ret();
+}
+
 // Singlepass calls import functions through a trampoline.
 pub(crate) fn gen_import_call_trampoline(
     vmoffsets: &VMOffsets,
@@ -8844,6 +9062,23 @@ pub(crate) fn gen_import_call_trampoline(
         }
     }
 
+    // Calls are only bracketed by the host call tracer hooks when a tracer is installed,
+    // so the untraced path costs a single compare against null.
+    let traced = a.get_label();
+    let caller_vmctx = match calling_convention {
+        CallingConvention::WindowsFastcall => GPR::RCX,
+        _ => GPR::RDI,
+    };
+    a.emit_cmp(
+        Size::S64,
+        Location::Imm32(0),
+        Location::Memory(
+            caller_vmctx,
+            vmoffsets.vmctx_host_call_tracer_pointer() as i32,
+        ),
+    );
+    a.emit_jmp(Condition::NotEqual, traced);
+
     // Emits a tail call trampoline that loads the address of the target import function
     // from Ctx and jumps to it.
 
@@ -8878,6 +9113,9 @@ pub(crate) fn gen_import_call_trampoline(
     }
     a.emit_host_redirection(GPR::RAX);
 
+    a.emit_label(traced);
+    emit_traced_import_call(&mut a, vmoffsets, index, sig, calling_convention);
+
     let section_body = SectionBody::new_with_vec(a.finalize().unwrap().to_vec());
 
     CustomSection {
@@ -6,18 +6,21 @@ use crate::codegen_x64::{
     gen_import_call_trampoline, gen_std_dynamic_import_trampoline, gen_std_trampoline,
     CodegenError, FuncGen,
 };
-use crate::config::Singlepass;
+use crate::config::{IntrinsicKind, Singlepass};
 #[cfg(feature = "rayon")]
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::sync::Arc;
+use std::time::Instant;
 use wasmer_compiler::{
-    Architecture, CallingConvention, Compilation, CompileError, CompileModuleInfo,
-    CompiledFunction, Compiler, CompilerConfig, CpuFeature, FunctionBody, FunctionBodyData,
-    ModuleTranslationState, OperatingSystem, SectionIndex, Target, TrapInformation,
+    Architecture, CallingConvention, Compilation, CompilationProfile, CompileError,
+    CompileModuleInfo, CompiledFunction, Compiler, CompilerConfig, CpuFeature, FunctionBody,
+    FunctionBodyData, MeteringInfo, ModuleTranslationState, OperatingSystem, SectionIndex, Target,
+    TrapInformation,
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
-    FunctionIndex, FunctionType, LocalFunctionIndex, MemoryIndex, ModuleInfo, TableIndex,
+    FunctionIndex, FunctionType, ImportIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo,
+    TableIndex,
 };
 use wasmer_vm::{TrapCode, VMOffsets};
 
@@ -25,30 +28,44 @@ use wasmer_vm::{TrapCode, VMOffsets};
 /// It does the compilation in one pass
 pub struct SinglepassCompiler {
     config: Singlepass,
+    name: String,
 }
 
 impl SinglepassCompiler {
     /// Creates a new Singlepass compiler
     pub fn new(config: Singlepass) -> Self {
-        Self { config }
+        // Options changing the generated code are part of the name, so that
+        // engines with different options get different identifiers.
+        let mut name = "singlepass".to_string();
+        if config.saturate_gas {
+            name.push_str("+saturate-gas");
+        }
+        if config.limit_call_depth {
+            name.push_str("+call-depth");
+        }
+        if config.check_interrupts {
+            name.push_str("+interrupts");
+        }
+        for intrinsic in &config.intrinsics {
+            if let Some(module) = &intrinsic.module {
+                name.push_str(&format!("+{}-module={}", intrinsic.name, module));
+            }
+        }
+        Self { config, name }
     }
 
     /// Gets the config for this Compiler
     fn config(&self) -> &Singlepass {
         &self.config
     }
-}
 
-impl Compiler for SinglepassCompiler {
-    /// Compile the module using Singlepass, producing a compilation result with
-    /// associated relocations.
-    fn compile_module(
+    /// Check that Singlepass can compile `compile_info` for `target`, and
+    /// return the calling convention to use.
+    fn check_target(
         &self,
         target: &Target,
         compile_info: &CompileModuleInfo,
-        module_translation: &ModuleTranslationState,
-        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
-    ) -> Result<Compilation, CompileError> {
+    ) -> Result<CallingConvention, CompileError> {
         /*if target.triple().operating_system == OperatingSystem::Windows {
             return Err(CompileError::UnsupportedTarget(
                 OperatingSystem::Windows.to_string(),
@@ -67,30 +84,127 @@ impl Compiler for SinglepassCompiler {
         if compile_info.features.multi_value {
             return Err(CompileError::UnsupportedFeature("multivalue".to_string()));
         }
-        let calling_convention = match target.triple().default_calling_convention() {
+        Ok(match target.triple().default_calling_convention() {
             Ok(CallingConvention::WindowsFastcall) => CallingConvention::WindowsFastcall,
             Ok(CallingConvention::SystemV) => CallingConvention::SystemV,
             //Ok(CallingConvention::AppleAarch64) => AppleAarch64,
             _ => panic!("Unsupported Calling convention for Singlepass compiler"),
-        };
+        })
+    }
+
+    /// Check that the imports named after an intrinsic, in its module if it
+    /// is restricted to one, have its signature.
+    ///
+    /// Such an import would otherwise silently be compiled as a regular call,
+    /// which for the gas intrinsic means no gas is charged.
+    fn check_intrinsic_imports(&self, module: &ModuleInfo) -> Result<(), CompileError> {
+        for ((module_name, field, _), index) in module.imports.iter() {
+            let function_index = match index {
+                ImportIndex::Function(function_index) => *function_index,
+                _ => continue,
+            };
+            let signature = &module.signatures[module.functions[function_index]];
+            for intrinsic in &self.config.intrinsics {
+                if intrinsic.is_named(module_name, field) && intrinsic.signature != *signature {
+                    return Err(CompileError::Validate(format!(
+                        "import `{}`.`{}` has signature {} but the `{}` intrinsic expects {}",
+                        module_name, field, signature, intrinsic.name, intrinsic.signature
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that `compile_info` can be compiled for `target`, and gather
+    /// what compiling its functions needs.
+    fn module_context<'a>(
+        &self,
+        target: &'a Target,
+        compile_info: &'a CompileModuleInfo,
+        module_translation: &'a ModuleTranslationState,
+    ) -> Result<ModuleContext<'a>, CompileError> {
+        let calling_convention = self.check_target(target, compile_info)?;
+        self.check_intrinsic_imports(&compile_info.module)?;
+        Ok(ModuleContext {
+            target,
+            compile_info,
+            module_translation,
+            vmoffsets: vmoffsets(target, compile_info)?,
+            calling_convention,
+        })
+    }
+
+    /// Generate the code of the local function `index`.
+    fn compile_function_body(
+        &self,
+        cx: &ModuleContext<'_>,
+        index: LocalFunctionIndex,
+        input: &FunctionBodyData<'_>,
+    ) -> Result<CompiledFunction, CompileError> {
+        let reader = wasmer_compiler::FunctionReader::new(input.module_offset, input.data);
+
+        let mut local_reader = reader.get_locals_reader()?;
+        // This local list excludes arguments.
+        let mut locals = vec![];
+        let num_locals = local_reader.get_count();
+        for _ in 0..num_locals {
+            let (count, ty) = local_reader.read()?;
+            for _ in 0..count {
+                locals.push(ty);
+            }
+        }
+
+        let mut generator = FuncGen::new(
+            &cx.compile_info.module,
+            cx.module_translation,
+            &self.config,
+            &cx.vmoffsets,
+            &cx.compile_info.table_styles,
+            index,
+            &locals,
+            cx.calling_convention,
+            cx.target,
+        )
+        .map_err(to_compile_error)?;
+
+        let mut operator_reader = reader.get_operators_reader()?.into_iter_with_offsets();
+        while generator.has_control_frames() {
+            let (op, pos) = operator_reader.next().unwrap()?;
+            generator.set_srcloc(pos as u32);
+            generator.feed_operator(op).map_err(to_compile_error)?;
+        }
+
+        Ok(generator.finalize(input))
+    }
+}
+
+impl Compiler for SinglepassCompiler {
+    fn name(&self) -> &str {
+        &self.name
+    }
 
-        let table_styles = &compile_info.table_styles;
+    /// Compile the module using Singlepass, producing a compilation result with
+    /// associated relocations.
+    fn compile_module(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) -> Result<Compilation, CompileError> {
+        let cx = self.module_context(target, compile_info, module_translation)?;
         let module = &compile_info.module;
-        let pointer_width = target
-            .triple()
-            .pointer_width()
-            .map_err(|()| {
-                CompileError::UnsupportedTarget("target with unknown pointer width".into())
-            })?
-            .bytes();
-        let vmoffsets = VMOffsets::new(pointer_width).with_module_info(&module);
+        let calling_convention = cx.calling_convention;
+        let vmoffsets = &cx.vmoffsets;
+        let trampolines_start = Instant::now();
         let import_idxs = 0..module.import_counts.functions as usize;
         let import_trampolines: PrimaryMap<SectionIndex, _> = import_idxs
             .into_par_iter_if_rayon()
             .map(|i| {
                 let i = FunctionIndex::new(i);
                 gen_import_call_trampoline(
-                    &vmoffsets,
+                    vmoffsets,
                     i,
                     &module.signatures[module.functions[i]],
                     calling_convention,
@@ -99,49 +213,25 @@ impl Compiler for SinglepassCompiler {
             .collect::<Vec<_>>()
             .into_iter()
             .collect();
+        let mut trampoline_generation = trampolines_start.elapsed();
         let functions = function_body_inputs
             .iter()
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .into_par_iter_if_rayon()
             .map(|(i, input)| {
-                let reader = wasmer_compiler::FunctionReader::new(input.module_offset, input.data);
-
-                let mut local_reader = reader.get_locals_reader()?;
-                // This local list excludes arguments.
-                let mut locals = vec![];
-                let num_locals = local_reader.get_count();
-                for _ in 0..num_locals {
-                    let (count, ty) = local_reader.read()?;
-                    for _ in 0..count {
-                        locals.push(ty);
-                    }
-                }
-
-                let mut generator = FuncGen::new(
-                    module,
-                    module_translation,
-                    &self.config,
-                    &vmoffsets,
-                    &table_styles,
-                    i,
-                    &locals,
-                    calling_convention,
-                )
-                .map_err(to_compile_error)?;
-
-                let mut operator_reader = reader.get_operators_reader()?.into_iter_with_offsets();
-                while generator.has_control_frames() {
-                    let (op, pos) = operator_reader.next().unwrap()?;
-                    generator.set_srcloc(pos as u32);
-                    generator.feed_operator(op).map_err(to_compile_error)?;
-                }
-
-                Ok(generator.finalize(&input))
+                let start = Instant::now();
+                let function = self.compile_function_body(&cx, i, input)?;
+                Ok((function, start.elapsed()))
             })
-            .collect::<Result<Vec<CompiledFunction>, CompileError>>()?
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        let mut profile = CompilationProfile::default();
+        profile.set_function_compilation_times(functions.iter().map(|(_, time)| *time));
+        let functions = functions
             .into_iter()
+            .map(|(function, _)| function)
             .collect::<PrimaryMap<LocalFunctionIndex, CompiledFunction>>();
 
+        let trampolines_start = Instant::now();
         let function_call_trampolines = module
             .signatures
             .values()
@@ -157,11 +247,13 @@ impl Compiler for SinglepassCompiler {
             .collect::<Vec<_>>()
             .into_par_iter_if_rayon()
             .map(|func_type| {
-                gen_std_dynamic_import_trampoline(&vmoffsets, &func_type, calling_convention)
+                gen_std_dynamic_import_trampoline(vmoffsets, &func_type, calling_convention)
             })
             .collect::<Vec<_>>()
             .into_iter()
             .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
+        trampoline_generation += trampolines_start.elapsed();
+        profile.trampoline_generation = trampoline_generation;
 
         Ok(Compilation::new(
             functions,
@@ -170,8 +262,78 @@ impl Compiler for SinglepassCompiler {
             dynamic_function_trampolines,
             None,
             None,
-        ))
+        )
+        .with_profile(profile))
     }
+
+    /// Compile a single function using Singlepass, without generating any
+    /// trampoline.
+    fn compile_function(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        index: LocalFunctionIndex,
+        body: &FunctionBodyData<'_>,
+    ) -> Result<CompiledFunction, CompileError> {
+        let cx = self.module_context(target, compile_info, module_translation)?;
+        self.compile_function_body(&cx, index, body)
+    }
+
+    fn metering_info(&self, module: &ModuleInfo) -> MeteringInfo {
+        let gas_intrinsic_imports = module
+            .imports
+            .iter()
+            .filter_map(|((module_name, field, _), index)| {
+                let function_index = match index {
+                    ImportIndex::Function(function_index) => *function_index,
+                    _ => return None,
+                };
+                let signature = &module.signatures[module.functions[function_index]];
+                self.config
+                    .intrinsics
+                    .iter()
+                    .any(|intrinsic| {
+                        matches!(intrinsic.kind, IntrinsicKind::Gas)
+                            && intrinsic.is_named(module_name, field)
+                            && intrinsic.signature == *signature
+                    })
+                    .then(|| (module_name.clone(), field.clone()))
+            })
+            .collect();
+        let gas_module = self
+            .config
+            .intrinsics
+            .iter()
+            .find(|intrinsic| matches!(intrinsic.kind, IntrinsicKind::Gas))
+            .and_then(|intrinsic| intrinsic.module.clone());
+        MeteringInfo {
+            gas_intrinsic_imports,
+            gas_module,
+            saturate_gas: self.config.saturate_gas,
+            limits_call_depth: self.config.limit_call_depth,
+            checks_interrupts: self.config.check_interrupts,
+        }
+    }
+}
+
+/// The offsets of the `VMContext` of `compile_info`'s module on `target`.
+/// What compiling the functions of a module needs, shared by all of them.
+struct ModuleContext<'a> {
+    target: &'a Target,
+    compile_info: &'a CompileModuleInfo,
+    module_translation: &'a ModuleTranslationState,
+    vmoffsets: VMOffsets,
+    calling_convention: CallingConvention,
+}
+
+fn vmoffsets(target: &Target, compile_info: &CompileModuleInfo) -> Result<VMOffsets, CompileError> {
+    let pointer_width = target
+        .triple()
+        .pointer_width()
+        .map_err(|()| CompileError::UnsupportedTarget("target with unknown pointer width".into()))?
+        .bytes();
+    Ok(VMOffsets::new(pointer_width).with_module_info(&compile_info.module))
 }
 
 trait ToCompileError {
@@ -16,6 +16,8 @@ pub(crate) enum IntrinsicKind {
 #[derive(Debug, Clone)]
 pub(crate) struct Intrinsic {
     pub(crate) kind: IntrinsicKind,
+    /// The module the intrinsic is imported from, any module if `None`.
+    pub(crate) module: Option<String>,
     pub(crate) name: String,
     pub(crate) signature: FunctionType,
 }
@@ -24,6 +26,14 @@ pub(crate) struct Intrinsic {
 pub struct Singlepass {
     pub(crate) enable_nan_canonicalization: bool,
     pub(crate) enable_stack_check: bool,
+    /// Maximum depth of the operand stack allowed during codegen.
+    pub(crate) max_operand_stack_depth: Option<usize>,
+    /// Whether an overflowing gas charge saturates the gas counter.
+    pub(crate) saturate_gas: bool,
+    /// Whether functions count their frames in the call depth of the instance.
+    pub(crate) limit_call_depth: bool,
+    /// Whether loop headers check the interrupt flag of the instance.
+    pub(crate) check_interrupts: bool,
     /// Compiler intrinsics.
     pub(crate) intrinsics: Vec<Intrinsic>,
 }
@@ -35,8 +45,13 @@ impl Singlepass {
         Self {
             enable_nan_canonicalization: true,
             enable_stack_check: false,
+            max_operand_stack_depth: None,
+            saturate_gas: false,
+            limit_call_depth: false,
+            check_interrupts: false,
             intrinsics: vec![Intrinsic {
                 kind: IntrinsicKind::Gas,
+                module: None,
                 name: "gas".to_string(),
                 signature: ([Type::I32], []).into(),
             }],
@@ -55,6 +70,77 @@ impl Singlepass {
         self
     }
 
+    /// Limit the depth of the operand stack.
+    ///
+    /// Deep operand stacks are spilled to the native stack, so adversarial
+    /// modules with deeply nested expressions can produce huge frames. When
+    /// set, compiling a function whose operand stack grows beyond `depth`
+    /// values fails with a `CompileError` instead.
+    pub fn max_operand_stack_depth(&mut self, depth: usize) -> &mut Self {
+        self.max_operand_stack_depth = Some(depth);
+        self
+    }
+
+    /// Saturate the gas counter when a gas charge overflows it.
+    ///
+    /// By default, a charge for which `burnt_gas` would overflow as a signed
+    /// integer traps with `TrapCode::IntegerOverflow`, leaving the counter as
+    /// it was. When enabled, a charge whose cost or resulting `burnt_gas`
+    /// overflows a `u64` instead clamps `burnt_gas` to `gas_limit`, so that
+    /// the next charge traps with `TrapCode::GasExceeded`.
+    ///
+    /// The mode is recorded in the `MeteringInfo` of the compiled code, and
+    /// in the name of the compiler.
+    pub fn saturate_gas(&mut self, enable: bool) -> &mut Self {
+        self.saturate_gas = enable;
+        self
+    }
+
+    /// Count the frames of the functions in the call depth of the instance.
+    ///
+    /// When enabled, every function decrements the remaining call depth of
+    /// its instance in its prologue, trapping once it is exhausted, and
+    /// increments it back in its epilogue. This is needed to instantiate the
+    /// module with `InstanceConfig::with_max_function_call_depth`.
+    ///
+    /// The option is recorded in the `MeteringInfo` of the compiled code, and
+    /// in the name of the compiler.
+    pub fn limit_call_depth(&mut self, enable: bool) -> &mut Self {
+        self.limit_call_depth = enable;
+        self
+    }
+
+    /// Check the interrupt flag of the instance at every loop header.
+    ///
+    /// When enabled, every loop header loads the flag and traps with
+    /// `TrapCode::Interrupted` once it is set. This is needed to instantiate
+    /// the module with `InstanceConfig::with_interrupt_flag`.
+    ///
+    /// The option is recorded in the `MeteringInfo` of the compiled code, and
+    /// in the name of the compiler.
+    pub fn check_interrupts(&mut self, enable: bool) -> &mut Self {
+        self.check_interrupts = enable;
+        self
+    }
+
+    /// Only compile the functions imported as `gas` from `module` into
+    /// updates of the gas counter.
+    ///
+    /// By default, a function imported as `gas` from any module is, as long
+    /// as it has the `[I32] -> []` signature. The globals bound to the gas
+    /// counter by `InstanceConfig::with_gas_globals` are then only the ones
+    /// imported from `module` too.
+    ///
+    /// The module is part of the name of the compiler.
+    pub fn gas_intrinsic_module(&mut self, module: &str) -> &mut Self {
+        for intrinsic in &mut self.intrinsics {
+            if matches!(intrinsic.kind, IntrinsicKind::Gas) {
+                intrinsic.module = Some(module.to_string());
+            }
+        }
+        self
+    }
+
     fn enable_nan_canonicalization(&mut self) {
         self.enable_nan_canonicalization = true;
     }
@@ -91,6 +177,11 @@ impl Default for Singlepass {
 }
 
 impl Intrinsic {
+    /// Whether the import `module`.`field` is named after the intrinsic.
+    pub(crate) fn is_named(&self, module: &str, field: &str) -> bool {
+        self.name == field && self.module.as_ref().map_or(true, |m| m == module)
+    }
+
     pub(crate) fn is_params_ok(&self, params: &SmallVec<[Location; 8]>) -> bool {
         match self.kind {
             IntrinsicKind::Gas => match params[0] {
@@ -122,6 +122,8 @@ pub(crate) trait Emitter {
     fn emit_bsr(&mut self, sz: Size, src: Location, dst: Location);
     fn emit_bsf(&mut self, sz: Size, src: Location, dst: Location);
     fn emit_popcnt(&mut self, sz: Size, src: Location, dst: Location);
+    fn emit_lzcnt(&mut self, sz: Size, src: Location, dst: Location);
+    fn emit_tzcnt(&mut self, sz: Size, src: Location, dst: Location);
     fn emit_movzx(&mut self, sz_src: Size, src: Location, sz_dst: Size, dst: Location);
     fn emit_movsx(&mut self, sz_src: Size, src: Location, sz_dst: Size, dst: Location);
     fn emit_xchg(&mut self, sz: Size, src: Location, dst: Location);
@@ -287,16 +289,6 @@ pub(crate) trait Emitter {
         unimplemented!()
     }
 
-    fn arch_has_xzcnt(&self) -> bool {
-        false
-    }
-    fn arch_emit_lzcnt(&mut self, _sz: Size, _src: Location, _dst: Location) {
-        unimplemented!()
-    }
-    fn arch_emit_tzcnt(&mut self, _sz: Size, _src: Location, _dst: Location) {
-        unimplemented!()
-    }
-
     fn arch_supports_canonicalize_nan(&self) -> bool {
         true
     }
@@ -917,7 +909,12 @@ impl Emitter for Assembler {
                 Ordering::Greater => dynasm!(self ; cmp DWORD [>const_pos_one_32], 0),
             },
             None => binop_all_nofp!(cmp, self, sz, left, right, {
-                panic!("singlepass can't emit CMP {:?} {:?} {:?}", sz, left, right);
+                match (sz, left, right) {
+                    (Size::S8, Location::Imm32(left), Location::Memory(base, disp)) => {
+                        dynasm!(self ; cmp BYTE [Rq(base as u8) + disp], left as i8);
+                    }
+                    _ => panic!("singlepass can't emit CMP {:?} {:?} {:?}", sz, left, right),
+                }
             }),
         }
     }
@@ -1036,6 +1033,20 @@ impl Emitter for Assembler {
             })
         });
     }
+    fn emit_lzcnt(&mut self, sz: Size, src: Location, dst: Location) {
+        binop_gpr_gpr!(lzcnt, self, sz, src, dst, {
+            binop_mem_gpr!(lzcnt, self, sz, src, dst, {
+                panic!("singlepass can't emit LZCNT {:?} {:?} {:?}", sz, src, dst)
+            })
+        });
+    }
+    fn emit_tzcnt(&mut self, sz: Size, src: Location, dst: Location) {
+        binop_gpr_gpr!(tzcnt, self, sz, src, dst, {
+            binop_mem_gpr!(tzcnt, self, sz, src, dst, {
+                panic!("singlepass can't emit TZCNT {:?} {:?} {:?}", sz, src, dst)
+            })
+        });
+    }
     fn emit_movzx(&mut self, sz_src: Size, src: Location, sz_dst: Size, dst: Location) {
         match (sz_src, src, sz_dst, dst) {
             (Size::S8, Location::GPR(src), Size::S32, Location::GPR(dst)) => {
@@ -2,7 +2,7 @@ use crate::emitter_x64::*;
 use smallvec::smallvec;
 use smallvec::SmallVec;
 use std::cmp;
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 use wasmer_compiler::wasmparser::Type as WpType;
 use wasmer_compiler::CallingConvention;
 
@@ -11,19 +11,23 @@ const NATIVE_PAGE_SIZE: usize = 4096;
 struct MachineStackOffset(usize);
 
 pub(crate) struct Machine {
-    used_gprs: HashSet<GPR>,
-    used_xmms: HashSet<XMM>,
+    // Ordered sets, so that anything derived from iterating over the used
+    // registers (e.g. save/restore sequences) is emitted deterministically.
+    used_gprs: BTreeSet<GPR>,
+    used_xmms: BTreeSet<XMM>,
     stack_offset: MachineStackOffset,
     save_area_offset: Option<MachineStackOffset>,
+    locals_offset: MachineStackOffset,
 }
 
 impl Machine {
     pub(crate) fn new() -> Self {
         Machine {
-            used_gprs: HashSet::new(),
-            used_xmms: HashSet::new(),
+            used_gprs: BTreeSet::new(),
+            used_xmms: BTreeSet::new(),
             stack_offset: MachineStackOffset(0),
             save_area_offset: None,
+            locals_offset: MachineStackOffset(0),
         }
     }
 
@@ -31,16 +35,20 @@ impl Machine {
         self.stack_offset.0
     }
 
+    /// Returns the used general purpose registers, in ascending `GPR` order.
+    ///
+    /// Callers rely on this order to emit identical save/restore sequences
+    /// across compilations and platforms.
     pub(crate) fn get_used_gprs(&self) -> Vec<GPR> {
-        let mut result = self.used_gprs.iter().cloned().collect::<Vec<_>>();
-        result.sort_unstable();
-        result
+        self.used_gprs.iter().cloned().collect()
     }
 
+    /// Returns the used XMM registers, in ascending `XMM` order.
+    ///
+    /// Callers rely on this order to emit identical save/restore sequences
+    /// across compilations and platforms.
     pub(crate) fn get_used_xmms(&self) -> Vec<XMM> {
-        let mut result = self.used_xmms.iter().cloned().collect::<Vec<_>>();
-        result.sort_unstable();
-        result
+        self.used_xmms.iter().cloned().collect()
     }
 
     pub(crate) fn get_vmctx_reg() -> GPR {
@@ -460,23 +468,35 @@ impl Machine {
             }
         }
         if init_stack_loc_cnt > 0 {
-            // Since these assemblies take up to 24 bytes, if more than 2 slots are initialized, then they are smaller.
-            a.emit_mov(
-                Size::S64,
-                Location::Imm64(init_stack_loc_cnt as u64),
-                Location::GPR(GPR::RCX),
-            );
-            a.emit_xor(Size::S64, Location::GPR(GPR::RAX), Location::GPR(GPR::RAX));
-            a.emit_lea(Size::S64, last_stack_loc, Location::GPR(GPR::RDI));
-            a.emit_rep_stosq();
+            Self::emit_zero_range(a, last_stack_loc, init_stack_loc_cnt);
         }
 
         // Add the size of all locals allocated to stack.
         self.stack_offset.0 += static_area_size - callee_saved_regs_size;
+        self.locals_offset = MachineStackOffset(self.stack_offset.0);
 
         locations
     }
 
+    /// Zeroes `count` consecutive 8-byte slots starting at `start_location`,
+    /// which must be the slot with the lowest address.
+    ///
+    /// Since this sequence takes up to 24 bytes, it is smaller than individual
+    /// `mov`s as soon as more than 2 slots are initialized.
+    ///
+    /// Clobbers RAX, RCX and RDI, so `start_location` must not be based on any
+    /// of them.
+    pub(crate) fn emit_zero_range<E: Emitter>(a: &mut E, start_location: Location, count: usize) {
+        a.emit_mov(
+            Size::S64,
+            Location::Imm64(count as u64),
+            Location::GPR(GPR::RCX),
+        );
+        a.emit_xor(Size::S64, Location::GPR(GPR::RAX), Location::GPR(GPR::RAX));
+        a.emit_lea(Size::S64, start_location, Location::GPR(GPR::RDI));
+        a.emit_rep_stosq();
+    }
+
     pub(crate) fn finalize_locals<E: Emitter>(
         &mut self,
         a: &mut E,
@@ -507,6 +527,19 @@ impl Machine {
                 a.emit_pop(Size::S64, *loc);
             }
         }
+
+        // The whole static area is gone with the frame.
+        self.stack_offset.0 -= self.locals_offset.0;
+    }
+
+    /// Checks, in debug builds, that every stack slot acquired for the values
+    /// of the function was released by the time `finalize_locals` returns.
+    pub(crate) fn verify_stack_state(&self) {
+        debug_assert_eq!(
+            self.stack_offset.0, 0,
+            "{} bytes of stack leaked at function return",
+            self.stack_offset.0
+        );
     }
 
     pub(crate) fn get_param_location(
@@ -553,4 +586,29 @@ mod test {
 
         machine.release_locations_keep_state(&mut assembler, &locs);
     }
+
+    #[test]
+    fn test_emit_zero_range() {
+        let mut assembler = Assembler::new(0);
+        Machine::emit_zero_range(&mut assembler, Location::Memory(GPR::RBP, -64), 4);
+        let code = assembler.finalize().unwrap();
+        // rep stosq
+        assert!(code.ends_with(&[0xf3, 0x48, 0xab]));
+    }
+
+    #[test]
+    fn test_used_registers_are_sorted() {
+        let mut machine = Machine::new();
+        machine.reserve_unused_temp_gpr(GPR::R11);
+        machine.reserve_unused_temp_gpr(GPR::RAX);
+        machine.reserve_unused_temp_gpr(GPR::R8);
+        machine.acquire_temp_xmm();
+        machine.acquire_locations(&mut Assembler::new(0), &[WpType::F64, WpType::F32], false);
+
+        assert_eq!(machine.get_used_gprs(), vec![GPR::RAX, GPR::R8, GPR::R11]);
+        assert_eq!(
+            machine.get_used_xmms(),
+            vec![XMM::XMM0, XMM::XMM3, XMM::XMM4]
+        );
+    }
 }
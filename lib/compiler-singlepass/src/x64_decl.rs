@@ -171,7 +171,8 @@ impl ArgumentRegisterAllocator {
                 static XMM_SEQ: &'static [XMM] = &[XMM::XMM0, XMM::XMM1, XMM::XMM2, XMM::XMM3];
                 let idx = self.n_gprs + self.n_xmms;
                 match ty {
-                    Type::I32 | Type::I64 => {
+                    // References are passed as pointers.
+                    Type::I32 | Type::I64 | Type::FuncRef | Type::ExternRef => {
                         if idx < 4 {
                             let gpr = GPR_SEQ[idx];
                             self.n_gprs += 1;
@@ -209,7 +210,8 @@ impl ArgumentRegisterAllocator {
                     XMM::XMM7,
                 ];
                 match ty {
-                    Type::I32 | Type::I64 => {
+                    // References are passed as pointers.
+                    Type::I32 | Type::I64 | Type::FuncRef | Type::ExternRef => {
                         if self.n_gprs < GPR_SEQ.len() {
                             let gpr = GPR_SEQ[self.n_gprs];
                             self.n_gprs += 1;
@@ -6,6 +6,7 @@ use crate::sourceloc::SourceLoc;
 
 /// Single source location to generated address mapping.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct InstructionAddressMap {
     /// Original source location.
     pub srcloc: SourceLoc,
@@ -21,6 +22,7 @@ pub struct InstructionAddressMap {
 #[derive(
     rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq, Default,
 )]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct FunctionAddressMap {
     /// Instructions maps.
     /// The array is sorted by the InstructionAddressMap::code_offset field.
@@ -38,3 +40,70 @@ pub struct FunctionAddressMap {
     /// Generated function body length.
     pub body_len: usize,
 }
+
+/// Mapping from the generated code of a function back to the offsets of the
+/// WebAssembly operators it was generated from.
+#[derive(
+    rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq, Default,
+)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
+pub struct TraceInfo {
+    /// Pairs of `(native_offset, wasm_offset)`: the code starting at
+    /// `native_offset` in the function body was generated for the operator
+    /// at `wasm_offset` in the module.
+    ///
+    /// The array is sorted by, and has no duplicates of, `native_offset`.
+    pub entries: Vec<(u32, u32)>,
+}
+
+impl TraceInfo {
+    /// Record that the code from `native_offset` on is generated for the
+    /// operator at `wasm_offset`.
+    ///
+    /// `native_offset` must not be lower than the one of any previous entry.
+    /// An operator that generated no code is superseded by the next one.
+    pub fn push(&mut self, native_offset: u32, wasm_offset: u32) {
+        match self.entries.last_mut() {
+            Some(last) if last.0 == native_offset => last.1 = wasm_offset,
+            _ => self.entries.push((native_offset, wasm_offset)),
+        }
+    }
+
+    /// The offset of the operator the code at `native_offset` in the function
+    /// body was generated for, `None` if it precedes the first operator.
+    pub fn wasm_offset_at(&self, native_offset: u32) -> Option<u32> {
+        let index = match self
+            .entries
+            .binary_search_by_key(&native_offset, |&(native, _)| native)
+        {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(index) => index - 1,
+        };
+        Some(self.entries[index].1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_info_lookup() {
+        let mut trace = TraceInfo::default();
+        trace.push(4, 10);
+        trace.push(8, 11);
+        trace.push(8, 12);
+        trace.push(20, 13);
+        assert_eq!(trace.entries, [(4, 10), (8, 12), (20, 13)]);
+
+        assert_eq!(trace.wasm_offset_at(0), None);
+        assert_eq!(trace.wasm_offset_at(3), None);
+        assert_eq!(trace.wasm_offset_at(4), Some(10));
+        assert_eq!(trace.wasm_offset_at(7), Some(10));
+        assert_eq!(trace.wasm_offset_at(8), Some(12));
+        assert_eq!(trace.wasm_offset_at(19), Some(12));
+        assert_eq!(trace.wasm_offset_at(100), Some(13));
+        assert_eq!(TraceInfo::default().wasm_offset_at(0), None);
+    }
+}
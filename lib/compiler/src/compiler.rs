@@ -2,15 +2,15 @@
 //! compilers will need to implement.
 
 use crate::error::CompileError;
-use crate::function::Compilation;
+use crate::function::{Compilation, CompiledFunction};
 use crate::lib::std::boxed::Box;
-use crate::module::CompileModuleInfo;
+use crate::module::{CompileModuleInfo, MeteringInfo};
 use crate::target::Target;
 use crate::FunctionBodyData;
 use crate::ModuleTranslationState;
 use crate::SectionIndex;
 use wasmer_types::entity::PrimaryMap;
-use wasmer_types::{Features, FunctionIndex, LocalFunctionIndex, SignatureIndex};
+use wasmer_types::{Features, FunctionIndex, LocalFunctionIndex, ModuleInfo, SignatureIndex};
 use wasmparser::{Validator, WasmFeatures};
 
 /// The compiler configuration options.
@@ -73,6 +73,13 @@ where
 
 /// An implementation of a Compiler from parsed WebAssembly module to Compiled native code.
 pub trait Compiler: Send {
+    /// The name of the compiler, such as `singlepass`.
+    ///
+    /// Defaults to the name of the implementing type.
+    fn name(&self) -> &str {
+        core::any::type_name::<Self>()
+    }
+
     /// Validates a module.
     ///
     /// It returns the a succesful Result in case is valid, `CompileError` in case is not.
@@ -81,25 +88,7 @@ pub trait Compiler: Send {
         features: &Features,
         data: &'data [u8],
     ) -> Result<(), CompileError> {
-        let mut validator = Validator::new();
-        let wasm_features = WasmFeatures {
-            bulk_memory: features.bulk_memory,
-            threads: features.threads,
-            reference_types: features.reference_types,
-            multi_value: features.multi_value,
-            simd: features.simd,
-            tail_call: features.tail_call,
-            module_linking: features.module_linking,
-            multi_memory: features.multi_memory,
-            memory64: features.memory64,
-            exceptions: features.exceptions,
-            deterministic_only: false,
-        };
-        validator.wasm_features(wasm_features);
-        validator
-            .validate_all(data)
-            .map_err(|e| CompileError::Validate(format!("{}", e)))?;
-        Ok(())
+        validate_module(features, data)
     }
 
     /// Compiles a parsed module.
@@ -114,6 +103,31 @@ pub trait Compiler: Send {
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
     ) -> Result<Compilation, CompileError>;
 
+    /// Compiles the single local function `index` of a parsed module, whose
+    /// body is `body`.
+    ///
+    /// The result is the same as the function's entry in the [`Compilation`]
+    /// returned by [`Compiler::compile_module`]. Compilers that can't compile
+    /// functions separately return [`CompileError::UnsupportedFeature`].
+    fn compile_function(
+        &self,
+        _target: &Target,
+        _module: &CompileModuleInfo,
+        _module_translation: &ModuleTranslationState,
+        _index: LocalFunctionIndex,
+        _body: &FunctionBodyData<'_>,
+    ) -> Result<CompiledFunction, CompileError> {
+        Err(CompileError::UnsupportedFeature(format!(
+            "compiling a single function with {}",
+            self.name()
+        )))
+    }
+
+    /// Describes how the code this compiler generates for `module` meters gas.
+    fn metering_info(&self, _module: &ModuleInfo) -> MeteringInfo {
+        MeteringInfo::default()
+    }
+
     /// Compiles a module into a native object file.
     ///
     /// It returns the bytes as a `&[u8]` or a [`CompileError`].
@@ -158,3 +172,144 @@ pub trait SymbolRegistry: Send + Sync {
     /// This function is the inverse of [`SymbolRegistry::symbol_to_name`]
     fn name_to_symbol(&self, name: &str) -> Option<Symbol>;
 }
+
+/// Validates a module against the given features.
+///
+/// The validation error reports the byte offset at which it was detected.
+pub fn validate_module(features: &Features, data: &[u8]) -> Result<(), CompileError> {
+    let mut validator = Validator::new();
+    validator.wasm_features(wasm_features(features));
+    validator
+        .validate_all(data)
+        .map_err(|e| CompileError::Validate(format!("{}", e)))?;
+    Ok(())
+}
+
+fn wasm_features(features: &Features) -> WasmFeatures {
+    WasmFeatures {
+        bulk_memory: features.bulk_memory,
+        threads: features.threads,
+        reference_types: features.reference_types,
+        multi_value: features.multi_value,
+        simd: features.simd,
+        tail_call: features.tail_call,
+        module_linking: features.module_linking,
+        multi_memory: features.multi_memory,
+        memory64: features.memory64,
+        exceptions: features.exceptions,
+        deterministic_only: false,
+    }
+}
+
+/// Reads a module from `reader`, validating it against the given features
+/// along the way, and returns its bytes once it has been read whole.
+///
+/// Sections and function bodies are validated as soon as they have been
+/// read, so an invalid module is rejected without reading past the error.
+#[cfg(feature = "std")]
+pub fn read_and_validate_module(
+    features: &Features,
+    reader: &mut dyn std::io::Read,
+) -> Result<Vec<u8>, CompileError> {
+    use crate::WasmError;
+    use std::mem;
+    use wasmparser::{BinaryReaderError, Chunk, Parser, Payload, ValidPayload};
+
+    const READ_SIZE: usize = 0x1_0000;
+    let validate_error = |e: BinaryReaderError| CompileError::Validate(format!("{}", e));
+    let mut validator = Validator::new();
+    validator.wasm_features(wasm_features(features));
+    let mut binary = Vec::new();
+    let mut parsed = 0;
+    let mut eof = false;
+    let mut parser = Parser::new(0);
+    // Parsers of the enclosing modules while a nested module is parsed.
+    let mut parsers = Vec::new();
+    loop {
+        let (consumed, payload) = match parser
+            .parse(&binary[parsed..], eof)
+            .map_err(validate_error)?
+        {
+            Chunk::NeedMoreData(_) => {
+                let len = binary.len();
+                binary.resize(len + READ_SIZE, 0);
+                let read = loop {
+                    match reader.read(&mut binary[len..]) {
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                        result => break result,
+                    }
+                };
+                let read = read.map_err(|e| {
+                    CompileError::Wasm(WasmError::Generic(format!(
+                        "failed to read the module: {}",
+                        e
+                    )))
+                })?;
+                binary.truncate(len + read);
+                eof = read == 0;
+                continue;
+            }
+            Chunk::Parsed { consumed, payload } => (consumed, payload),
+        };
+        match validator.payload(&payload).map_err(validate_error)? {
+            ValidPayload::Ok => {}
+            ValidPayload::Submodule(submodule) => {
+                parsers.push(mem::replace(&mut parser, submodule));
+            }
+            ValidPayload::Func(mut func_validator, body) => {
+                func_validator.validate(&body).map_err(validate_error)?;
+            }
+        }
+        let end = matches!(payload, Payload::End);
+        parsed += consumed;
+        if end {
+            match parsers.pop() {
+                Some(enclosing) => parser = enclosing,
+                None => break,
+            }
+        }
+    }
+    binary.truncate(parsed);
+    Ok(binary)
+}
+
+/// Returns a minimal set of features the module needs to be valid.
+///
+/// Features are dropped one by one, in declaration order, as long as the
+/// module stays valid. When either of two features is enough for some
+/// construct, only the one declared last is kept. Fails with the validation
+/// error if the module is invalid even with every feature enabled.
+pub fn required_features(data: &[u8]) -> Result<Features, CompileError> {
+    let mut features = Features {
+        threads: true,
+        reference_types: true,
+        simd: true,
+        bulk_memory: true,
+        multi_value: true,
+        tail_call: true,
+        module_linking: true,
+        multi_memory: true,
+        memory64: true,
+        exceptions: true,
+    };
+    validate_module(&features, data)?;
+    let flags: [fn(&mut Features) -> &mut bool; 10] = [
+        |f| &mut f.threads,
+        |f| &mut f.reference_types,
+        |f| &mut f.simd,
+        |f| &mut f.bulk_memory,
+        |f| &mut f.multi_value,
+        |f| &mut f.tail_call,
+        |f| &mut f.module_linking,
+        |f| &mut f.multi_memory,
+        |f| &mut f.memory64,
+        |f| &mut f.exceptions,
+    ];
+    for flag in flags.iter() {
+        *flag(&mut features) = false;
+        if validate_module(&features, data).is_err() {
+            *flag(&mut features) = true;
+        }
+    }
+    Ok(features)
+}
@@ -1,6 +1,8 @@
 use crate::lib::std::string::String;
+use crate::limits::ModuleLimit;
 #[cfg(feature = "std")]
 use thiserror::Error;
+use wasmer_types::LocalFunctionIndex;
 
 // Compilation Errors
 //
@@ -41,6 +43,21 @@ pub enum CompileError {
     #[cfg_attr(feature = "std", error("Insufficient resources: {0}"))]
     Resource(String),
 
+    /// The compiled body of a function is too large to be described by a `u32` length.
+    #[cfg_attr(
+        feature = "std",
+        error("Body of local function {} is {1} bytes, which exceeds 4GiB", .0.as_u32())
+    )]
+    FunctionTooLarge(LocalFunctionIndex, usize),
+
+    /// The module declares more of something than allowed by the engine's
+    /// [`ModuleLimits`](crate::ModuleLimits).
+    #[cfg_attr(
+        feature = "std",
+        error("The module declares {1} {0}, which exceeds the limit of {2}")
+    )]
+    LimitExceeded(ModuleLimit, usize, usize),
+
     /// Cannot downcast the engine to a specific type.
     #[cfg_attr(
         feature = "std",
@@ -4,12 +4,13 @@
 //! A `Compilation` contains the compiled function bodies for a WebAssembly
 //! module (`CompiledFunction`).
 
+use crate::lib::std::time::Duration;
 use crate::lib::std::vec::Vec;
 use crate::section::{CustomSection, SectionIndex};
 use crate::trap::TrapInformation;
 use crate::{
     CompiledFunctionUnwindInfo, CompiledFunctionUnwindInfoRef, FunctionAddressMap,
-    JumpTableOffsets, Relocation,
+    JumpTableOffsets, Relocation, TraceInfo,
 };
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
@@ -21,6 +22,7 @@ use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
 #[derive(
     rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq, Default,
 )]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct CompiledFunctionFrameInfo {
     /// The traps (in the function body).
     ///
@@ -33,6 +35,7 @@ pub struct CompiledFunctionFrameInfo {
 
 /// The function body.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct FunctionBody {
     /// The function body bytes.
     pub body: Vec<u8>,
@@ -50,6 +53,17 @@ pub struct FunctionBodyRef<'a> {
     pub unwind_info: Option<CompiledFunctionUnwindInfoRef<'a>>,
 }
 
+impl<'a> FunctionBodyRef<'a> {
+    /// The BLAKE3 hash of the function body bytes.
+    ///
+    /// Function bodies can be cached by this hash, to only compile again the
+    /// functions that changed. The unwind info is not part of the hash.
+    #[cfg(feature = "std")]
+    pub fn compute_hash(&self) -> [u8; 32] {
+        *blake3::hash(self.body).as_bytes()
+    }
+}
+
 impl<'a> From<&'a FunctionBody> for FunctionBodyRef<'a> {
     fn from(body: &'a FunctionBody) -> Self {
         FunctionBodyRef {
@@ -74,6 +88,7 @@ impl<'a> From<&'a ArchivedFunctionBody> for FunctionBodyRef<'a> {
 /// (function bytecode body, relocations, traps, jump tables
 /// and unwind information).
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct CompiledFunction {
     /// The function body.
     pub body: FunctionBody,
@@ -86,6 +101,9 @@ pub struct CompiledFunction {
 
     /// The frame information.
     pub frame_info: CompiledFunctionFrameInfo,
+
+    /// The native to WebAssembly offset mapping of the body.
+    pub trace_info: TraceInfo,
 }
 
 /// The compiled functions map (index in the Wasm -> function)
@@ -101,6 +119,7 @@ pub type CustomSections = PrimaryMap<SectionIndex, CustomSection>;
 /// In the future this structure may also hold other information useful
 /// for debugging.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, PartialEq, Eq, Clone)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct Dwarf {
     /// The section index in the [`Compilation`] that corresponds to the exception frames.
     /// [Learn
@@ -117,6 +136,7 @@ impl Dwarf {
 
 /// Trampolines section used by ARM short jump (26bits)
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, PartialEq, Eq, Clone)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct TrampolinesSection {
     /// SectionIndex for the actual Trampolines code
     pub section_index: SectionIndex,
@@ -138,7 +158,10 @@ impl TrampolinesSection {
 }
 
 /// The result of compiling a WebAssembly module's functions.
-#[derive(Debug, PartialEq, Eq)]
+///
+/// Compilations are compared by what they hold, the time the compiler took
+/// to produce them isn't part of the comparison.
+#[derive(Debug, Eq)]
 pub struct Compilation {
     /// Compiled code for the function bodies.
     functions: Functions,
@@ -185,6 +208,47 @@ pub struct Compilation {
 
     /// Trampolines for the arch that needs it
     trampolines: Option<TrampolinesSection>,
+
+    /// Time spent in the compiler, if it keeps track of it.
+    profile: CompilationProfile,
+}
+
+/// Time spent in the phases of compiling a module and loading its code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompilationProfile {
+    /// Validating the module.
+    pub validation: Duration,
+    /// Translating the module, i.e. parsing everything but function bodies.
+    pub translation: Duration,
+    /// Compiling the fastest function body.
+    pub function_compilation_min: Duration,
+    /// Compiling the slowest function body.
+    pub function_compilation_max: Duration,
+    /// Compiling all function bodies, summed over functions compiled in parallel.
+    pub function_compilation_total: Duration,
+    /// Generating the trampolines.
+    pub trampoline_generation: Duration,
+    /// Allocating and copying the code into executable memory.
+    pub code_allocation: Duration,
+    /// Applying relocations to the allocated code.
+    pub relocation: Duration,
+}
+
+impl CompilationProfile {
+    /// Set the function compilation times from the time taken by each function.
+    pub fn set_function_compilation_times(&mut self, times: impl IntoIterator<Item = Duration>) {
+        let mut times = times.into_iter();
+        let first = match times.next() {
+            Some(first) => first,
+            None => return,
+        };
+        let (min, max, total) = times.fold((first, first, first), |(min, max, total), time| {
+            (min.min(time), max.max(time), total + time)
+        });
+        self.function_compilation_min = min;
+        self.function_compilation_max = max;
+        self.function_compilation_total = total;
+    }
 }
 
 impl Compilation {
@@ -204,9 +268,21 @@ impl Compilation {
             dynamic_function_trampolines,
             debug,
             trampolines,
+            profile: CompilationProfile::default(),
         }
     }
 
+    /// Attach the time spent by the compiler to this compilation.
+    pub fn with_profile(mut self, profile: CompilationProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Gets the time spent by the compiler, as far as it keeps track of it.
+    pub fn get_profile(&self) -> &CompilationProfile {
+        &self.profile
+    }
+
     /// Gets the bytes of a single function
     pub fn get(&self, func: LocalFunctionIndex) -> &CompiledFunction {
         &self.functions[func]
@@ -254,6 +330,14 @@ impl Compilation {
             .collect::<PrimaryMap<LocalFunctionIndex, _>>()
     }
 
+    /// Gets functions trace info.
+    pub fn get_trace_info(&self) -> PrimaryMap<LocalFunctionIndex, TraceInfo> {
+        self.functions
+            .iter()
+            .map(|(_, func)| func.trace_info.clone())
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>()
+    }
+
     /// Gets function call trampolines.
     pub fn get_function_call_trampolines(&self) -> PrimaryMap<SignatureIndex, FunctionBody> {
         self.function_call_trampolines.clone()
@@ -288,6 +372,17 @@ impl Compilation {
     }
 }
 
+impl PartialEq for Compilation {
+    fn eq(&self, other: &Self) -> bool {
+        self.functions == other.functions
+            && self.custom_sections == other.custom_sections
+            && self.function_call_trampolines == other.function_call_trampolines
+            && self.dynamic_function_trampolines == other.dynamic_function_trampolines
+            && self.debug == other.debug
+            && self.trampolines == other.trampolines
+    }
+}
+
 impl<'a> IntoIterator for &'a Compilation {
     type IntoIter = Iter<'a>;
     type Item = <Self::IntoIter as Iterator>::Item;
@@ -310,3 +405,80 @@ impl<'a> Iterator for Iter<'a> {
         self.iterator.next().map(|(_, b)| b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CustomSectionProtection, JumpTable, RelocationKind, RelocationTarget, SectionBody,
+    };
+    use wasmer_types::entity::EntityRef;
+
+    fn relocation(reloc_target: RelocationTarget) -> Relocation {
+        Relocation {
+            kind: RelocationKind::X86PCRel4,
+            reloc_target,
+            offset: 0,
+            addend: 0,
+        }
+    }
+
+    fn function(relocations: Vec<Relocation>) -> CompiledFunction {
+        CompiledFunction {
+            body: FunctionBody {
+                body: vec![0xc3],
+                unwind_info: None,
+            },
+            relocations,
+            jt_offsets: JumpTableOffsets::new(),
+            frame_info: CompiledFunctionFrameInfo::default(),
+            trace_info: TraceInfo::default(),
+        }
+    }
+
+    fn compilation(functions: usize, sections: usize, debug: bool) -> Compilation {
+        let functions = (0..functions)
+            .map(|_| {
+                function(vec![
+                    relocation(RelocationTarget::LocalFunc(LocalFunctionIndex::new(0))),
+                    relocation(RelocationTarget::JumpTable(
+                        LocalFunctionIndex::new(0),
+                        JumpTable::new(0),
+                    )),
+                ])
+            })
+            .collect();
+        let custom_sections = (0..sections)
+            .map(|_| CustomSection {
+                protection: CustomSectionProtection::Read,
+                bytes: SectionBody::new_with_vec(vec![1, 2, 3]),
+                relocations: vec![relocation(RelocationTarget::CustomSection(
+                    SectionIndex::new(0),
+                ))],
+            })
+            .collect();
+        let body = || FunctionBody {
+            body: vec![0xc3],
+            unwind_info: None,
+        };
+        Compilation::new(
+            functions,
+            custom_sections,
+            vec![body()].into_iter().collect(),
+            vec![body(), body()].into_iter().collect(),
+            debug.then(|| Dwarf::new(SectionIndex::new(0))),
+            None,
+        )
+    }
+
+    #[test]
+    fn profile_is_not_compared() {
+        let profile = CompilationProfile {
+            validation: Duration::from_millis(1),
+            ..CompilationProfile::default()
+        };
+        let profiled = compilation(1, 1, false).with_profile(profile);
+        assert_eq!(profiled, compilation(1, 1, false));
+        assert_ne!(profiled, compilation(2, 1, false));
+    }
+}
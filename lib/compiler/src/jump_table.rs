@@ -13,6 +13,7 @@ use wasmer_types::entity::{entity_impl, SecondaryMap};
 /// 0-based jump offsets.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
 #[archive_attr(derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord))]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct JumpTable(u32);
 
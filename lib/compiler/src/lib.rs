@@ -39,13 +39,13 @@ mod lib {
     #[cfg(feature = "core")]
     pub mod std {
         pub use alloc::{borrow, boxed, str, string, sync, vec};
-        pub use core::fmt;
+        pub use core::{fmt, time};
         pub use hashbrown as collections;
     }
 
     #[cfg(feature = "std")]
     pub mod std {
-        pub use std::{borrow, boxed, collections, fmt, str, string, sync, vec};
+        pub use std::{borrow, boxed, collections, fmt, str, string, sync, time, vec};
     }
 }
 
@@ -55,6 +55,7 @@ mod compiler;
 mod error;
 mod function;
 mod jump_table;
+mod limits;
 mod module;
 mod relocation;
 mod target;
@@ -66,18 +67,23 @@ mod translator;
 mod section;
 mod sourceloc;
 
-pub use crate::address_map::{FunctionAddressMap, InstructionAddressMap};
+pub use crate::address_map::{FunctionAddressMap, InstructionAddressMap, TraceInfo};
+#[cfg(all(feature = "translator", feature = "std"))]
+pub use crate::compiler::read_and_validate_module;
 #[cfg(feature = "translator")]
-pub use crate::compiler::{Compiler, CompilerConfig, Symbol, SymbolRegistry};
+pub use crate::compiler::{
+    required_features, validate_module, Compiler, CompilerConfig, Symbol, SymbolRegistry,
+};
 pub use crate::error::{
     CompileError, MiddlewareError, ParseCpuFeatureError, WasmError, WasmResult,
 };
 pub use crate::function::{
-    Compilation, CompiledFunction, CompiledFunctionFrameInfo, CustomSections, Dwarf, FunctionBody,
-    FunctionBodyRef, Functions, TrampolinesSection,
+    Compilation, CompilationProfile, CompiledFunction, CompiledFunctionFrameInfo, CustomSections,
+    Dwarf, FunctionBody, FunctionBodyRef, Functions, TrampolinesSection,
 };
 pub use crate::jump_table::{JumpTable, JumpTableOffsets};
-pub use crate::module::CompileModuleInfo;
+pub use crate::limits::{ImportAllowList, ModuleLimit, ModuleLimits};
+pub use crate::module::{CompileModuleInfo, MeteringInfo};
 pub use crate::relocation::{Relocation, RelocationKind, RelocationTarget, Relocations};
 pub use crate::section::{
     CustomSection, CustomSectionProtection, CustomSectionRef, SectionBody, SectionIndex,
@@ -95,7 +101,7 @@ pub use crate::translator::{
 pub use crate::trap::TrapInformation;
 pub use crate::unwind::{CompiledFunctionUnwindInfo, CompiledFunctionUnwindInfoRef};
 
-pub use wasmer_types::Features;
+pub use wasmer_types::{Features, FeaturesDiff, UnknownFeatureError};
 
 #[cfg(feature = "translator")]
 /// wasmparser is exported as a module to slim compiler dependencies
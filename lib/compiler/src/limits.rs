@@ -0,0 +1,177 @@
+//! Limits on what a module may declare, checked before compiling it.
+use crate::error::CompileError;
+use crate::lib::std::collections::HashMap;
+use crate::lib::std::fmt;
+use crate::lib::std::string::String;
+#[cfg(feature = "translator")]
+use crate::translator::ModuleEnvironment;
+use wasmer_types::{ExternType, ImportIndex, ModuleInfo};
+
+/// Caps on the entities a WebAssembly module may declare.
+///
+/// Modules exceeding any of them are rejected with
+/// [`CompileError::LimitExceeded`] before being compiled. Every limit is
+/// unset, that is unlimited, by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModuleLimits {
+    /// Maximum number of functions defined by the module.
+    pub max_functions: Option<usize>,
+    /// Maximum number of imports, of any kind.
+    pub max_imports: Option<usize>,
+    /// Maximum number of tables, imported ones included.
+    pub max_tables: Option<usize>,
+    /// Maximum number of memories, imported ones included.
+    pub max_memories: Option<usize>,
+    /// Maximum total size, in bytes, of the function bodies.
+    pub max_code_size: Option<usize>,
+}
+
+/// One of the limits of [`ModuleLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleLimit {
+    /// [`ModuleLimits::max_functions`].
+    Functions,
+    /// [`ModuleLimits::max_imports`].
+    Imports,
+    /// [`ModuleLimits::max_tables`].
+    Tables,
+    /// [`ModuleLimits::max_memories`].
+    Memories,
+    /// [`ModuleLimits::max_code_size`].
+    CodeSize,
+}
+
+impl fmt::Display for ModuleLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Functions => "functions",
+            Self::Imports => "imports",
+            Self::Tables => "tables",
+            Self::Memories => "memories",
+            Self::CodeSize => "bytes of code",
+        })
+    }
+}
+
+impl ModuleLimits {
+    fn check_limit(
+        limit: ModuleLimit,
+        max: Option<usize>,
+        count: usize,
+    ) -> Result<(), CompileError> {
+        match max {
+            Some(max) if count > max => Err(CompileError::LimitExceeded(limit, count, max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that a translated module stays within these limits.
+    #[cfg(feature = "translator")]
+    pub fn check(&self, translation: &ModuleEnvironment<'_>) -> Result<(), CompileError> {
+        let module = &translation.module;
+        let code_size = translation
+            .function_body_inputs
+            .values()
+            .map(|body| body.data.len())
+            .sum();
+        Self::check_limit(
+            ModuleLimit::Functions,
+            self.max_functions,
+            translation.function_body_inputs.len(),
+        )?;
+        Self::check_limit(ModuleLimit::Imports, self.max_imports, module.imports.len())?;
+        Self::check_limit(ModuleLimit::Tables, self.max_tables, module.tables.len())?;
+        Self::check_limit(
+            ModuleLimit::Memories,
+            self.max_memories,
+            module.memories.len(),
+        )?;
+        Self::check_limit(ModuleLimit::CodeSize, self.max_code_size, code_size)
+    }
+}
+
+/// The only imports a module may declare, with their types.
+///
+/// Embedders needing deterministic execution can list the imports known to be
+/// deterministic, so that modules depending on anything else, say a source of
+/// randomness, are rejected with [`CompileError::Validate`] before being
+/// compiled.
+///
+/// Imported functions and globals must have exactly the type they are allowed
+/// with. Imported memories and tables only need to be memories and tables,
+/// their limits are checked when they are linked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportAllowList {
+    allowed: HashMap<(String, String), ExternType>,
+}
+
+impl ImportAllowList {
+    /// Create an allow-list with no imports allowed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow importing `module`.`field` with type `ty`.
+    pub fn allow(mut self, module: &str, field: &str, ty: ExternType) -> Self {
+        self.allowed.insert((module.into(), field.into()), ty);
+        self
+    }
+
+    /// Check that every import of `module` is allowed.
+    pub fn check(&self, module: &ModuleInfo) -> Result<(), CompileError> {
+        fn kind(ty: &ExternType) -> &'static str {
+            match ty {
+                ExternType::Function(_) => "a function",
+                ExternType::Global(_) => "a global",
+                ExternType::Table(_) => "a table",
+                ExternType::Memory(_) => "a memory",
+            }
+        }
+
+        for ((module_name, field, _), index) in module.imports.iter() {
+            let allowed = self
+                .allowed
+                .get(&(module_name.clone(), field.clone()))
+                .ok_or_else(|| {
+                    CompileError::Validate(format!(
+                        "import `{}`.`{}` is not allowed",
+                        module_name, field
+                    ))
+                })?;
+            let actual = match *index {
+                ImportIndex::Function(index) => {
+                    ExternType::Function(module.signatures[module.functions[index]].clone())
+                }
+                ImportIndex::Global(index) => ExternType::Global(module.globals[index]),
+                ImportIndex::Table(index) => ExternType::Table(module.tables[index]),
+                ImportIndex::Memory(index) => ExternType::Memory(module.memories[index]),
+            };
+            let error = match (&actual, allowed) {
+                (ExternType::Function(actual), ExternType::Function(allowed))
+                    if actual != allowed =>
+                {
+                    format!(
+                        "has signature {} but is only allowed as {}",
+                        actual, allowed
+                    )
+                }
+                (ExternType::Global(actual), ExternType::Global(allowed)) if actual != allowed => {
+                    format!("has type {} but is only allowed as {}", actual, allowed)
+                }
+                (actual, allowed) if kind(actual) != kind(allowed) => {
+                    format!(
+                        "is {} but is only allowed as {}",
+                        kind(actual),
+                        kind(allowed)
+                    )
+                }
+                _ => continue,
+            };
+            return Err(CompileError::Validate(format!(
+                "import `{}`.`{}` {}",
+                module_name, field, error
+            )));
+        }
+        Ok(())
+    }
+}
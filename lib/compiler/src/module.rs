@@ -1,4 +1,6 @@
+use crate::lib::std::string::String;
 use crate::lib::std::sync::Arc;
+use crate::lib::std::vec::Vec;
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{Features, MemoryIndex, ModuleInfo, TableIndex};
 use wasmer_vm::{MemoryStyle, TableStyle};
@@ -9,6 +11,7 @@ use wasmer_vm::{MemoryStyle, TableStyle};
 /// possible after translation (such as the features used for compiling,
 /// or the `MemoryStyle` and `TableStyle`).
 #[derive(Debug, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct CompileModuleInfo {
     /// The features used for compiling the module
     pub features: Features,
@@ -22,3 +25,39 @@ pub struct CompileModuleInfo {
     /// The table plans used for compiling.
     pub table_styles: PrimaryMap<TableIndex, TableStyle>,
 }
+
+/// How the code compiled for a module meters gas, limits the call depth and
+/// checks for interrupts.
+///
+/// Modules whose calls to a gas import were compiled into inline updates of
+/// the `FastGasCounter` need a gas counter in the `InstanceConfig` they are
+/// instantiated with. Only modules whose code limits the call depth can be
+/// instantiated with a maximum function call depth, and only modules whose
+/// code checks for interrupts with an interrupt flag.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
+)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
+pub struct MeteringInfo {
+    /// The `(module, field)` names of the imported functions whose calls are
+    /// compiled into inline updates of the gas counter.
+    pub gas_intrinsic_imports: Vec<(String, String)>,
+    /// The module the gas intrinsic is restricted to, any module if `None`.
+    /// The globals bound to the gas counter are only imported from it too.
+    pub gas_module: Option<String>,
+    /// Whether a gas charge overflowing the counter clamps it to the limit,
+    /// rather than trapping.
+    pub saturate_gas: bool,
+    /// Whether the functions count their frames in the call depth of the
+    /// instance.
+    pub limits_call_depth: bool,
+    /// Whether the loop headers check the interrupt flag of the instance.
+    pub checks_interrupts: bool,
+}
+
+impl MeteringInfo {
+    /// Returns whether the compiled code reads and updates the gas counter.
+    pub fn uses_gas_counter(&self) -> bool {
+        !self.gas_intrinsic_imports.is_empty()
+    }
+}
@@ -19,6 +19,7 @@ use wasmer_vm::libcalls::LibCall;
 
 /// Relocation kinds for every ISA.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Copy, Clone, Debug, PartialEq, Eq)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub enum RelocationKind {
     /// absolute 4-byte
     Abs4,
@@ -82,6 +83,7 @@ impl fmt::Display for RelocationKind {
 
 /// A record of a relocation to perform.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct Relocation {
     /// The relocation kind.
     pub kind: RelocationKind,
@@ -95,6 +97,7 @@ pub struct Relocation {
 
 /// Destination function. Can be either user function or some special one, like `memory.grow`.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Copy, Clone, PartialEq, Eq)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub enum RelocationTarget {
     /// A relocation to a function defined locally in the wasm (not an imported one).
     LocalFunc(LocalFunctionIndex),
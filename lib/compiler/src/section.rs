@@ -12,6 +12,7 @@ use wasmer_types::entity::entity_impl;
 /// Index type of a Section defined inside a WebAssembly `Compilation`.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
 #[archive_attr(derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug))]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub struct SectionIndex(u32);
 
@@ -23,6 +24,7 @@ entity_impl!(ArchivedSectionIndex);
 ///
 /// Determines how a custom section may be used.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Copy, Clone, PartialEq, Eq)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub enum CustomSectionProtection {
     /// A custom section with read permission.
     Read,
@@ -36,6 +38,7 @@ pub enum CustomSectionProtection {
 /// This is used so compilers can store arbitrary information
 /// in the emitted module.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct CustomSection {
     /// Memory protection that applies to this section.
     pub protection: CustomSectionProtection,
@@ -88,6 +91,7 @@ impl<'a> From<&'a ArchivedCustomSection> for CustomSectionRef<'a> {
 #[derive(
     rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq, Default,
 )]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct SectionBody(Vec<u8>);
 
 impl SectionBody {
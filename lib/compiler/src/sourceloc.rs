@@ -15,6 +15,7 @@ use crate::lib::std::fmt;
 /// that can't be given a real source location.
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, Copy, PartialEq, Eq)]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct SourceLoc(u32);
 
@@ -5,7 +5,7 @@ use crate::lib::std::borrow::ToOwned;
 use crate::lib::std::string::ToString;
 use crate::lib::std::{boxed::Box, string::String, vec::Vec};
 use crate::translate_module;
-use crate::{WasmError, WasmResult};
+use crate::{CompileError, WasmError, WasmResult};
 use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
 use wasmer_types::entity::PrimaryMap;
@@ -44,6 +44,9 @@ pub struct ModuleEnvironment<'data> {
 
     /// The decoded Wasm types for the module.
     pub module_translation_state: Option<ModuleTranslationState>,
+
+    /// The wasm binary this environment has been translated from.
+    pub wasm: &'data [u8],
 }
 
 impl<'data> ModuleEnvironment<'data> {
@@ -54,6 +57,7 @@ impl<'data> ModuleEnvironment<'data> {
             function_body_inputs: PrimaryMap::new(),
             data_initializers: Vec::new(),
             module_translation_state: None,
+            wasm: &[],
         }
     }
 
@@ -63,10 +67,67 @@ impl<'data> ModuleEnvironment<'data> {
         assert!(self.module_translation_state.is_none());
         let module_translation_state = translate_module(data, &mut self)?;
         self.module_translation_state = Some(module_translation_state);
+        self.wasm = data;
         Ok(self)
     }
 
+    /// Check that the active segments with a constant offset fit in the
+    /// minimum size of the memory or table they initialize.
+    ///
+    /// Such segments would make instantiation trap anyway, this lets it fail
+    /// at compile time instead. Segments with an offset read from a global, as
+    /// well as the segments of imported memories and tables, whose actual size
+    /// is only known once they are provided, are still checked at
+    /// instantiation.
+    pub fn check_segment_bounds(&self) -> Result<(), CompileError> {
+        let module = &self.module;
+        for (index, data) in self.data_initializers.iter().enumerate() {
+            let location = &data.location;
+            if location.base.is_some() || module.is_imported_memory(location.memory_index) {
+                continue;
+            }
+            let size = module.memories[location.memory_index].minimum.bytes().0;
+            let end = location.offset.checked_add(data.data.len());
+            if end.map_or(true, |end| end > size) {
+                return Err(CompileError::Validate(format!(
+                    "data segment {} at offset {} of {} bytes does not fit in memory {} of {} bytes",
+                    index,
+                    location.offset,
+                    data.data.len(),
+                    location.memory_index.as_u32(),
+                    size
+                )));
+            }
+        }
+        for (index, init) in module.table_initializers.iter().enumerate() {
+            if init.base.is_some() || module.is_imported_table(init.table_index) {
+                continue;
+            }
+            let size = module.tables[init.table_index].minimum as usize;
+            let end = init.offset.checked_add(init.elements.len());
+            if end.map_or(true, |end| end > size) {
+                return Err(CompileError::Validate(format!(
+                    "element segment {} at offset {} of {} elements does not fit in table {} of {} elements",
+                    index,
+                    init.offset,
+                    init.elements.len(),
+                    init.table_index.as_u32(),
+                    size
+                )));
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn declare_export(&mut self, export: ExportIndex, name: &str) -> WasmResult<()> {
+        // Export names must be unique. This is checked by validation already,
+        // but unvalidated modules must not silently lose an export either.
+        if self.module.exports.contains_key(name) {
+            return Err(WasmError::Generic(format!(
+                "duplicate export name `{}`",
+                name
+            )));
+        }
         self.module.exports.insert(String::from(name), export);
         Ok(())
     }
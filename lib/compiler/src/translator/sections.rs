@@ -119,6 +119,7 @@ pub fn parse_import_section<'data>(
                         minimum: Pages(memlimits.initial),
                         maximum: memlimits.maximum.map(Pages),
                         shared,
+                        memory64: false,
                     },
                     module_name,
                     field_name.unwrap_or_default(),
@@ -214,6 +215,7 @@ pub fn parse_memory_section(
                     minimum: Pages(limits.initial),
                     maximum: limits.maximum.map(Pages),
                     shared,
+                    memory64: false,
                 })?;
             }
             WPMemoryType::M64 { .. } => unimplemented!("64bit memory not implemented yet"),
@@ -25,8 +25,8 @@ pub struct ModuleTranslationState {
     /// which are encoded to refer to their type signature via index.
     pub(crate) wasm_types: WasmTypes,
 
-    /// Imported functions names map.
-    pub import_map: HashMap<FunctionIndex, String>,
+    /// Imported functions `(module, field)` names map.
+    pub import_map: HashMap<FunctionIndex, (String, String)>,
 }
 
 impl ModuleTranslationState {
@@ -44,7 +44,8 @@ impl ModuleTranslationState {
             let value = &module.imports[key];
             match value {
                 ImportIndex::Function(index) => {
-                    self.import_map.insert(*index, key.1.clone());
+                    self.import_map
+                        .insert(*index, (key.0.clone(), key.1.clone()));
                 }
                 _ => {
                     // Non-function import.
@@ -3,17 +3,24 @@
 
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
-use std::sync::Arc;
-use wasmer_engine::InstantiationError;
+use std::ptr::{self, NonNull};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use wasmer_compiler::{MeteringInfo, TraceInfo, TrapInformation};
+use wasmer_engine::{
+    Engine, GlobalFrameInfoRegistration, ImportError, InstantiationError, LinkError,
+    ResolvedImports,
+};
 use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
-    DataIndex, ElemIndex, FunctionIndex, GlobalInit, GlobalType, ImportCounts, LocalFunctionIndex,
-    LocalGlobalIndex, MemoryType, OwnedDataInitializer, OwnedTableInitializer, SignatureIndex,
-    TableType,
+    ElemIndex, ExternType, FastGasCounter, FunctionIndex, GlobalIndex, GlobalInit, GlobalType,
+    ImportCounts, LocalFunctionIndex, LocalGlobalIndex, MemoryType, Mutability,
+    OwnedDataInitializer, OwnedTableInitializer, PassiveData, SignatureIndex, TableType, Type,
 };
 use wasmer_vm::{
-    Artifact, FunctionBodyPtr, FunctionExtent, InstanceHandle, Instantiatable, MemoryStyle,
-    Resolver, TableStyle, Tunables, VMImport, VMImportType, VMLocalFunction, VMOffsets,
+    Artifact, ExportFunction, FunctionBodyPtr, FunctionExtent, ImportFunctionEnv, Imports,
+    InstanceHandle, Instantiatable, InstantiationProfile, MemoryStyle, MmapAdvice, Resolver,
+    TableStyle, Tunables, VMFunctionImport, VMImport, VMImportType, VMLocalFunction, VMOffsets,
     VMSharedSignatureIndex,
 };
 
@@ -31,12 +38,18 @@ pub struct UniversalArtifact {
     pub(crate) signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     pub(crate) local_memories: Vec<(MemoryType, MemoryStyle)>,
     pub(crate) data_segments: Vec<OwnedDataInitializer>,
-    pub(crate) passive_data: BTreeMap<DataIndex, Arc<[u8]>>,
+    pub(crate) passive_data: PassiveData,
     pub(crate) local_tables: Vec<(TableType, TableStyle)>,
     pub(crate) element_segments: Vec<OwnedTableInitializer>,
     // TODO: does this need to be a BTreeMap? Can it be a plain vector?
     pub(crate) passive_elements: BTreeMap<ElemIndex, Box<[FunctionIndex]>>,
     pub(crate) local_globals: Vec<(GlobalType, GlobalInit)>,
+    pub(crate) function_trace_info: BoxedSlice<LocalFunctionIndex, TraceInfo>,
+    pub(crate) function_traps: BoxedSlice<LocalFunctionIndex, Box<[TrapInformation]>>,
+    pub(crate) function_names: BTreeMap<FunctionIndex, String>,
+    pub(crate) metering_info: MeteringInfo,
+    pub(crate) module_hash: [u8; 32],
+    pub(crate) frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
 }
 
 impl UniversalArtifact {
@@ -49,31 +62,323 @@ impl UniversalArtifact {
         })
     }
 
+    /// Fault in the code of all the local functions of this artifact, so that
+    /// first calls into them don't pay for it.
+    ///
+    /// The kernel is advised that the code pages will be needed soon, and each
+    /// of them is then read once. This is only an optimization, for deployments
+    /// where the latency of the first calls matters, and it can be called any
+    /// number of times.
+    pub fn warm_up(&self) {
+        let page_size = region::page::size();
+        let mut warmed_up = None;
+        for func in self.functions.values() {
+            let start = *func.body as usize;
+            let end = start + usize::try_from(func.length).unwrap();
+            // Functions are laid out in order, so pages shared with the previous
+            // function were already read.
+            let start = match warmed_up {
+                Some(warmed_up) if warmed_up > start => warmed_up,
+                _ => start & !(page_size - 1),
+            };
+            if start >= end {
+                continue;
+            }
+            // The code lives as long as the artifact, and is always readable.
+            unsafe {
+                let _ = MmapAdvice::WillNeed.apply(start as *const u8, end - start);
+                for page in (start..end).step_by(page_size) {
+                    std::ptr::read_volatile(page as *const u8);
+                }
+            }
+            warmed_up = Some((end + page_size - 1) & !(page_size - 1));
+        }
+    }
+
+    /// Return the name of the specified function, as given by the `name`
+    /// custom section.
+    pub fn function_name(&self, index: FunctionIndex) -> Option<&str> {
+        self.function_names.get(&index).map(|name| &**name)
+    }
+
+    /// Iterate over the functions named by the `name` custom section, in
+    /// index order.
+    pub fn function_names(&self) -> impl Iterator<Item = (FunctionIndex, &str)> + '_ {
+        self.function_names
+            .iter()
+            .map(|(index, name)| (*index, &**name))
+    }
+
+    /// Iterate over the exports of this artifact, in name order.
+    ///
+    /// Export names are unique, and a name yielded here resolves to the same
+    /// entity through `Artifact::export_field`. Several names may export the
+    /// same entity.
+    pub fn exports(&self) -> impl Iterator<Item = (&str, &wasmer_types::ExportIndex)> + '_ {
+        self.exports.iter().map(|(name, index)| (&**name, index))
+    }
+
+    /// Return the native to WebAssembly offset mapping of the specified local
+    /// function.
+    pub fn trace_info(&self, index: LocalFunctionIndex) -> Option<&TraceInfo> {
+        self.function_trace_info.get(index)
+    }
+
+    /// Return the trap sites of the specified local function, in ascending
+    /// code offset order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not a function of this artifact.
+    pub fn function_traps(&self, index: LocalFunctionIndex) -> &[TrapInformation] {
+        &self.function_traps[index]
+    }
+
+    /// Return the reverse trampoline through which the imported function
+    /// `index` is called when a dynamic host function provides it.
+    pub fn dynamic_function_trampoline(&self, index: FunctionIndex) -> Option<FunctionBodyPtr> {
+        self.dynamic_function_trampolines.get(index).copied()
+    }
+
+    /// How the compiled code of this artifact meters gas.
+    pub fn metering_info(&self) -> &MeteringInfo {
+        &self.metering_info
+    }
+
+    /// The buffer holding all the passive data segments of this artifact.
+    #[cfg(feature = "compact-passive-data")]
+    pub fn passive_data_bytes(&self) -> &[u8] {
+        self.passive_data.bytes()
+    }
+
+    /// Return the contents of the passive data segment `idx`, unless the
+    /// module has no such segment.
+    #[cfg(feature = "compact-passive-data")]
+    pub fn passive_segment(&self, idx: wasmer_types::DataIndex) -> Option<&[u8]> {
+        self.passive_data.get(&idx)
+    }
+
+    /// Deregister the frame information of this artifact's functions, so that
+    /// traps in them are no longer symbolicated.
+    ///
+    /// This is done when the artifact is dropped, calling it beforehand is
+    /// only needed to stop symbolicating earlier.
+    pub fn deregister_frame_info(&self) {
+        drop(self.frame_info_registration.lock().unwrap().take());
+    }
+
+    /// The BLAKE3 hash of the wasm binary this artifact has been compiled from.
+    pub fn module_hash(&self) -> [u8; 32] {
+        self.module_hash
+    }
+
     /// Return the engine instance this artifact is loaded into.
     pub fn engine(&self) -> &crate::UniversalEngine {
         &self.engine
     }
-}
 
-impl Instantiatable for UniversalArtifact {
-    type Error = InstantiationError;
+    /// Resolve the imports of this artifact with `resolver`, so that they can be
+    /// reused by any number of [`Self::instantiate_resolved`] calls.
+    pub fn resolve_imports(
+        &self,
+        resolver: &dyn Resolver,
+    ) -> Result<ResolvedImports, InstantiationError> {
+        wasmer_engine::resolve_exports(&self.engine, resolver, &self.imports)
+            .map_err(InstantiationError::Link)
+    }
 
-    unsafe fn instantiate(
+    /// Link `export` as the function this artifact imports as `module`.`field`,
+    /// for [`InstanceHandle::replace_function_import`].
+    ///
+    /// Returns the index of the import, along with its new entry and host env.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no such function import, or if the
+    /// signature of `export` differs from the one of the import.
+    pub fn link_function_import(
+        &self,
+        module: &str,
+        field: &str,
+        export: &ExportFunction,
+    ) -> Result<(FunctionIndex, VMFunctionImport, ImportFunctionEnv), LinkError> {
+        let signature = |sig| {
+            ExternType::Function(
+                self.engine
+                    .lookup_signature(sig)
+                    .expect("VMSharedSignatureIndex not registered with engine (wrong engine?)"),
+            )
+        };
+        let import_error = |error| LinkError::Import(module.to_string(), field.to_string(), error);
+        let mut function_imports = 0;
+        for import in &self.imports {
+            let (sig, static_trampoline) = match import.ty {
+                VMImportType::Function {
+                    sig,
+                    static_trampoline,
+                } => (sig, static_trampoline),
+                _ => continue,
+            };
+            let index = FunctionIndex::new(function_imports);
+            function_imports += 1;
+            if import.module != module || import.field != field {
+                continue;
+            }
+            if export.vm_function.signature != sig {
+                return Err(import_error(ImportError::IncompatibleType(
+                    signature(sig),
+                    signature(export.vm_function.signature),
+                )));
+            }
+            let (import, env) = wasmer_engine::link_function_import(
+                export,
+                sig,
+                static_trampoline,
+                self.dynamic_function_trampolines[index],
+            );
+            return Ok((index, import, env));
+        }
+        Err(import_error(ImportError::UnknownImport(signature(
+            export.vm_function.signature,
+        ))))
+    }
+
+    /// Make the globals imported as `burnt_gas` and `gas_limit`, from the
+    /// module of the gas intrinsic, read these fields of `gas_counter`, see
+    /// `InstanceConfig::with_gas_globals`.
+    fn link_gas_globals(
+        &self,
+        imports: &mut Imports,
+        gas_counter: *mut FastGasCounter,
+    ) -> Result<(), LinkError> {
+        let counter_type = GlobalType::new(Type::I64, Mutability::Const);
+        let mut global_imports = 0;
+        for import in &self.imports {
+            let ty = match import.ty {
+                VMImportType::Global(ty) => ty,
+                _ => continue,
+            };
+            let index = GlobalIndex::new(global_imports);
+            global_imports += 1;
+            if let Some(module) = &self.metering_info.gas_module {
+                if import.module != *module {
+                    continue;
+                }
+            }
+            let burnt_gas = match &*import.field {
+                "burnt_gas" => true,
+                "gas_limit" => false,
+                _ => continue,
+            };
+            if gas_counter.is_null() {
+                return Err(LinkError::Resource(format!(
+                    "no gas counter to bind the `{}` global to",
+                    import.field
+                )));
+            }
+            if ty != counter_type {
+                return Err(LinkError::Import(
+                    import.module.clone(),
+                    import.field.clone(),
+                    ImportError::IncompatibleType(
+                        ExternType::Global(ty),
+                        ExternType::Global(counter_type),
+                    ),
+                ));
+            }
+            // Compiled code reads imported globals through this pointer, only
+            // the first 8 bytes of the definition are accessed for an `i64`.
+            let field = unsafe {
+                if burnt_gas {
+                    ptr::addr_of_mut!((*gas_counter).burnt_gas)
+                } else {
+                    ptr::addr_of_mut!((*gas_counter).gas_limit)
+                }
+            };
+            imports.globals[index].definition = unsafe { NonNull::new_unchecked(field.cast()) };
+        }
+        Ok(())
+    }
+
+    /// Like [`Instantiatable::instantiate`], also recording the time spent in
+    /// each phase of the instantiation, up to the initializers, into `profile`
+    /// if `config` asks for it.
+    ///
+    /// # Safety
+    ///
+    /// See [`InstanceHandle::new`].
+    pub unsafe fn instantiate_with_profile(
         self: Arc<Self>,
         tunables: &dyn Tunables,
         resolver: &dyn Resolver,
         host_state: Box<dyn std::any::Any>,
         config: wasmer_types::InstanceConfig,
-    ) -> Result<InstanceHandle, Self::Error> {
+        profile: &mut InstantiationProfile,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        let resolution_start = config.profile_instantiation.then(Instant::now);
+        let resolved = self.resolve_imports(resolver)?;
+        if let Some(start) = resolution_start {
+            profile.import_resolution = start.elapsed();
+        }
+        // SAFETY: the caller upholds the requirements of `InstanceHandle::new`.
+        unsafe {
+            self.instantiate_resolved_with_profile(tunables, &resolved, host_state, config, profile)
+        }
+    }
+
+    /// Create an `Instance` from this `Artifact` with imports previously
+    /// resolved by [`Self::resolve_imports`].
+    ///
+    /// # Safety
+    ///
+    /// See [`InstanceHandle::new`].
+    pub unsafe fn instantiate_resolved(
+        self: Arc<Self>,
+        tunables: &dyn Tunables,
+        resolved: &ResolvedImports,
+        host_state: Box<dyn std::any::Any>,
+        config: wasmer_types::InstanceConfig,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        // SAFETY: the caller upholds the requirements of `InstanceHandle::new`.
+        unsafe {
+            self.instantiate_resolved_with_profile(
+                tunables,
+                resolved,
+                host_state,
+                config,
+                &mut InstantiationProfile::default(),
+            )
+        }
+    }
+
+    /// Like [`Self::instantiate_resolved`], also recording the time spent
+    /// linking the imports and creating the memories, tables and globals into
+    /// `profile` if `config` asks for it.
+    ///
+    /// # Safety
+    ///
+    /// See [`InstanceHandle::new`].
+    pub unsafe fn instantiate_resolved_with_profile(
+        self: Arc<Self>,
+        tunables: &dyn Tunables,
+        resolved: &ResolvedImports,
+        host_state: Box<dyn std::any::Any>,
+        config: wasmer_types::InstanceConfig,
+        profile: &mut InstantiationProfile,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        let linking_start = config.profile_instantiation.then(Instant::now);
         let (imports, import_function_envs) = {
-            let mut imports = wasmer_engine::resolve_imports(
-                &self.engine,
-                resolver,
+            let mut imports = wasmer_engine::link_imports(
+                resolved,
                 &self.import_counts,
                 &self.imports,
                 &self.dynamic_function_trampolines,
             )
             .map_err(InstantiationError::Link)?;
+            if config.gas_globals {
+                self.link_gas_globals(&mut imports, config.gas_counter)
+                    .map_err(InstantiationError::Link)?;
+            }
 
             // Get the `WasmerEnv::init_with_instance` function pointers and the pointers
             // to the envs to call it on.
@@ -81,55 +386,120 @@ impl Instantiatable for UniversalArtifact {
 
             (imports, import_function_envs)
         };
+        if let Some(start) = linking_start {
+            profile.import_resolution += start.elapsed();
+        }
 
         let (allocator, memory_definition_locations, table_definition_locations) =
-            wasmer_vm::InstanceAllocator::new(self.vmoffsets.clone());
+            wasmer_vm::InstanceAllocator::with_provider(
+                self.vmoffsets.clone(),
+                config.allocator.clone(),
+            );
 
         // Memories
+        let memory_creation_start = config.profile_instantiation.then(Instant::now);
         let mut memories: PrimaryMap<wasmer_types::LocalMemoryIndex, _> =
             PrimaryMap::with_capacity(self.local_memories.len());
         for (idx, (ty, style)) in (self.import_counts.memories..).zip(self.local_memories.iter()) {
-            let memory = tunables
-                .create_vm_memory(&ty, &style, memory_definition_locations[idx as usize])
-                .map_err(|e| {
-                    InstantiationError::Link(wasmer_engine::LinkError::Resource(format!(
-                        "Failed to create memory: {}",
-                        e
-                    )))
-                })?;
+            // SAFETY: the allocator gave out a location for the definition of
+            // every local memory, which the instance will own.
+            let memory = unsafe {
+                tunables.create_vm_memory(&ty, &style, memory_definition_locations[idx as usize])
+            }
+            .map_err(|e| {
+                InstantiationError::Link(wasmer_engine::LinkError::Resource(format!(
+                    "Failed to create memory: {}",
+                    e
+                )))
+            })?;
             memories.push(memory);
         }
+        if let Some(start) = memory_creation_start {
+            profile.memory_creation = start.elapsed();
+        }
 
         // Tables
+        let table_creation_start = config.profile_instantiation.then(Instant::now);
         let mut tables: PrimaryMap<wasmer_types::LocalTableIndex, _> =
             PrimaryMap::with_capacity(self.local_tables.len());
         for (idx, (ty, style)) in (self.import_counts.tables..).zip(self.local_tables.iter()) {
-            let table = tunables
-                .create_vm_table(ty, style, table_definition_locations[idx as usize])
-                .map_err(|e| InstantiationError::Link(wasmer_engine::LinkError::Resource(e)))?;
+            // SAFETY: likewise for the definitions of the local tables.
+            let table = unsafe {
+                tunables.create_vm_table(ty, style, table_definition_locations[idx as usize])
+            }
+            .map_err(|e| InstantiationError::Link(wasmer_engine::LinkError::Resource(e)))?;
             tables.push(table);
         }
+        if let Some(start) = table_creation_start {
+            profile.table_creation = start.elapsed();
+        }
 
         // Globals
+        let global_creation_start = config.profile_instantiation.then(Instant::now);
         let mut globals =
             PrimaryMap::<LocalGlobalIndex, _>::with_capacity(self.local_globals.len());
         for (ty, _) in self.local_globals.iter() {
             globals.push(Arc::new(wasmer_vm::Global::new(*ty)));
         }
+        if let Some(start) = global_creation_start {
+            profile.global_creation = start.elapsed();
+        }
 
         let passive_data = self.passive_data.clone();
-        Ok(InstanceHandle::new(
-            self,
-            allocator,
-            memories.into_boxed_slice(),
-            tables.into_boxed_slice(),
-            globals.into_boxed_slice(),
-            imports,
-            passive_data,
-            host_state,
-            import_function_envs,
-            config,
-        ))
+        // SAFETY: the memories and tables have been created in the locations
+        // given out by the allocator, and the caller upholds the other
+        // requirements of `InstanceHandle::new`.
+        Ok(unsafe {
+            InstanceHandle::new(
+                self,
+                allocator,
+                memories.into_boxed_slice(),
+                tables.into_boxed_slice(),
+                globals.into_boxed_slice(),
+                imports,
+                passive_data,
+                host_state,
+                import_function_envs,
+                config,
+            )
+        })
+    }
+}
+
+impl Drop for UniversalArtifact {
+    /// Deregister the frame information before the fields are dropped.
+    ///
+    /// `engine` may hold the last reference to the `CodeMemory` the functions
+    /// live in, and fields are dropped in declaration order: left to the
+    /// fields, the code memory would be unmapped, and its unwind information
+    /// deregistered, while the frame information still covers its addresses,
+    /// which a new mapping could then reuse. Doing it here ensures that frame
+    /// information is always deregistered before the code memory it describes.
+    fn drop(&mut self) {
+        self.deregister_frame_info();
+    }
+}
+
+impl Instantiatable for UniversalArtifact {
+    type Error = InstantiationError;
+
+    unsafe fn instantiate(
+        self: Arc<Self>,
+        tunables: &dyn Tunables,
+        resolver: &dyn Resolver,
+        host_state: Box<dyn std::any::Any>,
+        config: wasmer_types::InstanceConfig,
+    ) -> Result<InstanceHandle, Self::Error> {
+        // SAFETY: the caller upholds the requirements of `InstanceHandle::new`.
+        unsafe {
+            self.instantiate_with_profile(
+                tunables,
+                resolver,
+                host_state,
+                config,
+                &mut InstantiationProfile::default(),
+            )
+        }
     }
 }
 
@@ -190,4 +560,21 @@ impl Artifact for UniversalArtifact {
                 .nth(import.index()),
         }
     }
+
+    fn function_code(&self, index: LocalFunctionIndex) -> Option<&[u8]> {
+        let function = self.functions.get(index)?;
+        // The code is owned by the engine, which the artifact keeps alive, and
+        // is never written to once published.
+        Some(unsafe {
+            std::slice::from_raw_parts(function.body.0 as *const u8, function.length as usize)
+        })
+    }
+
+    fn function_name(&self, index: FunctionIndex) -> Option<&str> {
+        UniversalArtifact::function_name(self, index)
+    }
+
+    fn imports(&self) -> &[VMImport] {
+        &self.imports
+    }
 }
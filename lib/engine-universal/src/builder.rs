@@ -1,5 +1,7 @@
 use crate::UniversalEngine;
 use wasmer_compiler::{CompilerConfig, Features, Target};
+#[cfg(feature = "compiler")]
+use wasmer_compiler::{ImportAllowList, ModuleLimits};
 
 /// The Universal builder
 pub struct Universal {
@@ -7,6 +9,16 @@ pub struct Universal {
     compiler_config: Option<Box<dyn CompilerConfig>>,
     target: Option<Target>,
     features: Option<Features>,
+    #[cfg(feature = "compiler")]
+    limits: ModuleLimits,
+    zero_code_memory_on_drop: bool,
+    numa_node: Option<u32>,
+    #[cfg(feature = "compiler")]
+    embed_source_hash: bool,
+    #[cfg(feature = "compiler")]
+    check_segment_bounds: bool,
+    #[cfg(feature = "compiler")]
+    import_allow_list: Option<ImportAllowList>,
 }
 
 impl Universal {
@@ -19,6 +31,16 @@ impl Universal {
             compiler_config: Some(compiler_config.into()),
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            limits: ModuleLimits::default(),
+            zero_code_memory_on_drop: false,
+            numa_node: None,
+            #[cfg(feature = "compiler")]
+            embed_source_hash: false,
+            #[cfg(feature = "compiler")]
+            check_segment_bounds: false,
+            #[cfg(feature = "compiler")]
+            import_allow_list: None,
         }
     }
 
@@ -28,6 +50,16 @@ impl Universal {
             compiler_config: None,
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            limits: ModuleLimits::default(),
+            zero_code_memory_on_drop: false,
+            numa_node: None,
+            #[cfg(feature = "compiler")]
+            embed_source_hash: false,
+            #[cfg(feature = "compiler")]
+            check_segment_bounds: false,
+            #[cfg(feature = "compiler")]
+            import_allow_list: None,
         }
     }
 
@@ -38,29 +70,95 @@ impl Universal {
     }
 
     /// Set the features
-    pub fn features(mut self, features: Features) -> Self {
+    pub fn features(self, features: Features) -> Self {
+        self.with_features(features)
+    }
+
+    /// Set the features to compile the modules with, instead of the default
+    /// features of the compiler for the target.
+    ///
+    /// Enabling a feature the compiler doesn't support, such as
+    /// `multi_value` with Singlepass, makes compiling any module fail with
+    /// `CompileError::UnsupportedFeature`.
+    pub fn with_features(mut self, features: Features) -> Self {
         self.features = Some(features);
         self
     }
 
+    /// Set the limits on what the compiled modules may declare
+    #[cfg(feature = "compiler")]
+    pub fn limits(mut self, limits: ModuleLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Zero the memory holding the compiled code before releasing it, once
+    /// the engine is dropped
+    pub fn zero_code_memory_on_drop(mut self, enable: bool) -> Self {
+        self.zero_code_memory_on_drop = enable;
+        self
+    }
+
+    /// Place the compiled code preferably in memory of the NUMA node `node`,
+    /// for the threads running on it
+    ///
+    /// This is only a hint, ignored on platforms without NUMA support.
+    pub fn numa_node(mut self, node: u32) -> Self {
+        self.numa_node = Some(node);
+        self
+    }
+
+    /// Embed the hash of the compilation inputs in the header of the
+    /// serialized executables, see [`source_hash`](crate::source_hash)
+    #[cfg(feature = "compiler")]
+    pub fn embed_source_hash(mut self, enable: bool) -> Self {
+        self.embed_source_hash = enable;
+        self
+    }
+
+    /// Reject the modules whose active segments with a constant offset don't
+    /// fit in the minimum size of their memory or table at compile time,
+    /// instead of trapping when they are instantiated
+    #[cfg(feature = "compiler")]
+    pub fn check_segment_bounds(mut self, enable: bool) -> Self {
+        self.check_segment_bounds = enable;
+        self
+    }
+
+    /// Reject the modules declaring imports that `allow_list` doesn't allow
+    #[cfg(feature = "compiler")]
+    pub fn import_allow_list(mut self, allow_list: ImportAllowList) -> Self {
+        self.import_allow_list = Some(allow_list);
+        self
+    }
+
     /// Build the `UniversalEngine` for this configuration
     #[cfg(feature = "compiler")]
     pub fn engine(self) -> UniversalEngine {
         let target = self.target.unwrap_or_default();
-        if let Some(compiler_config) = self.compiler_config {
+        let engine = if let Some(compiler_config) = self.compiler_config {
             let features = self
                 .features
                 .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
             let compiler = compiler_config.compiler();
             UniversalEngine::new(compiler, target, features)
+                .with_limits(self.limits)
+                .with_embed_source_hash(self.embed_source_hash)
+                .with_check_segment_bounds(self.check_segment_bounds)
+                .with_import_allow_list(self.import_allow_list)
         } else {
             UniversalEngine::headless()
-        }
+        };
+        engine
+            .with_zero_code_memory_on_drop(self.zero_code_memory_on_drop)
+            .with_numa_node(self.numa_node)
     }
 
     /// Build the `UniversalEngine` for this configuration
     #[cfg(not(feature = "compiler"))]
     pub fn engine(self) -> UniversalEngine {
         UniversalEngine::headless()
+            .with_zero_code_memory_on_drop(self.zero_code_memory_on_drop)
+            .with_numa_node(self.numa_node)
     }
 }
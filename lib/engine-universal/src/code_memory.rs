@@ -4,7 +4,7 @@
 //! Memory management for executable code.
 use crate::unwind::UnwindRegistry;
 use wasmer_compiler::{CompiledFunctionUnwindInfoRef, CustomSectionRef, FunctionBodyRef};
-use wasmer_vm::{Mmap, VMFunctionBody};
+use wasmer_vm::{Mmap, MmapAdvice, VMFunctionBody};
 
 /// The optimal alignment for functions.
 ///
@@ -22,6 +22,8 @@ pub struct CodeMemory {
     unwind_registry: UnwindRegistry,
     mmap: Mmap,
     start_of_nonexecutable_pages: usize,
+    zero_on_drop: bool,
+    numa_node: Option<u32>,
 }
 
 impl CodeMemory {
@@ -31,9 +33,26 @@ impl CodeMemory {
             unwind_registry: UnwindRegistry::new(),
             mmap: Mmap::new(),
             start_of_nonexecutable_pages: 0,
+            zero_on_drop: false,
+            numa_node: None,
         }
     }
 
+    /// Zero the memory before releasing it when this is dropped, so that no
+    /// stale code is left behind in freed memory.
+    pub fn zero_on_drop(mut self, zero_on_drop: bool) -> Self {
+        self.zero_on_drop = zero_on_drop;
+        self
+    }
+
+    /// Place the memory on the NUMA node `numa_node`, if any and if the
+    /// platform supports it, so that the code runs faster on the CPUs of
+    /// that node.
+    pub fn numa_node(mut self, numa_node: Option<u32>) -> Self {
+        self.numa_node = numa_node;
+        self
+    }
+
     /// Mutably get the UnwindRegistry.
     pub fn unwind_registry_mut(&mut self) -> &mut UnwindRegistry {
         &mut self.unwind_registry
@@ -79,6 +98,15 @@ impl CodeMemory {
         // 2. Allocate the pages. Mark them all read-write.
 
         self.mmap = Mmap::with_at_least(total_len)?;
+        // The code is copied in linearly below, so let the kernel read ahead.
+        // This is only a hint, failing to apply it is harmless, and it leaves
+        // the contents of the mapping alone.
+        let _ = unsafe { self.mmap.advise(MmapAdvice::Sequential) };
+        // Nothing has been written yet, so all the pages will be allocated
+        // on the node. This is only a hint as well.
+        if let Some(node) = self.numa_node {
+            let _ = self.mmap.prefer_numa_node(node);
+        }
 
         // 3. Determine where the pointers to each function, executable section
         // or data section are. Copy the functions. Collect the addresses of each and return them.
@@ -151,6 +179,30 @@ impl CodeMemory {
         .expect("unable to make memory readonly and executable");
     }
 
+    /// Deregister the unwind information and zero the memory, which is left
+    /// readable and writable, but not executable.
+    pub(crate) fn clear(&mut self) {
+        if self.mmap.is_empty() {
+            return;
+        }
+        // The registered unwind information lives in this memory, it must
+        // not be zeroed before being deregistered.
+        drop(std::mem::replace(
+            &mut self.unwind_registry,
+            UnwindRegistry::new(),
+        ));
+        unsafe {
+            region::protect(
+                self.mmap.as_mut_ptr(),
+                self.mmap.len(),
+                region::Protection::READ_WRITE,
+            )
+            .expect("unable to make code memory writable");
+            std::ptr::write_bytes(self.mmap.as_mut_ptr(), 0, self.mmap.len());
+        }
+        self.start_of_nonexecutable_pages = 0;
+    }
+
     /// Calculates the allocation size of the given compiled function.
     fn function_allocation_size(func: FunctionBodyRef<'_>) -> usize {
         match &func.unwind_info {
@@ -208,6 +260,16 @@ impl CodeMemory {
     }
 }
 
+impl Drop for CodeMemory {
+    fn drop(&mut self) {
+        if self.zero_on_drop {
+            self.clear();
+        }
+        // The memory itself is unmapped when `mmap` is dropped, after the
+        // unwind information it holds, as `unwind_registry` is declared first.
+    }
+}
+
 fn round_up(size: usize, multiple: usize) -> usize {
     debug_assert!(multiple.is_power_of_two());
     (size + (multiple - 1)) & !(multiple - 1)
@@ -216,8 +278,37 @@ fn round_up(size: usize, multiple: usize) -> usize {
 #[cfg(test)]
 mod tests {
     use super::CodeMemory;
+    use wasmer_compiler::FunctionBodyRef;
+
     fn _assert() {
         fn _assert_send_sync<T: Send + Sync>() {}
         _assert_send_sync::<CodeMemory>();
     }
+
+    #[test]
+    fn zero_on_drop() {
+        let body = [0xc3; 64];
+        let function = FunctionBodyRef {
+            body: &body,
+            unwind_info: None,
+        };
+        let mut memory = CodeMemory::new().zero_on_drop(true);
+        let ptr = memory.allocate(&[function], &[], &[]).unwrap().0[0].as_ptr() as *const u8;
+        memory.publish();
+        let code = unsafe { std::slice::from_raw_parts(ptr, body.len()) };
+        assert_eq!(code, &body[..]);
+
+        memory.clear();
+        assert!(code.iter().all(|byte| *byte == 0));
+        assert!(!region::query(ptr)
+            .unwrap()
+            .protection()
+            .contains(region::Protection::EXECUTE));
+
+        drop(memory);
+        // Best-effort: the pages are gone, unless something else got mapped
+        // there in the meantime.
+        #[cfg(target_os = "linux")]
+        assert!(region::query(ptr).is_err());
+    }
 }
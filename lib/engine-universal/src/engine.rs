@@ -2,17 +2,22 @@
 
 use crate::executable::{unrkyv, UniversalExecutableRef};
 use crate::{CodeMemory, UniversalArtifact, UniversalExecutable};
+use enumset::EnumSet;
 use rkyv::de::deserializers::SharedDeserializeMap;
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
-use std::sync::{Arc, Mutex};
 #[cfg(feature = "compiler")]
-use wasmer_compiler::Compiler;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use wasmer_compiler::{
-    CompileError, CustomSectionProtection, CustomSectionRef, FunctionBodyRef, JumpTable,
-    SectionIndex, Target,
+    CompilationProfile, CompileError, CompiledFunctionFrameInfo, CpuFeature,
+    CustomSectionProtection, CustomSectionRef, FunctionBodyRef, JumpTable, SectionIndex, Target,
+    TraceInfo,
 };
-use wasmer_engine::{Engine, EngineId};
+#[cfg(feature = "compiler")]
+use wasmer_compiler::{Compiler, ImportAllowList, ModuleLimits};
+use wasmer_engine::{register_frame_info, Engine, EngineId};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     DataInitializer, ExportIndex, Features, FunctionIndex, FunctionType, FunctionTypeRef,
@@ -25,6 +30,26 @@ use wasmer_vm::{
     VMSharedSignatureIndex, VMTrampoline,
 };
 
+/// Check that the host has all the CPU features code compiled for
+/// `cpu_features` may use.
+///
+/// Such code may use instructions the host doesn't have, or decodes as
+/// different ones, e.g. `lzcnt` runs as `bsr` without LZCNT.
+fn check_host_cpu_features(cpu_features: u64) -> Result<(), CompileError> {
+    let cpu_features = EnumSet::<CpuFeature>::try_from_u64(cpu_features).ok_or_else(|| {
+        CompileError::UnsupportedTarget(format!("unknown CPU features {:#x}", cpu_features))
+    })?;
+    let missing = cpu_features - CpuFeature::for_host();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(CompileError::UnsupportedTarget(format!(
+            "code compiled with CPU features missing from the host: {:?}",
+            missing
+        )))
+    }
+}
+
 /// A WebAssembly `Universal` Engine.
 #[derive(Clone)]
 pub struct UniversalEngine {
@@ -38,6 +63,7 @@ impl UniversalEngine {
     /// Create a new `UniversalEngine` with the given config
     #[cfg(feature = "compiler")]
     pub fn new(compiler: Box<dyn Compiler>, target: Target, features: Features) -> Self {
+        let engine_id = EngineId::new(&target, Some(compiler.name()));
         Self {
             inner: Arc::new(Mutex::new(UniversalEngineInner {
                 compiler: Some(compiler),
@@ -45,9 +71,19 @@ impl UniversalEngine {
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 features,
+                zero_code_memory_on_drop: false,
+                numa_node: None,
+                #[cfg(feature = "compiler")]
+                limits: ModuleLimits::default(),
+                #[cfg(feature = "compiler")]
+                embed_source_hash: false,
+                #[cfg(feature = "compiler")]
+                check_segment_bounds: false,
+                #[cfg(feature = "compiler")]
+                import_allow_list: None,
             })),
             target: Arc::new(target),
-            engine_id: EngineId::default(),
+            engine_id,
         }
     }
 
@@ -73,12 +109,78 @@ impl UniversalEngine {
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 features: Features::default(),
+                zero_code_memory_on_drop: false,
+                numa_node: None,
+                #[cfg(feature = "compiler")]
+                limits: ModuleLimits::default(),
+                #[cfg(feature = "compiler")]
+                embed_source_hash: false,
+                #[cfg(feature = "compiler")]
+                check_segment_bounds: false,
+                #[cfg(feature = "compiler")]
+                import_allow_list: None,
             })),
             target: Arc::new(Target::default()),
             engine_id: EngineId::default(),
         }
     }
 
+    /// Reject the modules exceeding `limits` instead of compiling them.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn with_limits(self, limits: ModuleLimits) -> Self {
+        self.inner_mut().limits = limits;
+        self
+    }
+
+    /// Embed the hash of the compilation inputs in the serialized executables.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn with_embed_source_hash(self, enable: bool) -> Self {
+        self.inner_mut().embed_source_hash = enable;
+        self
+    }
+
+    /// Reject the modules with constant-offset active segments out of the
+    /// bounds of their memory or table instead of compiling them.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn with_check_segment_bounds(self, enable: bool) -> Self {
+        self.inner_mut().check_segment_bounds = enable;
+        self
+    }
+
+    /// Reject the modules declaring imports outside of `allow_list` instead
+    /// of compiling them.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn with_import_allow_list(self, allow_list: Option<ImportAllowList>) -> Self {
+        self.inner_mut().import_allow_list = allow_list;
+        self
+    }
+
+    /// Zero the code memory before releasing it when the engine is dropped.
+    pub(crate) fn with_zero_code_memory_on_drop(self, enable: bool) -> Self {
+        self.inner_mut().zero_code_memory_on_drop = enable;
+        self
+    }
+
+    /// Zero the code memory of the engine right away, as dropping it does
+    /// with `zero_code_memory_on_drop`, but leave it mapped, so that tests
+    /// can read it back.
+    ///
+    /// # Safety
+    ///
+    /// None of the code loaded by this engine may run afterwards.
+    #[doc(hidden)]
+    pub unsafe fn zero_code_memory(&self) {
+        for code_memory in self.inner_mut().code_memory.iter_mut() {
+            code_memory.clear();
+        }
+    }
+
+    /// Place the code memory on the NUMA node `numa_node`, if any.
+    pub(crate) fn with_numa_node(self, numa_node: Option<u32>) -> Self {
+        self.inner_mut().numa_node = numa_node;
+        self
+    }
+
     pub(crate) fn inner(&self) -> std::sync::MutexGuard<'_, UniversalEngineInner> {
         self.inner.lock().unwrap()
     }
@@ -87,18 +189,115 @@ impl UniversalEngine {
         self.inner.lock().unwrap()
     }
 
+    /// The features the modules are compiled with.
+    ///
+    /// These are the features given to the `Universal` builder, or the
+    /// default features of the compiler for the target.
+    pub fn features(&self) -> Features {
+        self.inner().features().clone()
+    }
+
     /// Compile a WebAssembly binary
     #[cfg(feature = "compiler")]
     pub fn compile_universal(
         &self,
         binary: &[u8],
         tunables: &dyn Tunables,
+    ) -> Result<crate::UniversalExecutable, CompileError> {
+        let environ = wasmer_compiler::ModuleEnvironment::new();
+        let translation = environ.translate(binary).map_err(CompileError::Wasm)?;
+        self.compile_from_translation(translation, tunables)
+    }
+
+    /// Compile a WebAssembly binary for `target` rather than for the target
+    /// of this engine, for example to cross-compile it.
+    ///
+    /// The executable is not loaded. Code compiled for another target can't
+    /// be loaded into this engine, but it can be serialized and loaded by an
+    /// engine running on a matching host. `tunables` must be fit for `target`.
+    #[cfg(feature = "compiler")]
+    pub fn compile_universal_for_target(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+        target: &Target,
+    ) -> Result<crate::UniversalExecutable, CompileError> {
+        let environ = wasmer_compiler::ModuleEnvironment::new();
+        let translation = environ.translate(binary).map_err(CompileError::Wasm)?;
+        self.compile_translation(
+            translation,
+            tunables,
+            target,
+            &mut CompilationProfile::default(),
+        )
+    }
+
+    /// Validate and compile a WebAssembly binary, reporting the time spent in
+    /// each phase.
+    ///
+    /// The code allocation and relocation times are only known once the
+    /// executable is loaded, see [`Self::load_universal_executable_with_profile`].
+    #[cfg(feature = "compiler")]
+    pub fn compile_universal_with_profile(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<(crate::UniversalExecutable, CompilationProfile), CompileError> {
+        let mut profile = CompilationProfile::default();
+        let start = Instant::now();
+        self.validate(binary)?;
+        profile.validation = start.elapsed();
+
+        let start = Instant::now();
+        let environ = wasmer_compiler::ModuleEnvironment::new();
+        let translation = environ.translate(binary).map_err(CompileError::Wasm)?;
+        profile.translation = start.elapsed();
+
+        let executable =
+            self.compile_translation(translation, tunables, self.target(), &mut profile)?;
+        Ok((executable, profile))
+    }
+
+    /// Compile a WebAssembly module that has already been translated with
+    /// [`ModuleEnvironment::translate`](wasmer_compiler::ModuleEnvironment::translate).
+    ///
+    /// This is what [`Self::compile_universal`] does once it has translated
+    /// the binary, and it produces the same executable.
+    #[cfg(feature = "compiler")]
+    pub fn compile_from_translation(
+        &self,
+        translation: wasmer_compiler::ModuleEnvironment<'_>,
+        tunables: &dyn Tunables,
+    ) -> Result<crate::UniversalExecutable, CompileError> {
+        self.compile_translation(
+            translation,
+            tunables,
+            self.target(),
+            &mut CompilationProfile::default(),
+        )
+    }
+
+    #[cfg(feature = "compiler")]
+    fn compile_translation(
+        &self,
+        translation: wasmer_compiler::ModuleEnvironment<'_>,
+        tunables: &dyn Tunables,
+        target: &Target,
+        profile: &mut CompilationProfile,
     ) -> Result<crate::UniversalExecutable, CompileError> {
         let inner_engine = self.inner_mut();
+        inner_engine.limits.check(&translation)?;
+        if inner_engine.check_segment_bounds {
+            translation.check_segment_bounds()?;
+        }
+        if let Some(allow_list) = &inner_engine.import_allow_list {
+            allow_list.check(&translation.module)?;
+        }
+        let module_translation_state = translation.module_translation_state.ok_or_else(|| {
+            CompileError::Validate("the module has not been translated".to_string())
+        })?;
         let features = inner_engine.features();
         let compiler = inner_engine.compiler()?;
-        let environ = wasmer_compiler::ModuleEnvironment::new();
-        let translation = environ.translate(binary).map_err(CompileError::Wasm)?;
 
         let memory_styles: PrimaryMap<wasmer_types::MemoryIndex, _> = translation
             .module
@@ -121,12 +320,9 @@ impl UniversalEngine {
             table_styles,
         };
         let compilation = compiler.compile_module(
-            &self.target(),
+            target,
             &compile_info,
-            // SAFETY: Calling `unwrap` is correct since
-            // `environ.translate()` above will write some data into
-            // `module_translation_state`.
-            translation.module_translation_state.as_ref().unwrap(),
+            &module_translation_state,
             translation.function_body_inputs,
         )?;
         let function_call_trampolines = compilation.get_function_call_trampolines();
@@ -137,12 +333,31 @@ impl UniversalEngine {
             .map(wasmer_types::OwnedDataInitializer::new)
             .collect();
 
+        *profile = CompilationProfile {
+            validation: profile.validation,
+            translation: profile.translation,
+            ..compilation.get_profile().clone()
+        };
+
         let frame_infos = compilation.get_frame_info();
+        let metering_info = compiler.metering_info(&compile_info.module);
+        let module_hash = *blake3::hash(translation.wasm).as_bytes();
+        let cpu_features = target.cpu_features().as_u64();
+        let source_hash = if inner_engine.embed_source_hash {
+            Some(crate::executable::source_hash_from_module_hash(
+                &module_hash,
+                &compile_info.features,
+                cpu_features,
+            ))
+        } else {
+            None
+        };
         Ok(crate::UniversalExecutable {
             function_bodies: compilation.get_function_bodies(),
             function_relocations: compilation.get_relocations(),
             function_jt_offsets: compilation.get_jt_offsets(),
             function_frame_info: frame_infos,
+            function_trace_info: compilation.get_trace_info(),
             function_call_trampolines,
             dynamic_function_trampolines,
             custom_sections: compilation.get_custom_sections(),
@@ -151,7 +366,10 @@ impl UniversalEngine {
             trampolines: compilation.get_trampolines(),
             compile_info,
             data_initializers,
-            cpu_features: self.target().cpu_features().as_u64(),
+            cpu_features,
+            metering_info,
+            module_hash,
+            source_hash,
         })
     }
 
@@ -160,6 +378,17 @@ impl UniversalEngine {
         &self,
         executable: &UniversalExecutable,
     ) -> Result<UniversalArtifact, CompileError> {
+        self.load_universal_executable_with_profile(executable, &mut CompilationProfile::default())
+    }
+
+    /// Like [`Self::load_universal_executable`], but records the time spent
+    /// allocating the code and relocating it into `profile`.
+    pub fn load_universal_executable_with_profile(
+        &self,
+        executable: &UniversalExecutable,
+        profile: &mut CompilationProfile,
+    ) -> Result<UniversalArtifact, CompileError> {
+        check_host_cpu_features(executable.cpu_features)?;
         let info = &executable.compile_info;
         let module = &info.module;
         let local_memories = (module.import_counts.memories as usize..module.memories.len())
@@ -195,6 +424,7 @@ impl UniversalEngine {
             .map(|(_, sig)| inner_engine.signatures.register(sig.into()))
             .collect::<PrimaryMap<SignatureIndex, _>>()
             .into_boxed_slice();
+        let start = Instant::now();
         let (functions, trampolines, dynamic_trampolines, custom_sections) = inner_engine
             .allocate(
                 local_functions,
@@ -207,6 +437,7 @@ impl UniversalEngine {
                     (sig_idx, signatures[sig_idx])
                 },
             )?;
+        profile.code_allocation = start.elapsed();
         let imports = module
             .imports
             .iter()
@@ -234,6 +465,7 @@ impl UniversalEngine {
 
         let function_relocations = executable.function_relocations.iter();
         let section_relocations = executable.custom_section_relocations.iter();
+        let start = Instant::now();
         crate::link_module(
             &functions,
             |func_idx, jt_idx| executable.function_jt_offsets[func_idx][jt_idx],
@@ -242,6 +474,7 @@ impl UniversalEngine {
             section_relocations.map(|(i, rs)| (i, rs.iter().cloned())),
             &executable.trampolines,
         );
+        profile.relocation = start.elapsed();
 
         // Make all code loaded executable.
         inner_engine.publish_compiled_code();
@@ -259,6 +492,19 @@ impl UniversalEngine {
             .iter()
             .map(|(s, i)| (s.clone(), i.clone()))
             .collect::<BTreeMap<String, ExportIndex>>();
+        let functions = functions.into_boxed_slice();
+        let function_names: BTreeMap<FunctionIndex, String> = module
+            .function_names
+            .iter()
+            .map(|(index, name)| (*index, name.clone()))
+            .collect();
+        let frame_info_registration = register_frame_info(
+            module.name(),
+            module.import_counts.functions as usize,
+            function_names.clone(),
+            &functions,
+            executable.function_frame_info.clone(),
+        );
 
         Ok(UniversalArtifact {
             engine: self.clone(),
@@ -267,7 +513,7 @@ impl UniversalEngine {
             vmoffsets: VMOffsets::for_host().with_module_info(&*module),
             imports,
             dynamic_function_trampolines: dynamic_trampolines.into_boxed_slice(),
-            functions: functions.into_boxed_slice(),
+            functions,
             exports,
             signatures,
             local_memories,
@@ -277,6 +523,17 @@ impl UniversalEngine {
             element_segments: module.table_initializers.clone(),
             passive_elements: module.passive_elements.clone(),
             local_globals,
+            function_trace_info: executable.function_trace_info.clone().into_boxed_slice(),
+            function_traps: executable
+                .function_frame_info
+                .values()
+                .map(|info| info.traps.clone().into_boxed_slice())
+                .collect::<PrimaryMap<_, _>>()
+                .into_boxed_slice(),
+            function_names,
+            metering_info: executable.metering_info.clone(),
+            module_hash: executable.module_hash,
+            frame_info_registration: Mutex::new(frame_info_registration),
         })
     }
 
@@ -285,6 +542,7 @@ impl UniversalEngine {
         &self,
         executable: &UniversalExecutableRef,
     ) -> Result<UniversalArtifact, CompileError> {
+        check_host_cpu_features(unrkyv(&executable.cpu_features))?;
         let info = &executable.compile_info;
         let module = &info.module;
         let import_counts: ImportCounts = unrkyv(&module.import_counts);
@@ -407,6 +665,23 @@ impl UniversalEngine {
             .iter()
             .map(|(s, i)| (unrkyv(s), unrkyv(i)))
             .collect::<BTreeMap<String, ExportIndex>>();
+        let functions = functions.into_boxed_slice();
+        let function_names: BTreeMap<FunctionIndex, String> = unrkyv(&module.function_names);
+        let module_name: Option<String> = unrkyv(&module.name);
+        let function_frame_info: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo> =
+            unrkyv(&executable.function_frame_info);
+        let function_traps = function_frame_info
+            .values()
+            .map(|info| info.traps.clone().into_boxed_slice())
+            .collect::<PrimaryMap<_, _>>()
+            .into_boxed_slice();
+        let frame_info_registration = register_frame_info(
+            module_name.unwrap_or_else(|| "<module>".to_string()),
+            import_counts.functions as usize,
+            function_names.clone(),
+            &functions,
+            function_frame_info,
+        );
         Ok(UniversalArtifact {
             engine: self.clone(),
             import_counts,
@@ -414,7 +689,7 @@ impl UniversalEngine {
             vmoffsets: VMOffsets::for_host().with_archived_module_info(&*module),
             imports,
             dynamic_function_trampolines: dynamic_trampolines.into_boxed_slice(),
-            functions: functions.into_boxed_slice(),
+            functions,
             exports,
             signatures,
             local_memories,
@@ -424,6 +699,15 @@ impl UniversalEngine {
             element_segments,
             passive_elements,
             local_globals,
+            function_trace_info: unrkyv::<PrimaryMap<LocalFunctionIndex, TraceInfo>>(
+                &executable.function_trace_info,
+            )
+            .into_boxed_slice(),
+            function_traps,
+            function_names,
+            metering_info: executable.metering_info(),
+            module_hash: executable.module_hash(),
+            frame_info_registration: Mutex::new(frame_info_registration),
         })
     }
 }
@@ -476,6 +760,41 @@ impl Engine for UniversalEngine {
             .map(|ex| Box::new(ex) as _)
     }
 
+    /// Compile a WebAssembly binary read from `reader`.
+    ///
+    /// The module is validated while it is being read, so an invalid module
+    /// is rejected as soon as the offending section or function body has
+    /// arrived. It is compiled once it has been read whole.
+    #[cfg(feature = "compiler")]
+    fn compile_stream<R: Read + Send + 'static>(
+        &self,
+        mut reader: R,
+        tunables: &dyn Tunables,
+    ) -> Result<Box<dyn wasmer_engine::Executable>, CompileError> {
+        let features = {
+            let inner = self.inner();
+            inner.compiler()?;
+            inner.features().clone()
+        };
+        let binary = wasmer_compiler::read_and_validate_module(&features, &mut reader)?;
+        // The module was validated while it was read, only translate it.
+        let environ = wasmer_compiler::ModuleEnvironment::new();
+        let translation = environ.translate(&binary).map_err(CompileError::Wasm)?;
+        self.compile_from_translation(translation, tunables)
+            .map(|ex| Box::new(ex) as _)
+    }
+
+    /// Compile a WebAssembly binary, reporting the time spent in each phase
+    #[cfg(feature = "compiler")]
+    fn compile_with_profile(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<(Box<dyn wasmer_engine::Executable>, CompilationProfile), CompileError> {
+        self.compile_universal_with_profile(binary, tunables)
+            .map(|(ex, profile)| (Box::new(ex) as _, profile))
+    }
+
     fn load(
         &self,
         executable: &(dyn wasmer_engine::Executable),
@@ -499,9 +818,25 @@ pub struct UniversalEngineInner {
     compiler: Option<Box<dyn Compiler>>,
     /// The features to compile the Wasm module with
     features: Features,
+    /// The limits the compiled modules must stay within
+    #[cfg(feature = "compiler")]
+    limits: ModuleLimits,
+    /// Whether the compiled executables embed the hash of their inputs
+    #[cfg(feature = "compiler")]
+    embed_source_hash: bool,
+    /// Whether constant-offset active segments are bounds checked at compile time
+    #[cfg(feature = "compiler")]
+    check_segment_bounds: bool,
+    /// The only imports the compiled modules may declare, if restricted
+    #[cfg(feature = "compiler")]
+    import_allow_list: Option<ImportAllowList>,
     /// The code memory is responsible of publishing the compiled
     /// functions to memory.
     code_memory: Vec<CodeMemory>,
+    /// Whether the code memory is zeroed before being released
+    zero_code_memory_on_drop: bool,
+    /// The NUMA node the code memory is preferably placed on
+    numa_node: Option<u32>,
     /// The signature registry is used mainly to operate with trampolines
     /// performantly.
     pub(crate) signatures: SignatureRegistry,
@@ -579,7 +914,11 @@ impl UniversalEngineInner {
             }
             section_types.push(section.protection);
         }
-        code_memory.push(CodeMemory::new());
+        code_memory.push(
+            CodeMemory::new()
+                .zero_on_drop(self.zero_code_memory_on_drop)
+                .numa_node(self.numa_node),
+        );
         let code_memory = self.code_memory.last_mut().expect("infallible");
 
         let (mut allocated_functions, allocated_executable_sections, allocated_data_sections) =
@@ -616,9 +955,7 @@ impl UniversalEngineInner {
                 let (sig_idx, sig) = function_signature(index);
                 Ok(VMLocalFunction {
                     body: FunctionBodyPtr(slice.as_ptr()),
-                    length: u32::try_from(slice.len()).map_err(|_| {
-                        CompileError::Codegen("function body length exceeds 4GiB".into())
-                    })?,
+                    length: function_body_length(index, slice.len())?,
                     signature: sig,
                     trampoline: allocated_function_call_trampolines[sig_idx],
                 })
@@ -678,3 +1015,74 @@ impl UniversalEngineInner {
         &self.func_data
     }
 }
+
+/// Length of the allocated body of the local function `index`, as stored in
+/// [`VMLocalFunction`].
+fn function_body_length(index: LocalFunctionIndex, length: usize) -> Result<u32, CompileError> {
+    u32::try_from(length).map_err(|_| CompileError::FunctionTooLarge(index, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn function_body_length_fits() {
+        let index = LocalFunctionIndex::new(3);
+        assert_eq!(function_body_length(index, 42).unwrap(), 42);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn function_body_length_reports_index() {
+        let index = LocalFunctionIndex::new(7);
+        let length = u32::MAX as usize + 1;
+        match function_body_length(index, length) {
+            Err(CompileError::FunctionTooLarge(i, l)) => {
+                assert_eq!(i, index);
+                assert_eq!(l, length);
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn corrupted_executable_refs_are_rejected() {
+        let executable = UniversalExecutable {
+            function_bodies: PrimaryMap::new(),
+            function_relocations: PrimaryMap::new(),
+            function_jt_offsets: PrimaryMap::new(),
+            function_frame_info: PrimaryMap::new(),
+            function_trace_info: PrimaryMap::new(),
+            function_call_trampolines: PrimaryMap::new(),
+            dynamic_function_trampolines: PrimaryMap::new(),
+            custom_sections: PrimaryMap::new(),
+            custom_section_relocations: PrimaryMap::new(),
+            debug: None,
+            trampolines: None,
+            compile_info: wasmer_compiler::CompileModuleInfo {
+                features: Features::default(),
+                module: Arc::new(wasmer_types::ModuleInfo::new()),
+                memory_styles: PrimaryMap::new(),
+                table_styles: PrimaryMap::new(),
+            },
+            data_initializers: Vec::new(),
+            cpu_features: 0,
+            metering_info: wasmer_compiler::MeteringInfo::default(),
+            module_hash: [0; 32],
+            source_hash: None,
+        };
+        let mut serialized = wasmer_engine::Executable::serialize(&executable).unwrap();
+        assert!(unsafe { UniversalExecutableRef::deserialize(&serialized) }.is_ok());
+
+        // Point the root of the archive past its end.
+        let position = serialized.len() - 8;
+        serialized[position..].copy_from_slice(&(position as u64).to_le_bytes());
+        match unsafe { UniversalExecutableRef::deserialize(&serialized) } {
+            Err(wasmer_engine::DeserializeError::CorruptedBinary(_)) => {}
+            Err(other) => panic!("Unexpected error: {:?}", other),
+            Ok(_) => panic!("the executable should be rejected"),
+        }
+    }
+}
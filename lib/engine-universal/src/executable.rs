@@ -7,27 +7,104 @@ use rkyv::ser::serializers::{
 };
 use wasmer_compiler::{
     CompileError, CompileModuleInfo, CompiledFunctionFrameInfo, CpuFeature, CustomSection, Dwarf,
-    Features, FunctionBody, JumpTableOffsets, Relocation, SectionIndex, TrampolinesSection,
+    Features, FunctionBody, JumpTableOffsets, MeteringInfo, Relocation, SectionIndex, TraceInfo,
+    TrampolinesSection,
 };
 use wasmer_engine::{DeserializeError, Engine};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
-    ExportIndex, FunctionIndex, ImportIndex, LocalFunctionIndex, OwnedDataInitializer,
-    SignatureIndex,
+    entity::EntityRef, ExportIndex, FunctionIndex, ImportIndex, LocalFunctionIndex,
+    OwnedDataInitializer, SignatureIndex,
 };
 use wasmer_vm::Artifact;
 
-const MAGIC_HEADER: [u8; 32] = {
-    let value = *b"\0wasmer-universal\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF";
-    let _length_must_be_multiple_of_16: bool = [true][value.len() % 16];
-    value
+/// Version of the serialized executable format, stored in the header.
+///
+/// Executables are read back without validation, so this must be bumped
+/// whenever the archived `UniversalExecutable`, the `VMContext` layout or
+/// what the compiled code expects from the runtime changes. Executables
+/// serialized by other versions are then rejected instead of misread.
+const FORMAT_VERSION: u8 = 5;
+
+/// Cargo features changing the archived `UniversalExecutable`, stored in the
+/// header right after the format version.
+///
+/// Such features aren't additive: an executable can only be read back by
+/// builds with the same set of them enabled.
+const LAYOUT_FEATURES: u8 = if cfg!(feature = "compact-passive-data") {
+    1
+} else {
+    0
 };
 
+/// Name at the start of every serialized executable.
+const MAGIC_NAME: &[u8] = b"\0wasmer-universal";
+
+const MAGIC_HEADER: [u8; 32] = magic_header(MAGIC_NAME);
+
+/// Header of the executables serialized with their source hash, which
+/// immediately follows it.
+const SOURCE_HASH_MAGIC_HEADER: [u8; 32] = magic_header(SOURCE_HASH_MAGIC_NAME);
+
+/// Name at the start of the executables serialized with their source hash.
+const SOURCE_HASH_MAGIC_NAME: &[u8] = b"\0wasmer-universal+hash";
+
+/// Builds a header made of `name`, the format version, the layout features
+/// and `0xFF` padding.
+const fn magic_header(name: &[u8]) -> [u8; 32] {
+    let mut header = [0xFF; 32];
+    let mut i = 0;
+    while i < name.len() {
+        header[i] = name[i];
+        i += 1;
+    }
+    header[i] = FORMAT_VERSION;
+    header[i + 1] = LAYOUT_FEATURES;
+    header
+}
+
+/// Hash identifying the inputs of a compilation, as embedded in serialized
+/// executables by engines built with
+/// [`Universal::embed_source_hash`](crate::Universal::embed_source_hash).
+///
+/// This is the BLAKE3 hash of the BLAKE3 hash of `wasm`, followed by the name
+/// of each enabled feature terminated by a NUL byte, and by `cpu_features`
+/// as a little-endian `u64`.
+#[cfg(feature = "compiler")]
+pub fn source_hash(
+    wasm: &[u8],
+    features: &Features,
+    cpu_features: EnumSet<CpuFeature>,
+) -> [u8; 32] {
+    source_hash_from_module_hash(
+        blake3::hash(wasm).as_bytes(),
+        features,
+        cpu_features.as_u64(),
+    )
+}
+
+#[cfg(feature = "compiler")]
+pub(crate) fn source_hash_from_module_hash(
+    module_hash: &[u8; 32],
+    features: &Features,
+    cpu_features: u64,
+) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(module_hash);
+    for name in features.enabled() {
+        hasher.update(name.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.update(&cpu_features.to_le_bytes());
+    *hasher.finalize().as_bytes()
+}
+
 /// A 0-copy view of the encoded `UniversalExecutable` payload.
 #[derive(Clone, Copy)]
 pub struct UniversalExecutableRef<'a> {
     buffer: &'a [u8],
     archive: &'a ArchivedUniversalExecutable,
+    source_hash: Option<[u8; 32]>,
 }
 
 impl<'a> std::ops::Deref for UniversalExecutableRef<'a> {
@@ -40,10 +117,8 @@ impl<'a> std::ops::Deref for UniversalExecutableRef<'a> {
 impl<'a> UniversalExecutableRef<'a> {
     /// Verify the buffer for whether it is a valid `UniversalExecutable`.
     pub fn verify_serialized(data: &[u8]) -> Result<(), &'static str> {
-        if !data.starts_with(&MAGIC_HEADER) {
-            return Err("the provided bytes are not wasmer-universal");
-        }
-        if data.len() < MAGIC_HEADER.len() + 8 {
+        let header_len = Self::header_len(data)?;
+        if data.len() < header_len + 8 {
             return Err("the data buffer is too small to be valid");
         }
         let (remaining, position) = data.split_at(data.len() - 8);
@@ -52,17 +127,36 @@ impl<'a> UniversalExecutableRef<'a> {
         if u64::from_le_bytes(position_value) > remaining.len() as u64 {
             return Err("the buffer is malformed");
         }
-        // TODO(0-copy): bytecheck too.
         Ok(())
     }
 
+    /// Length of the header preceding the payload, source hash included.
+    fn header_len(data: &[u8]) -> Result<usize, &'static str> {
+        if data.starts_with(&MAGIC_HEADER) {
+            Ok(MAGIC_HEADER.len())
+        } else if data.starts_with(&SOURCE_HASH_MAGIC_HEADER) {
+            Ok(SOURCE_HASH_MAGIC_HEADER.len() + 32)
+        } else {
+            let name = if data.starts_with(SOURCE_HASH_MAGIC_NAME) {
+                SOURCE_HASH_MAGIC_NAME
+            } else if data.starts_with(MAGIC_NAME) {
+                MAGIC_NAME
+            } else {
+                return Err("the provided bytes are not wasmer-universal");
+            };
+            if data.get(name.len()) == Some(&FORMAT_VERSION) {
+                Err("the executable was serialized with other layout features of wasmer-universal")
+            } else {
+                Err("the executable was serialized by an incompatible version of wasmer-universal")
+            }
+        }
+    }
+
     /// # Safety
     ///
     /// This method is unsafe since it deserializes data directly
-    /// from memory.
-    /// Right now we are not doing any extra work for validation, but
-    /// `rkyv` has an option to do bytecheck on the serialized data before
-    /// serializing (via `rkyv::check_archived_value`).
+    /// from memory. Only debug builds check the archived data (via
+    /// `rkyv::check_archived_value`) before using it.
     pub unsafe fn deserialize(
         data: &'a [u8],
     ) -> Result<UniversalExecutableRef<'a>, DeserializeError> {
@@ -70,22 +164,61 @@ impl<'a> UniversalExecutableRef<'a> {
         let (archive, position) = data.split_at(data.len() - 8);
         let mut position_value = [0u8; 8];
         position_value.copy_from_slice(position);
-        let (_, data) = archive.split_at(MAGIC_HEADER.len());
+        let (header, data) = archive.split_at(Self::header_len(archive).unwrap());
+        let source_hash = if header.starts_with(&SOURCE_HASH_MAGIC_HEADER) {
+            let mut source_hash = [0; 32];
+            source_hash.copy_from_slice(&header[SOURCE_HASH_MAGIC_HEADER.len()..]);
+            Some(source_hash)
+        } else {
+            None
+        };
+        let position = u64::from_le_bytes(position_value) as usize;
+        #[cfg(debug_assertions)]
+        let archive = rkyv::check_archived_value::<UniversalExecutable>(data, position)
+            .map_err(|e| DeserializeError::CorruptedBinary(e.to_string()))?;
+        #[cfg(not(debug_assertions))]
+        let archive = rkyv::archived_value::<UniversalExecutable>(data, position);
         Ok(UniversalExecutableRef {
             buffer: data,
-            archive: rkyv::archived_value::<UniversalExecutable>(
-                data,
-                u64::from_le_bytes(position_value) as usize,
-            ),
+            archive,
+            source_hash,
         })
     }
 
+    /// The hash of the compilation inputs this executable was serialized
+    /// with, see [`source_hash`](crate::source_hash).
+    ///
+    /// It is read from the header, so loaders can check it against an
+    /// expected value before touching the rest of the executable.
+    pub fn source_hash(&self) -> Option<[u8; 32]> {
+        self.source_hash
+    }
+
+    /// How the compiled code meters gas.
+    pub fn metering_info(&self) -> MeteringInfo {
+        unrkyv(&self.archive.metering_info)
+    }
+
+    /// The BLAKE3 hash of the wasm binary this has been compiled from.
+    pub fn module_hash(&self) -> [u8; 32] {
+        self.archive.module_hash
+    }
+
     // TODO(0-copy): this should never fail.
     /// Convert this reference to an owned `UniversalExecutable` value.
+    ///
+    /// In debug builds, the result is checked with
+    /// [`UniversalExecutable::validate_invariants`].
     pub fn to_owned(self) -> Result<UniversalExecutable, DeserializeError> {
         let mut deserializer = SharedDeserializeMap::new();
-        rkyv::Deserialize::deserialize(self.archive, &mut deserializer)
-            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))
+        let executable: UniversalExecutable =
+            rkyv::Deserialize::deserialize(self.archive, &mut deserializer)
+                .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+        #[cfg(debug_assertions)]
+        executable
+            .validate_invariants()
+            .map_err(DeserializeError::CorruptedBinary)?;
+        Ok(executable)
     }
 }
 
@@ -95,11 +228,13 @@ impl<'a> UniversalExecutableRef<'a> {
 /// This is the result obtained after validating and compiling a WASM module with any of the
 /// supported compilers. This type falls in-between a module and [`Artifact`](crate::Artifact).
 #[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct UniversalExecutable {
     pub(crate) function_bodies: PrimaryMap<LocalFunctionIndex, FunctionBody>,
     pub(crate) function_relocations: PrimaryMap<LocalFunctionIndex, Vec<Relocation>>,
     pub(crate) function_jt_offsets: PrimaryMap<LocalFunctionIndex, JumpTableOffsets>,
     pub(crate) function_frame_info: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    pub(crate) function_trace_info: PrimaryMap<LocalFunctionIndex, TraceInfo>,
     pub(crate) function_call_trampolines: PrimaryMap<SignatureIndex, FunctionBody>,
     pub(crate) dynamic_function_trampolines: PrimaryMap<FunctionIndex, FunctionBody>,
     pub(crate) custom_sections: PrimaryMap<SectionIndex, CustomSection>,
@@ -111,6 +246,151 @@ pub struct UniversalExecutable {
     pub(crate) compile_info: CompileModuleInfo,
     pub(crate) data_initializers: Vec<OwnedDataInitializer>,
     pub(crate) cpu_features: u64,
+    pub(crate) metering_info: MeteringInfo,
+    pub(crate) module_hash: [u8; 32],
+    pub(crate) source_hash: Option<[u8; 32]>,
+}
+
+impl UniversalExecutable {
+    /// The BLAKE3 hash of the wasm binary this has been compiled from.
+    pub fn module_hash(&self) -> [u8; 32] {
+        self.module_hash
+    }
+
+    /// The hash of the compilation inputs, see [`source_hash`](crate::source_hash),
+    /// if the engine was asked to embed it in the serialized executable.
+    pub fn source_hash(&self) -> Option<[u8; 32]> {
+        self.source_hash
+    }
+
+    /// How the compiled code meters gas.
+    pub fn metering_info(&self) -> &MeteringInfo {
+        &self.metering_info
+    }
+
+    /// Check that the parts of this executable are consistent with each
+    /// other and with the module it has been compiled from.
+    ///
+    /// This catches executables that deserialized successfully but were not
+    /// produced by a compilation, such as corrupted or hand-crafted ones. It
+    /// does not look at the machine code itself.
+    pub fn validate_invariants(&self) -> Result<(), String> {
+        fn check_len(what: &str, len: usize, expected: usize) -> Result<(), String> {
+            if len == expected {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} has {} entries instead of {}",
+                    what, len, expected
+                ))
+            }
+        }
+
+        let module = &self.compile_info.module;
+        let imported_functions = module.import_counts.functions as usize;
+        let local_functions = module
+            .functions
+            .len()
+            .checked_sub(imported_functions)
+            .ok_or("the module imports more functions than it has")?;
+        check_len(
+            "function_bodies",
+            self.function_bodies.len(),
+            local_functions,
+        )?;
+        let local_function_tables = [
+            ("function_relocations", self.function_relocations.len()),
+            ("function_jt_offsets", self.function_jt_offsets.len()),
+            ("function_frame_info", self.function_frame_info.len()),
+            ("function_trace_info", self.function_trace_info.len()),
+        ];
+        for &(what, len) in &local_function_tables {
+            check_len(what, len, local_functions)?;
+        }
+        check_len(
+            "function_call_trampolines",
+            self.function_call_trampolines.len(),
+            module.signatures.len(),
+        )?;
+        check_len(
+            "dynamic_function_trampolines",
+            self.dynamic_function_trampolines.len(),
+            imported_functions,
+        )?;
+        check_len(
+            "custom_section_relocations",
+            self.custom_section_relocations.len(),
+            self.custom_sections.len(),
+        )?;
+        check_len(
+            "memory_styles",
+            self.compile_info.memory_styles.len(),
+            module.memories.len(),
+        )?;
+        check_len(
+            "table_styles",
+            self.compile_info.table_styles.len(),
+            module.tables.len(),
+        )?;
+
+        if let Some(&signature) = module
+            .functions
+            .values()
+            .find(|&&signature| signature.index() >= module.signatures.len())
+        {
+            return Err(format!("unknown signature {}", signature.index()));
+        }
+        if let Some(start) = module.start_function {
+            if start.index() >= module.functions.len() {
+                return Err(format!("unknown start function {}", start.index()));
+            }
+        }
+        for (name, index) in module.exports.iter() {
+            let in_bounds = match index {
+                ExportIndex::Function(i) => i.index() < module.functions.len(),
+                ExportIndex::Table(i) => i.index() < module.tables.len(),
+                ExportIndex::Memory(i) => i.index() < module.memories.len(),
+                ExportIndex::Global(i) => i.index() < module.globals.len(),
+            };
+            if !in_bounds {
+                return Err(format!("export `{}` refers to an unknown entity", name));
+            }
+        }
+        for initializer in &self.data_initializers {
+            let memory = initializer.location.memory_index;
+            if memory.index() >= module.memories.len() {
+                return Err(format!(
+                    "data initializer for unknown memory {}",
+                    memory.index()
+                ));
+            }
+        }
+        if let Some(ref debug) = self.debug {
+            if debug.eh_frame.index() >= self.custom_sections.len() {
+                return Err("the eh_frame section is not a custom section".to_string());
+            }
+        }
+        if EnumSet::<CpuFeature>::try_from_u64(self.cpu_features).is_none() {
+            return Err(format!("unknown CPU features {:#x}", self.cpu_features));
+        }
+        Ok(())
+    }
+
+    /// Whether `other` holds the same machine code as this executable.
+    ///
+    /// This compares the function bodies, the trampolines and the custom
+    /// sections along with their relocations, but not the metadata describing
+    /// the module, such as its hash, name or frame information. Compiling the
+    /// same module twice with the same configuration is expected to produce
+    /// identical code.
+    pub fn code_equals(&self, other: &Self) -> bool {
+        self.function_bodies == other.function_bodies
+            && self.function_relocations == other.function_relocations
+            && self.function_call_trampolines == other.function_call_trampolines
+            && self.dynamic_function_trampolines == other.dynamic_function_trampolines
+            && self.custom_sections == other.custom_sections
+            && self.custom_section_relocations == other.custom_section_relocations
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -150,6 +430,7 @@ impl wasmer_engine::Executable for UniversalExecutable {
         // The format is as thus:
         //
         // HEADER
+        // SOURCE HASH, only if the header is `SOURCE_HASH_MAGIC_HEADER`
         // RKYV PAYLOAD
         // RKYV POSITION
         //
@@ -159,8 +440,14 @@ impl wasmer_engine::Executable for UniversalExecutable {
             .map_err(ExecutableSerializeError::Executable)? as u64;
         let pos_bytes = pos.to_le_bytes();
         let data = serializer.into_serializer().into_inner();
-        let mut out = Vec::with_capacity(MAGIC_HEADER.len() + pos_bytes.len() + data.len());
-        out.extend(&MAGIC_HEADER);
+        let mut out = Vec::with_capacity(MAGIC_HEADER.len() + 32 + pos_bytes.len() + data.len());
+        match &self.source_hash {
+            Some(source_hash) => {
+                out.extend(&SOURCE_HASH_MAGIC_HEADER);
+                out.extend(source_hash);
+            }
+            None => out.extend(&MAGIC_HEADER),
+        }
         out.extend(data.as_slice());
         out.extend(&pos_bytes);
         Ok(out)
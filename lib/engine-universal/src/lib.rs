@@ -37,6 +37,8 @@ pub use crate::artifact::UniversalArtifact;
 pub use crate::builder::Universal;
 pub use crate::code_memory::CodeMemory;
 pub use crate::engine::UniversalEngine;
+#[cfg(feature = "compiler")]
+pub use crate::executable::source_hash;
 pub use crate::executable::{UniversalExecutable, UniversalExecutableRef};
 pub use crate::link::link_module;
 
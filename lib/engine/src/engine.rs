@@ -1,8 +1,13 @@
 //! Engine trait and associated types.
 
+use enumset::EnumSet;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
-use wasmer_compiler::{CompileError, Target};
+use std::time::Instant;
+use wasmer_compiler::{CompilationProfile, CompileError, CpuFeature, Target, Triple, WasmError};
 use wasmer_types::{FunctionType, FunctionTypeRef};
 use wasmer_vm::{Artifact, Tunables, VMCallerCheckedAnyfunc, VMFuncRef, VMSharedSignatureIndex};
 
@@ -39,6 +44,46 @@ pub trait Engine {
         tunables: &dyn Tunables,
     ) -> Result<Box<dyn crate::Executable>, CompileError>;
 
+    /// Compile a WebAssembly binary read from `reader`.
+    ///
+    /// By default the binary is read whole before being compiled, engines
+    /// may start processing it while it is still being read.
+    fn compile_stream<R: Read + Send + 'static>(
+        &self,
+        mut reader: R,
+        tunables: &dyn Tunables,
+    ) -> Result<Box<dyn crate::Executable>, CompileError>
+    where
+        Self: Sized,
+    {
+        let mut binary = Vec::new();
+        reader.read_to_end(&mut binary).map_err(|e| {
+            CompileError::Wasm(WasmError::Generic(format!(
+                "failed to read the module: {}",
+                e
+            )))
+        })?;
+        self.compile(&binary, tunables)
+    }
+
+    /// Validate and compile a WebAssembly binary, reporting the time spent in
+    /// each phase of the compilation.
+    ///
+    /// Engines that can't tell the phases apart only report the validation time.
+    fn compile_with_profile(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<(Box<dyn crate::Executable>, CompilationProfile), CompileError> {
+        let start = Instant::now();
+        self.validate(binary)?;
+        let profile = CompilationProfile {
+            validation: start.elapsed(),
+            ..CompilationProfile::default()
+        };
+        Ok((self.compile(binary, tunables)?, profile))
+    }
+
     /// Load a compiled executable with this engine.
     fn load(&self, executable: &(dyn crate::Executable))
         -> Result<Arc<dyn Artifact>, CompileError>;
@@ -64,31 +109,103 @@ pub trait Engine {
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-#[repr(transparent)]
 /// A unique identifier for an Engine.
+///
+/// Besides the identifier unique to this process, it carries a stable one,
+/// shared by all the engines producing the same code, which is what its
+/// `Display` and `FromStr` implementations read and write.
 pub struct EngineId {
     id: usize,
+    stable_id: String,
 }
 
 impl EngineId {
+    /// Create the identifier of an engine producing code for `target` with
+    /// the compiler called `compiler`, if it has one.
+    ///
+    /// The CPU features of `target` are part of the identifier, since the
+    /// code compilers produce depends on them.
+    pub fn new(target: &Target, compiler: Option<&str>) -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let mut stable_id = target.triple().to_string();
+        if !target.cpu_features().is_empty() {
+            let features = target
+                .cpu_features()
+                .iter()
+                .map(|feature| feature.to_string())
+                .collect::<Vec<_>>();
+            stable_id = format!("{}+{}", stable_id, features.join(","));
+        }
+        if let Some(compiler) = compiler {
+            stable_id = format!("{}/{}", stable_id, compiler);
+        }
+        Self {
+            id: NEXT_ID.fetch_add(1, SeqCst),
+            stable_id,
+        }
+    }
+
     /// Format this identifier as a string.
     pub fn id(&self) -> String {
         format!("{}", &self.id)
     }
+
+    /// The identifier of the code this engine produces, stable across
+    /// processes, such as `x86_64-unknown-linux-gnu+sse2,lzcnt/singlepass`.
+    pub fn stable_id(&self) -> &str {
+        &self.stable_id
+    }
 }
 
 impl Clone for EngineId {
     fn clone(&self) -> Self {
-        Self::default()
+        Self {
+            stable_id: self.stable_id.clone(),
+            ..Self::default()
+        }
     }
 }
 
 impl Default for EngineId {
     fn default() -> Self {
-        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
-        Self {
-            id: NEXT_ID.fetch_add(1, SeqCst),
+        Self::new(&Target::default(), None)
+    }
+}
+
+impl fmt::Display for EngineId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.stable_id)
+    }
+}
+
+impl FromStr for EngineId {
+    type Err = String;
+
+    /// Parse a stable identifier, as written by `Display`, into the
+    /// identifier of a new engine.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, compiler) = match s.find('/') {
+            Some(index) => (&s[..index], Some(&s[index + 1..])),
+            None => (s, None),
+        };
+        if compiler == Some("") {
+            return Err(format!("missing compiler name in engine id `{}`", s));
         }
+        let (triple, features) = match target.find('+') {
+            Some(index) => (&target[..index], Some(&target[index + 1..])),
+            None => (target, None),
+        };
+        let triple = Triple::from_str(triple)
+            .map_err(|e| format!("invalid target in engine id `{}`: {}", s, e))?;
+        let mut cpu_features = EnumSet::new();
+        for feature in features
+            .into_iter()
+            .flat_map(|features| features.split(','))
+        {
+            cpu_features |= CpuFeature::from_str(feature)
+                .map_err(|e| format!("invalid CPU feature in engine id `{}`: {}", s, e))?;
+        }
+        Ok(Self::new(&Target::new(triple, cpu_features), compiler))
     }
 }
 
@@ -102,3 +219,41 @@ impl dyn Engine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_id_round_trip() {
+        let triple = Triple::from_str("x86_64-unknown-linux-gnu").unwrap();
+        let target = Target::new(triple.clone(), CpuFeature::SSE2 | CpuFeature::LZCNT);
+        let id = EngineId::new(&target, Some("singlepass"));
+        assert_eq!(
+            id.to_string(),
+            "x86_64-unknown-linux-gnu+sse2,lzcnt/singlepass"
+        );
+
+        let parsed: EngineId = id.to_string().parse().unwrap();
+        assert_eq!(parsed.stable_id(), id.stable_id());
+        assert_ne!(parsed.id(), id.id());
+        let cloned = id.clone();
+        assert_eq!(cloned.stable_id(), id.stable_id());
+        assert_ne!(cloned, id);
+
+        // Engines producing code for other CPU features differ.
+        let other = EngineId::new(
+            &Target::new(triple, CpuFeature::SSE2.into()),
+            Some("singlepass"),
+        );
+        assert_ne!(other.stable_id(), id.stable_id());
+
+        let headless: EngineId = "x86_64-unknown-linux-gnu".parse().unwrap();
+        assert_eq!(headless.stable_id(), "x86_64-unknown-linux-gnu");
+        assert!("x86_64-unknown-linux-gnu/".parse::<EngineId>().is_err());
+        assert!("not-a-target/singlepass".parse::<EngineId>().is_err());
+        assert!("x86_64-unknown-linux-gnu+sse9/singlepass"
+            .parse::<EngineId>()
+            .is_err());
+    }
+}
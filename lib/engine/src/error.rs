@@ -64,6 +64,10 @@ pub enum LinkError {
     /// Insufficient resources available for linking.
     #[error("Insufficient resources: {0}")]
     Resource(String),
+
+    /// The resolved imports were not resolved for the imports of this module.
+    #[error("the imports were resolved for another module")]
+    ResolvedForAnotherModule,
 }
 
 /// An error while instantiating a module.
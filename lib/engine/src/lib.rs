@@ -29,7 +29,9 @@ mod trap;
 pub use crate::engine::{Engine, EngineId};
 pub use crate::error::{DeserializeError, ImportError, InstantiationError, LinkError};
 pub use crate::executable::Executable;
-pub use crate::resolver::resolve_imports;
+pub use crate::resolver::{
+    link_function_import, link_imports, resolve_exports, resolve_imports, ResolvedImports,
+};
 pub use crate::trap::*;
 
 /// Version number of this crate.
@@ -7,9 +7,10 @@ use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{ExternType, FunctionIndex, ImportCounts, MemoryType, TableType};
 
 use wasmer_vm::{
-    Export, ExportFunctionMetadata, FunctionBodyPtr, ImportFunctionEnv, Imports, MemoryStyle,
-    Resolver, VMFunctionBody, VMFunctionEnvironment, VMFunctionImport, VMFunctionKind,
-    VMGlobalImport, VMImport, VMImportType, VMMemoryImport, VMTableImport,
+    Export, ExportFunction, ExportFunctionMetadata, FunctionBodyPtr, ImportFunctionEnv, Imports,
+    MemoryStyle, Resolver, VMFunctionBody, VMFunctionEnvironment, VMFunctionImport, VMFunctionKind,
+    VMGlobalImport, VMImport, VMImportType, VMMemoryImport, VMSharedSignatureIndex, VMTableImport,
+    VMTrampoline,
 };
 
 fn is_compatible_table(ex: &TableType, im: &TableType) -> bool {
@@ -19,11 +20,24 @@ fn is_compatible_table(ex: &TableType, im: &TableType) -> bool {
             || (!ex.maximum.is_none() && im.maximum.unwrap() >= ex.maximum.unwrap()))
 }
 
+/// Limits of both memories are in `Pages`, so they can't be mixed up with
+/// sizes in bytes.
 fn is_compatible_memory(ex: &MemoryType, im: &MemoryType) -> bool {
     im.minimum <= ex.minimum
         && (im.maximum.is_none()
             || (!ex.maximum.is_none() && im.maximum.unwrap() >= ex.maximum.unwrap()))
         && ex.shared == im.shared
+        && ex.memory64 == im.memory64
+}
+
+/// Definitions provided by a `Resolver` for every import of a module, checked
+/// against the types the module expects.
+///
+/// Resolution only depends on the resolver, so the same `ResolvedImports` can be
+/// linked into any number of instances with [`link_imports`].
+#[derive(Clone)]
+pub struct ResolvedImports {
+    exports: Vec<Export>,
 }
 
 /// This function allows to match all imports of a `ModuleInfo` with concrete definitions provided by
@@ -37,12 +51,24 @@ pub fn resolve_imports(
     imports: &[VMImport],
     finished_dynamic_function_trampolines: &BoxedSlice<FunctionIndex, FunctionBodyPtr>,
 ) -> Result<Imports, LinkError> {
-    let mut function_imports = PrimaryMap::with_capacity(import_counts.functions as _);
-    let mut host_function_env_initializers =
-        PrimaryMap::with_capacity(import_counts.functions as _);
-    let mut table_imports = PrimaryMap::with_capacity(import_counts.tables as _);
-    let mut memory_imports = PrimaryMap::with_capacity(import_counts.memories as _);
-    let mut global_imports = PrimaryMap::with_capacity(import_counts.globals as _);
+    let resolved = resolve_exports(engine, resolver, imports)?;
+    link_imports(
+        &resolved,
+        import_counts,
+        imports,
+        finished_dynamic_function_trampolines,
+    )
+}
+
+/// Find the definitions provided by `resolver` for `imports` and check that
+/// they are compatible with the imported types, without linking them into an
+/// instance yet.
+pub fn resolve_exports(
+    engine: &dyn Engine,
+    resolver: &dyn Resolver,
+    imports: &[VMImport],
+) -> Result<ResolvedImports, LinkError> {
+    let mut exports = Vec::with_capacity(imports.len());
     for VMImport {
         import_no,
         module,
@@ -89,84 +115,8 @@ pub fn resolve_imports(
             }
         };
         match (&resolved, ty) {
-            (
-                Export::Function(ex),
-                VMImportType::Function {
-                    sig,
-                    static_trampoline,
-                },
-            ) if ex.vm_function.signature == *sig => {
-                let address = match ex.vm_function.kind {
-                    VMFunctionKind::Dynamic => {
-                        // If this is a dynamic imported function,
-                        // the address of the function is the address of the
-                        // reverse trampoline.
-                        let index = FunctionIndex::new(function_imports.len());
-                        finished_dynamic_function_trampolines[index].0 as *mut VMFunctionBody as _
-
-                        // TODO: We should check that the f.vmctx actually matches
-                        // the shape of `VMDynamicFunctionImportContext`
-                    }
-                    VMFunctionKind::Static => ex.vm_function.address,
-                };
-
-                // Clone the host env for this `Instance`.
-                let env = if let Some(ExportFunctionMetadata {
-                    host_env_clone_fn: clone,
-                    ..
-                }) = ex.metadata.as_deref()
-                {
-                    // TODO: maybe start adding asserts in all these
-                    // unsafe blocks to prevent future changes from
-                    // horribly breaking things.
-                    unsafe {
-                        assert!(!ex.vm_function.vmctx.host_env.is_null());
-                        (clone)(ex.vm_function.vmctx.host_env)
-                    }
-                } else {
-                    // No `clone` function means we're dealing with some
-                    // other kind of `vmctx`, not a host env of any
-                    // kind.
-                    unsafe { ex.vm_function.vmctx.host_env }
-                };
-
-                let trampoline = if let Some(t) = ex.vm_function.call_trampoline {
-                    Some(t)
-                } else if let VMFunctionKind::Static = ex.vm_function.kind {
-                    // Look up a trampoline by finding one by the signature and fill it in.
-                    Some(*static_trampoline)
-                } else {
-                    // FIXME: remove this possibility entirely.
-                    None
-                };
-
-                function_imports.push(VMFunctionImport {
-                    body: FunctionBodyPtr(address),
-                    signature: *sig,
-                    environment: VMFunctionEnvironment { host_env: env },
-                    trampoline,
-                });
-
-                let initializer = ex
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| m.import_init_function_ptr);
-                let clone = ex.metadata.as_ref().map(|m| m.host_env_clone_fn);
-                let destructor = ex.metadata.as_ref().map(|m| m.host_env_drop_fn);
-                let import_function_env =
-                    if let (Some(clone), Some(destructor)) = (clone, destructor) {
-                        ImportFunctionEnv::Env {
-                            env,
-                            clone,
-                            initializer,
-                            destructor,
-                        }
-                    } else {
-                        ImportFunctionEnv::NoEnv
-                    };
-
-                host_function_env_initializers.push(import_function_env);
-            }
+            (Export::Function(ex), VMImportType::Function { sig, .. })
+                if ex.vm_function.signature == *sig => {}
             (Export::Table(ex), VMImportType::Table(im)) if is_compatible_table(ex.ty(), im) => {
                 let import_table_ty = ex.from.ty();
                 if import_table_ty.ty != im.ty {
@@ -176,10 +126,6 @@ pub fn resolve_imports(
                         ImportError::IncompatibleType(import_extern(), export_extern()),
                     ));
                 }
-                table_imports.push(VMTableImport {
-                    definition: ex.from.vmtable(),
-                    from: ex.from.clone(),
-                });
             }
             (Export::Memory(ex), VMImportType::Memory(im, import_memory_style))
                 if is_compatible_memory(&ex.ty(), im) =>
@@ -201,25 +147,85 @@ pub fn resolve_imports(
                     export_memory_style.offset_guard_size(),
                     import_memory_style.offset_guard_size()
                 );
+            }
+            (Export::Global(ex), VMImportType::Global(im)) if ex.from.ty() == im => {}
+            _ => {
+                return Err(LinkError::Import(
+                    module.to_string(),
+                    field.to_string(),
+                    ImportError::IncompatibleType(import_extern(), export_extern()),
+                ));
+            }
+        }
+        exports.push(resolved);
+    }
+    Ok(ResolvedImports { exports })
+}
+
+/// Link previously resolved definitions into an `Imports` instance required for
+/// a module instantiation.
+///
+/// Host environments of imported functions are cloned, so every call produces
+/// imports with their own environments, just like [`resolve_imports`] does.
+///
+/// Returns [`LinkError::ResolvedForAnotherModule`] unless `resolved` matches the
+/// kinds and types of `imports`, as when produced by [`resolve_exports`] for them.
+pub fn link_imports(
+    resolved: &ResolvedImports,
+    import_counts: &ImportCounts,
+    imports: &[VMImport],
+    finished_dynamic_function_trampolines: &BoxedSlice<FunctionIndex, FunctionBodyPtr>,
+) -> Result<Imports, LinkError> {
+    if resolved.exports.len() != imports.len() {
+        return Err(LinkError::ResolvedForAnotherModule);
+    }
+    let mut function_imports = PrimaryMap::with_capacity(import_counts.functions as _);
+    let mut host_function_env_initializers =
+        PrimaryMap::with_capacity(import_counts.functions as _);
+    let mut table_imports = PrimaryMap::with_capacity(import_counts.tables as _);
+    let mut memory_imports = PrimaryMap::with_capacity(import_counts.memories as _);
+    let mut global_imports = PrimaryMap::with_capacity(import_counts.globals as _);
+    for (resolved, VMImport { ty, .. }) in resolved.exports.iter().zip(imports) {
+        match (resolved, ty) {
+            (
+                Export::Function(ex),
+                VMImportType::Function {
+                    sig,
+                    static_trampoline,
+                },
+            ) if ex.vm_function.signature == *sig => {
+                let index = FunctionIndex::new(function_imports.len());
+                let dynamic_trampoline = *finished_dynamic_function_trampolines
+                    .get(index)
+                    .ok_or(LinkError::ResolvedForAnotherModule)?;
+                let (import, import_function_env) =
+                    link_function_import(ex, *sig, *static_trampoline, dynamic_trampoline);
+                function_imports.push(import);
+                host_function_env_initializers.push(import_function_env);
+            }
+            (Export::Table(ex), VMImportType::Table(im))
+                if is_compatible_table(ex.ty(), im) && ex.from.ty().ty == im.ty =>
+            {
+                table_imports.push(VMTableImport {
+                    definition: ex.from.vmtable(),
+                    from: ex.from.clone(),
+                });
+            }
+            (Export::Memory(ex), VMImportType::Memory(im, _))
+                if is_compatible_memory(&ex.ty(), im) =>
+            {
                 memory_imports.push(VMMemoryImport {
                     definition: ex.from.vmmemory(),
                     from: ex.from.clone(),
                 });
             }
-
             (Export::Global(ex), VMImportType::Global(im)) if ex.from.ty() == im => {
                 global_imports.push(VMGlobalImport {
                     definition: ex.from.vmglobal(),
                     from: ex.from.clone(),
                 });
             }
-            _ => {
-                return Err(LinkError::Import(
-                    module.to_string(),
-                    field.to_string(),
-                    ImportError::IncompatibleType(import_extern(), export_extern()),
-                ));
-            }
+            _ => return Err(LinkError::ResolvedForAnotherModule),
         }
     }
     Ok(Imports::new(
@@ -230,3 +236,130 @@ pub fn resolve_imports(
         global_imports,
     ))
 }
+
+/// Link the function `ex` as an import of signature `sig`.
+///
+/// `dynamic_trampoline` is the reverse trampoline the module has for this
+/// import, it is only called through if `ex` is a dynamic function. The host
+/// env of `ex` is cloned, the clone belongs to the returned `ImportFunctionEnv`.
+pub fn link_function_import(
+    ex: &ExportFunction,
+    sig: VMSharedSignatureIndex,
+    static_trampoline: VMTrampoline,
+    dynamic_trampoline: FunctionBodyPtr,
+) -> (VMFunctionImport, ImportFunctionEnv) {
+    let address = match ex.vm_function.kind {
+        VMFunctionKind::Dynamic => {
+            // If this is a dynamic imported function,
+            // the address of the function is the address of the
+            // reverse trampoline.
+            dynamic_trampoline.0 as *mut VMFunctionBody as _
+
+            // TODO: We should check that the f.vmctx actually matches
+            // the shape of `VMDynamicFunctionImportContext`
+        }
+        VMFunctionKind::Static => ex.vm_function.address,
+    };
+
+    // Clone the host env for this `Instance`.
+    let env = if let Some(ExportFunctionMetadata {
+        host_env_clone_fn: clone,
+        ..
+    }) = ex.metadata.as_deref()
+    {
+        // TODO: maybe start adding asserts in all these
+        // unsafe blocks to prevent future changes from
+        // horribly breaking things.
+        unsafe {
+            assert!(!ex.vm_function.vmctx.host_env.is_null());
+            (clone)(ex.vm_function.vmctx.host_env)
+        }
+    } else {
+        // No `clone` function means we're dealing with some
+        // other kind of `vmctx`, not a host env of any
+        // kind.
+        unsafe { ex.vm_function.vmctx.host_env }
+    };
+
+    let trampoline = if let Some(t) = ex.vm_function.call_trampoline {
+        Some(t)
+    } else if let VMFunctionKind::Static = ex.vm_function.kind {
+        // Look up a trampoline by finding one by the signature and fill it in.
+        Some(static_trampoline)
+    } else {
+        // FIXME: remove this possibility entirely.
+        None
+    };
+
+    let import = VMFunctionImport {
+        body: FunctionBodyPtr(address),
+        signature: sig,
+        environment: VMFunctionEnvironment { host_env: env },
+        trampoline,
+    };
+
+    let initializer = ex
+        .metadata
+        .as_ref()
+        .and_then(|m| m.import_init_function_ptr);
+    let clone = ex.metadata.as_ref().map(|m| m.host_env_clone_fn);
+    let destructor = ex.metadata.as_ref().map(|m| m.host_env_drop_fn);
+    let import_function_env = if let (Some(clone), Some(destructor)) = (clone, destructor) {
+        ImportFunctionEnv::Env {
+            env,
+            clone,
+            initializer,
+            destructor,
+        }
+    } else {
+        ImportFunctionEnv::NoEnv
+    };
+    (import, import_function_env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_compatible_memory;
+    use wasmer_types::MemoryType;
+
+    #[test]
+    fn memory_compatibility() {
+        let im = MemoryType::new(2, Some(10), false);
+        assert!(is_compatible_memory(
+            &MemoryType::new(2, Some(10), false),
+            &im
+        ));
+        assert!(is_compatible_memory(
+            &MemoryType::new(5, Some(8), false),
+            &im
+        ));
+        assert!(!is_compatible_memory(
+            &MemoryType::new(1, Some(10), false),
+            &im
+        ));
+        assert!(!is_compatible_memory(&MemoryType::new(2, None, false), &im));
+        assert!(!is_compatible_memory(
+            &MemoryType::new(2, Some(10), true),
+            &im
+        ));
+    }
+
+    #[test]
+    fn memory64_compatibility() {
+        let memory64 = |minimum, maximum| MemoryType {
+            memory64: true,
+            ..MemoryType::new(minimum, maximum, false)
+        };
+        let im = memory64(2, Some(10));
+        assert!(is_compatible_memory(&memory64(2, Some(10)), &im));
+        assert!(!is_compatible_memory(
+            &MemoryType::new(2, Some(10), false),
+            &im
+        ));
+        assert!(!is_compatible_memory(
+            &memory64(2, Some(10)),
+            &MemoryType::new(2, Some(10), false)
+        ));
+        assert!(!is_compatible_memory(&memory64(1, Some(10)), &im));
+    }
+}
@@ -3,7 +3,7 @@ use backtrace::Backtrace;
 use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
-use wasmer_vm::{raise_user_trap, Trap, TrapCode};
+use wasmer_vm::{raise_user_trap, Trap, TrapCode, VMBuiltinFunctionIndex};
 
 /// A struct representing an aborted instruction execution, with a message
 /// indicating the cause.
@@ -35,6 +35,8 @@ impl fmt::Display for RuntimeErrorSource {
 struct RuntimeErrorInner {
     /// The source error (this can be a custom user `Error` or a [`TrapCode`])
     source: RuntimeErrorSource,
+    /// The builtin function the trap was raised from, if any.
+    builtin: Option<VMBuiltinFunctionIndex>,
     /// The reconstructed Wasm trace (from the native trace and the `GlobalFrameInfo`).
     wasm_trace: Vec<FrameInfo>,
     /// The native backtrace
@@ -59,6 +61,7 @@ impl RuntimeError {
         Self::new_with_trace(
             &info,
             None,
+            None,
             RuntimeErrorSource::Generic(msg),
             Backtrace::new_unresolved(),
         )
@@ -67,6 +70,7 @@ impl RuntimeError {
     /// Create a new RuntimeError from a Trap.
     pub fn from_trap(trap: Trap) -> Self {
         let info = FRAME_INFO.read().unwrap();
+        let builtin = trap.builtin();
         match trap {
             // A user error
             Trap::User(error) => {
@@ -76,6 +80,7 @@ impl RuntimeError {
                     Err(e) => Self::new_with_trace(
                         &info,
                         None,
+                        None,
                         RuntimeErrorSource::User(e),
                         Backtrace::new_unresolved(),
                     ),
@@ -83,26 +88,39 @@ impl RuntimeError {
             }
             // A trap caused by the VM being Out of Memory
             Trap::OOM { backtrace } => {
-                Self::new_with_trace(&info, None, RuntimeErrorSource::OOM, backtrace)
+                Self::new_with_trace(&info, None, None, RuntimeErrorSource::OOM, backtrace)
             }
             // A trap caused by an error on the generated machine code for a Wasm function
             Trap::Wasm {
                 pc,
                 signal_trap,
                 backtrace,
+                frames,
             } => {
                 let code = info
                     .lookup_trap_info(pc)
                     .map_or(signal_trap.unwrap_or(TrapCode::StackOverflow), |info| {
                         info.trap_code
                     });
-                Self::new_with_trace(&info, Some(pc), RuntimeErrorSource::Trap(code), backtrace)
+                Self::new_with_trace(
+                    &info,
+                    Some((pc, &frames)),
+                    None,
+                    RuntimeErrorSource::Trap(code),
+                    backtrace,
+                )
             }
             // A trap triggered manually from the Wasmer runtime
             Trap::Lib {
                 trap_code,
                 backtrace,
-            } => Self::new_with_trace(&info, None, RuntimeErrorSource::Trap(trap_code), backtrace),
+            } => Self::new_with_trace(
+                &info,
+                None,
+                builtin,
+                RuntimeErrorSource::Trap(trap_code),
+                backtrace,
+            ),
         }
     }
 
@@ -113,43 +131,44 @@ impl RuntimeError {
 
     fn new_with_trace(
         info: &GlobalFrameInfo,
-        trap_pc: Option<usize>,
+        wasm_frames: Option<(usize, &[usize])>,
+        builtin: Option<VMBuiltinFunctionIndex>,
         source: RuntimeErrorSource,
         native_trace: Backtrace,
     ) -> Self {
-        let frames: Vec<usize> = native_trace
-            .frames()
-            .iter()
-            .filter_map(|frame| {
-                let pc = frame.ip() as usize;
-                if pc == 0 {
-                    None
-                } else {
-                    // Note that we need to be careful about the pc we pass in here to
-                    // lookup frame information. This program counter is used to
-                    // translate back to an original source location in the origin wasm
-                    // module. If this pc is the exact pc that the trap happened at,
-                    // then we look up that pc precisely. Otherwise backtrace
-                    // information typically points at the pc *after* the call
-                    // instruction (because otherwise it's likely a call instruction on
-                    // the stack). In that case we want to lookup information for the
-                    // previous instruction (the call instruction) so we subtract one as
-                    // the lookup.
-                    let pc_to_lookup = if Some(pc) == trap_pc { pc } else { pc - 1 };
-                    Some(pc_to_lookup)
-                }
-            })
-            .collect();
-
-        // Let's construct the trace
-        let wasm_trace = frames
-            .into_iter()
-            .filter_map(|pc| info.lookup_frame_info(pc))
-            .collect::<Vec<_>>();
+        // Note that we need to be careful about the pc we pass in here to
+        // lookup frame information. This program counter is used to
+        // translate back to an original source location in the origin wasm
+        // module. If this pc is the exact pc that the trap happened at,
+        // then we look up that pc precisely. Otherwise the frames typically
+        // point at the pc *after* the call instruction (because otherwise
+        // it's likely a call instruction on the stack). In that case we want
+        // to lookup information for the previous instruction (the call
+        // instruction) so we subtract one as the lookup.
+        let wasm_trace = match wasm_frames {
+            // The generated code has no unwind information, so the native
+            // backtrace stops at the first wasm frame. Use the return
+            // addresses collected by the trap handler instead, up to the
+            // first one outside of wasm code.
+            Some((trap_pc, frames)) => std::iter::once(trap_pc)
+                .chain(frames.iter().map(|pc| pc - 1))
+                .map(|pc| info.lookup_frame_info(pc))
+                .take_while(Option::is_some)
+                .flatten()
+                .collect::<Vec<_>>(),
+            None => native_trace
+                .frames()
+                .iter()
+                .map(|frame| frame.ip() as usize)
+                .filter(|pc| *pc != 0)
+                .filter_map(|pc| info.lookup_frame_info(pc - 1))
+                .collect::<Vec<_>>(),
+        };
 
         Self {
             inner: Arc::new(RuntimeErrorInner {
                 source,
+                builtin,
                 wasm_trace,
                 native_trace,
             }),
@@ -161,12 +180,26 @@ impl RuntimeError {
         self.inner.source.to_string()
     }
 
+    /// Returns the name of the runtime builtin function (e.g. `memory_fill`) this
+    /// trap was raised from, if it was raised from one.
+    pub fn builtin_name(&self) -> Option<&'static str> {
+        self.inner.builtin.map(VMBuiltinFunctionIndex::name)
+    }
+
     /// Returns a list of function frames in WebAssembly code that led to this
     /// trap happening.
     pub fn trace(&self) -> &[FrameInfo] {
         &self.inner.wasm_trace
     }
 
+    /// Returns the WebAssembly frames that were on the stack when this trap
+    /// happened, innermost first.
+    ///
+    /// This is an owned copy of [`RuntimeError::trace`].
+    pub fn wasm_trace(&self) -> Vec<FrameInfo> {
+        self.inner.wasm_trace.clone()
+    }
+
     /// Attempts to downcast the `RuntimeError` to a concrete type.
     pub fn downcast<T: Error + 'static>(self) -> Result<T, Self> {
         match Arc::try_unwrap(self.inner) {
@@ -191,6 +224,15 @@ impl RuntimeError {
         }
     }
 
+    /// Returns whether execution ran out of gas, i.e. trapped with
+    /// `TrapCode::GasExceeded`.
+    pub fn is_out_of_gas(&self) -> bool {
+        matches!(
+            self.inner.source,
+            RuntimeErrorSource::Trap(TrapCode::GasExceeded)
+        )
+    }
+
     /// Returns true if the `RuntimeError` is the same as T
     pub fn is<T: Error + 'static>(&self) -> bool {
         match &self.inner.source {
@@ -204,6 +246,7 @@ impl fmt::Debug for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("RuntimeError")
             .field("source", &self.inner.source)
+            .field("builtin", &self.builtin_name())
             .field("wasm_trace", &self.inner.wasm_trace)
             .field("native_trace", &self.inner.native_trace)
             .finish()
@@ -213,6 +256,10 @@ impl fmt::Debug for RuntimeError {
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "RuntimeError: {}", self.message())?;
+        if let Some(name) = self.builtin_name() {
+            writeln!(f)?;
+            write!(f, "    in builtin {}", name)?;
+        }
         let trace = self.trace();
         if trace.is_empty() {
             return Ok(());
@@ -256,3 +303,25 @@ impl From<Trap> for RuntimeError {
         Self::from_trap(trap)
     }
 }
+
+/// A [`RuntimeError`] split by where it comes from: a trap raised by the
+/// WebAssembly code itself, or any other error such as one returned by a host
+/// function.
+#[derive(Debug, thiserror::Error)]
+pub enum TrapOrHostError {
+    /// The WebAssembly code trapped.
+    #[error(transparent)]
+    Trap(TrapCode),
+    /// The error did not come from a WebAssembly trap.
+    #[error(transparent)]
+    Host(RuntimeError),
+}
+
+impl From<RuntimeError> for TrapOrHostError {
+    fn from(error: RuntimeError) -> Self {
+        match error.inner.source {
+            RuntimeErrorSource::Trap(trap_code) => Self::Trap(trap_code),
+            _ => Self::Host(error),
+        }
+    }
+}
@@ -5,17 +5,23 @@
 //!
 //! # Example
 //! ```ignore
-//! use wasmer_vm::{FRAME_INFO};
-//! use wasmer_types::ModuleInfo;
+//! use wasmer_engine::register_frame_info;
 //!
-//! let module: ModuleInfo = ...;
-//! FRAME_INFO.register(module, compiled_functions);
+//! let registration = register_frame_info(
+//!     module_name,
+//!     num_imported_functions,
+//!     function_names,
+//!     &functions,
+//!     frame_infos,
+//! );
 //! ```
 use std::collections::BTreeMap;
-use std::sync::{Arc, RwLock};
+use std::convert::TryFrom;
+use std::sync::RwLock;
 use wasmer_compiler::{CompiledFunctionFrameInfo, SourceLoc, TrapInformation};
-use wasmer_types::entity::{EntityRef, PrimaryMap};
-use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
+use wasmer_types::{FunctionIndex, LocalFunctionIndex};
+use wasmer_vm::VMLocalFunction;
 
 lazy_static::lazy_static! {
     /// This is a global cache of backtrace frame information for all active
@@ -52,7 +58,9 @@ pub struct GlobalFrameInfoRegistration {
 struct ModuleInfoFrameInfo {
     start: usize,
     functions: BTreeMap<usize, FunctionInfo>,
-    module: Arc<ModuleInfo>,
+    module_name: String,
+    num_imported_functions: usize,
+    function_names: BTreeMap<FunctionIndex, String>,
     frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
 }
 
@@ -126,11 +134,12 @@ impl GlobalFrameInfo {
             // start offset of the function.
             None => instr_map.start_srcloc,
         };
-        let func_index = module.module.func_index(func.local_index);
+        let func_index =
+            FunctionIndex::new(module.num_imported_functions + func.local_index.index());
         Some(FrameInfo {
-            module_name: module.module.name(),
+            module_name: module.module_name.clone(),
             func_index: func_index.index() as u32,
-            function_name: module.module.function_names.get(&func_index).cloned(),
+            function_name: module.function_names.get(&func_index).cloned(),
             instr,
             func_start: instr_map.start_srcloc,
         })
@@ -158,6 +167,60 @@ impl GlobalFrameInfo {
     }
 }
 
+/// Registers the frame information of a module's `functions`, so that the
+/// traps happening in them can be symbolicated.
+///
+/// Returns `None` if the module doesn't define any function. The information
+/// is unregistered once the returned value is dropped.
+pub fn register(
+    module_name: String,
+    num_imported_functions: usize,
+    function_names: BTreeMap<FunctionIndex, String>,
+    functions: &BoxedSlice<LocalFunctionIndex, VMLocalFunction>,
+    frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+) -> Option<GlobalFrameInfoRegistration> {
+    let mut min = usize::MAX;
+    let mut max = 0;
+    let mut function_infos = BTreeMap::new();
+    for (local_index, function) in functions.iter() {
+        let start = function.body.0 as *const u8 as usize;
+        let end = start + usize::try_from(function.length).unwrap() - 1;
+        min = min.min(start);
+        max = max.max(end);
+        function_infos.insert(end, FunctionInfo { start, local_index });
+    }
+    if function_infos.is_empty() {
+        return None;
+    }
+
+    let module_info = ModuleInfoFrameInfo {
+        start: min,
+        functions: function_infos,
+        module_name,
+        num_imported_functions,
+        function_names,
+        frame_infos,
+    };
+
+    let mut info = FRAME_INFO.write().unwrap();
+    // Modules live in disjoint sections of the code memory, make sure that
+    // this one doesn't collide with any registered one.
+    debug_assert!(info
+        .ranges
+        .range(max..)
+        .next()
+        .map_or(true, |(_, next)| next.start > max));
+    debug_assert!(info
+        .ranges
+        .range(..=min)
+        .next_back()
+        .map_or(true, |(prev_end, _)| *prev_end < min));
+    let prev = info.ranges.insert(max, module_info);
+    drop(info);
+    debug_assert!(prev.is_none());
+    Some(GlobalFrameInfoRegistration { key: max })
+}
+
 impl Drop for GlobalFrameInfoRegistration {
     fn drop(&mut self) {
         if let Ok(mut info) = FRAME_INFO.write() {
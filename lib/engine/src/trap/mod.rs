@@ -1,4 +1,4 @@
 mod error;
 mod frame_info;
-pub use error::RuntimeError;
-pub use frame_info::{FrameInfo, GlobalFrameInfoRegistration};
+pub use error::{RuntimeError, TrapOrHostError};
+pub use frame_info::{register as register_frame_info, FrameInfo, GlobalFrameInfoRegistration};
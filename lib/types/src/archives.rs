@@ -6,6 +6,7 @@ use rkyv::Archive;
 use std::hash::Hash;
 
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 /// See [`IndexMap`]
 pub struct ArchivableIndexMap<K: Hash + Ord + Archive, V: Archive> {
     entries: Vec<(K, V)>,
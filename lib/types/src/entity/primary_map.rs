@@ -31,6 +31,7 @@ use crate::lib::std::vec::Vec;
 /// plain slice would make it easier to use incorrectly. To make a slice of a `PrimaryMap`, use
 /// `into_boxed_slice`.
 #[derive(Debug, Clone, Hash, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct PrimaryMap<K, V>
 where
     K: EntityRef,
@@ -3,6 +3,7 @@
 ///
 /// [WebAssembly proposal]: https://github.com/WebAssembly/proposals
 #[derive(Clone, Debug, Eq, PartialEq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct Features {
     /// Threads proposal should be enabled
     pub threads: bool,
@@ -219,6 +220,134 @@ impl Features {
         self.memory64 = enable;
         self
     }
+
+    /// Returns the features enabled in both `self` and `other`.
+    pub fn intersection(&self, other: &Features) -> Features {
+        self.combine(other, |a, b| a && b)
+    }
+
+    /// Returns the features enabled in either `self` or `other`.
+    pub fn union(&self, other: &Features) -> Features {
+        self.combine(other, |a, b| a || b)
+    }
+
+    /// Returns whether every feature enabled in `self` is also enabled in
+    /// `other`.
+    pub fn is_subset_of(&self, other: &Features) -> bool {
+        self.intersection(other) == *self
+    }
+
+    /// Lists the features that are enabled in only one of `self` and `other`.
+    pub fn diff(&self, other: &Features) -> FeaturesDiff {
+        let mut diff = FeaturesDiff::default();
+        for ((name, mine), (_, theirs)) in self.flags().iter().zip(other.flags().iter()) {
+            if *mine && !*theirs {
+                diff.only_in_self.push(name);
+            } else if !*mine && *theirs {
+                diff.only_in_other.push(name);
+            }
+        }
+        diff
+    }
+
+    /// Returns the names of the enabled features, as used by
+    /// [`Features::diff`].
+    pub fn enabled(&self) -> Vec<&'static str> {
+        self.flags()
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Returns the feature set made of exactly the features called `names`,
+    /// as listed by [`Features::enabled`]. Blank names are ignored.
+    pub fn from_names<'a>(
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, UnknownFeatureError> {
+        let mut features = Self {
+            threads: false,
+            reference_types: false,
+            simd: false,
+            bulk_memory: false,
+            multi_value: false,
+            tail_call: false,
+            module_linking: false,
+            multi_memory: false,
+            memory64: false,
+            exceptions: false,
+        };
+        for name in names {
+            let flag = match name.trim() {
+                "threads" => &mut features.threads,
+                "reference_types" => &mut features.reference_types,
+                "simd" => &mut features.simd,
+                "bulk_memory" => &mut features.bulk_memory,
+                "multi_value" => &mut features.multi_value,
+                "tail_call" => &mut features.tail_call,
+                "module_linking" => &mut features.module_linking,
+                "multi_memory" => &mut features.multi_memory,
+                "memory64" => &mut features.memory64,
+                "exceptions" => &mut features.exceptions,
+                "" => continue,
+                other => return Err(UnknownFeatureError(other.to_string())),
+            };
+            *flag = true;
+        }
+        Ok(features)
+    }
+
+    fn combine(&self, other: &Features, op: impl Fn(bool, bool) -> bool) -> Features {
+        Features {
+            threads: op(self.threads, other.threads),
+            reference_types: op(self.reference_types, other.reference_types),
+            simd: op(self.simd, other.simd),
+            bulk_memory: op(self.bulk_memory, other.bulk_memory),
+            multi_value: op(self.multi_value, other.multi_value),
+            tail_call: op(self.tail_call, other.tail_call),
+            module_linking: op(self.module_linking, other.module_linking),
+            multi_memory: op(self.multi_memory, other.multi_memory),
+            memory64: op(self.memory64, other.memory64),
+            exceptions: op(self.exceptions, other.exceptions),
+        }
+    }
+
+    fn flags(&self) -> [(&'static str, bool); 10] {
+        [
+            ("threads", self.threads),
+            ("reference_types", self.reference_types),
+            ("simd", self.simd),
+            ("bulk_memory", self.bulk_memory),
+            ("multi_value", self.multi_value),
+            ("tail_call", self.tail_call),
+            ("module_linking", self.module_linking),
+            ("multi_memory", self.multi_memory),
+            ("memory64", self.memory64),
+            ("exceptions", self.exceptions),
+        ]
+    }
+}
+
+/// A feature name not known to [`Features::from_names`].
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("unknown WebAssembly feature `{0}`")]
+pub struct UnknownFeatureError(pub String);
+
+/// The features that differ between two [`Features`], as returned by
+/// [`Features::diff`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FeaturesDiff {
+    /// Names of the features enabled in `self` but not in `other`.
+    pub only_in_self: Vec<&'static str>,
+    /// Names of the features enabled in `other` but not in `self`.
+    pub only_in_other: Vec<&'static str>,
+}
+
+impl FeaturesDiff {
+    /// Returns whether both feature sets are the same.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty() && self.only_in_other.is_empty()
+    }
 }
 
 impl Default for Features {
@@ -325,4 +454,63 @@ mod test_features {
         features.memory64(true);
         assert!(features.memory64);
     }
+
+    #[test]
+    fn combine_features() {
+        let default = Features::new();
+        let mut other = Features::new();
+        other.simd(false).threads(true);
+
+        let intersection = default.intersection(&other);
+        assert!(!intersection.simd);
+        assert!(!intersection.threads);
+        assert!(intersection.bulk_memory);
+
+        let union = default.union(&other);
+        assert!(union.simd);
+        assert!(union.threads);
+        assert!(!union.tail_call);
+
+        assert!(intersection.is_subset_of(&default));
+        assert!(intersection.is_subset_of(&other));
+        assert!(default.is_subset_of(&union));
+        assert!(!default.is_subset_of(&other));
+        assert!(default.is_subset_of(&default));
+    }
+
+    #[test]
+    fn diff_features() {
+        let default = Features::new();
+        assert!(default.diff(&default).is_empty());
+
+        let mut other = Features::new();
+        other.simd(false).threads(true);
+        let diff = default.diff(&other);
+        assert_eq!(diff.only_in_self, vec!["simd"]);
+        assert_eq!(diff.only_in_other, vec!["threads"]);
+    }
+
+    #[test]
+    fn features_from_names() {
+        let features = Features::from_names(vec!["simd", " threads", ""]).unwrap();
+        assert_eq!(features.enabled(), vec!["threads", "simd"]);
+        assert!(Features::from_names(vec![]).unwrap().enabled().is_empty());
+        for name in Features::new().enabled() {
+            assert_eq!(Features::from_names(vec![name]).unwrap().enabled(), [name]);
+        }
+        assert_eq!(
+            Features::from_names(vec!["simd", "gc"]),
+            Err(UnknownFeatureError("gc".to_string()))
+        );
+    }
+
+    #[test]
+    fn enabled_features() {
+        let mut features = Features::new();
+        features.simd(false).threads(true);
+        assert_eq!(
+            features.enabled(),
+            vec!["threads", "reference_types", "bulk_memory", "multi_value"]
+        );
+    }
 }
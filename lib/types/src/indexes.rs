@@ -17,6 +17,7 @@ use core::u32;
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct LocalFunctionIndex(u32);
 entity_impl!(LocalFunctionIndex);
@@ -46,6 +47,7 @@ entity_impl!(LocalMemoryIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct LocalGlobalIndex(u32);
 entity_impl!(LocalGlobalIndex);
@@ -65,6 +67,7 @@ entity_impl!(LocalGlobalIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct FunctionIndex(u32);
 entity_impl!(FunctionIndex);
@@ -84,6 +87,7 @@ entity_impl!(FunctionIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct TableIndex(u32);
 entity_impl!(TableIndex);
@@ -103,6 +107,7 @@ entity_impl!(TableIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct GlobalIndex(u32);
 entity_impl!(GlobalIndex);
@@ -122,6 +127,7 @@ entity_impl!(GlobalIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct MemoryIndex(u32);
 entity_impl!(MemoryIndex);
@@ -141,6 +147,7 @@ entity_impl!(MemoryIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct SignatureIndex(u32);
 entity_impl!(SignatureIndex);
@@ -160,6 +167,7 @@ entity_impl!(SignatureIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct DataIndex(u32);
 entity_impl!(DataIndex);
@@ -179,6 +187,7 @@ entity_impl!(DataIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct ElemIndex(u32);
 entity_impl!(ElemIndex);
@@ -198,6 +207,7 @@ entity_impl!(ElemIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct CustomSectionIndex(u32);
 entity_impl!(CustomSectionIndex);
@@ -216,6 +226,7 @@ entity_impl!(CustomSectionIndex);
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(u8)]
 pub enum ExportIndex {
     /// Function export.
@@ -242,6 +253,7 @@ pub enum ExportIndex {
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(u8)]
 pub enum ImportIndex {
     /// Function import.
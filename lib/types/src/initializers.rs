@@ -3,6 +3,7 @@ use crate::lib::std::boxed::Box;
 
 /// A WebAssembly table initializer.
 #[derive(Clone, Debug, Hash, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct OwnedTableInitializer {
     /// The index of a table to initialize.
     pub table_index: TableIndex,
@@ -17,6 +18,7 @@ pub struct OwnedTableInitializer {
 /// A memory index and offset within that memory where a data initialization
 /// should be performed.
 #[derive(Clone, Debug, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct DataInitializerLocation {
     /// The index of the memory to initialize.
     pub memory_index: MemoryIndex,
@@ -41,6 +43,7 @@ pub struct DataInitializer<'data> {
 /// As `DataInitializer` but owning the data rather than
 /// holding a reference to it
 #[derive(Debug, Clone, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct OwnedDataInitializer {
     /// The location where the initialization is to be performed.
     pub location: DataInitializerLocation,
@@ -57,6 +60,19 @@ impl OwnedDataInitializer {
             data: borrowed.data.to_vec(),
         }
     }
+
+    /// Creates an `OwnedDataInitializer` writing `data` at the constant
+    /// `offset` of the first memory.
+    pub fn new_active(offset: u32, data: Vec<u8>) -> Self {
+        Self {
+            location: DataInitializerLocation {
+                memory_index: MemoryIndex::from_u32(0),
+                base: None,
+                offset: offset as usize,
+            },
+            data,
+        }
+    }
 }
 
 impl<'a> From<&'a OwnedDataInitializer> for DataInitializer<'a> {
@@ -86,3 +102,25 @@ impl<'a> From<DataInitializer<'a>> for OwnedDataInitializer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_active_matches_a_constant_offset_segment() {
+        let data = [1, 2, 3];
+        let borrowed = DataInitializer {
+            location: DataInitializerLocation {
+                memory_index: MemoryIndex::from_u32(0),
+                base: None,
+                offset: 42,
+            },
+            data: &data,
+        };
+        assert_eq!(
+            OwnedDataInitializer::new_active(42, data.to_vec()),
+            OwnedDataInitializer::new(&borrowed)
+        );
+    }
+}
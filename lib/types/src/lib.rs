@@ -62,6 +62,8 @@ mod initializers;
 mod memory_view;
 mod module;
 mod native;
+#[cfg(feature = "compact-passive-data")]
+mod passive_data;
 mod types;
 mod units;
 mod values;
@@ -69,7 +71,7 @@ mod values;
 /// The entity module, with common helpers for Rust structures
 pub mod entity;
 pub use crate::extern_ref::{ExternRef, VMExternRef};
-pub use crate::features::Features;
+pub use crate::features::{Features, FeaturesDiff, UnknownFeatureError};
 pub use crate::indexes::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, ImportIndex,
     LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
@@ -79,15 +81,23 @@ pub use crate::initializers::{
     DataInitializer, DataInitializerLocation, OwnedDataInitializer, OwnedTableInitializer,
 };
 pub use crate::memory_view::{Atomically, MemoryView};
-pub use crate::module::{ImportCounts, ModuleInfo};
+pub use crate::module::{
+    ArchivableModuleInfo, ImportCounts, ModuleInfo, ModuleMetadataError, PassiveData,
+};
 pub use crate::native::{NativeWasmType, ValueType};
+#[cfg(feature = "compact-passive-data")]
+pub use crate::passive_data::PassiveDataArena;
 pub use crate::units::{
-    Bytes, PageCountOutOfRange, Pages, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    ByteCountOutOfRange, Bytes, PageCountOutOfRange, Pages, WASM_MAX_PAGES, WASM_MIN_PAGES,
+    WASM_PAGE_SIZE,
 };
 pub use crate::values::{Value, WasmValueType};
+#[cfg(feature = "wat")]
+pub use types::FunctionTypeParseError;
 pub use types::{
-    ExportType, ExternType, FastGasCounter, FunctionType, FunctionTypeRef, GlobalInit, GlobalType,
-    Import, InstanceConfig, MemoryType, Mutability, TableType, Type, V128,
+    ExportType, ExternType, FakeClock, FastGasCounter, FunctionType, FunctionTypeRef, GlobalError,
+    GlobalInit, GlobalType, HostCallTracer, Import, InstanceAllocatorProvider, InstanceConfig,
+    MemoryType, Mutability, RealClock, TableType, Type, WasmClock, V128,
 };
 
 pub use archives::ArchivableIndexMap;
@@ -14,16 +14,27 @@ use crate::{
 };
 use indexmap::IndexMap;
 use rkyv::{
-    de::SharedDeserializeRegistry, ser::ScratchSpace, ser::Serializer,
-    ser::SharedSerializeRegistry, Archive, Archived, Fallible,
+    de::deserializers::SharedDeserializeMap, de::SharedDeserializeRegistry,
+    ser::serializers::AllocSerializer, ser::ScratchSpace, ser::Serializer,
+    ser::SharedSerializeRegistry, AlignedVec, Archive, Archived, Fallible,
 };
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
+use thiserror::Error;
+
+/// The passive data segments of a module, by index.
+#[cfg(not(feature = "compact-passive-data"))]
+pub type PassiveData = BTreeMap<DataIndex, Arc<[u8]>>;
+
+/// The passive data segments of a module, by index.
+#[cfg(feature = "compact-passive-data")]
+pub type PassiveData = crate::PassiveDataArena;
 
 #[derive(Debug, Clone, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct ModuleId {
     id: usize,
 }
@@ -48,6 +59,7 @@ impl Default for ModuleId {
     Debug, Copy, Clone, Default, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 pub struct ImportCounts {
     /// Number of imported functions in the module.
     pub functions: u32,
@@ -156,7 +168,7 @@ pub struct ModuleInfo {
     pub passive_elements: BTreeMap<ElemIndex, Box<[FunctionIndex]>>,
 
     /// WebAssembly passive data segments.
-    pub passive_data: BTreeMap<DataIndex, Arc<[u8]>>,
+    pub passive_data: PassiveData,
 
     /// WebAssembly global initializers.
     pub global_initializers: PrimaryMap<LocalGlobalIndex, GlobalInit>,
@@ -191,23 +203,41 @@ pub struct ModuleInfo {
 
 /// Mirror version of ModuleInfo that can derive rkyv traits
 #[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct ArchivableModuleInfo {
+    /// Mirror of `ModuleInfo::name`.
     pub name: Option<String>,
+    /// Mirror of `ModuleInfo::imports`.
     pub imports: ArchivableIndexMap<(String, String, u32), ImportIndex>,
+    /// Mirror of `ModuleInfo::exports`.
     pub exports: ArchivableIndexMap<String, ExportIndex>,
+    /// Mirror of `ModuleInfo::start_function`.
     pub start_function: Option<FunctionIndex>,
+    /// Mirror of `ModuleInfo::table_initializers`.
     pub table_initializers: Vec<OwnedTableInitializer>,
+    /// Mirror of `ModuleInfo::passive_elements`.
     pub passive_elements: BTreeMap<ElemIndex, Box<[FunctionIndex]>>,
-    pub passive_data: BTreeMap<DataIndex, Arc<[u8]>>,
+    /// Mirror of `ModuleInfo::passive_data`.
+    pub passive_data: PassiveData,
+    /// Mirror of `ModuleInfo::global_initializers`.
     pub global_initializers: PrimaryMap<LocalGlobalIndex, GlobalInit>,
+    /// Mirror of `ModuleInfo::function_names`.
     pub function_names: BTreeMap<FunctionIndex, String>,
+    /// Mirror of `ModuleInfo::signatures`.
     pub signatures: PrimaryMap<SignatureIndex, FunctionType>,
+    /// Mirror of `ModuleInfo::functions`.
     pub functions: PrimaryMap<FunctionIndex, SignatureIndex>,
+    /// Mirror of `ModuleInfo::tables`.
     pub tables: PrimaryMap<TableIndex, TableType>,
+    /// Mirror of `ModuleInfo::memories`.
     pub memories: PrimaryMap<MemoryIndex, MemoryType>,
+    /// Mirror of `ModuleInfo::globals`.
     pub globals: PrimaryMap<GlobalIndex, GlobalType>,
+    /// Mirror of `ModuleInfo::custom_sections`.
     pub custom_sections: ArchivableIndexMap<String, CustomSectionIndex>,
+    /// Mirror of `ModuleInfo::custom_sections_data`.
     pub custom_sections_data: PrimaryMap<CustomSectionIndex, Arc<[u8]>>,
+    /// Mirror of `ModuleInfo::import_counts`.
     pub import_counts: ImportCounts,
 }
 
@@ -220,7 +250,7 @@ impl From<ModuleInfo> for ArchivableModuleInfo {
             start_function: it.start_function,
             table_initializers: it.table_initializers,
             passive_elements: it.passive_elements.into_iter().collect(),
-            passive_data: it.passive_data.into_iter().collect(),
+            passive_data: it.passive_data,
             global_initializers: it.global_initializers,
             function_names: it.function_names.into_iter().collect(),
             signatures: it.signatures,
@@ -245,7 +275,7 @@ impl From<ArchivableModuleInfo> for ModuleInfo {
             start_function: it.start_function,
             table_initializers: it.table_initializers,
             passive_elements: it.passive_elements.into_iter().collect(),
-            passive_data: it.passive_data.into_iter().collect(),
+            passive_data: it.passive_data,
             global_initializers: it.global_initializers,
             function_names: it.function_names.into_iter().collect(),
             signatures: it.signatures,
@@ -318,12 +348,85 @@ impl PartialEq for ModuleInfo {
 
 impl Eq for ModuleInfo {}
 
+/// An error while serializing or deserializing the metadata of a
+/// [`ModuleInfo`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ModuleMetadataError {
+    /// The metadata could not be serialized.
+    #[error("could not serialize the module metadata: {0}")]
+    Serialize(String),
+    /// The provided bytes are not valid module metadata.
+    #[error("could not deserialize the module metadata: {0}")]
+    Deserialize(String),
+}
+
 impl ModuleInfo {
     /// Allocates the module data structures.
     pub fn new() -> Self {
         Default::default()
     }
 
+    /// The buffer holding all the passive data segments.
+    #[cfg(feature = "compact-passive-data")]
+    pub fn passive_data_bytes(&self) -> &[u8] {
+        self.passive_data.bytes()
+    }
+
+    /// Returns the contents of the passive data segment `idx`.
+    #[cfg(feature = "compact-passive-data")]
+    pub fn passive_segment(&self, idx: DataIndex) -> Option<&[u8]> {
+        self.passive_data.get(&idx)
+    }
+
+    /// Serialize only the metadata of this module (types, imports, exports,
+    /// initializers, ...) through [`ArchivableModuleInfo`], without any
+    /// compiled code.
+    ///
+    /// The format is the rkyv payload followed by the little-endian `u64`
+    /// position of the archived value.
+    pub fn serialize_metadata(&self) -> Result<Vec<u8>, ModuleMetadataError> {
+        let mut serializer = AllocSerializer::<1024>::default();
+        let pos = serializer
+            .serialize_value(self)
+            .map_err(|e| ModuleMetadataError::Serialize(format!("{:?}", e)))?
+            as u64;
+        let mut out = serializer.into_serializer().into_inner().into_vec();
+        out.extend(&pos.to_le_bytes());
+        Ok(out)
+    }
+
+    /// Deserialize metadata produced by [`ModuleInfo::serialize_metadata`].
+    ///
+    /// The resulting `ModuleInfo` gets a fresh module id.
+    ///
+    /// # Safety
+    ///
+    /// The archived data is not validated (beyond its framing), so `bytes`
+    /// must come from a trusted call to [`ModuleInfo::serialize_metadata`].
+    pub unsafe fn deserialize_metadata(bytes: &[u8]) -> Result<Self, ModuleMetadataError> {
+        if bytes.len() < 8 {
+            return Err(ModuleMetadataError::Deserialize(
+                "the data buffer is too small to be valid".to_string(),
+            ));
+        }
+        let (payload, position) = bytes.split_at(bytes.len() - 8);
+        let mut position_value = [0u8; 8];
+        position_value.copy_from_slice(position);
+        let position = u64::from_le_bytes(position_value);
+        if position >= payload.len() as u64 {
+            return Err(ModuleMetadataError::Deserialize(
+                "the buffer is malformed".to_string(),
+            ));
+        }
+        // The archive must be aligned, which a borrowed slice doesn't guarantee.
+        let mut aligned = AlignedVec::with_capacity(payload.len());
+        aligned.extend_from_slice(payload);
+        let archive = rkyv::archived_value::<ModuleInfo>(&aligned, position as usize);
+        let mut deserializer = SharedDeserializeMap::new();
+        rkyv::Deserialize::<ModuleInfo, _>::deserialize(archive, &mut deserializer)
+            .map_err(|e| ModuleMetadataError::Deserialize(format!("{:?}", e)))
+    }
+
     /// Get the given passive element, if it exists.
     pub fn get_passive_element(&self, index: ElemIndex) -> Option<&[FunctionIndex]> {
         self.passive_elements.get(&index).map(|es| &**es)
@@ -442,3 +545,50 @@ impl fmt::Display for ModuleInfo {
         write!(f, "{}", self.name())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExportIndex, GlobalInit, ImportIndex, Mutability, Type};
+
+    #[test]
+    fn metadata_round_trip() {
+        let mut module = ModuleInfo::new();
+        module.name = Some("test".to_string());
+        let sig = module
+            .signatures
+            .push(FunctionType::new(vec![Type::I32], vec![Type::I64]));
+        let imported = module.functions.push(sig);
+        let local = module.functions.push(sig);
+        module.import_counts.functions = 1;
+        module.imports.insert(
+            ("env".to_string(), "f".to_string(), 0),
+            ImportIndex::Function(imported),
+        );
+        module
+            .exports
+            .insert("g".to_string(), ExportIndex::Function(local));
+        module.memories.push(MemoryType::new(1, Some(2), false));
+        module
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+        module.global_initializers.push(GlobalInit::I32Const(42));
+        module.function_names.insert(local, "g_impl".to_string());
+        module
+            .passive_data
+            .insert(DataIndex::new(0), Arc::from(&b"data"[..]));
+        let section = module.custom_sections_data.push(Arc::from(&b"custom"[..]));
+        module.custom_sections.insert("name".to_string(), section);
+
+        let bytes = module.serialize_metadata().unwrap();
+        let deserialized = unsafe { ModuleInfo::deserialize_metadata(&bytes) }.unwrap();
+        assert_eq!(module, deserialized);
+        assert_ne!(module.id.id(), deserialized.id.id());
+    }
+
+    #[test]
+    fn metadata_rejects_malformed_buffer() {
+        assert!(unsafe { ModuleInfo::deserialize_metadata(&[0; 4]) }.is_err());
+        assert!(unsafe { ModuleInfo::deserialize_metadata(&[0xff; 16]) }.is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Storage of the passive data segments of a module.
+
+use crate::DataIndex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+/// The passive data segments of a module, stored in a single buffer.
+///
+/// Modules with many small passive segments otherwise need one allocation
+/// per segment, this keeps them all concatenated instead, along with the
+/// position of each segment in the buffer.
+///
+/// The segments are shared between clones, so that instantiating a module
+/// doesn't copy them. Each clone keeps track of the segments it dropped.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
+)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
+pub struct PassiveDataArena {
+    segments: Arc<Segments>,
+    dropped: BTreeSet<DataIndex>,
+}
+
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
+)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
+struct Segments {
+    bytes: Vec<u8>,
+    positions: BTreeMap<DataIndex, (usize, usize)>,
+}
+
+impl PassiveDataArena {
+    /// Creates an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the segment `index` to the arena.
+    ///
+    /// Returns the position of the segment previously stored with this index,
+    /// if any. Its bytes are kept in the buffer.
+    ///
+    /// The segments are copied first if they are shared with other clones.
+    pub fn insert(&mut self, index: DataIndex, data: impl AsRef<[u8]>) -> Option<(usize, usize)> {
+        let data = data.as_ref();
+        let segments = Arc::make_mut(&mut self.segments);
+        let offset = segments.bytes.len();
+        segments.bytes.extend_from_slice(data);
+        let previous = segments.positions.insert(index, (offset, data.len()));
+        if self.dropped.remove(&index) {
+            None
+        } else {
+            previous
+        }
+    }
+
+    /// Returns the contents of the segment `index`.
+    pub fn get(&self, index: &DataIndex) -> Option<&[u8]> {
+        if self.dropped.contains(index) {
+            return None;
+        }
+        self.segments
+            .positions
+            .get(index)
+            .map(|&(offset, len)| &self.segments.bytes[offset..offset + len])
+    }
+
+    /// Forgets the segment `index`, as done by `data.drop`.
+    ///
+    /// Only this arena forgets the segment, the buffer and its clones are
+    /// left untouched.
+    pub fn remove(&mut self, index: &DataIndex) -> Option<(usize, usize)> {
+        let position = *self.segments.positions.get(index)?;
+        if self.dropped.insert(*index) {
+            Some(position)
+        } else {
+            None
+        }
+    }
+
+    /// The buffer holding all the segments, dropped ones included.
+    pub fn bytes(&self) -> &[u8] {
+        &self.segments.bytes
+    }
+
+    /// Iterates over the segments, in index order.
+    pub fn iter(&self) -> impl Iterator<Item = (DataIndex, &[u8])> + '_ {
+        self.segments
+            .positions
+            .iter()
+            .filter(move |(index, _)| !self.dropped.contains(index))
+            .map(move |(&index, &(offset, len))| {
+                (index, &self.segments.bytes[offset..offset + len])
+            })
+    }
+
+    /// The number of segments in the arena.
+    pub fn len(&self) -> usize {
+        self.segments.positions.len() - self.dropped.len()
+    }
+
+    /// Whether the arena holds no segment.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::EntityRef;
+
+    #[test]
+    fn segments_share_one_buffer() {
+        let mut arena = PassiveDataArena::new();
+        assert!(arena.insert(DataIndex::new(1), b"world").is_none());
+        assert!(arena.insert(DataIndex::new(0), b"hello ").is_none());
+        arena.insert(DataIndex::new(2), b"");
+        assert_eq!(arena.bytes(), b"worldhello ");
+        assert_eq!(arena.get(&DataIndex::new(0)), Some(&b"hello "[..]));
+        assert_eq!(arena.get(&DataIndex::new(1)), Some(&b"world"[..]));
+        assert_eq!(arena.get(&DataIndex::new(2)), Some(&b""[..]));
+        assert_eq!(arena.get(&DataIndex::new(3)), None);
+        let indices: Vec<_> = arena.iter().map(|(index, _)| index.index()).collect();
+        assert_eq!(indices, [0, 1, 2]);
+
+        assert_eq!(arena.remove(&DataIndex::new(1)), Some((0, 5)));
+        assert_eq!(arena.get(&DataIndex::new(1)), None);
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn clones_share_segments_but_not_drops() {
+        let mut arena = PassiveDataArena::new();
+        arena.insert(DataIndex::new(0), b"hello");
+        let mut clone = arena.clone();
+        assert!(Arc::ptr_eq(&arena.segments, &clone.segments));
+
+        assert_eq!(clone.remove(&DataIndex::new(0)), Some((0, 5)));
+        assert_eq!(clone.remove(&DataIndex::new(0)), None);
+        assert_eq!(clone.get(&DataIndex::new(0)), None);
+        assert!(clone.is_empty());
+        assert_eq!(arena.get(&DataIndex::new(0)), Some(&b"hello"[..]));
+        assert!(Arc::ptr_eq(&arena.segments, &clone.segments));
+    }
+}
@@ -5,9 +5,14 @@ use crate::lib::std::string::{String, ToString};
 use crate::lib::std::vec::Vec;
 use crate::units::Pages;
 use crate::values::{Value, WasmValueType};
+use std::alloc::Layout;
+use std::any::Any;
 use std::cell::UnsafeCell;
+use std::ptr::NonNull;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 // Type Representations
 
@@ -18,6 +23,8 @@ use std::sync::Arc;
     Copy, Debug, Clone, Eq, PartialEq, Hash, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
+#[repr(u8)]
 pub enum Type {
     /// Signed 32 bit integer.
     I32,
@@ -61,6 +68,7 @@ impl fmt::Display for Type {
     Copy, Clone, Debug, Eq, PartialEq, Hash, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 /// The WebAssembly V128 type
 pub struct V128(pub(crate) [u8; 16]);
 
@@ -159,6 +167,7 @@ impl ExternType {
 ///
 /// WebAssembly functions can have 0 or more parameters and results.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct FunctionType {
     /// The parameters of the function
     params: Arc<[Type]>,
@@ -188,8 +197,74 @@ impl FunctionType {
     pub fn results(&self) -> &[Type] {
         &self.results
     }
+
+    /// Parses a signature in the WebAssembly text format, such as
+    /// `(param i32 i64) (result funcref)`.
+    ///
+    /// Parameters may be named, as in `(param $x i32)`, and must come before
+    /// the results.
+    #[cfg(feature = "wat")]
+    pub fn parse(signature: &str) -> Result<Self, FunctionTypeParseError> {
+        let error = |message: &str| FunctionTypeParseError(message.to_string());
+        let spaced = signature.replace('(', " ( ").replace(')', " ) ");
+        let mut tokens = spaced.split_whitespace();
+        let mut params = Vec::new();
+        let mut results = Vec::new();
+        // Even empty `(result)` clauses end the parameters.
+        let mut in_results = false;
+        while let Some(token) = tokens.next() {
+            if token != "(" {
+                return Err(error(&format!("expected `(`, found `{}`", token)));
+            }
+            let (types, is_param) = match tokens.next() {
+                Some("param") if !in_results => (&mut params, true),
+                Some("param") => return Err(error("parameters must come before results")),
+                Some("result") => {
+                    in_results = true;
+                    (&mut results, false)
+                }
+                Some(other) => return Err(error(&format!("unexpected `{}`", other))),
+                None => return Err(error("unexpected end of signature")),
+            };
+            let mut named = false;
+            let mut count = 0;
+            loop {
+                let ty = match tokens.next() {
+                    Some(")") => break,
+                    Some(name) if name.starts_with('$') && is_param && count == 0 && !named => {
+                        named = true;
+                        continue;
+                    }
+                    Some("i32") => Type::I32,
+                    Some("i64") => Type::I64,
+                    Some("f32") => Type::F32,
+                    Some("f64") => Type::F64,
+                    Some("v128") => Type::V128,
+                    Some("externref") => Type::ExternRef,
+                    Some("funcref") => Type::FuncRef,
+                    Some(other) => return Err(error(&format!("unknown type `{}`", other))),
+                    None => return Err(error("unexpected end of signature")),
+                };
+                if named && count == 1 {
+                    return Err(error("a named parameter has a single type"));
+                }
+                types.push(ty);
+                count += 1;
+            }
+            if named && count == 0 {
+                return Err(error("a named parameter needs a type"));
+            }
+        }
+        Ok(Self::new(params, results))
+    }
 }
 
+/// An error while parsing a [`FunctionType`] with [`FunctionType::parse`].
+#[cfg(feature = "wat")]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid function signature: {0}")]
+pub struct FunctionTypeParseError(String);
+
 impl fmt::Display for FunctionType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let params = self
@@ -286,6 +361,8 @@ impl<'a> From<&'a ArchivedFunctionType> for FunctionTypeRef<'a> {
     Debug, Clone, Copy, PartialEq, Eq, Hash, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
+#[repr(u8)]
 pub enum Mutability {
     /// The global is constant and its value does not change
     Const,
@@ -308,6 +385,7 @@ impl Mutability {
     Debug, Clone, Copy, PartialEq, Eq, Hash, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 pub struct GlobalType {
     /// The type of the value stored in the global.
     pub ty: Type,
@@ -336,6 +414,57 @@ impl GlobalType {
     pub fn new(ty: Type, mutability: Mutability) -> Self {
         Self { ty, mutability }
     }
+
+    /// Returns whether the value of the global may change at runtime.
+    pub fn is_mutable(&self) -> bool {
+        self.mutability.is_mutable()
+    }
+
+    /// Returns the type of the value stored in the global.
+    pub fn value_type(&self) -> Type {
+        self.ty
+    }
+
+    /// Checks that `val` may be stored in a global of this type.
+    ///
+    /// # Usage:
+    /// ```
+    /// use wasmer_types::{GlobalType, Mutability, Type, Value};
+    ///
+    /// let global = GlobalType::new(Type::I32, Mutability::Var);
+    /// assert!(global.validate_set(&Value::<()>::I32(1)).is_ok());
+    /// assert!(global.validate_set(&Value::<()>::I64(1)).is_err());
+    /// ```
+    pub fn validate_set<T: WasmValueType>(&self, val: &Value<T>) -> Result<(), GlobalError> {
+        if !self.is_mutable() {
+            return Err(GlobalError::ImmutableGlobalCannotBeSet);
+        }
+        if val.ty() != self.ty {
+            return Err(GlobalError::IncorrectType {
+                expected: self.ty,
+                found: val.ty(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Error type describing things that can go wrong when operating on Wasm Globals.
+#[derive(Debug, Clone, PartialEq, Hash, thiserror::Error)]
+pub enum GlobalError {
+    /// The error returned when attempting to set an immutable global.
+    #[error("Attempted to set an immutable global")]
+    ImmutableGlobalCannotBeSet,
+
+    /// The error returned when attempting to operate on a global as a specific type
+    /// that differs from the global's own type.
+    #[error("Attempted to operate on a global of type {expected} as a global of type {found}")]
+    IncorrectType {
+        /// The type that the global is.
+        expected: Type,
+        /// The type that we were asked to use it as.
+        found: Type,
+    },
 }
 
 impl fmt::Display for GlobalType {
@@ -351,6 +480,8 @@ impl fmt::Display for GlobalType {
 /// Globals are initialized via the `const` operators or by referring to another import.
 #[derive(Debug, Clone, Copy, PartialEq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
+#[repr(u8)]
 pub enum GlobalInit {
     /// An `i32.const`.
     I32Const(i32),
@@ -408,6 +539,7 @@ impl GlobalInit {
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
 )]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct TableType {
     /// The type of data stored in elements of the table.
     pub ty: Type,
@@ -448,6 +580,7 @@ impl fmt::Display for TableType {
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive,
 )]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub struct MemoryType {
     /// The minimum number of pages in the memory.
     pub minimum: Pages,
@@ -455,6 +588,9 @@ pub struct MemoryType {
     pub maximum: Option<Pages>,
     /// Whether the memory may be shared between multiple threads.
     pub shared: bool,
+    /// Whether the memory is indexed with 64-bit addresses, as introduced
+    /// by the memory64 proposal.
+    pub memory64: bool,
 }
 
 impl MemoryType {
@@ -468,6 +604,7 @@ impl MemoryType {
             minimum: minimum.into(),
             maximum: maximum.map(Into::into),
             shared,
+            memory64: false,
         }
     }
 }
@@ -475,10 +612,15 @@ impl MemoryType {
 impl fmt::Display for MemoryType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let shared = if self.shared { "shared" } else { "not shared" };
+        let index = if self.memory64 { "i64" } else { "i32" };
         if let Some(maximum) = self.maximum {
-            write!(f, "{} ({:?}..{:?})", shared, self.minimum, maximum)
+            write!(
+                f,
+                "{} {} ({:?}..{:?})",
+                shared, index, self.minimum, maximum
+            )
         } else {
-            write!(f, "{} ({:?}..)", shared, self.minimum)
+            write!(f, "{} {} ({:?}..)", shared, index, self.minimum)
         }
     }
 }
@@ -602,6 +744,14 @@ impl FastGasCounter {
     pub fn burnt(&self) -> u64 {
         self.burnt_gas
     }
+
+    /// Set the cost of a single gas unit reported by the gas intrinsic.
+    ///
+    /// Compiled code reads it on every charge, so it can be adjusted, for
+    /// example on a protocol upgrade, without recompiling anything.
+    pub fn set_cost_multiplier(&mut self, multiplier: u64) {
+        self.opcode_cost = multiplier;
+    }
 }
 
 impl fmt::Display for FastGasCounter {
@@ -616,14 +766,117 @@ impl fmt::Display for FastGasCounter {
     }
 }
 
+/// Observer of control transfers between WebAssembly code and its imported functions.
+///
+/// Every call to an imported function produces exactly one `on_enter` and one `on_exit`
+/// event, including when the callee traps. Calls made re-entrantly from inside the import
+/// are reported as nested pairs.
+///
+/// Instances can move across threads, taking their tracer along, hence the `Send` and
+/// `Sync` bounds.
+pub trait HostCallTracer: Send + Sync {
+    /// Called right before control is transferred to the imported function `index`.
+    fn on_enter(&self, index: FunctionIndex, timestamp: Instant);
+    /// Called once the imported function `index` returns, or a trap unwinds through it.
+    fn on_exit(&self, index: FunctionIndex, timestamp: Instant);
+}
+
+/// Source of the memory regions holding an instance and its `VMContext`.
+///
+/// Regions handed out by `allocate` are returned through `deallocate`, with the
+/// same layout, once the instance is dropped.
+pub trait InstanceAllocatorProvider: Send + Sync {
+    /// Allocate a region with the given layout, or return `None` if it can't be
+    /// satisfied.
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>>;
+    /// Release a region previously returned by `allocate`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `allocate` on this provider with the same
+    /// `layout`, and must not be used afterwards.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+/// Source of the wall-clock time observed by an instance.
+///
+/// Host functions exposing time to WebAssembly code should read it from the clock of
+/// the instance, see [`InstanceConfig::with_clock`], so that executions can be made
+/// reproducible by substituting a [`FakeClock`].
+pub trait WasmClock: Send + Sync {
+    /// Current time, in nanoseconds since the Unix epoch.
+    fn now_ns(&self) -> u64;
+}
+
+/// Clock reading the system time, used unless another clock is configured.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RealClock;
+
+impl WasmClock for RealClock {
+    fn now_ns(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    }
+}
+
+/// Clock returning a time set explicitly, which only changes through `set_time`.
+#[derive(Debug, Default)]
+pub struct FakeClock {
+    now_ns: AtomicU64,
+}
+
+impl FakeClock {
+    /// Create a clock returning `ns` until it is changed.
+    pub fn new(ns: u64) -> Self {
+        Self {
+            now_ns: AtomicU64::new(ns),
+        }
+    }
+
+    /// Set the time returned by the clock, in nanoseconds since the Unix epoch.
+    pub fn set_time(&self, ns: u64) {
+        self.now_ns.store(ns, Ordering::SeqCst);
+    }
+}
+
+impl WasmClock for FakeClock {
+    fn now_ns(&self) -> u64 {
+        self.now_ns.load(Ordering::SeqCst)
+    }
+}
+
 /// External configuration of execution environment for Instance.
 #[derive(Clone)]
 pub struct InstanceConfig {
     /// External gas counter pointer.
     pub gas_counter: *mut FastGasCounter,
     default_gas_counter: Option<Rc<UnsafeCell<FastGasCounter>>>,
-    /// Stack limit, in 8-byte slots.
+    /// Stack limit, in 8-byte slots. Execution traps with
+    /// `TrapCode::WasmStackOverflow` when it is reached.
     pub stack_limit: i32,
+    /// Maximum number of nested WebAssembly function calls, see
+    /// [`InstanceConfig::with_max_function_call_depth`].
+    pub max_function_call_depth: Option<u32>,
+    /// Tracer notified about every call into an imported function.
+    pub host_call_tracer: Option<Arc<dyn HostCallTracer>>,
+    /// Flag checked at every loop header, execution traps with
+    /// `TrapCode::Interrupted` once it is set.
+    pub interrupt_flag: Option<Arc<AtomicBool>>,
+    /// Provider of the instance memory, the global allocator is used if unset.
+    pub allocator: Option<Arc<dyn InstanceAllocatorProvider>>,
+    /// Arbitrary state of the embedder, attached to the instance.
+    pub host_state: Option<Arc<dyn Any + Send + Sync>>,
+    /// Whether the globals imported as `burnt_gas` and `gas_limit` read the
+    /// gas counter, see [`InstanceConfig::with_gas_globals`].
+    pub gas_globals: bool,
+    /// Whether the start function runs during instantiation, see
+    /// [`InstanceConfig::with_run_start`].
+    pub run_start: bool,
+    /// Whether the phases of the instantiation are timed, see
+    /// [`InstanceConfig::with_profile_instantiation`].
+    pub profile_instantiation: bool,
+    clock: Arc<dyn WasmClock>,
 }
 
 // Default stack limit, in 8-byte stack slots.
@@ -641,6 +894,15 @@ impl InstanceConfig {
             gas_counter: result.get(),
             default_gas_counter: Some(result),
             stack_limit: DEFAULT_STACK_LIMIT,
+            max_function_call_depth: None,
+            host_call_tracer: None,
+            interrupt_flag: None,
+            allocator: None,
+            host_state: None,
+            gas_globals: false,
+            run_start: true,
+            profile_instantiation: false,
+            clock: Arc::new(RealClock),
         }
     }
 
@@ -658,6 +920,110 @@ impl InstanceConfig {
         self.stack_limit = stack_limit;
         self
     }
+
+    /// Create instance configuration limiting the number of nested calls.
+    ///
+    /// Unlike the stack limit, which depends on the size of the frames, this
+    /// counts the frames of the WebAssembly functions being executed: up to
+    /// `depth` of them may run at once, and calling a function while `depth`
+    /// of them are already running traps with `TrapCode::WasmStackOverflow`.
+    /// Both limits apply, whichever is hit first. Calls into imported
+    /// functions are not counted, and the frames unwound by a trap are given
+    /// back once the call from the host returns.
+    ///
+    /// Only the code of modules compiled to limit the call depth counts its
+    /// frames, instantiating other modules with this configuration fails.
+    pub fn with_max_function_call_depth(mut self, depth: u32) -> Self {
+        self.max_function_call_depth = Some(depth);
+        self
+    }
+
+    /// Create instance configuration with given host call tracer.
+    pub fn with_host_call_tracer(mut self, tracer: Arc<dyn HostCallTracer>) -> Self {
+        self.host_call_tracer = Some(tracer);
+        self
+    }
+
+    /// Create instance configuration with given interrupt flag.
+    ///
+    /// Setting the flag, e.g. from another thread, makes the running code of the
+    /// instance trap at the next loop iteration.
+    ///
+    /// Only the code of modules compiled to check for interrupts reads the
+    /// flag, instantiating other modules with this configuration fails.
+    pub fn with_interrupt_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.interrupt_flag = Some(flag);
+        self
+    }
+
+    /// Create instance configuration with given instance allocator.
+    pub fn with_allocator(mut self, allocator: Arc<dyn InstanceAllocatorProvider>) -> Self {
+        self.allocator = Some(allocator);
+        self
+    }
+
+    /// Create instance configuration with given host state.
+    ///
+    /// The state can then be retrieved from the instance, for example by the
+    /// environment of host functions when it is initialized. Instances can
+    /// move across threads, so the state must be `Send` and `Sync`.
+    pub fn with_host_state(mut self, state: Arc<dyn Any + Send + Sync>) -> Self {
+        self.host_state = Some(state);
+        self
+    }
+
+    /// Create instance configuration binding the globals imported as
+    /// `burnt_gas` and `gas_limit` to these fields of the gas counter.
+    ///
+    /// Like the `gas` intrinsic, the globals are imported from any module,
+    /// unless the compiler restricts the intrinsic to a single module, see
+    /// `Singlepass::gas_intrinsic_module`.
+    ///
+    /// Such globals must be immutable `i64`s. They are read from the counter
+    /// itself, so they account for the gas charged up to the point they are
+    /// read, and `gas_limit - burnt_gas` is the gas remaining. Like for the
+    /// `gas` intrinsic, the imports still have to be resolved, the value of
+    /// the globals provided for them is ignored.
+    pub fn with_gas_globals(mut self) -> Self {
+        self.gas_globals = true;
+        self
+    }
+
+    /// Create instance configuration choosing whether the start function of
+    /// the module runs during instantiation.
+    ///
+    /// When `run_start` is false, the tables and memories are initialized but
+    /// the start function is not called, it can be called later, with the gas
+    /// and stack limits of the instance, by `Instance::run_start`.
+    pub fn with_run_start(mut self, run_start: bool) -> Self {
+        self.run_start = run_start;
+        self
+    }
+
+    /// Create instance configuration choosing whether the phases of the
+    /// instantiation are timed into the `InstantiationProfile` it is given.
+    ///
+    /// This is disabled by default, so that instantiating doesn't read the
+    /// clock, and the profile is left untouched.
+    pub fn with_profile_instantiation(mut self, profile: bool) -> Self {
+        self.profile_instantiation = profile;
+        self
+    }
+
+    /// Create instance configuration with given clock.
+    ///
+    /// The time observed by the instance, as reported by the host functions
+    /// reading it from [`InstanceConfig::clock`], then comes from `clock`
+    /// instead of the system time.
+    pub fn with_clock(mut self, clock: Arc<dyn WasmClock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Clock of the instance, a [`RealClock`] unless configured otherwise.
+    pub fn clock(&self) -> &Arc<dyn WasmClock> {
+        &self.clock
+    }
 }
 
 #[cfg(test)]
@@ -669,6 +1035,44 @@ mod tests {
     const V128_I64_TO_I32: ([Type; 2], [Type; 1]) = ([Type::V128, Type::I64], [Type::I32]);
     const NINE_V128_TO_NINE_I32: ([Type; 9], [Type; 9]) = ([Type::V128; 9], [Type::I32; 9]);
 
+    #[test]
+    #[cfg(feature = "wat")]
+    fn parse_functiontype() {
+        let parse = |signature| FunctionType::parse(signature).unwrap();
+        assert_eq!(parse(""), FunctionType::new(vec![], vec![]));
+        assert_eq!(
+            parse("(param funcref) (result funcref)"),
+            FunctionType::new(vec![Type::FuncRef], vec![Type::FuncRef])
+        );
+        assert_eq!(
+            parse("(param i32 i64) (param $x f32) (param) (result f64 v128) (result externref)"),
+            FunctionType::new(
+                vec![Type::I32, Type::I64, Type::F32],
+                vec![Type::F64, Type::V128, Type::ExternRef]
+            )
+        );
+        assert_eq!(
+            parse("(result i32)"),
+            FunctionType::new(vec![], vec![Type::I32])
+        );
+        assert_eq!(parse("(param i32)(param i32)"), I32_I32_TO_VOID.into());
+
+        for invalid in &[
+            "i32",
+            "(param i8)",
+            "(param i32",
+            "(result i32) (param i32)",
+            "(result) (param i32)",
+            "(param $x i32 i32)",
+            "(param $x)",
+            "(result $x i32)",
+            "(local i32)",
+            "(param (i32))",
+        ] {
+            assert!(FunctionType::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
     #[test]
     fn convert_tuple_to_functiontype() {
         let ty: FunctionType = VOID_TO_VOID.into();
@@ -692,4 +1096,26 @@ mod tests {
         assert_eq!(ty.params().len(), 9);
         assert_eq!(ty.results().len(), 9);
     }
+
+    #[test]
+    fn validate_global_set() {
+        let var = GlobalType::new(Type::I64, Mutability::Var);
+        assert!(var.is_mutable());
+        assert_eq!(var.value_type(), Type::I64);
+        assert_eq!(var.validate_set(&Value::<()>::I64(1)), Ok(()));
+        assert_eq!(
+            var.validate_set(&Value::<()>::F64(1.0)),
+            Err(GlobalError::IncorrectType {
+                expected: Type::I64,
+                found: Type::F64
+            })
+        );
+
+        let constant = GlobalType::new(Type::I64, Mutability::Const);
+        assert!(!constant.is_mutable());
+        assert_eq!(
+            constant.validate_set(&Value::<()>::I64(1)),
+            Err(GlobalError::ImmutableGlobalCannotBeSet)
+        );
+    }
 }
@@ -30,6 +30,7 @@ pub const WASM_MIN_PAGES: u32 = 0x100;
     rkyv::Archive,
 )]
 #[archive(as = "Self")]
+#[derive(bytecheck::CheckBytes)]
 #[repr(transparent)]
 pub struct Pages(pub u32);
 
@@ -54,9 +55,24 @@ impl Pages {
     }
 
     /// Calculate number of bytes from pages.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the size doesn't fit in a `usize`, which
+    /// can only happen on hosts with pointers narrower than 64 bits, see
+    /// [`Pages::to_bytes`].
     pub fn bytes(self) -> Bytes {
         self.into()
     }
+
+    /// Calculate number of bytes from pages, failing if the size doesn't fit
+    /// in a `usize`.
+    pub fn to_bytes(self) -> Result<Bytes, ByteCountOutOfRange> {
+        (self.0 as usize)
+            .checked_mul(WASM_PAGE_SIZE)
+            .map(Bytes)
+            .ok_or(ByteCountOutOfRange)
+    }
 }
 
 impl fmt::Debug for Pages {
@@ -119,6 +135,19 @@ where
     }
 }
 
+impl Bytes {
+    /// Calculate number of whole pages from bytes, rounding down, failing if
+    /// the count doesn't fit in a `u32`.
+    pub fn to_pages(self) -> Result<Pages, PageCountOutOfRange> {
+        Pages::try_from(self)
+    }
+}
+
+/// The only error that can happen when converting `Pages` to `Bytes`
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+#[error("Number of bytes exceeds usize range")]
+pub struct ByteCountOutOfRange;
+
 /// The only error that can happen when converting `Bytes` to `Pages`
 #[derive(Debug, Clone, Copy, PartialEq, Error)]
 #[error("Number of pages exceeds uint32 range")]
@@ -185,4 +214,31 @@ mod tests {
         let result = Pages::try_from(Bytes(usize::MAX));
         assert_eq!(result.unwrap_err(), PageCountOutOfRange);
     }
+
+    #[test]
+    fn convert_pages_to_bytes() {
+        assert_eq!(Pages(0).to_bytes(), Ok(Bytes(0)));
+        assert_eq!(Pages(1).to_bytes(), Ok(Bytes(WASM_PAGE_SIZE)));
+        assert_eq!(
+            Pages(WASM_MAX_PAGES - 1).to_bytes(),
+            Ok(Bytes((WASM_MAX_PAGES as usize - 1) * WASM_PAGE_SIZE))
+        );
+        assert_eq!(Bytes(WASM_PAGE_SIZE + 1).to_pages(), Ok(Pages(1)));
+
+        // The whole 32-bit address space is one byte more than a 32-bit
+        // `usize` can hold.
+        #[cfg(target_pointer_width = "32")]
+        {
+            assert_eq!(Pages(WASM_MAX_PAGES).to_bytes(), Err(ByteCountOutOfRange));
+            assert_eq!(Pages(u32::MAX).to_bytes(), Err(ByteCountOutOfRange));
+        }
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert_eq!(Pages(WASM_MAX_PAGES).to_bytes(), Ok(Bytes(1 << 32)));
+            assert_eq!(
+                Pages(u32::MAX).to_bytes(),
+                Ok(Bytes(u32::MAX as usize * WASM_PAGE_SIZE))
+            );
+        }
+    }
 }
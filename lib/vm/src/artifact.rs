@@ -1,10 +1,39 @@
-use crate::{InstanceHandle, Resolver, Tunables, VMLocalFunction, VMSharedSignatureIndex};
+use crate::{Imports, InstanceHandle, Resolver, Tunables, VMLocalFunction, VMSharedSignatureIndex};
 use std::{any::Any, collections::BTreeMap, sync::Arc};
+use thiserror::Error;
 use wasmer_types::{
-    entity::BoxedSlice, ElemIndex, FunctionIndex, GlobalInit, GlobalType, ImportCounts,
-    InstanceConfig, LocalFunctionIndex, OwnedDataInitializer, OwnedTableInitializer,
+    entity::{BoxedSlice, EntityRef},
+    ElemIndex, ExternRef, FunctionIndex, GlobalIndex, GlobalInit, GlobalType, ImportCounts,
+    InstanceConfig, LocalFunctionIndex, LocalGlobalIndex, ModuleInfo, OwnedDataInitializer,
+    OwnedTableInitializer, Type, Value,
 };
 
+/// Error type describing why [`Artifact::evaluate_global_init`] failed.
+#[derive(Error, Debug, Clone, PartialEq, Hash)]
+pub enum GlobalInitError {
+    /// The module doesn't define the local global.
+    #[error("unknown local global {}", .0.index())]
+    UnknownGlobal(LocalGlobalIndex),
+
+    /// The initializer reads an imported global missing from the imports.
+    #[error("imported global {} is missing", .0.index())]
+    MissingImport(GlobalIndex),
+
+    /// The initializer reads an imported global of another type.
+    #[error("imported global of type {found} read as a global of type {expected}")]
+    IncorrectType {
+        /// The type of the initialized global.
+        expected: Type,
+        /// The type of the imported global.
+        found: Type,
+    },
+
+    /// The initializer reads an imported `funcref` global that isn't null, which
+    /// can't be described by a function index of this module.
+    #[error("imported global {} refers to a function of another module", .0.index())]
+    ForeignFuncRef(GlobalIndex),
+}
+
 mod private {
     pub struct Internal(pub(super) ());
 }
@@ -69,17 +98,126 @@ pub trait Artifact: Send + Sync {
     /// Passive table elements.
     fn globals(&self) -> &[(GlobalType, GlobalInit)];
 
+    /// Evaluate the initializer of the local global `index`, as done when
+    /// instantiating the module with `imports`.
+    ///
+    /// A `ref.func` evaluates to the index of the referenced function.
+    ///
+    /// # Safety
+    ///
+    /// The `definition` of every global in `imports` must point to the live
+    /// value of its `from` global.
+    unsafe fn evaluate_global_init(
+        &self,
+        index: LocalGlobalIndex,
+        imports: &Imports,
+    ) -> Result<Value<FunctionIndex>, GlobalInitError> {
+        let (ty, init) = self
+            .globals()
+            .get(index.index())
+            .ok_or(GlobalInitError::UnknownGlobal(index))?;
+        Ok(match *init {
+            GlobalInit::I32Const(x) => Value::I32(x),
+            GlobalInit::I64Const(x) => Value::I64(x),
+            GlobalInit::F32Const(x) => Value::F32(x),
+            GlobalInit::F64Const(x) => Value::F64(x),
+            GlobalInit::V128Const(x) => Value::V128(u128::from_ne_bytes(*x.bytes())),
+            GlobalInit::RefNullConst => match ty.ty {
+                Type::ExternRef => Value::ExternRef(ExternRef::null()),
+                _ => Value::FuncRef(None),
+            },
+            GlobalInit::RefFunc(function) => Value::FuncRef(Some(function)),
+            GlobalInit::GetGlobal(global) => {
+                let global = match self.import_counts().local_global_index(global) {
+                    Ok(local) => return self.evaluate_global_init(local, imports),
+                    Err(global) => global,
+                };
+                let import = imports
+                    .globals
+                    .get(global)
+                    .ok_or(GlobalInitError::MissingImport(global))?;
+                if import.from.ty().ty != ty.ty {
+                    return Err(GlobalInitError::IncorrectType {
+                        expected: ty.ty,
+                        found: import.from.ty().ty,
+                    });
+                }
+                let definition = import.definition.as_ref();
+                match ty.ty {
+                    Type::I32 => Value::I32(definition.to_i32()),
+                    Type::I64 => Value::I64(definition.to_i64()),
+                    Type::F32 => Value::F32(definition.to_f32()),
+                    Type::F64 => Value::F64(definition.to_f64()),
+                    Type::V128 => Value::V128(definition.to_u128()),
+                    Type::ExternRef => {
+                        Value::ExternRef(definition.to_externref().ref_clone().into())
+                    }
+                    Type::FuncRef if definition.to_funcref().is_null() => Value::FuncRef(None),
+                    Type::FuncRef => return Err(GlobalInitError::ForeignFuncRef(global)),
+                }
+            }
+        })
+    }
+
     /// The function index to the start function.
     fn start_function(&self) -> Option<FunctionIndex>;
 
     /// Function by export name.
     fn export_field(&self, name: &str) -> Option<wasmer_types::ExportIndex>;
 
+    /// The imports of the module, in declaration order.
+    ///
+    /// Artifacts that don't keep their imports around report none.
+    fn imports(&self) -> &[crate::VMImport] {
+        &[]
+    }
+
     /// Mapping between module SignatureIndex and VMSharedSignatureIndex.
     fn signatures(&self) -> &[VMSharedSignatureIndex];
 
     /// Obtain the function signature for either the import or local definition.
     fn function_signature(&self, index: FunctionIndex) -> Option<VMSharedSignatureIndex>;
+
+    /// The machine code of the local function `index`, as published, that is
+    /// with its relocations applied.
+    ///
+    /// Implementations must keep the code alive and unchanged for as long as
+    /// the artifact is. Artifacts that can't hand out their code return
+    /// `None`.
+    fn function_code(&self, _index: LocalFunctionIndex) -> Option<&[u8]> {
+        None
+    }
+
+    /// Find the function whose code contains `native_addr`, among the local
+    /// ones.
+    fn function_for_address(&self, native_addr: usize) -> Option<FunctionIndex> {
+        let (index, _) = self.functions().iter().find(|(_, function)| {
+            let start = function.body.0 as usize;
+            start <= native_addr && native_addr < start + function.length as usize
+        })?;
+        Some(self.import_counts().function_index(index))
+    }
+
+    /// The name of the function `index`, if the artifact kept the names of
+    /// its functions.
+    fn function_name(&self, _index: FunctionIndex) -> Option<&str> {
+        None
+    }
+
+    /// Find the name `module` gives to the local function whose code contains
+    /// `native_addr`.
+    ///
+    /// This is the lookup profilers need, going from a native code address
+    /// back to the function it belongs to. `module` must be the module this
+    /// artifact has been compiled from.
+    fn function_name_for_address<'a>(
+        &self,
+        module: &'a ModuleInfo,
+        native_addr: usize,
+    ) -> Option<&'a str> {
+        let index = self.function_for_address(native_addr)?;
+        module.function_names.get(&index).map(|name| &**name)
+    }
 }
 
 impl dyn Artifact {
@@ -2,8 +2,9 @@ use crate::vmcontext::VMGlobalDefinition;
 use std::cell::UnsafeCell;
 use std::ptr::NonNull;
 use std::sync::Mutex;
-use thiserror::Error;
-use wasmer_types::{GlobalType, Mutability, Type, Value, WasmValueType};
+use wasmer_types::{GlobalType, Type, Value, WasmValueType};
+
+pub use wasmer_types::GlobalError;
 
 #[derive(Debug)]
 /// A Global instance
@@ -23,24 +24,6 @@ unsafe impl Send for Global {}
 /// This is safe to share between threads because it uses a `Mutex` internally.
 unsafe impl Sync for Global {}
 
-/// Error type describing things that can go wrong when operating on Wasm Globals.
-#[derive(Error, Debug, Clone, PartialEq, Hash)]
-pub enum GlobalError {
-    /// The error returned when attempting to set an immutable global.
-    #[error("Attempted to set an immutable global")]
-    ImmutableGlobalCannotBeSet,
-
-    /// The error returned when attempting to operate on a global as a specific type
-    /// that differs from the global's own type.
-    #[error("Attempted to operate on a global of type {expected} as a global of type {found}")]
-    IncorrectType {
-        /// The type that the global is.
-        expected: Type,
-        /// The type that we were asked to use it as.
-        found: Type,
-    },
-}
-
 impl Global {
     /// Create a new, zero bit-pattern initialized global from a [`GlobalType`].
     pub fn new(global_type: GlobalType) -> Self {
@@ -97,15 +80,7 @@ impl Global {
     /// The caller should check that the `val` comes from the same store as this global.
     pub unsafe fn set<T: WasmValueType>(&self, val: Value<T>) -> Result<(), GlobalError> {
         let _global_guard = self.lock.lock().unwrap();
-        if self.ty().mutability != Mutability::Var {
-            return Err(GlobalError::ImmutableGlobalCannotBeSet);
-        }
-        if val.ty() != self.ty().ty {
-            return Err(GlobalError::IncorrectType {
-                expected: self.ty.ty,
-                found: val.ty(),
-            });
-        }
+        self.ty.validate_set(&val)?;
         self.set_unchecked(val)
     }
 
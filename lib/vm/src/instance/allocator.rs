@@ -5,8 +5,9 @@ use std::alloc::{self, Layout};
 use std::convert::TryFrom;
 use std::mem;
 use std::ptr::{self, NonNull};
+use std::sync::Arc;
 use wasmer_types::entity::EntityRef;
-use wasmer_types::{LocalMemoryIndex, LocalTableIndex};
+use wasmer_types::{InstanceAllocatorProvider, LocalMemoryIndex, LocalTableIndex};
 
 /// This is an intermediate type that manages the raw allocation and
 /// metadata when creating an [`Instance`].
@@ -33,6 +34,10 @@ pub struct InstanceAllocator {
     /// The layout of the `instance_ptr` buffer.
     instance_layout: Layout,
 
+    /// Where the `instance_ptr` buffer comes from, the global allocator
+    /// if `None`.
+    provider: Option<Arc<dyn InstanceAllocatorProvider>>,
+
     /// Information about the offsets into the `instance_ptr` buffer for
     /// the dynamic fields.
     offsets: VMOffsets,
@@ -48,10 +53,12 @@ impl Drop for InstanceAllocator {
         if !self.consumed {
             // If `consumed` has not been set, then we still have ownership
             // over the buffer and must free it.
-            let instance_ptr = self.instance_ptr.as_ptr();
-
             unsafe {
-                std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+                deallocate(
+                    self.provider.as_deref(),
+                    self.instance_ptr.cast(),
+                    self.instance_layout,
+                );
             }
         }
     }
@@ -73,14 +80,30 @@ impl InstanceAllocator {
         Self,
         Vec<NonNull<VMMemoryDefinition>>,
         Vec<NonNull<VMTableDefinition>>,
+    ) {
+        Self::with_provider(offsets, None)
+    }
+
+    /// Like [`InstanceAllocator::new`], but takes the instance data from
+    /// `provider` rather than from the global allocator. The data is
+    /// returned to `provider` once the instance is dropped.
+    pub fn with_provider(
+        offsets: VMOffsets,
+        provider: Option<Arc<dyn InstanceAllocatorProvider>>,
+    ) -> (
+        Self,
+        Vec<NonNull<VMMemoryDefinition>>,
+        Vec<NonNull<VMTableDefinition>>,
     ) {
         let instance_layout = Self::instance_layout(&offsets);
 
-        #[allow(clippy::cast_ptr_alignment)]
-        let instance_ptr = unsafe { alloc::alloc(instance_layout) as *mut Instance };
+        let instance_ptr = match &provider {
+            Some(provider) => provider.allocate(instance_layout),
+            None => NonNull::new(unsafe { alloc::alloc(instance_layout) }),
+        };
 
-        let instance_ptr = if let Some(ptr) = NonNull::new(instance_ptr) {
-            ptr
+        let instance_ptr = if let Some(ptr) = instance_ptr {
+            ptr.cast::<Instance>()
         } else {
             alloc::handle_alloc_error(instance_layout);
         };
@@ -88,6 +111,7 @@ impl InstanceAllocator {
         let allocator = Self {
             instance_ptr,
             instance_layout,
+            provider,
             offsets,
             consumed: false,
         };
@@ -103,7 +127,7 @@ impl InstanceAllocator {
     }
 
     /// Calculate the appropriate layout for the [`Instance`].
-    fn instance_layout(offsets: &VMOffsets) -> Layout {
+    pub(super) fn instance_layout(offsets: &VMOffsets) -> Layout {
         let vmctx_size = usize::try_from(offsets.size_of_vmctx())
             .expect("Failed to convert the size of `vmctx` to a `usize`");
 
@@ -200,9 +224,27 @@ impl InstanceAllocator {
         }
         let instance = self.instance_ptr;
         let instance_layout = self.instance_layout;
+        let provider = self.provider.take();
 
         // This is correct because of the invariants of `Self` and
         // because we write `Instance` to the pointer in this function.
-        unsafe { InstanceRef::new(instance, instance_layout) }
+        unsafe { InstanceRef::new(instance, instance_layout, provider) }
+    }
+}
+
+/// Release instance data allocated by [`InstanceAllocator`].
+///
+/// # Safety
+///
+/// `ptr` must have been allocated with `layout`, by `provider` if it is
+/// set or by the global allocator otherwise.
+pub(super) unsafe fn deallocate(
+    provider: Option<&dyn InstanceAllocatorProvider>,
+    ptr: NonNull<u8>,
+    layout: Layout,
+) {
+    match provider {
+        Some(provider) => provider.deallocate(ptr, layout),
+        None => alloc::dealloc(ptr.as_ptr(), layout),
     }
 }
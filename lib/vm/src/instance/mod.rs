@@ -8,21 +8,24 @@
 //! wrapper around an `InstanceRef`.
 
 mod allocator;
+mod pool;
 mod r#ref;
+mod registry;
 
 pub use allocator::InstanceAllocator;
+pub use pool::PooledInstanceAllocator;
 pub use r#ref::{InstanceRef, WeakInstanceRef, WeakOrStrongInstanceRef};
 
 use crate::func_data_registry::VMFuncRef;
 use crate::global::Global;
-use crate::imports::Imports;
+use crate::imports::{Imports, VMImportType};
 use crate::memory::{Memory, MemoryError};
 use crate::sig_registry::VMSharedSignatureIndex;
 use crate::table::{Table, TableElement};
 use crate::trap::traphandlers::get_trap_handler;
 use crate::trap::{catch_traps, Trap, TrapCode};
 use crate::vmcontext::{
-    VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionBody,
+    FunctionExtent, VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionBody,
     VMFunctionEnvironment, VMFunctionImport, VMFunctionKind, VMGlobalDefinition, VMGlobalImport,
     VMLocalFunction, VMMemoryDefinition, VMMemoryImport, VMTableDefinition, VMTableImport,
 };
@@ -39,14 +42,19 @@ use std::fmt;
 use std::mem;
 use std::ptr::{self, NonNull};
 use std::slice;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     DataIndex, DataInitializer, ElemIndex, ExportIndex, FastGasCounter, FunctionIndex, GlobalIndex,
-    GlobalInit, InstanceConfig, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    OwnedTableInitializer, Pages, TableIndex,
+    GlobalInit, HostCallTracer, InstanceConfig, LocalGlobalIndex, LocalMemoryIndex,
+    LocalTableIndex, MemoryIndex, OwnedTableInitializer, Pages, PassiveData, TableIndex, WasmClock,
 };
 
+/// The interrupt flag of instances that were not given one.
+static NEVER_INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
 /// The function pointer to call with data and an [`Instance`] pointer to
 /// finish initializing the host env.
 pub type ImportInitializerFuncPtr<ResultErr = *mut ffi::c_void> =
@@ -80,7 +88,7 @@ pub(crate) struct Instance {
 
     /// Passive data segments from our module. As `data.drop`s happen, entries
     /// get removed. A missing entry is considered equivalent to an empty slice.
-    passive_data: RefCell<BTreeMap<DataIndex, Arc<[u8]>>>,
+    passive_data: RefCell<PassiveData>,
 
     /// Mapping of function indices to their func ref backing data. `VMFuncRef`s
     /// will point to elements here for functions defined or imported by this
@@ -355,6 +363,19 @@ impl Instance {
         &*self.host_state
     }
 
+    /// Return the state attached with `InstanceConfig::with_host_state`, if
+    /// it is a `T`.
+    #[inline]
+    pub fn host_state_ref<T: Any>(&self) -> Option<&T> {
+        self.config.host_state.as_deref()?.downcast_ref()
+    }
+
+    /// Return the clock attached with `InstanceConfig::with_clock`.
+    #[inline]
+    pub fn clock(&self) -> &Arc<dyn WasmClock> {
+        self.config.clock()
+    }
+
     /// Return a pointer to the trap catcher.
     fn trap_catcher_ptr(&self) -> *mut *const u8 {
         unsafe { self.vmctx_plus_offset(self.offsets().vmctx_trap_handler()) }
@@ -375,6 +396,31 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets().vmctx_stack_limit_begin()) }
     }
 
+    /// Return a pointer to the initial call depth.
+    pub fn call_depth_initial_ptr(&self) -> *mut i32 {
+        unsafe { self.vmctx_plus_offset(self.offsets().vmctx_call_depth_initial_begin()) }
+    }
+
+    /// Return a pointer to the remaining call depth.
+    pub fn call_depth_ptr(&self) -> *mut i32 {
+        unsafe { self.vmctx_plus_offset(self.offsets().vmctx_call_depth_begin()) }
+    }
+
+    /// Return a pointer to the host call tracer, null if tracing is disabled.
+    fn host_call_tracer_ptr(&self) -> *mut *const u8 {
+        unsafe { self.vmctx_plus_offset(self.offsets().vmctx_host_call_tracer_pointer()) }
+    }
+
+    /// Return a pointer to the interrupt flag checked by compiled code.
+    fn interrupt_flag_ptr(&self) -> *mut *const AtomicBool {
+        unsafe { self.vmctx_plus_offset(self.offsets().vmctx_interrupt_flag_pointer()) }
+    }
+
+    /// Return the tracer notified about calls into imported functions, if any.
+    pub(crate) fn host_call_tracer(&self) -> Option<&Arc<dyn HostCallTracer>> {
+        self.config.host_call_tracer.as_ref()
+    }
+
     /// Invoke the WebAssembly start function of the instance, if one is present.
     fn invoke_start_function(&self) -> Result<(), Trap> {
         let start_index = match self.artifact.start_function() {
@@ -397,6 +443,7 @@ impl Instance {
     fn reset_stack_meter(&self) {
         unsafe {
             *(self.stack_limit_ptr()) = *(self.stack_limit_initial_ptr());
+            *(self.call_depth_ptr()) = *(self.call_depth_initial_ptr());
         }
     }
 
@@ -474,7 +521,7 @@ impl Instance {
         self.memories
             .get(memory_index)
             .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()))
-            .size()
+            .current_pages()
     }
 
     /// Returns the number of allocated wasm pages in an imported memory.
@@ -483,7 +530,7 @@ impl Instance {
     /// This and `imported_memory_grow` are currently unsafe because they
     /// dereference the memory import's pointers.
     pub(crate) unsafe fn imported_memory_size(&self, memory_index: MemoryIndex) -> Pages {
-        self.imported_memory(memory_index).from.size()
+        self.imported_memory(memory_index).from.current_pages()
     }
 
     /// Returns the number of elements in a given table.
@@ -648,25 +695,7 @@ impl Instance {
         item: TableElement,
         len: u32,
     ) -> Result<(), Trap> {
-        // https://webassembly.github.io/bulk-memory-operations/core/exec/instructions.html#exec-table-init
-
-        let table = self.get_table(table_index);
-        let table_size = table.size() as usize;
-
-        if start_index
-            .checked_add(len)
-            .map_or(true, |n| n as usize > table_size)
-        {
-            return Err(Trap::lib(TrapCode::TableAccessOutOfBounds));
-        }
-
-        for i in start_index..(start_index + len) {
-            table
-                .set(i, item.clone())
-                .expect("should never panic because we already did the bounds check above");
-        }
-
-        Ok(())
+        self.get_table(table_index).fill(start_index, len, item)
     }
 
     /// Drop an element.
@@ -764,7 +793,7 @@ impl Instance {
 
         let memory = self.memory_definition(memory_index);
         let passive_data = self.passive_data.borrow();
-        let data = passive_data.get(&data_index).map_or(&[][..], |d| &**d);
+        let data = passive_data.get(&data_index).map_or(&[][..], |d| &d[..]);
 
         let oob_access = src
             .checked_add(len)
@@ -812,6 +841,51 @@ impl Instance {
     }
 }
 
+impl VMFunction {
+    /// Run `call`, which calls into this function, then restore the remaining
+    /// call depth of the instance defining it to its value before the call.
+    ///
+    /// A trap unwinds the frames of the call without running their epilogues,
+    /// so that they would otherwise stay counted, see
+    /// `InstanceConfig::with_max_function_call_depth`. Functions that an
+    /// instance re-exports from its imports, and host functions, are just
+    /// called.
+    pub fn with_call_depth_restored<T>(&self, call: impl FnOnce() -> T) -> T {
+        let instance_ref = match self.instance_ref.as_ref().and_then(|r| r.upgrade()) {
+            Some(WeakOrStrongInstanceRef::Strong(instance_ref)) => instance_ref,
+            _ => return call(),
+        };
+        let instance = instance_ref.as_ref();
+        if instance.vmctx_ptr() != unsafe { self.vmctx.vmctx } {
+            return call();
+        }
+        let call_depth = instance.call_depth_ptr();
+        // SAFETY: the instance is kept alive by `instance_ref`, and the call
+        // depth is only accessed by the code running on this thread.
+        let depth = unsafe { *call_depth };
+        let result = call();
+        unsafe { *call_depth = depth };
+        result
+    }
+}
+
+/// Time spent in the phases of instantiating a module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstantiationProfile {
+    /// Resolving the imports of the module and linking them.
+    pub import_resolution: Duration,
+    /// Creating the local memories.
+    pub memory_creation: Duration,
+    /// Creating the local tables.
+    pub table_creation: Duration,
+    /// Creating the local globals.
+    pub global_creation: Duration,
+    /// Applying the element and data segments.
+    pub initialization: Duration,
+    /// Running the start function.
+    pub start_function: Duration,
+}
+
 /// A handle holding an `InstanceRef`, which holds an `Instance`
 /// of a WebAssembly module.
 ///
@@ -854,7 +928,7 @@ impl InstanceHandle {
         finished_tables: BoxedSlice<LocalTableIndex, Arc<dyn Table>>,
         finished_globals: BoxedSlice<LocalGlobalIndex, Arc<Global>>,
         imports: Imports,
-        passive_data: BTreeMap<DataIndex, Arc<[u8]>>,
+        passive_data: PassiveData,
         host_state: Box<dyn Any>,
         imported_function_envs: BoxedSlice<FunctionIndex, ImportFunctionEnv>,
         instance_config: InstanceConfig,
@@ -899,6 +973,22 @@ impl InstanceHandle {
                 *(instance.gas_counter_ptr()) = instance_config.gas_counter;
                 *(instance.stack_limit_ptr()) = instance_config.stack_limit;
                 *(instance.stack_limit_initial_ptr()) = instance_config.stack_limit;
+                let call_depth = instance_config
+                    .max_function_call_depth
+                    .map_or(i32::MAX, |depth| i32::try_from(depth).unwrap_or(i32::MAX));
+                *(instance.call_depth_ptr()) = call_depth;
+                *(instance.call_depth_initial_ptr()) = call_depth;
+                *(instance.host_call_tracer_ptr()) = instance
+                    .host_call_tracer()
+                    .map_or(ptr::null(), |tracer| Arc::as_ptr(tracer) as *const u8);
+                // Compiled code always dereferences the flag, so point it at a
+                // flag that is never set when the instance can't be interrupted.
+                *(instance.interrupt_flag_ptr()) = instance
+                    .config
+                    .interrupt_flag
+                    .as_ref()
+                    .map_or(&NEVER_INTERRUPTED as *const AtomicBool, Arc::as_ptr);
+                registry::register(vmctx_ptr, &instance.artifact);
             }
 
             Self {
@@ -964,21 +1054,55 @@ impl InstanceHandle {
     ///
     /// Only safe to call immediately after instantiation.
     pub unsafe fn finish_instantiation(&self) -> Result<(), Trap> {
+        self.finish_instantiation_with_profile(&mut InstantiationProfile::default())
+    }
+
+    /// Like [`InstanceHandle::finish_instantiation`], also recording the time
+    /// spent in initializers and the start function into `profile` if the
+    /// instance was configured with `InstanceConfig::with_profile_instantiation`.
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after instantiation.
+    pub unsafe fn finish_instantiation_with_profile(
+        &self,
+        profile: &mut InstantiationProfile,
+    ) -> Result<(), Trap> {
         let instance = self.instance().as_ref();
 
         // Apply the initializers.
+        let initialization_start = instance.config.profile_instantiation.then(Instant::now);
         initialize_tables(instance)?;
         initialize_memories(
             instance,
             instance.artifact.data_segments().iter().map(Into::into),
         )?;
+        if let Some(start) = initialization_start {
+            profile.initialization = start.elapsed();
+        }
 
         // The WebAssembly spec specifies that the start function is
-        // invoked automatically at instantiation time.
-        instance.invoke_start_function()?;
+        // invoked automatically at instantiation time, unless the embedder
+        // asked to run it later.
+        if instance.config.run_start {
+            let start_function_start = instance.config.profile_instantiation.then(Instant::now);
+            instance.invoke_start_function()?;
+            if let Some(start) = start_function_start {
+                profile.start_function = start.elapsed();
+            }
+        }
         Ok(())
     }
 
+    /// Invoke the start function of the instance, if it has one.
+    ///
+    /// This is meant for instances created with
+    /// `InstanceConfig::with_run_start(false)`: nothing prevents the start
+    /// function from running more than once otherwise.
+    pub fn run_start(&self) -> Result<(), Trap> {
+        self.instance().as_ref().invoke_start_function()
+    }
+
     /// See [`traphandlers::wasmer_call_trampoline`].
     pub unsafe fn invoke_function(
         &self,
@@ -1103,11 +1227,79 @@ impl InstanceHandle {
         })
     }
 
+    /// Return the extent of the native code of the locally defined function
+    /// exported with the given name.
+    pub fn function_extent_by_export_field(&self, field: &str) -> Option<FunctionExtent> {
+        let artifact = &self.instance.as_ref().artifact;
+        let index = match artifact.export_field(field)? {
+            ExportIndex::Function(idx) => {
+                artifact.import_counts().local_function_index(idx).ok()?
+            }
+            _ => return None,
+        };
+        let function = artifact.functions().get(index)?;
+        Some(FunctionExtent {
+            address: function.body,
+            length: usize::try_from(function.length).unwrap(),
+        })
+    }
+
+    /// Return the module and field names of every imported function, along
+    /// with the body the import has been resolved to.
+    ///
+    /// Imports of dynamic host functions resolve to the reverse trampoline
+    /// generated for them, imports of static ones to the host function itself.
+    /// This is empty if the artifact doesn't report its imports.
+    pub fn imported_functions(&self) -> Vec<(String, String, *const VMFunctionBody)> {
+        let instance = self.instance.as_ref();
+        instance
+            .artifact
+            .imports()
+            .iter()
+            .filter(|import| matches!(import.ty, VMImportType::Function { .. }))
+            .enumerate()
+            .map(|(index, import)| {
+                let body = instance.imported_function(FunctionIndex::new(index)).body;
+                (import.module.clone(), import.field.clone(), *body)
+            })
+            .collect()
+    }
+
+    /// Return the host env of the function imported as `module`.`field`.
+    ///
+    /// Each instance owns its own copy of the envs of its imports, which is
+    /// the one `WasmerEnv::init_with_instance` was called on. Returns `None`
+    /// if there is no such function import or if it has no env.
+    pub fn function_env_ptr(&self, module: &str, field: &str) -> Option<*mut ffi::c_void> {
+        let instance = self.instance.as_ref();
+        let index = instance
+            .artifact
+            .imports()
+            .iter()
+            .filter(|import| matches!(import.ty, VMImportType::Function { .. }))
+            .position(|import| import.module == module && import.field == field)?;
+        match instance.imported_function_envs[FunctionIndex::new(index)] {
+            ImportFunctionEnv::Env { env, .. } => Some(env),
+            ImportFunctionEnv::NoEnv => None,
+        }
+    }
+
     /// Return a reference to the custom state attached to this instance.
     pub fn host_state(&self) -> &dyn Any {
         self.instance().as_ref().host_state()
     }
 
+    /// Return the state attached with `InstanceConfig::with_host_state`, if
+    /// it is a `T`.
+    pub fn host_state_ref<T: Any>(&self) -> Option<&T> {
+        self.instance().as_ref().host_state_ref()
+    }
+
+    /// Return the clock attached with `InstanceConfig::with_clock`.
+    pub fn clock(&self) -> &Arc<dyn WasmClock> {
+        self.instance().as_ref().clock()
+    }
+
     /// Return the memory index for the given `VMMemoryDefinition` in this instance.
     pub fn memory_index(&self, memory: &VMMemoryDefinition) -> LocalMemoryIndex {
         self.instance().as_ref().memory_index(memory)
@@ -1171,6 +1363,44 @@ impl InstanceHandle {
     pub fn get_local_table(&self, index: LocalTableIndex) -> &dyn Table {
         self.instance().as_ref().get_local_table(index)
     }
+
+    /// Make the function import `index` call `import` from now on, `env`
+    /// being its host env, and drop the host env of the previous function.
+    ///
+    /// Both the code of this instance and its function references, in tables
+    /// for example, see the new function.
+    ///
+    /// # Safety
+    ///
+    /// - `index` must be a function import of this instance, and `import`
+    ///   must have its signature, as returned by `link_function_import`.
+    /// - The function of `import` must stay valid for as long as this
+    ///   instance may call it.
+    /// - The import is not patched atomically, so no code of this instance
+    ///   may run while this is called, on this thread or on any other one.
+    pub unsafe fn replace_function_import(
+        &mut self,
+        index: FunctionIndex,
+        import: VMFunctionImport,
+        env: ImportFunctionEnv,
+    ) {
+        let instance = self.instance.as_mut_unchecked();
+        assert!(
+            instance
+                .artifact
+                .import_counts()
+                .local_function_index(index)
+                .is_err(),
+            "not a function import"
+        );
+        let funcref = &mut instance.funcrefs[index];
+        debug_assert_eq!(funcref.type_index, import.signature);
+        funcref.func_ptr = *import.body;
+        funcref.vmctx = import.environment;
+        ptr::write(instance.imported_functions_ptr().add(index.index()), import);
+        // Only now that nothing refers to it can the previous env be dropped.
+        instance.imported_function_envs[index] = env;
+    }
 }
 
 /// Initializes the host environments.
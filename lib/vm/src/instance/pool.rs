@@ -0,0 +1,127 @@
+use super::InstanceAllocator;
+use crate::VMOffsets;
+use std::alloc::{self, Layout};
+use std::ptr::NonNull;
+use std::sync::Mutex;
+use wasmer_types::InstanceAllocatorProvider;
+
+/// An [`InstanceAllocatorProvider`] that keeps the memory of dropped
+/// instances around and hands it out again to new instances with the
+/// same layout, instead of going through the global allocator every time.
+///
+/// At most `pool_size` free regions are retained, any region released
+/// while the pool is full goes back to the global allocator.
+pub struct PooledInstanceAllocator {
+    pool_size: usize,
+    free: Mutex<Vec<(Layout, NonNull<u8>)>>,
+}
+
+// The pooled regions are plain, unused memory, owned by the pool alone.
+unsafe impl Send for PooledInstanceAllocator {}
+unsafe impl Sync for PooledInstanceAllocator {}
+
+impl PooledInstanceAllocator {
+    /// Create an empty pool retaining up to `pool_size` free regions.
+    pub fn with_pool_size(pool_size: usize) -> Self {
+        Self {
+            pool_size,
+            free: Mutex::new(Vec::with_capacity(pool_size)),
+        }
+    }
+
+    /// Fill the pool with regions fit for instances laid out according
+    /// to `offsets`, so that the next instantiations don't allocate.
+    pub fn reserve(&self, offsets: &VMOffsets) {
+        let layout = InstanceAllocator::instance_layout(offsets);
+        let mut free = self.free.lock().unwrap();
+        while free.len() < self.pool_size {
+            let ptr = match NonNull::new(unsafe { alloc::alloc(layout) }) {
+                Some(ptr) => ptr,
+                None => alloc::handle_alloc_error(layout),
+            };
+            free.push((layout, ptr));
+        }
+    }
+
+    /// Maximum number of free regions retained by the pool.
+    pub fn pool_size(&self) -> usize {
+        self.pool_size
+    }
+
+    /// Number of free regions currently held by the pool.
+    pub fn free_regions(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+}
+
+impl InstanceAllocatorProvider for PooledInstanceAllocator {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        {
+            let mut free = self.free.lock().unwrap();
+            if let Some(index) = free.iter().position(|(l, _)| *l == layout) {
+                return Some(free.swap_remove(index).1);
+            }
+        }
+        NonNull::new(unsafe { alloc::alloc(layout) })
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        {
+            let mut free = self.free.lock().unwrap();
+            if free.len() < self.pool_size {
+                free.push((layout, ptr));
+                return;
+            }
+        }
+        alloc::dealloc(ptr.as_ptr(), layout);
+    }
+}
+
+impl Drop for PooledInstanceAllocator {
+    fn drop(&mut self) {
+        for (layout, ptr) in self.free.get_mut().unwrap().drain(..) {
+            unsafe { alloc::dealloc(ptr.as_ptr(), layout) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_released_regions() {
+        let pool = PooledInstanceAllocator::with_pool_size(1);
+        let layout = Layout::from_size_align(256, 16).unwrap();
+        let first = pool.allocate(layout).unwrap();
+        unsafe { pool.deallocate(first, layout) };
+        assert_eq!(pool.free_regions(), 1);
+
+        let other = Layout::from_size_align(512, 16).unwrap();
+        let second = pool.allocate(other).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(pool.free_regions(), 1);
+
+        assert_eq!(pool.allocate(layout), Some(first));
+        assert_eq!(pool.free_regions(), 0);
+        unsafe {
+            pool.deallocate(first, layout);
+            pool.deallocate(second, other);
+        }
+        assert_eq!(pool.free_regions(), 1);
+    }
+
+    #[test]
+    fn reserve_fills_the_pool() {
+        let pool = PooledInstanceAllocator::with_pool_size(3);
+        let offsets = VMOffsets::new(8);
+        pool.reserve(&offsets);
+        assert_eq!(pool.free_regions(), 3);
+
+        let layout = InstanceAllocator::instance_layout(&offsets);
+        let region = pool.allocate(layout).unwrap();
+        assert_eq!(pool.free_regions(), 2);
+        unsafe { pool.deallocate(region, layout) };
+        assert_eq!(pool.free_regions(), 3);
+    }
+}
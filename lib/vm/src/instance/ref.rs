@@ -1,8 +1,11 @@
+use super::allocator::deallocate;
 use super::Instance;
 use std::alloc::Layout;
 use std::convert::TryFrom;
+use std::fmt;
 use std::ptr::{self, NonNull};
 use std::sync::{Arc, Weak};
+use wasmer_types::InstanceAllocatorProvider;
 
 /// Dynamic instance allocation.
 ///
@@ -13,12 +16,15 @@ use std::sync::{Arc, Weak};
 /// if and only if it has been set correctly. The `Drop` implementation of
 /// [`InstanceInner`] calls its `deallocate_instance` method without
 /// checking if this property holds, only when `Self.strong` is equal to 1.
-#[derive(Debug)]
 #[repr(C)]
 struct InstanceInner {
     /// The layout of `Instance` (which can vary).
     instance_layout: Layout,
 
+    /// The provider `Instance` was allocated from, the global
+    /// allocator if `None`.
+    provider: Option<Arc<dyn InstanceAllocatorProvider>>,
+
     /// The `Instance` itself. It must be the last field of
     /// `InstanceRef` since `Instance` is dyamically-sized.
     ///
@@ -42,8 +48,13 @@ impl InstanceInner {
     unsafe fn deallocate_instance(&mut self) {
         let instance_ptr = self.instance.as_ptr();
 
+        super::registry::unregister(self.as_ref().vmctx_ptr());
         ptr::drop_in_place(instance_ptr);
-        std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+        deallocate(
+            self.provider.as_deref(),
+            self.instance.cast(),
+            self.instance_layout,
+        );
     }
 
     /// Get a reference to the `Instance`.
@@ -61,6 +72,15 @@ impl InstanceInner {
     }
 }
 
+impl fmt::Debug for InstanceInner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InstanceInner")
+            .field("instance_layout", &self.instance_layout)
+            .field("instance", &self.instance)
+            .finish()
+    }
+}
+
 impl PartialEq for InstanceInner {
     /// Two `InstanceInner` are equal if and only if
     /// `Self.instance` points to the same location.
@@ -113,9 +133,14 @@ impl InstanceRef {
     /// and correctly initialized pointer to `Instance`. See
     /// [`InstanceAllocator`] for an example of how to correctly use
     /// this API.
-    pub(super) unsafe fn new(instance: NonNull<Instance>, instance_layout: Layout) -> Self {
+    pub(super) unsafe fn new(
+        instance: NonNull<Instance>,
+        instance_layout: Layout,
+        provider: Option<Arc<dyn InstanceAllocatorProvider>>,
+    ) -> Self {
         Self(Arc::new(InstanceInner {
             instance_layout,
+            provider,
             instance,
         }))
     }
@@ -188,6 +213,20 @@ impl WeakOrStrongInstanceRef {
         }
     }
 
+    /// Returns whether this reference keeps the `Instance` alive.
+    pub fn is_strong(&self) -> bool {
+        matches!(self, Self::Strong(_))
+    }
+
+    /// Returns whether the `Instance` is still alive, which is always the
+    /// case for strong references.
+    pub fn is_alive(&self) -> bool {
+        match self {
+            Self::Weak(weak) => weak.0.strong_count() > 0,
+            Self::Strong(_) => true,
+        }
+    }
+
     /// Clones self into a weak reference.
     pub fn downgrade(&self) -> Self {
         match self {
@@ -0,0 +1,55 @@
+//! Registry of the live instances, mapping the address of their `VMContext`
+//! to the artifact they were created from.
+
+use crate::vmcontext::VMContext;
+use crate::{Artifact, VMFunction};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use wasmer_types::FunctionIndex;
+
+lazy_static! {
+    static ref ARTIFACTS: Mutex<HashMap<usize, Weak<dyn Artifact>>> = Mutex::new(HashMap::new());
+}
+
+/// Record that the instance whose `VMContext` is at `vmctx` was created
+/// from `artifact`.
+pub(crate) fn register(vmctx: *const VMContext, artifact: &Arc<dyn Artifact>) {
+    ARTIFACTS
+        .lock()
+        .unwrap()
+        .insert(vmctx as usize, Arc::downgrade(artifact));
+}
+
+/// Forget the instance whose `VMContext` is at `vmctx`, before it goes away.
+pub(crate) fn unregister(vmctx: *const VMContext) {
+    ARTIFACTS.lock().unwrap().remove(&(vmctx as usize));
+}
+
+impl VMContext {
+    /// Find the artifact the instance owning `vmctx` was created from.
+    ///
+    /// `vmctx` is only looked up in the registry of live instances, never
+    /// dereferenced, so that any address may be passed, such as the one a
+    /// trap handler found in a register. `None` is returned for addresses not
+    /// belonging to a live instance.
+    pub fn find_enclosing_artifact(vmctx: *const Self) -> Option<Arc<dyn Artifact>> {
+        ARTIFACTS.lock().unwrap().get(&(vmctx as usize))?.upgrade()
+    }
+}
+
+impl VMFunction {
+    /// Find the live instance defining this function, returned as its
+    /// `VMContext`, along with the index of the function in its module.
+    ///
+    /// Returns `None` for host functions and for WebAssembly functions whose
+    /// instance is gone. Like [`VMContext::find_enclosing_artifact`], this
+    /// never dereferences the environment of the function, so it can be used
+    /// on functions rebuilt from a `funcref` passed to the host.
+    pub fn defining_instance(&self) -> Option<(*const VMContext, FunctionIndex)> {
+        let vmctx = unsafe { self.vmctx.vmctx } as *const VMContext;
+        let artifact = VMContext::find_enclosing_artifact(vmctx)?;
+        let index = artifact.function_for_address(self.address as usize)?;
+        Some((vmctx, index))
+    }
+}
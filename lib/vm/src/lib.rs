@@ -40,21 +40,23 @@ mod vmoffsets;
 
 pub mod libcalls;
 
-pub use crate::artifact::{Artifact, Instantiatable};
+pub use crate::artifact::{Artifact, GlobalInitError, Instantiatable};
 pub use crate::export::*;
 pub use crate::func_data_registry::{FuncDataRegistry, VMFuncRef};
 pub use crate::global::*;
 pub use crate::imports::{Imports, VMImport, VMImportType};
 pub use crate::instance::{
     initialize_host_envs, ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator,
-    InstanceHandle, WeakOrStrongInstanceRef,
+    InstanceHandle, InstantiationProfile, PooledInstanceAllocator, WeakOrStrongInstanceRef,
 };
-pub use crate::memory::{LinearMemory, Memory, MemoryError, MemoryStyle};
-pub use crate::mmap::Mmap;
+pub use crate::memory::{
+    AllocatedMemory, LinearMemory, Memory, MemoryAllocator, MemoryError, MemoryStyle,
+};
+pub use crate::mmap::{Mmap, MmapAdvice};
 pub use crate::probestack::PROBESTACK;
 pub use crate::resolver::{
     ChainableNamedResolver, Export, ExportFunction, ExportFunctionMetadata, NamedResolver,
-    NamedResolverChain, NullResolver, Resolver,
+    NamedResolverChain, NullResolver, RemappingResolver, Resolver,
 };
 pub use crate::sig_registry::{SignatureRegistry, VMSharedSignatureIndex};
 pub use crate::table::{LinearTable, Table, TableElement, TableStyle};
@@ -65,9 +67,9 @@ pub use crate::vmcontext::{
     VMCallerCheckedAnyfunc, VMContext, VMDynamicFunctionContext, VMFunctionBody,
     VMFunctionEnvironment, VMFunctionImport, VMFunctionKind, VMGlobalDefinition, VMGlobalImport,
     VMLocalFunction, VMMemoryDefinition, VMMemoryImport, VMTableDefinition, VMTableImport,
-    VMTrampoline,
+    VMTrampoline, VMTrampolineWrapper,
 };
-pub use crate::vmoffsets::{TargetSharedSignatureIndex, VMOffsets};
+pub use crate::vmoffsets::{TargetSharedSignatureIndex, VMContextRegion, VMOffsets};
 #[deprecated(
     since = "2.1.0",
     note = "ModuleInfo, ExportsIterator, ImportsIterator should be imported from wasmer_types."
@@ -40,10 +40,12 @@
 use crate::func_data_registry::VMFuncRef;
 use crate::probestack::PROBESTACK;
 use crate::table::{RawTableElement, TableElement};
+use crate::trap::traphandlers::{enter_host_call, exit_host_call};
 use crate::trap::{raise_lib_trap, Trap, TrapCode};
 use crate::vmcontext::VMContext;
 use crate::VMExternRef;
 use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
 use wasmer_types::{
     DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
     TableIndex, Type,
@@ -471,7 +473,8 @@ pub unsafe extern "C" fn wasmer_vm_imported_table_grow(
         .unwrap_or(u32::max_value())
 }
 
-/// Implementation of `func.ref`.
+/// Implementation of `ref.func`, returning the `VMCallerCheckedAnyfunc` of
+/// the function `function_index` of the instance.
 ///
 /// # Safety
 ///
@@ -512,6 +515,32 @@ pub unsafe extern "C" fn wasmer_vm_externref_dec(mut externref: VMExternRef) {
     externref.ref_drop()
 }
 
+/// Reports entry into an imported function to the instance's host call tracer.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_host_call_enter(vmctx: *mut VMContext, function_index: u32) {
+    let instance = (&*vmctx).instance();
+    if let Some(tracer) = instance.host_call_tracer() {
+        enter_host_call(tracer, FunctionIndex::from_u32(function_index));
+    }
+}
+
+/// Reports exit from an imported function to the instance's host call tracer.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_host_call_exit(vmctx: *mut VMContext, function_index: u32) {
+    let instance = (&*vmctx).instance();
+    if let Some(tracer) = instance.host_call_tracer() {
+        exit_host_call(tracer, FunctionIndex::from_u32(function_index));
+    }
+}
+
 /// Implementation of `elem.drop`.
 ///
 /// # Safety
@@ -665,6 +694,21 @@ pub unsafe extern "C" fn wasmer_vm_raise_trap(trap_code: TrapCode) -> ! {
     raise_lib_trap(trap)
 }
 
+/// Implementation for atomically loading a `u32` from a shared memory
+///
+/// This is how the host reads the memories for which
+/// [`VMMemoryImport::is_shared`](crate::VMMemoryImport::is_shared) holds, as
+/// they may be concurrently written to.
+///
+/// # Safety
+///
+/// `base + offset` must be in bounds of a live memory and aligned to 4 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_atomic_load_u32(base: *const u8, offset: u32) -> u32 {
+    let ptr = base.add(offset as usize) as *const AtomicU32;
+    (*ptr).load(Ordering::SeqCst)
+}
+
 /// Probestack check
 ///
 /// # Safety
@@ -680,6 +724,7 @@ pub static wasmer_vm_probestack: unsafe extern "C" fn() = PROBESTACK;
 #[derive(
     rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Copy, Clone, Debug, PartialEq, Eq, Hash,
 )]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub enum LibCall {
     /// ceil.f32
     CeilF32,
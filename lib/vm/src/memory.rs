@@ -10,10 +10,9 @@ use crate::vmcontext::VMMemoryDefinition;
 use more_asserts::assert_ge;
 use std::borrow::BorrowMut;
 use std::cell::UnsafeCell;
-use std::convert::TryInto;
 use std::fmt;
-use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::ptr::{self, NonNull};
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use wasmer_types::{Bytes, MemoryType, Pages};
 
@@ -54,13 +53,28 @@ pub enum MemoryError {
         /// The number of pages requested as the maximum amount of memory.
         max_allowed: Pages,
     },
+    /// An access to the memory contents was out of its bounds.
+    #[error("Out of bounds memory access")]
+    AccessViolation,
     /// A user defined error value, used for error cases not listed above.
     #[error("A user-defined error occurred: {0}")]
     Generic(String),
 }
 
+/// Size of `pages` in bytes, failing instead of overflowing on hosts whose
+/// address space can't hold them.
+fn pages_to_bytes(pages: Pages) -> Result<usize, MemoryError> {
+    pages
+        .to_bytes()
+        .map(|bytes| bytes.0)
+        .map_err(|_| MemoryError::InvalidMemory {
+            reason: format!("{:?} don't fit in the address space", pages),
+        })
+}
+
 /// Implementation styles for WebAssembly linear memory.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub enum MemoryStyle {
     /// The actual memory can be resized and moved.
     Dynamic {
@@ -105,6 +119,17 @@ pub trait Memory: fmt::Debug + Send + Sync {
     /// Returns the number of allocated wasm pages.
     fn size(&self) -> Pages;
 
+    /// Returns the current size of this memory in wasm pages.
+    ///
+    /// This is derived from the `current_length` visible to compiled code, so
+    /// it can be used for bounds checking without reading through
+    /// [`VMMemoryDefinition`].
+    fn current_pages(&self) -> Pages;
+
+    /// Returns the maximum number of wasm pages this memory may grow to, if
+    /// one was declared.
+    fn maximum_pages(&self) -> Option<Pages>;
+
     /// Grow memory by the specified amount of wasm pages.
     fn grow(&self, delta: Pages) -> Result<Pages, MemoryError>;
 
@@ -112,6 +137,46 @@ pub trait Memory: fmt::Debug + Send + Sync {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Copy the contents of the memory at `offset` into `buf`.
+    ///
+    /// Fails with [`MemoryError::AccessViolation`], leaving `buf` untouched,
+    /// if the range isn't within the current size of the memory.
+    fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), MemoryError> {
+        let definition = unsafe { self.vmmemory().as_ref() };
+        let offset = checked_range(definition, offset, buf.len())?;
+        unsafe {
+            ptr::copy_nonoverlapping(definition.base.add(offset), buf.as_mut_ptr(), buf.len());
+        }
+        Ok(())
+    }
+
+    /// Copy `data` into the memory at `offset`.
+    ///
+    /// Fails with [`MemoryError::AccessViolation`], leaving the memory
+    /// untouched, if the range isn't within the current size of the memory.
+    fn write(&self, offset: u32, data: &[u8]) -> Result<(), MemoryError> {
+        let definition = unsafe { self.vmmemory().as_ref() };
+        let offset = checked_range(definition, offset, data.len())?;
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), definition.base.add(offset), data.len());
+        }
+        Ok(())
+    }
+}
+
+/// Check that `len` bytes at `offset` are within `definition`, returning the
+/// offset as a `usize`.
+fn checked_range(
+    definition: &VMMemoryDefinition,
+    offset: u32,
+    len: usize,
+) -> Result<usize, MemoryError> {
+    let offset = offset as usize;
+    match offset.checked_add(len) {
+        Some(end) if end <= definition.current_length => Ok(offset),
+        _ => Err(MemoryError::AccessViolation),
+    }
 }
 
 /// A linear memory instance.
@@ -233,19 +298,22 @@ impl LinearMemory {
                 *bound
             }
         };
-        let minimum_bytes = minimum_pages.bytes().0;
-        let request_bytes = minimum_bytes.checked_add(offset_guard_bytes).unwrap();
-        let mapped_pages = memory.minimum;
-        let mapped_bytes = mapped_pages.bytes();
+        let minimum_bytes = pages_to_bytes(minimum_pages)?;
+        let request_bytes = minimum_bytes
+            .checked_add(offset_guard_bytes)
+            .ok_or_else(|| MemoryError::InvalidMemory {
+                reason: "its guard pages don't fit in the address space".to_string(),
+            })?;
+        let mapped_bytes = pages_to_bytes(memory.minimum)?;
 
         let mut mmap = WasmMmap {
-            alloc: Mmap::accessible_reserved(mapped_bytes.0, request_bytes)
+            alloc: Mmap::accessible_reserved(mapped_bytes, request_bytes)
                 .map_err(MemoryError::Region)?,
             size: memory.minimum,
         };
 
         let base_ptr = mmap.alloc.as_mut_ptr();
-        let mem_length = memory.minimum.bytes().0;
+        let mem_length = mapped_bytes;
         Ok(Self {
             mmap: Mutex::new(mmap),
             maximum: memory.maximum,
@@ -289,7 +357,7 @@ impl LinearMemory {
 impl Memory for LinearMemory {
     /// Returns the type for this memory.
     fn ty(&self) -> MemoryType {
-        let minimum = self.size();
+        let minimum = self.current_pages();
         let mut out = self.memory;
         out.minimum = minimum;
 
@@ -303,14 +371,24 @@ impl Memory for LinearMemory {
 
     /// Returns the number of allocated wasm pages.
     fn size(&self) -> Pages {
+        self.current_pages()
+    }
+
+    /// Returns the current size of this memory in wasm pages.
+    fn current_pages(&self) -> Pages {
         // TODO: investigate this function for race conditions
         unsafe {
             let md_ptr = self.get_vm_memory_definition();
             let md = md_ptr.as_ref();
-            Bytes::from(md.current_length).try_into().unwrap()
+            Bytes(md.current_length).to_pages().unwrap()
         }
     }
 
+    /// Returns the maximum number of wasm pages, if any.
+    fn maximum_pages(&self) -> Option<Pages> {
+        self.maximum
+    }
+
     /// Grow memory by the specified amount of wasm pages.
     ///
     /// Returns `None` if memory can't be grown by the specified amount
@@ -352,9 +430,13 @@ impl Memory for LinearMemory {
             });
         }
 
-        let delta_bytes = delta.bytes().0;
-        let prev_bytes = prev_pages.bytes().0;
-        let new_bytes = new_pages.bytes().0;
+        let could_not_grow = |_| MemoryError::CouldNotGrow {
+            current: prev_pages,
+            attempted_delta: delta,
+        };
+        let delta_bytes = pages_to_bytes(delta).map_err(could_not_grow)?;
+        let prev_bytes = pages_to_bytes(prev_pages).map_err(could_not_grow)?;
+        let new_bytes = pages_to_bytes(new_pages).map_err(could_not_grow)?;
 
         if new_bytes > mmap.alloc.len() - self.offset_guard_size {
             // If the new size is within the declared maximum, but needs more memory than we
@@ -365,7 +447,7 @@ impl Memory for LinearMemory {
                     .checked_add(guard_bytes)
                     .ok_or_else(|| MemoryError::CouldNotGrow {
                         current: new_pages,
-                        attempted_delta: Bytes(guard_bytes).try_into().unwrap(),
+                        attempted_delta: Bytes(guard_bytes).to_pages().unwrap(),
                     })?;
 
             let mut new_mmap =
@@ -388,7 +470,7 @@ impl Memory for LinearMemory {
         unsafe {
             let mut md_ptr = self.get_vm_memory_definition();
             let md = md_ptr.as_mut();
-            md.current_length = new_pages.bytes().0;
+            md.current_length = new_bytes;
             md.base = mmap.alloc.as_mut_ptr() as _;
         }
 
@@ -401,3 +483,267 @@ impl Memory for LinearMemory {
         unsafe { self.get_vm_memory_definition() }
     }
 }
+
+/// Source of the regions backing [`AllocatedMemory`], for example slices of
+/// a preallocated arena.
+///
+/// Regions handed out by `allocate` are returned through `deallocate`, with the
+/// same size, once the memory is dropped.
+pub trait MemoryAllocator: Send + Sync {
+    /// Allocate a writable region of `size` bytes, or return `None` if it can't
+    /// be satisfied. The region doesn't need to be zeroed.
+    fn allocate(&self, size: usize) -> Option<NonNull<u8>>;
+    /// Release a region previously returned by `allocate`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `allocate` on this allocator with the
+    /// same `size`, and must not be used afterwards.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, size: usize);
+}
+
+/// A linear memory living in a region handed out by a [`MemoryAllocator`].
+///
+/// The region is sized for the declared maximum of the memory, which must
+/// therefore have one, and never moves: growing past it fails. The region has
+/// no guard pages either, so the memory must use a [`MemoryStyle::Dynamic`]
+/// style without offset guard, which makes compiled code check every access.
+pub struct AllocatedMemory {
+    allocator: Arc<dyn MemoryAllocator>,
+    base: NonNull<u8>,
+    capacity: usize,
+    size: Mutex<Pages>,
+    memory: MemoryType,
+    style: MemoryStyle,
+    vm_memory_definition: VMMemoryDefinitionOwnership,
+}
+
+/// The region is owned by the memory alone, and accessed under the same
+/// rules as the one of a `LinearMemory`.
+unsafe impl Send for AllocatedMemory {}
+
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl Sync for AllocatedMemory {}
+
+impl fmt::Debug for AllocatedMemory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AllocatedMemory")
+            .field("base", &self.base)
+            .field("capacity", &self.capacity)
+            .field("size", &self.size)
+            .field("memory", &self.memory)
+            .field("style", &self.style)
+            .finish()
+    }
+}
+
+impl AllocatedMemory {
+    /// Create a memory, with owned metadata, in a region taken from `allocator`.
+    pub fn new(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        allocator: Arc<dyn MemoryAllocator>,
+    ) -> Result<Self, MemoryError> {
+        unsafe { Self::new_internal(memory, style, allocator, None) }
+    }
+
+    /// Create a memory, with metadata owned by a VM and pointed to by
+    /// `vm_memory_location`, in a region taken from `allocator`.
+    ///
+    /// # Safety
+    /// - `vm_memory_location` must point to a valid location in VM memory.
+    pub unsafe fn from_definition(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        allocator: Arc<dyn MemoryAllocator>,
+        vm_memory_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Self, MemoryError> {
+        Self::new_internal(memory, style, allocator, Some(vm_memory_location))
+    }
+
+    unsafe fn new_internal(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        allocator: Arc<dyn MemoryAllocator>,
+        vm_memory_location: Option<NonNull<VMMemoryDefinition>>,
+    ) -> Result<Self, MemoryError> {
+        if *style
+            != (MemoryStyle::Dynamic {
+                offset_guard_size: 0,
+            })
+        {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "{:?} requires guard pages the allocator can't provide",
+                    style
+                ),
+            });
+        }
+        let maximum = memory.maximum.ok_or_else(|| MemoryError::InvalidMemory {
+            reason: "it has no maximum to size its region after".to_string(),
+        })?;
+        if maximum > Pages::max_value() {
+            return Err(MemoryError::MaximumMemoryTooLarge {
+                max_requested: maximum,
+                max_allowed: Pages::max_value(),
+            });
+        }
+        if maximum < memory.minimum {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "the maximum ({} pages) is less than the minimum ({} pages)",
+                    maximum.0, memory.minimum.0
+                ),
+            });
+        }
+
+        let capacity = pages_to_bytes(maximum)?;
+        let base = if capacity == 0 {
+            NonNull::dangling()
+        } else {
+            allocator.allocate(capacity).ok_or_else(|| {
+                MemoryError::Region(format!("could not allocate {} bytes", capacity))
+            })?
+        };
+        let mem_length = pages_to_bytes(memory.minimum)?;
+        ptr::write_bytes(base.as_ptr(), 0, mem_length);
+        let definition = VMMemoryDefinition {
+            base: base.as_ptr(),
+            current_length: mem_length,
+        };
+        Ok(Self {
+            allocator,
+            base,
+            capacity,
+            size: Mutex::new(memory.minimum),
+            memory: *memory,
+            style: style.clone(),
+            vm_memory_definition: if let Some(mut mem_loc) = vm_memory_location {
+                *mem_loc.as_mut() = definition;
+                VMMemoryDefinitionOwnership::VMOwned(mem_loc)
+            } else {
+                VMMemoryDefinitionOwnership::HostOwned(Box::new(UnsafeCell::new(definition)))
+            },
+        })
+    }
+
+    /// Get the `VMMemoryDefinition`.
+    ///
+    /// # Safety
+    /// - You must ensure that you have mutually exclusive access before calling
+    ///   this function. You can get this by locking the `size` mutex.
+    unsafe fn get_vm_memory_definition(&self) -> NonNull<VMMemoryDefinition> {
+        match &self.vm_memory_definition {
+            VMMemoryDefinitionOwnership::VMOwned(ptr) => *ptr,
+            VMMemoryDefinitionOwnership::HostOwned(boxed_ptr) => {
+                NonNull::new_unchecked(boxed_ptr.get())
+            }
+        }
+    }
+}
+
+impl Memory for AllocatedMemory {
+    fn ty(&self) -> MemoryType {
+        let mut out = self.memory;
+        out.minimum = self.current_pages();
+        out
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        &self.style
+    }
+
+    fn size(&self) -> Pages {
+        self.current_pages()
+    }
+
+    fn current_pages(&self) -> Pages {
+        *self.size.lock().unwrap()
+    }
+
+    fn maximum_pages(&self) -> Option<Pages> {
+        self.memory.maximum
+    }
+
+    /// Grow memory by the specified amount of wasm pages, within the region
+    /// allocated for it.
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let mut size = self.size.lock().unwrap();
+        let prev_pages = *size;
+        let (new_pages, new_bytes) = prev_pages
+            .checked_add(delta)
+            .and_then(|pages| Some((pages, pages.to_bytes().ok()?.0)))
+            .filter(|(_, bytes)| *bytes <= self.capacity)
+            .ok_or(MemoryError::CouldNotGrow {
+                current: prev_pages,
+                attempted_delta: delta,
+            })?;
+        // The memory is smaller than its capacity, so its size fits.
+        let prev_bytes = prev_pages.bytes().0;
+        unsafe {
+            ptr::write_bytes(
+                self.base.as_ptr().add(prev_bytes),
+                0,
+                new_bytes - prev_bytes,
+            );
+            self.get_vm_memory_definition().as_mut().current_length = new_bytes;
+        }
+        *size = new_pages;
+        Ok(prev_pages)
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        let _size_guard = self.size.lock().unwrap();
+        unsafe { self.get_vm_memory_definition() }
+    }
+}
+
+impl Drop for AllocatedMemory {
+    fn drop(&mut self) {
+        if self.capacity != 0 {
+            unsafe { self.allocator.deallocate(self.base, self.capacity) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_and_maximum_pages() {
+        let ty = MemoryType::new(1, Some(3), false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+        };
+        let memory = LinearMemory::new(&ty, &style).unwrap();
+        assert_eq!(memory.current_pages(), Pages(1));
+        assert_eq!(memory.maximum_pages(), Some(Pages(3)));
+
+        memory.grow(Pages(2)).unwrap();
+        assert_eq!(memory.current_pages(), Pages(3));
+        assert_eq!(memory.size(), memory.current_pages());
+        assert!(memory.grow(Pages(1)).is_err());
+
+        let unbounded = LinearMemory::new(&MemoryType::new(0, None, false), &style).unwrap();
+        assert_eq!(unbounded.current_pages(), Pages(0));
+        assert_eq!(unbounded.maximum_pages(), None);
+    }
+
+    #[test]
+    fn page_sizes_do_not_wrap() {
+        assert_eq!(
+            pages_to_bytes(Pages(2)).unwrap(),
+            2 * wasmer_types::WASM_PAGE_SIZE
+        );
+        // The whole 32-bit address space is one byte more than a 32-bit
+        // `usize` can hold.
+        #[cfg(target_pointer_width = "32")]
+        assert!(matches!(
+            pages_to_bytes(Pages::max_value()),
+            Err(MemoryError::InvalidMemory { .. })
+        ));
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(pages_to_bytes(Pages::max_value()).unwrap(), 1 << 32);
+    }
+}
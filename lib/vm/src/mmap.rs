@@ -15,6 +15,76 @@ fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
     (size + (page_size - 1)) & !(page_size - 1)
 }
 
+/// Hints about the expected usage of a range of memory, passed to `madvise`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MmapAdvice {
+    /// The range will be accessed sequentially (`MADV_SEQUENTIAL`).
+    Sequential,
+    /// The range will be accessed in random order (`MADV_RANDOM`).
+    Random,
+    /// The range will be accessed soon (`MADV_WILLNEED`).
+    WillNeed,
+    /// The range is not needed anymore (`MADV_DONTNEED`). Its contents read
+    /// back as zeroes afterwards.
+    DontNeed,
+    /// The pages in range may be reclaimed lazily (`MADV_FREE`). Their contents
+    /// are unspecified until written again.
+    Free,
+}
+
+#[cfg(not(target_os = "windows"))]
+impl MmapAdvice {
+    fn to_libc(self) -> libc::c_int {
+        match self {
+            Self::Sequential => libc::MADV_SEQUENTIAL,
+            Self::Random => libc::MADV_RANDOM,
+            Self::WillNeed => libc::MADV_WILLNEED,
+            Self::DontNeed => libc::MADV_DONTNEED,
+            Self::Free => libc::MADV_FREE,
+        }
+    }
+
+    /// Advise the kernel about the expected usage of the pages overlapping the
+    /// `len` bytes starting at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// These pages must be mapped, and stay valid under the given advice.
+    pub unsafe fn apply(self, ptr: *const u8, len: usize) -> Result<(), io::Error> {
+        if len == 0 {
+            return Ok(());
+        }
+        let page_size = region::page::size();
+        let start = ptr as usize & !(page_size - 1);
+        let end = round_up_to_page_size(ptr as usize + len, page_size);
+        let r = libc::madvise(start as *mut libc::c_void, end - start, self.to_libc());
+        if r != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl MmapAdvice {
+    /// Advise the kernel about the expected usage of the pages overlapping the
+    /// `len` bytes starting at `ptr`.
+    ///
+    /// There is no `madvise` equivalent for these hints on Windows, so this
+    /// does nothing.
+    ///
+    /// # Safety
+    ///
+    /// These pages must be mapped, and stay valid under the given advice.
+    pub unsafe fn apply(self, _ptr: *const u8, _len: usize) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+/// The number of NUMA nodes supported by Linux, at most.
+#[cfg(target_os = "linux")]
+const MAX_NUMA_NODES: u32 = 1024;
+
 /// A simple struct consisting of a page-aligned pointer to page-aligned
 /// and initially-zeroed memory and a length.
 #[derive(Debug)]
@@ -227,6 +297,114 @@ impl Mmap {
         Ok(())
     }
 
+    /// Advise the kernel about the expected usage of the whole mapping.
+    ///
+    /// # Safety
+    ///
+    /// See [`Mmap::advise_range`].
+    pub unsafe fn advise(&self, advice: MmapAdvice) -> Result<(), io::Error> {
+        self.advise_range(0, self.len, advice)
+    }
+
+    /// Advise the kernel about the expected usage of the memory starting at
+    /// `offset` and extending for `len` bytes. `offset` must be a native
+    /// page-size multiple and the range must lie within `self`'s mapping.
+    ///
+    /// # Safety
+    ///
+    /// `MmapAdvice::DontNeed` and `MmapAdvice::Free` discard the contents of
+    /// the range. The caller must ensure that nothing still reading them, such
+    /// as a slice returned by [`Mmap::as_slice`], is used afterwards.
+    #[cfg(not(target_os = "windows"))]
+    pub unsafe fn advise_range(
+        &self,
+        offset: usize,
+        len: usize,
+        advice: MmapAdvice,
+    ) -> Result<(), io::Error> {
+        let page_size = region::page::size();
+        assert_eq!(offset & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(offset, self.len - len);
+
+        advice.apply((self.ptr as *const u8).add(offset), len)
+    }
+
+    /// Advise the kernel about the expected usage of the memory starting at
+    /// `offset` and extending for `len` bytes. `offset` must be a native
+    /// page-size multiple and the range must lie within `self`'s mapping.
+    ///
+    /// There is no `madvise` equivalent for these hints on Windows, so this
+    /// only validates the range.
+    ///
+    /// # Safety
+    ///
+    /// `MmapAdvice::DontNeed` and `MmapAdvice::Free` discard the contents of
+    /// the range on other platforms. The caller must ensure that nothing still
+    /// reading them, such as a slice returned by [`Mmap::as_slice`], is used
+    /// afterwards.
+    #[cfg(target_os = "windows")]
+    pub unsafe fn advise_range(
+        &self,
+        offset: usize,
+        len: usize,
+        _advice: MmapAdvice,
+    ) -> Result<(), io::Error> {
+        let page_size = region::page::size();
+        assert_eq!(offset & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(offset, self.len - len);
+        Ok(())
+    }
+
+    /// Ask the kernel to back the pages of the mapping touched from now on
+    /// with memory of the NUMA node `node`, falling back to other nodes when
+    /// it has none left (`MPOL_PREFERRED`).
+    ///
+    /// Fails with `EINVAL` for nodes the kernel can't have, that is from node
+    /// 1024 on, without allocating a node mask for them.
+    #[cfg(target_os = "linux")]
+    pub fn prefer_numa_node(&self, node: u32) -> Result<(), io::Error> {
+        const MPOL_PREFERRED: libc::c_ulong = 1;
+        if node >= MAX_NUMA_NODES {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+        if self.len == 0 {
+            return Ok(());
+        }
+        let bits = libc::c_ulong::BITS as usize;
+        let node = node as usize;
+        let mut nodemask: Vec<libc::c_ulong> = vec![0; node / bits + 1];
+        nodemask[node / bits] |= 1 << (node % bits);
+        let r = unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                self.ptr as *mut libc::c_void,
+                self.len,
+                MPOL_PREFERRED,
+                nodemask.as_ptr(),
+                (nodemask.len() * bits + 1) as libc::c_ulong,
+                0,
+            )
+        };
+        if r != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Ask the kernel to back the pages of the mapping touched from now on
+    /// with memory of the NUMA node `node`.
+    ///
+    /// This is only supported on Linux, elsewhere it always fails.
+    #[cfg(not(target_os = "linux"))]
+    pub fn prefer_numa_node(&self, _node: u32) -> Result<(), io::Error> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "NUMA placement is only supported on Linux",
+        ))
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
@@ -295,4 +473,38 @@ mod tests {
         assert_eq!(round_up_to_page_size(4096, 4096), 4096);
         assert_eq!(round_up_to_page_size(4097, 4096), 8192);
     }
+
+    #[test]
+    fn test_advise() {
+        let page_size = region::page::size();
+        let mut mmap = Mmap::with_at_least(4 * page_size).unwrap();
+        mmap.as_mut_slice().iter_mut().for_each(|b| *b = 0xAB);
+
+        // No slice of the mapping is alive while its contents are discarded.
+        unsafe {
+            mmap.advise(MmapAdvice::Sequential).unwrap();
+            mmap.advise(MmapAdvice::Random).unwrap();
+            mmap.advise(MmapAdvice::WillNeed).unwrap();
+            mmap.advise_range(page_size, page_size, MmapAdvice::Free)
+                .unwrap();
+            mmap.advise_range(2 * page_size, 2 * page_size, MmapAdvice::DontNeed)
+                .unwrap();
+        }
+
+        assert!(mmap.as_slice()[..page_size].iter().all(|&b| b == 0xAB));
+        #[cfg(not(target_os = "windows"))]
+        assert!(mmap.as_slice()[2 * page_size..].iter().all(|&b| b == 0));
+
+        unsafe { Mmap::new().advise(MmapAdvice::DontNeed).unwrap() };
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_prefer_unknown_numa_node() {
+        let mmap = Mmap::with_at_least(region::page::size()).unwrap();
+        for &node in &[MAX_NUMA_NODES, u32::MAX] {
+            let error = mmap.prefer_numa_node(node).unwrap_err();
+            assert_eq!(error.raw_os_error(), Some(libc::EINVAL));
+        }
+    }
 }
@@ -1,4 +1,7 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::thread::LocalKey;
 
 use crate::{ImportInitializerFuncPtr, VMExtern, VMFunction, VMGlobal, VMMemory, VMTable};
 
@@ -113,6 +116,39 @@ impl ExportFunctionMetadata {
             host_env_drop_fn,
         }
     }
+
+    /// Create an `ExportFunctionMetadata` whose host environment lives in
+    /// the thread-local `key` rather than on the heap.
+    ///
+    /// Instead of cloning a master copy of the env, `host_env_clone_fn`
+    /// returns a pointer to the value of `key` for the current thread, so
+    /// every `Instance` created on a thread shares that thread's env
+    /// without any locking. Nothing is freed on drop, as the thread-local
+    /// owns the env.
+    ///
+    /// # Safety
+    /// - The env pointer handed to an `Instance` is only valid on the thread
+    ///   that created the `Instance`, and only for as long as that thread
+    ///   lives. The `Instance` must not be called from any other thread.
+    /// - The host function must not hold a `RefCell` borrow of `key` while
+    ///   the env is in use by wasm code.
+    pub unsafe fn with_thread_local_env<T: 'static>(key: &'static LocalKey<RefCell<T>>) -> Self {
+        let host_env_clone_fn = |ptr: *mut std::ffi::c_void| -> *mut std::ffi::c_void {
+            let key: &'static LocalKey<RefCell<T>> = unsafe {
+                ptr.cast::<LocalKey<RefCell<T>>>()
+                    .as_ref()
+                    .expect("`ptr` to the thread-local key is null when cloning it")
+            };
+            key.with(|cell| cell.as_ptr().cast())
+        };
+        let host_env_drop_fn = |_: *mut std::ffi::c_void| {};
+        Self {
+            host_env: key as *const LocalKey<RefCell<T>> as *mut std::ffi::c_void,
+            import_init_function_ptr: None,
+            host_env_clone_fn,
+            host_env_drop_fn,
+        }
+    }
 }
 
 // We have to free `host_env` here because we always clone it before using it
@@ -189,6 +225,14 @@ pub trait Resolver {
     /// )
     /// ```
     fn resolve(&self, _index: u32, module: &str, field: &str) -> Option<Export>;
+
+    /// Describes why `module`.`field` could not be resolved, suggesting the
+    /// names the import could have been meant to have.
+    ///
+    /// This is meant for developers, the format of the message is not stable.
+    fn explain_resolution_failure(&self, module: &str, field: &str) -> String {
+        explain_unresolved_import(module, field, None::<(&str, &str)>)
+    }
 }
 
 /// Import resolver connects imports with available exported values.
@@ -202,6 +246,54 @@ pub trait NamedResolver {
     /// It receives the `module` and `field` names and return the [`Export`] in
     /// case it's found.
     fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export>;
+
+    /// The `module` and `field` names this resolver provides, if it can list
+    /// them.
+    fn import_names(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Describes why `module`.`field` could not be resolved, suggesting the
+    /// names the import could have been meant to have.
+    ///
+    /// By default this looks the names up again with the surrounding
+    /// whitespace trimmed and in lowercase, and also considers the
+    /// [`import_names`](NamedResolver::import_names) close enough to be a
+    /// plausible typo. It suggests the closest of those.
+    fn explain_resolution_failure(&self, module: &str, field: &str) -> String {
+        if self.resolve_by_name(module, field).is_some() {
+            return format!(
+                "import `{}`.`{}` is provided, but with an incompatible type",
+                module, field
+            );
+        }
+        let variants = |name: &str| {
+            let trimmed = name.trim();
+            vec![
+                name.to_string(),
+                trimmed.to_string(),
+                trimmed.to_lowercase(),
+            ]
+        };
+        let mut candidates = Vec::new();
+        for m in variants(module) {
+            for f in variants(field) {
+                if (m != module || f != field)
+                    && !candidates.contains(&(m.clone(), f.clone()))
+                    && self.resolve_by_name(&m, &f).is_some()
+                {
+                    candidates.push((m.clone(), f));
+                }
+            }
+        }
+        let max_distance = (module.chars().count() + field.chars().count()) / 3 + 1;
+        candidates.extend(
+            self.import_names().into_iter().filter(|(m, f)| {
+                edit_distance(module, m) + edit_distance(field, f) <= max_distance
+            }),
+        );
+        explain_unresolved_import(module, field, candidates)
+    }
 }
 
 // All NamedResolvers should extend `Resolver`.
@@ -211,18 +303,90 @@ impl<T: NamedResolver> Resolver for T {
     fn resolve(&self, _index: u32, module: &str, field: &str) -> Option<Export> {
         self.resolve_by_name(module, field)
     }
+
+    fn explain_resolution_failure(&self, module: &str, field: &str) -> String {
+        NamedResolver::explain_resolution_failure(self, module, field)
+    }
 }
 
 impl<T: NamedResolver> NamedResolver for &T {
     fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
         (**self).resolve_by_name(module, field)
     }
+
+    fn import_names(&self) -> Vec<(String, String)> {
+        (**self).import_names()
+    }
+
+    fn explain_resolution_failure(&self, module: &str, field: &str) -> String {
+        NamedResolver::explain_resolution_failure(&**self, module, field)
+    }
 }
 
 impl NamedResolver for Box<dyn NamedResolver + Send + Sync> {
     fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
         (**self).resolve_by_name(module, field)
     }
+
+    fn import_names(&self) -> Vec<(String, String)> {
+        (**self).import_names()
+    }
+
+    fn explain_resolution_failure(&self, module: &str, field: &str) -> String {
+        NamedResolver::explain_resolution_failure(&**self, module, field)
+    }
+}
+
+impl<T: NamedResolver + ?Sized> NamedResolver for Arc<T> {
+    fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
+        (**self).resolve_by_name(module, field)
+    }
+
+    fn import_names(&self) -> Vec<(String, String)> {
+        (**self).import_names()
+    }
+
+    fn explain_resolution_failure(&self, module: &str, field: &str) -> String {
+        NamedResolver::explain_resolution_failure(&**self, module, field)
+    }
+}
+
+/// Formats the explanation of [`Resolver::explain_resolution_failure`] for
+/// `module`.`field`, suggesting the closest of `candidates`, if any.
+pub(crate) fn explain_unresolved_import<I, M, F>(module: &str, field: &str, candidates: I) -> String
+where
+    I: IntoIterator<Item = (M, F)>,
+    M: AsRef<str>,
+    F: AsRef<str>,
+{
+    let mut message = format!("import `{}`.`{}` could not be resolved", module, field);
+    let closest = candidates
+        .into_iter()
+        .min_by_key(|(m, f)| edit_distance(module, m.as_ref()) + edit_distance(field, f.as_ref()));
+    if let Some((m, f)) = closest {
+        message.push_str(&format!(
+            "; did you mean `{}`.`{}`?",
+            m.as_ref(),
+            f.as_ref()
+        ));
+    }
+    message
+}
+
+/// The Levenshtein distance between `a` and `b`, counted in characters.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != *cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
 }
 
 impl NamedResolver for () {
@@ -316,6 +480,12 @@ where
             .resolve_by_name(module, field)
             .or_else(|| self.b.resolve_by_name(module, field))
     }
+
+    fn import_names(&self) -> Vec<(String, String)> {
+        let mut names = self.a.import_names();
+        names.extend(self.b.import_names());
+        names
+    }
 }
 
 impl<A, B> Clone for NamedResolverChain<A, B>
@@ -330,3 +500,136 @@ where
         }
     }
 }
+
+/// A [`NamedResolver`] renaming some imports before resolving them with
+/// another resolver.
+///
+/// This lets a module importing a name that was since changed link against
+/// the new one, without rewriting the module.
+///
+/// ```
+/// # use wasmer_vm::{ChainableNamedResolver, NamedResolver, RemappingResolver};
+/// # fn remapping_test<A, B>(imports1: A, imports2: B)
+/// # where A: NamedResolver + Sized + Send + Sync,
+/// #       B: NamedResolver + Sized + Send + Sync,
+/// # {
+/// // resolve `env.log_v1` as `env.log` in `imports1`
+/// RemappingResolver::new(imports1)
+///     .remap("env", "log_v1", "env", "log")
+///     .chain_back(imports2);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RemappingResolver<R: NamedResolver + Send + Sync> {
+    inner: R,
+    renames: HashMap<(String, String), (String, String)>,
+}
+
+impl<R: NamedResolver + Send + Sync> RemappingResolver<R> {
+    /// Create a resolver delegating to `inner`, without renaming anything.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            renames: HashMap::new(),
+        }
+    }
+
+    /// Resolve the import `module`.`field` as `to_module`.`to_field`.
+    ///
+    /// The import is then no longer resolved under its own name.
+    pub fn remap(
+        mut self,
+        module: impl Into<String>,
+        field: impl Into<String>,
+        to_module: impl Into<String>,
+        to_field: impl Into<String>,
+    ) -> Self {
+        self.renames.insert(
+            (module.into(), field.into()),
+            (to_module.into(), to_field.into()),
+        );
+        self
+    }
+}
+
+impl<R: NamedResolver + Send + Sync> NamedResolver for RemappingResolver<R> {
+    fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
+        match self.renames.get(&(module.to_string(), field.to_string())) {
+            Some((module, field)) => self.inner.resolve_by_name(module, field),
+            None => self.inner.resolve_by_name(module, field),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    thread_local! {
+        static COUNTER: RefCell<u64> = RefCell::new(0);
+    }
+
+    #[test]
+    fn thread_local_env_is_per_thread() {
+        let metadata = Arc::new(unsafe { ExportFunctionMetadata::with_thread_local_env(&COUNTER) });
+
+        let env = (metadata.host_env_clone_fn)(metadata.host_env);
+        assert_eq!(env, COUNTER.with(|cell| cell.as_ptr().cast()));
+        unsafe { *env.cast::<u64>() += 1 };
+        assert_eq!(COUNTER.with(|cell| *cell.borrow()), 1);
+
+        let other = Arc::clone(&metadata);
+        std::thread::spawn(move || {
+            let env = (other.host_env_clone_fn)(other.host_env);
+            assert_eq!(env, COUNTER.with(|cell| cell.as_ptr().cast()));
+            assert_eq!(unsafe { *env.cast::<u64>() }, 0);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("random", "random"), 0);
+        assert_eq!(edit_distance("random", "randm"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("é", "e"), 1);
+    }
+
+    #[test]
+    fn explanation_suggests_trimmed_and_lowercase_names() {
+        struct Env;
+        impl NamedResolver for Env {
+            fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
+                if module == "env" && field == "abort" {
+                    let global = crate::Global::new(wasmer_types::GlobalType::new(
+                        wasmer_types::Type::I32,
+                        wasmer_types::Mutability::Const,
+                    ));
+                    Some(Export::Global(VMGlobal {
+                        from: Arc::new(global),
+                        instance_ref: None,
+                    }))
+                } else {
+                    None
+                }
+            }
+        }
+        let resolver: &dyn Resolver = &Env;
+        assert_eq!(
+            resolver.explain_resolution_failure("env ", "Abort"),
+            "import `env `.`Abort` could not be resolved; did you mean `env`.`abort`?"
+        );
+        assert_eq!(
+            resolver.explain_resolution_failure("env", "exit"),
+            "import `env`.`exit` could not be resolved"
+        );
+        assert_eq!(
+            resolver.explain_resolution_failure("env", "abort"),
+            "import `env`.`abort` is provided, but with an incompatible type"
+        );
+    }
+}
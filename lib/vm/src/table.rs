@@ -19,6 +19,7 @@ use wasmer_types::{ExternRef, TableType, Type as ValType};
 
 /// Implementation styles for WebAssembly tables.
 #[derive(Debug, Clone, Hash, PartialEq, Eq, rkyv::Serialize, rkyv::Deserialize, rkyv::Archive)]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 pub enum TableStyle {
     /// Signatures are stored in the table and checked in the caller.
     CallerChecksSignature,
@@ -53,6 +54,13 @@ pub trait Table: fmt::Debug + Send + Sync {
     /// Returns an error if the index is out of bounds.
     fn set(&self, index: u32, reference: TableElement) -> Result<(), Trap>;
 
+    /// Set `len` elements starting at `start` to `element`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is out of bounds of the table.
+    fn fill(&self, start: u32, len: u32, element: TableElement) -> Result<(), Trap>;
+
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
     fn vmtable(&self) -> NonNull<VMTableDefinition>;
 
@@ -101,6 +109,44 @@ pub trait Table: fmt::Debug + Send + Sync {
 
         Ok(())
     }
+
+    /// Copy out the elements of a `funcref` table, with `None` for the
+    /// null references.
+    ///
+    /// Function references aren't reference counted, so taking a snapshot
+    /// has no effect on the table or the functions it refers to.
+    ///
+    /// Returns `None` if this is not a `funcref` table.
+    fn funcref_snapshot(&self) -> Option<Vec<Option<VMFuncRef>>> {
+        if self.ty().ty != ValType::FuncRef {
+            return None;
+        }
+        (0..self.size())
+            .map(|index| match self.get(index)? {
+                TableElement::FuncRef(func_ref) if func_ref.is_null() => Some(None),
+                TableElement::FuncRef(func_ref) => Some(Some(func_ref)),
+                TableElement::ExternRef(_) => None,
+            })
+            .collect()
+    }
+
+    /// Copy out the elements of an `externref` table.
+    ///
+    /// Every returned [`ExternRef`] holds a reference of its own, the
+    /// reference counts drop back when the snapshot is dropped.
+    ///
+    /// Returns `None` if this is not an `externref` table.
+    fn externref_snapshot(&self) -> Option<Vec<ExternRef>> {
+        if self.ty().ty != ValType::ExternRef {
+            return None;
+        }
+        (0..self.size())
+            .map(|index| match self.get(index)? {
+                TableElement::ExternRef(extern_ref) => Some(extern_ref),
+                TableElement::FuncRef(_) => None,
+            })
+            .collect()
+    }
 }
 
 /// A reference stored in a table. Can be either an externref or a funcref.
@@ -401,6 +447,56 @@ impl Table for LinearTable {
         }
     }
 
+    /// Set `len` elements starting at `start` to `element`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is out of bounds of the table.
+    fn fill(&self, start: u32, len: u32, element: TableElement) -> Result<(), Trap> {
+        // https://webassembly.github.io/bulk-memory-operations/core/exec/instructions.html#exec-table-fill
+
+        let mut vec_guard = self.vec.lock().unwrap();
+        let vec = vec_guard.borrow_mut();
+        let end = match start.checked_add(len) {
+            Some(end) if end as usize <= vec.len() => end,
+            _ => return Err(Trap::lib(TrapCode::TableAccessOutOfBounds)),
+        };
+        let slots = &mut vec[start as usize..end as usize];
+        match (self.table.ty, element) {
+            (ValType::ExternRef, TableElement::ExternRef(extern_ref)) => {
+                let mut extern_ref: VMExternRef = extern_ref.into();
+                // `into` hands us one reference already, every further slot
+                // needs one more.
+                match slots.len().checked_sub(1) {
+                    Some(extra) => extern_ref.ref_inc_by(extra),
+                    None => extern_ref.ref_drop(),
+                }
+                for slot in slots {
+                    unsafe {
+                        slot.extern_ref.ref_drop();
+                        slot.extern_ref = extern_ref;
+                    }
+                }
+            }
+            (ValType::FuncRef, r @ TableElement::FuncRef(_)) => {
+                let element_data = r.into();
+                for slot in slots {
+                    *slot = element_data;
+                }
+            }
+            // This path should never be hit by the generated code due to Wasm
+            // validation.
+            (ty, v) => {
+                panic!(
+                    "Attempted to fill a table of type {} with the value {:?}",
+                    ty, v
+                )
+            }
+        }
+
+        Ok(())
+    }
+
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
     fn vmtable(&self) -> NonNull<VMTableDefinition> {
         let _vec_guard = self.vec.lock().unwrap();
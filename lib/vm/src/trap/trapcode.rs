@@ -22,9 +22,10 @@ use thiserror::Error;
     rkyv::Deserialize,
     rkyv::Archive,
 )]
+#[archive_attr(derive(bytecheck::CheckBytes))]
 #[repr(u32)]
 pub enum TrapCode {
-    /// The current stack space was exhausted.
+    /// The native stack space was exhausted.
     ///
     /// On some platforms, a stack overflow may also be indicated by a segmentation fault from the
     /// stack guard page.
@@ -69,13 +70,25 @@ pub enum TrapCode {
 
     /// Hit the gas limit.
     GasExceeded = 12,
+
+    /// Execution was interrupted through the instance's interrupt flag.
+    Interrupted = 13,
+
+    /// The wasm stack limit, or the maximum call depth, of the instance was
+    /// reached.
+    ///
+    /// Unlike [`TrapCode::StackOverflow`], this is detected by the compiled
+    /// code before the native stack is exhausted, see
+    /// `InstanceConfig::with_stack_limit` and
+    /// `InstanceConfig::with_max_function_call_depth`.
+    WasmStackOverflow = 14,
 }
 
 impl TrapCode {
     /// Gets the message for this trap code
     pub fn message(&self) -> &str {
         match self {
-            Self::StackOverflow => "call stack exhausted",
+            Self::StackOverflow | Self::WasmStackOverflow => "call stack exhausted",
             Self::HeapAccessOutOfBounds => "out of bounds memory access",
             Self::HeapMisaligned => "misaligned heap",
             Self::TableAccessOutOfBounds => "undefined element: out of bounds table access",
@@ -88,6 +101,7 @@ impl TrapCode {
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unaligned atomic access",
             Self::GasExceeded => "gas limit exceeded",
+            Self::Interrupted => "execution interrupted",
         }
     }
 }
@@ -108,6 +122,8 @@ impl Display for TrapCode {
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unalign_atom",
             Self::GasExceeded => "out_of_gas",
+            Self::Interrupted => "interrupted",
+            Self::WasmStackOverflow => "wasm_stk_ovf",
         };
         f.write_str(identifier)
     }
@@ -130,6 +146,8 @@ impl FromStr for TrapCode {
             "bad_toint" => Ok(Self::BadConversionToInteger),
             "unreachable" => Ok(Self::UnreachableCodeReached),
             "unalign_atom" => Ok(Self::UnalignedAtomic),
+            "interrupted" => Ok(Self::Interrupted),
+            "wasm_stk_ovf" => Ok(Self::WasmStackOverflow),
             _ => Err(()),
         }
     }
@@ -140,7 +158,7 @@ mod tests {
     use super::*;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 12] = [
+    const CODES: [TrapCode; 14] = [
         TrapCode::StackOverflow,
         TrapCode::HeapAccessOutOfBounds,
         TrapCode::HeapMisaligned,
@@ -153,6 +171,8 @@ mod tests {
         TrapCode::BadConversionToInteger,
         TrapCode::UnreachableCodeReached,
         TrapCode::UnalignedAtomic,
+        TrapCode::Interrupted,
+        TrapCode::WasmStackOverflow,
     ];
 
     #[test]
@@ -5,14 +5,20 @@
 //! signalhandling mechanisms.
 
 use super::trapcode::TrapCode;
-use crate::vmcontext::{VMFunctionBody, VMFunctionEnvironment, VMTrampoline};
+use crate::vmcontext::{
+    VMBuiltinFunctionIndex, VMBuiltinFunctionsArray, VMContext, VMFunctionBody,
+    VMFunctionEnvironment, VMTrampoline, VMTrampolineWrapper,
+};
 use backtrace::Backtrace;
 use std::any::Any;
-use std::cell::{Cell, UnsafeCell};
+use std::cell::{Cell, RefCell, UnsafeCell};
 use std::error::Error;
 use std::mem::{self, MaybeUninit};
 use std::ptr;
+use std::sync::Arc;
+use std::time::Instant;
 pub use tls::TlsRestore;
+use wasmer_types::{FunctionIndex, HostCallTracer};
 
 extern "C" {
     fn wasmer_register_setjmp(
@@ -82,6 +88,9 @@ pub enum Trap {
         backtrace: Backtrace,
         /// Optional trapcode associated to the signal that caused the trap
         signal_trap: Option<TrapCode>,
+        /// Return addresses found on the frame pointer chain of the
+        /// generated code at the time the trap occurred, innermost first.
+        frames: Vec<usize>,
     },
 
     /// A trap raised from a wasm libcall
@@ -107,11 +116,17 @@ impl Trap {
     /// Construct a new Wasm trap with the given source location and backtrace.
     ///
     /// Internally saves a backtrace when constructed.
-    pub fn wasm(pc: usize, backtrace: Backtrace, signal_trap: Option<TrapCode>) -> Self {
+    pub fn wasm(
+        pc: usize,
+        backtrace: Backtrace,
+        signal_trap: Option<TrapCode>,
+        frames: Vec<usize>,
+    ) -> Self {
         Self::Wasm {
             pc,
             backtrace,
             signal_trap,
+            frames,
         }
     }
 
@@ -133,6 +148,17 @@ impl Trap {
         let backtrace = Backtrace::new_unresolved();
         Self::OOM { backtrace }
     }
+
+    /// Returns the builtin function this trap was raised from, if any.
+    pub fn builtin(&self) -> Option<VMBuiltinFunctionIndex> {
+        match self {
+            Self::Lib { backtrace, .. } => backtrace
+                .frames()
+                .iter()
+                .find_map(|frame| VMBuiltinFunctionsArray::lookup(frame.symbol_address() as usize)),
+            _ => None,
+        }
+    }
 }
 
 /// Call the VM function pointed to by `callee`.
@@ -157,9 +183,7 @@ pub unsafe fn wasmer_call_trampoline(
     values_vec: *mut u8,
 ) -> Result<(), Trap> {
     catch_traps(|| {
-        mem::transmute::<_, extern "C" fn(VMFunctionEnvironment, *const VMFunctionBody, *mut u8)>(
-            trampoline,
-        )(callee_env, callee, values_vec);
+        VMTrampolineWrapper(trampoline).call(callee_env.vmctx, callee, values_vec as *mut u128);
     })
 }
 
@@ -209,12 +233,38 @@ where
     Ok(global_results.assume_init())
 }
 
+/// Reports entry into the imported function `index` to `tracer`.
+///
+/// The call is remembered by the innermost `catch_traps`, so that the matching
+/// exit is still reported if a trap unwinds through the imported function.
+pub(crate) fn enter_host_call(tracer: &Arc<dyn HostCallTracer>, index: FunctionIndex) {
+    tracer.on_enter(index, Instant::now());
+    tls::with(|info| {
+        if let Some(info) = info {
+            info.host_calls
+                .borrow_mut()
+                .push((Arc::clone(tracer), index));
+        }
+    });
+}
+
+/// Reports that the imported function `index` returned to `tracer`.
+pub(crate) fn exit_host_call(tracer: &Arc<dyn HostCallTracer>, index: FunctionIndex) {
+    tls::with(|info| {
+        if let Some(info) = info {
+            info.host_calls.borrow_mut().pop();
+        }
+    });
+    tracer.on_exit(index, Instant::now());
+}
+
 /// Temporary state stored on the stack which is registered in the `tls` module
 /// below for calls into wasm.
 pub struct CallThreadState {
     unwind: UnsafeCell<MaybeUninit<UnwindReason>>,
     jmp_buf: Cell<*const u8>,
     prev: Cell<tls::Ptr>,
+    host_calls: RefCell<Vec<(Arc<dyn HostCallTracer>, FunctionIndex)>>,
 }
 
 enum UnwindReason {
@@ -229,6 +279,7 @@ enum UnwindReason {
         backtrace: Backtrace,
         pc: usize,
         signal_trap: Option<TrapCode>,
+        frames: Vec<usize>,
     },
 }
 
@@ -239,6 +290,7 @@ impl<'a> CallThreadState {
             unwind: UnsafeCell::new(MaybeUninit::uninit()),
             jmp_buf: Cell::new(ptr::null()),
             prev: Cell::new(ptr::null()),
+            host_calls: RefCell::new(Vec::new()),
         }
     }
 
@@ -251,6 +303,12 @@ impl<'a> CallThreadState {
         // only happen if a trap did happen. As such, it's safe to
         // assume that the `unwind` field is already initialized
         // at this moment.
+        //
+        // Imported functions the trap unwound through never returned,
+        // so report their exits here, innermost first.
+        for (tracer, index) in self.host_calls.take().into_iter().rev() {
+            tracer.on_exit(index, Instant::now());
+        }
         match unsafe { (*self.unwind.get()).as_ptr().read() } {
             UnwindReason::UserTrap(data) => Err(Trap::User(data)),
             UnwindReason::LibTrap(trap) => Err(trap),
@@ -258,7 +316,8 @@ impl<'a> CallThreadState {
                 backtrace,
                 pc,
                 signal_trap,
-            } => Err(Trap::wasm(pc, backtrace, signal_trap)),
+                frames,
+            } => Err(Trap::wasm(pc, backtrace, signal_trap, frames)),
             UnwindReason::Panic(panic) => std::panic::resume_unwind(panic),
         }
     }
@@ -402,17 +461,70 @@ mod tls {
     }
 }
 
-extern "C" fn signal_less_trap_handler(pc: *const u8, trap: TrapCode) {
+/// Collect the return addresses along the frame pointer chain starting at
+/// `frame_pointer`.
+///
+/// The generated code doesn't come with unwind information, but it always
+/// keeps `RBP` as a frame pointer. The walk never reads past `limit`, the
+/// address of the `CallThreadState` living in the frame which called into
+/// wasm, so it stays within the stack even once it leaves the wasm frames;
+/// it is up to the caller to drop the addresses which aren't in wasm code.
+unsafe fn walk_frame_pointers(mut frame_pointer: *const usize, limit: usize) -> Vec<usize> {
+    let mut frames = Vec::new();
+    while !frame_pointer.is_null()
+        && frame_pointer as usize & (mem::align_of::<usize>() - 1) == 0
+        && frame_pointer as usize + 2 * mem::size_of::<usize>() <= limit
+    {
+        // The saved frame pointer is followed by the return address.
+        frames.push(*frame_pointer.add(1));
+        let next = *frame_pointer as *const usize;
+        if next <= frame_pointer {
+            break;
+        }
+        frame_pointer = next;
+    }
+    frames
+}
+
+/// Describe the function containing `pc`, in the instance owning `vmctx`.
+fn describe_trap_site(pc: usize, vmctx: *const VMContext) -> String {
+    let artifact = match VMContext::find_enclosing_artifact(vmctx) {
+        Some(artifact) => artifact,
+        None => return format!("at {:#x}, in an unknown instance", pc),
+    };
+    match artifact.function_for_address(pc) {
+        Some(index) => match artifact.function_name(index) {
+            Some(name) => format!("at {:#x}, in function {} `{}`", pc, index.as_u32(), name),
+            None => format!("at {:#x}, in function {}", pc, index.as_u32()),
+        },
+        None => format!("at {:#x}, outside of the functions of its instance", pc),
+    }
+}
+
+extern "C" fn signal_less_trap_handler(
+    pc: *const u8,
+    trap: TrapCode,
+    frame_pointer: *const usize,
+    vmctx: *const VMContext,
+) {
     let jmp_buf = tls::with(|info| {
         let backtrace = Backtrace::new_unresolved();
-        let info = info.unwrap();
+        let info = info.unwrap_or_else(|| {
+            panic!(
+                "wasm trap `{}` {} with no `catch_traps` to handle it",
+                trap.message(),
+                describe_trap_site(pc as usize, vmctx)
+            )
+        });
         unsafe {
+            let frames = walk_frame_pointers(frame_pointer, info as *const _ as usize);
             (*info.unwind.get())
                 .as_mut_ptr()
                 .write(UnwindReason::WasmTrap {
                     backtrace,
                     signal_trap: Some(trap),
                     pc: pc as usize,
+                    frames,
                 });
             info.jmp_buf.get()
         }
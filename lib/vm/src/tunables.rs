@@ -4,7 +4,7 @@ use crate::{MemoryStyle, TableStyle};
 use crate::{VMMemoryDefinition, VMTableDefinition};
 use std::ptr::NonNull;
 use std::sync::Arc;
-use wasmer_types::{MemoryType, TableType};
+use wasmer_types::{MemoryType, TableType, Type};
 
 /// An engine delegates the creation of memories, tables, and globals
 /// to a foreign implementor of this trait.
@@ -15,6 +15,18 @@ pub trait Tunables {
     /// Construct a `TableStyle` for the provided `TableType`
     fn table_style(&self, table: &TableType) -> TableStyle;
 
+    /// Describe the configuration of these tunables, for logging purposes.
+    ///
+    /// By default this reports the styles given to a memory and to a table
+    /// without a maximum.
+    fn describe(&self) -> String {
+        format!(
+            "memory style: {:?}, table style: {:?}",
+            self.memory_style(&MemoryType::new(0, None, false)),
+            self.table_style(&TableType::new(Type::FuncRef, 0, None)),
+        )
+    }
+
     /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
     fn create_host_memory(
         &self,
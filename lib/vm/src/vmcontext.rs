@@ -89,10 +89,51 @@ pub struct VMFunctionImport {
     pub environment: VMFunctionEnvironment,
 }
 
+impl VMFunctionImport {
+    /// Call the imported function through `trampoline`, with its own
+    /// `environment` as first argument.
+    ///
+    /// `args` holds one value per parameter of the function, in signature
+    /// order, each stored in the low bits of its slot as done by
+    /// `Value::write_value_to`. On success, `results` receives one value per
+    /// result in the same layout. Traps raised by the function are caught
+    /// and returned.
+    ///
+    /// # Safety
+    ///
+    /// - `trampoline` must have been generated for the signature of the
+    ///   function, in the native calling convention of the host, see
+    ///   [`VMTrampolineWrapper::call`].
+    /// - `args` and `results` must have exactly as many slots as the function
+    ///   has parameters and results, holding values of the right types.
+    /// - The instance or host env the function belongs to must be alive.
+    pub unsafe fn call_with_args(
+        &self,
+        args: &[u128],
+        results: &mut [u128],
+        trampoline: VMTrampoline,
+    ) -> Result<(), Trap> {
+        // The trampoline reads the arguments from and writes the results to
+        // the same buffer.
+        let mut values_vec = vec![0u128; args.len().max(results.len())];
+        values_vec[..args.len()].copy_from_slice(args);
+        crate::wasmer_call_trampoline(
+            self.environment,
+            trampoline,
+            *self.body,
+            values_vec.as_mut_ptr() as *mut u8,
+        )?;
+        results.copy_from_slice(&values_vec[..results.len()]);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test_vmfunction_import {
-    use super::VMFunctionImport;
-    use crate::VMOffsets;
+    use super::{
+        FunctionBodyPtr, VMContext, VMFunctionBody, VMFunctionEnvironment, VMFunctionImport,
+    };
+    use crate::{VMOffsets, VMSharedSignatureIndex};
     use memoffset::offset_of;
     use std::mem::size_of;
     use wasmer_types::ModuleInfo;
@@ -114,6 +155,46 @@ mod test_vmfunction_import {
             usize::from(offsets.vmfunction_import_vmctx())
         );
     }
+
+    #[test]
+    fn call_function_import_with_args() {
+        unsafe extern "C" fn scale_trampoline(
+            vmctx: *mut VMContext,
+            _body: *const VMFunctionBody,
+            args: *mut u128,
+        ) {
+            let factor = *(vmctx as *const u128);
+            let (a, b) = (*args, *args.add(1));
+            *args = a * factor;
+            *args.add(1) = b * factor;
+            *args.add(2) = (a + b) * factor;
+        }
+
+        let mut factor = 10u128;
+        let import = VMFunctionImport {
+            body: FunctionBodyPtr(std::ptr::null()),
+            signature: VMSharedSignatureIndex::new(0),
+            trampoline: None,
+            environment: VMFunctionEnvironment {
+                host_env: &mut factor as *mut u128 as *mut _,
+            },
+        };
+        let mut results = [0; 3];
+        unsafe { import.call_with_args(&[2, 3], &mut results, scale_trampoline) }.unwrap();
+        assert_eq!(results, [20, 30, 50]);
+    }
+
+    #[test]
+    fn function_body_ptr_null_check() {
+        let null = FunctionBodyPtr(std::ptr::null());
+        assert!(null.is_null());
+        assert!(null.to_option().is_none());
+
+        let body = 0u8;
+        let ptr = FunctionBodyPtr(&body as *const u8 as *const VMFunctionBody);
+        assert!(!ptr.is_null());
+        assert_eq!(ptr.to_option().map(|ptr| ptr.0), Some(ptr.0));
+    }
 }
 
 /// A locally defined function.
@@ -219,6 +300,22 @@ mod test_vmfunction_body {
 #[repr(transparent)]
 pub struct FunctionBodyPtr(pub *const VMFunctionBody);
 
+impl FunctionBodyPtr {
+    /// Whether this pointer is null, that is doesn't point to any function.
+    pub fn is_null(&self) -> bool {
+        self.0.is_null()
+    }
+
+    /// Return this pointer, or `None` if it is null.
+    pub fn to_option(&self) -> Option<Self> {
+        if self.is_null() {
+            None
+        } else {
+            Some(*self)
+        }
+    }
+}
+
 impl std::ops::Deref for FunctionBodyPtr {
     type Target = *const VMFunctionBody;
 
@@ -306,13 +403,23 @@ pub struct VMMemoryImport {
     pub from: Arc<dyn Memory>,
 }
 
+impl VMMemoryImport {
+    /// Returns whether the imported memory is shared, in which case the host
+    /// must access it with atomic operations, see
+    /// [`wasmer_vm_atomic_load_u32`](crate::libcalls::wasmer_vm_atomic_load_u32).
+    pub fn is_shared(&self) -> bool {
+        self.from.ty().shared
+    }
+}
+
 #[cfg(test)]
 mod test_vmmemory_import {
     use super::VMMemoryImport;
-    use crate::VMOffsets;
+    use crate::{LinearMemory, Memory, MemoryStyle, VMOffsets};
     use memoffset::offset_of;
     use std::mem::size_of;
-    use wasmer_types::ModuleInfo;
+    use std::sync::Arc;
+    use wasmer_types::{MemoryType, ModuleInfo};
 
     #[test]
     fn check_vmmemory_import_offsets() {
@@ -331,6 +438,21 @@ mod test_vmmemory_import {
             usize::from(offsets.vmmemory_import_from())
         );
     }
+
+    #[test]
+    fn is_shared() {
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+        };
+        for &shared in &[false, true] {
+            let memory = LinearMemory::new(&MemoryType::new(1, Some(1), shared), &style).unwrap();
+            let import = VMMemoryImport {
+                definition: memory.vmmemory(),
+                from: Arc::new(memory),
+            };
+            assert_eq!(import.is_shared(), shared);
+        }
+    }
 }
 
 /// The fields compiled code needs to access to utilize a WebAssembly global
@@ -889,121 +1011,129 @@ mod test_vmcaller_checked_anyfunc {
 #[derive(Copy, Clone, Debug)]
 pub struct VMBuiltinFunctionIndex(u32);
 
-impl VMBuiltinFunctionIndex {
+/// Defines the constructor of every builtin function index along with its
+/// name, so that both come from the same table.
+macro_rules! builtin_functions {
+    ($($(#[doc = $doc:literal])* $index:literal => $getter:ident, $name:literal;)*) => {
+        impl VMBuiltinFunctionIndex {
+            $(
+                $(#[doc = $doc])*
+                pub const fn $getter() -> Self {
+                    Self($index)
+                }
+            )*
+
+            /// Returns the total number of builtin functions.
+            pub const fn builtin_functions_total_number() -> u32 {
+                [$($index),*].len() as u32
+            }
+
+            /// Returns a human-readable name of the builtin function, for diagnostics.
+            pub fn name(self) -> &'static str {
+                match self.0 {
+                    $($index => $name,)*
+                    _ => unreachable!("invalid builtin function index {}", self.0),
+                }
+            }
+        }
+    };
+}
+
+builtin_functions! {
     /// Returns an index for wasm's `memory.grow` builtin function.
-    pub const fn get_memory32_grow_index() -> Self {
-        Self(0)
-    }
+    0 => get_memory32_grow_index, "memory32_grow";
     /// Returns an index for wasm's imported `memory.grow` builtin function.
-    pub const fn get_imported_memory32_grow_index() -> Self {
-        Self(1)
-    }
+    1 => get_imported_memory32_grow_index, "imported_memory32_grow";
     /// Returns an index for wasm's `memory.size` builtin function.
-    pub const fn get_memory32_size_index() -> Self {
-        Self(2)
-    }
+    2 => get_memory32_size_index, "memory32_size";
     /// Returns an index for wasm's imported `memory.size` builtin function.
-    pub const fn get_imported_memory32_size_index() -> Self {
-        Self(3)
-    }
+    3 => get_imported_memory32_size_index, "imported_memory32_size";
     /// Returns an index for wasm's `table.copy` when both tables are locally
     /// defined.
-    pub const fn get_table_copy_index() -> Self {
-        Self(4)
-    }
+    4 => get_table_copy_index, "table_copy";
     /// Returns an index for wasm's `table.init`.
-    pub const fn get_table_init_index() -> Self {
-        Self(5)
-    }
+    5 => get_table_init_index, "table_init";
     /// Returns an index for wasm's `elem.drop`.
-    pub const fn get_elem_drop_index() -> Self {
-        Self(6)
-    }
+    6 => get_elem_drop_index, "elem_drop";
     /// Returns an index for wasm's `memory.copy` for locally defined memories.
-    pub const fn get_memory_copy_index() -> Self {
-        Self(7)
-    }
+    7 => get_memory_copy_index, "memory_copy";
     /// Returns an index for wasm's `memory.copy` for imported memories.
-    pub const fn get_imported_memory_copy_index() -> Self {
-        Self(8)
-    }
+    8 => get_imported_memory_copy_index, "imported_memory_copy";
     /// Returns an index for wasm's `memory.fill` for locally defined memories.
-    pub const fn get_memory_fill_index() -> Self {
-        Self(9)
-    }
+    9 => get_memory_fill_index, "memory_fill";
     /// Returns an index for wasm's `memory.fill` for imported memories.
-    pub const fn get_imported_memory_fill_index() -> Self {
-        Self(10)
-    }
+    10 => get_imported_memory_fill_index, "imported_memory_fill";
     /// Returns an index for wasm's `memory.init` instruction.
-    pub const fn get_memory_init_index() -> Self {
-        Self(11)
-    }
+    11 => get_memory_init_index, "memory_init";
     /// Returns an index for wasm's `data.drop` instruction.
-    pub const fn get_data_drop_index() -> Self {
-        Self(12)
-    }
+    12 => get_data_drop_index, "data_drop";
     /// Returns an index for wasm's `raise_trap` instruction.
-    pub const fn get_raise_trap_index() -> Self {
-        Self(13)
-    }
+    13 => get_raise_trap_index, "raise_trap";
     /// Returns an index for wasm's `table.size` instruction for local tables.
-    pub const fn get_table_size_index() -> Self {
-        Self(14)
-    }
+    14 => get_table_size_index, "table_size";
     /// Returns an index for wasm's `table.size` instruction for imported tables.
-    pub const fn get_imported_table_size_index() -> Self {
-        Self(15)
-    }
+    15 => get_imported_table_size_index, "imported_table_size";
     /// Returns an index for wasm's `table.grow` instruction for local tables.
-    pub const fn get_table_grow_index() -> Self {
-        Self(16)
-    }
+    16 => get_table_grow_index, "table_grow";
     /// Returns an index for wasm's `table.grow` instruction for imported tables.
-    pub const fn get_imported_table_grow_index() -> Self {
-        Self(17)
-    }
+    17 => get_imported_table_grow_index, "imported_table_grow";
     /// Returns an index for wasm's `table.get` instruction for local tables.
-    pub const fn get_table_get_index() -> Self {
-        Self(18)
-    }
+    18 => get_table_get_index, "table_get";
     /// Returns an index for wasm's `table.get` instruction for imported tables.
-    pub const fn get_imported_table_get_index() -> Self {
-        Self(19)
-    }
+    19 => get_imported_table_get_index, "imported_table_get";
     /// Returns an index for wasm's `table.set` instruction for local tables.
-    pub const fn get_table_set_index() -> Self {
-        Self(20)
-    }
+    20 => get_table_set_index, "table_set";
     /// Returns an index for wasm's `table.set` instruction for imported tables.
-    pub const fn get_imported_table_set_index() -> Self {
-        Self(21)
-    }
+    21 => get_imported_table_set_index, "imported_table_set";
     /// Returns an index for wasm's `func.ref` instruction.
-    pub const fn get_func_ref_index() -> Self {
-        Self(22)
-    }
+    22 => get_func_ref_index, "func_ref";
     /// Returns an index for wasm's `table.fill` instruction for local tables.
-    pub const fn get_table_fill_index() -> Self {
-        Self(23)
-    }
+    23 => get_table_fill_index, "table_fill";
     /// Returns an index for a function to increment the externref count.
-    pub const fn get_externref_inc_index() -> Self {
-        Self(24)
-    }
+    24 => get_externref_inc_index, "externref_inc";
     /// Returns an index for a function to decrement the externref count.
-    pub const fn get_externref_dec_index() -> Self {
-        Self(25)
-    }
-    /// Returns the total number of builtin functions.
-    pub const fn builtin_functions_total_number() -> u32 {
-        26
-    }
+    25 => get_externref_dec_index, "externref_dec";
+    /// Returns an index for a function reporting entry into an imported function.
+    26 => get_host_call_enter_index, "host_call_enter";
+    /// Returns an index for a function reporting exit from an imported function.
+    27 => get_host_call_exit_index, "host_call_exit";
+}
 
+impl VMBuiltinFunctionIndex {
     /// Return the index as an u32 number.
     pub const fn index(self) -> u32 {
         self.0
     }
+
+    /// Returns an iterator over all the builtin functions.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..Self::builtin_functions_total_number()).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod test_vmbuiltin_function_index {
+    use super::VMBuiltinFunctionIndex;
+    use std::collections::HashSet;
+
+    #[test]
+    fn check_vmbuiltin_function_names() {
+        let names = VMBuiltinFunctionIndex::all()
+            .map(VMBuiltinFunctionIndex::name)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            names.len(),
+            VMBuiltinFunctionIndex::builtin_functions_total_number() as usize
+        );
+        assert_eq!(
+            VMBuiltinFunctionIndex::get_table_copy_index().name(),
+            "table_copy"
+        );
+        assert_eq!(
+            VMBuiltinFunctionIndex::get_host_call_exit_index().name(),
+            "host_call_exit"
+        );
+    }
 }
 
 /// An array that stores addresses of builtin functions. We translate code
@@ -1018,6 +1148,12 @@ impl VMBuiltinFunctionsArray {
         VMBuiltinFunctionIndex::builtin_functions_total_number() as usize
     }
 
+    /// Returns the builtin function whose code starts at `address`, if any.
+    pub(crate) fn lookup(address: usize) -> Option<VMBuiltinFunctionIndex> {
+        let array = Self::initialized();
+        VMBuiltinFunctionIndex::all().find(|index| array.ptrs[index.index() as usize] == address)
+    }
+
     pub fn initialized() -> Self {
         use crate::libcalls::*;
 
@@ -1079,6 +1215,10 @@ impl VMBuiltinFunctionsArray {
             wasmer_vm_externref_inc as usize;
         ptrs[VMBuiltinFunctionIndex::get_externref_dec_index().index() as usize] =
             wasmer_vm_externref_dec as usize;
+        ptrs[VMBuiltinFunctionIndex::get_host_call_enter_index().index() as usize] =
+            wasmer_vm_host_call_enter as usize;
+        ptrs[VMBuiltinFunctionIndex::get_host_call_exit_index().index() as usize] =
+            wasmer_vm_host_call_exit as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
@@ -1120,15 +1260,80 @@ impl VMContext {
     pub unsafe fn host_state(&self) -> &dyn Any {
         self.instance().host_state()
     }
+
+    /// Return the state attached with `InstanceConfig::with_host_state` to
+    /// this `Instance`, if it is a `T`. Libcalls can reach it this way.
+    ///
+    /// # Safety
+    /// This is unsafe because it doesn't work on just any `VMContext`, it must
+    /// be a `VMContext` allocated as part of an `Instance`.
+    #[inline]
+    pub unsafe fn host_state_ref<T: Any>(&self) -> Option<&T> {
+        self.instance().host_state_ref()
+    }
 }
 
+/// A trampoline calling a function of a given signature from the host.
 ///
+/// See [`VMTrampolineWrapper::call`] for the calling contract.
 pub type VMTrampoline = unsafe extern "C" fn(
     *mut VMContext,        // callee vmctx
     *const VMFunctionBody, // function we're actually calling
     *mut u128,             // space for arguments and return values
 );
 
+/// A [`VMTrampoline`], to be called through [`VMTrampolineWrapper::call`].
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct VMTrampolineWrapper(pub VMTrampoline);
+
+impl VMTrampolineWrapper {
+    /// Call `body` through the trampoline.
+    ///
+    /// The trampoline reads the arguments of `body` from `args`, one 16-byte
+    /// slot per parameter in signature order, and calls `body` with `vmctx`
+    /// followed by those arguments. The results of `body` are then written
+    /// back to `args`, one slot per result starting from the first one.
+    ///
+    /// # Safety
+    ///
+    /// - The trampoline must have been generated for the signature of `body`.
+    /// - `vmctx` must be the environment `body` expects: the `VMContext` of
+    ///   its instance for a wasm function, or its host env for a host one.
+    /// - `args` must point to as many writable slots as the larger of the
+    ///   number of parameters and of results, with the parameters already
+    ///   stored in them.
+    /// - Traps in `body` unwind with a `longjmp`, so this must run within
+    ///   [`catch_traps`](crate::catch_traps).
+    #[inline]
+    pub unsafe fn call(&self, vmctx: *mut VMContext, body: *const VMFunctionBody, args: *mut u128) {
+        (self.0)(vmctx, body, args)
+    }
+}
+
+#[cfg(test)]
+mod test_vmtrampoline_wrapper {
+    use super::{VMContext, VMFunctionBody, VMTrampoline, VMTrampolineWrapper};
+    use std::mem::size_of;
+
+    unsafe extern "C" fn sum_trampoline(
+        _vmctx: *mut VMContext,
+        _body: *const VMFunctionBody,
+        args: *mut u128,
+    ) {
+        *args += *args.add(1);
+    }
+
+    #[test]
+    fn check_vmtrampoline_wrapper() {
+        assert_eq!(size_of::<VMTrampolineWrapper>(), size_of::<VMTrampoline>());
+        let mut args = [2u128, 3];
+        let trampoline = VMTrampolineWrapper(sum_trampoline);
+        unsafe { trampoline.call(std::ptr::null_mut(), std::ptr::null(), args.as_mut_ptr()) };
+        assert_eq!(args[0], 5);
+    }
+}
+
 /// Pointers to section data.
 #[derive(Clone, Copy, Debug)]
 #[repr(transparent)]
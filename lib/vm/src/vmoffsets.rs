@@ -513,11 +513,46 @@ impl VMOffsets {
         self.vmctx_stack_limit_begin().checked_add(4).unwrap()
     }
 
+    /// The offset of the host call tracer pointer.
+    pub fn vmctx_host_call_tracer_pointer(&self) -> u32 {
+        offset_by(
+            self.vmctx_stack_limit_initial_begin(),
+            1,
+            4,
+            align_of::<*const u8>(),
+        )
+    }
+
+    /// The offset of the interrupt flag pointer.
+    pub fn vmctx_interrupt_flag_pointer(&self) -> u32 {
+        offset_by(
+            self.vmctx_host_call_tracer_pointer(),
+            1,
+            u32::from(self.pointer_size),
+            align_of::<*const u8>(),
+        )
+    }
+
+    /// The offset of the remaining call depth.
+    pub fn vmctx_call_depth_begin(&self) -> u32 {
+        offset_by(
+            self.vmctx_interrupt_flag_pointer(),
+            1,
+            u32::from(self.pointer_size),
+            align_of::<u32>(),
+        )
+    }
+
+    /// The offset of the initial call depth.
+    pub fn vmctx_call_depth_initial_begin(&self) -> u32 {
+        self.vmctx_call_depth_begin().checked_add(4).unwrap()
+    }
+
     /// Return the size of the [`VMContext`] allocation.
     ///
     /// [`VMContext`]: crate::vmcontext::VMContext
     pub fn size_of_vmctx(&self) -> u32 {
-        self.vmctx_stack_limit_initial_begin()
+        self.vmctx_call_depth_initial_begin()
             .checked_add(4)
             .unwrap()
     }
@@ -748,6 +783,151 @@ impl VMOffsets {
     }
 }
 
+/// A region of the [`VMContext`], as reported by [`VMOffsets::layout`].
+///
+/// [`VMContext`]: crate::vmcontext::VMContext
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VMContextRegion {
+    /// What the region holds.
+    pub name: &'static str,
+    /// Offset of the region from the start of the [`VMContext`].
+    ///
+    /// [`VMContext`]: crate::vmcontext::VMContext
+    pub offset: u32,
+    /// Size of the region in bytes.
+    pub size: u32,
+}
+
+/// Layout reports, meant for debugging code that accesses the vmctx.
+impl VMOffsets {
+    /// Return every region of the [`VMContext`], in increasing offset order.
+    ///
+    /// Padding inserted to align a region is not part of the preceding one.
+    ///
+    /// [`VMContext`]: crate::vmcontext::VMContext
+    pub fn layout(&self) -> Vec<VMContextRegion> {
+        let region = |name, offset, count: u32, item_size: u32| VMContextRegion {
+            name,
+            offset,
+            size: count.checked_mul(item_size).unwrap(),
+        };
+        let pointer_size = u32::from(self.pointer_size);
+        vec![
+            region(
+                "signature ids",
+                self.vmctx_signature_ids_begin(),
+                self.num_signature_ids,
+                u32::from(self.size_of_vmshared_signature_index()),
+            ),
+            region(
+                "imported functions",
+                self.vmctx_imported_functions_begin(),
+                self.num_imported_functions,
+                u32::from(self.size_of_vmfunction_import()),
+            ),
+            region(
+                "imported tables",
+                self.vmctx_imported_tables_begin(),
+                self.num_imported_tables,
+                u32::from(self.size_of_vmtable_import()),
+            ),
+            region(
+                "imported memories",
+                self.vmctx_imported_memories_begin(),
+                self.num_imported_memories,
+                u32::from(self.size_of_vmmemory_import()),
+            ),
+            region(
+                "imported globals",
+                self.vmctx_imported_globals_begin(),
+                self.num_imported_globals,
+                u32::from(self.size_of_vmglobal_import()),
+            ),
+            region(
+                "tables",
+                self.vmctx_tables_begin(),
+                self.num_local_tables,
+                u32::from(self.size_of_vmtable_definition()),
+            ),
+            region(
+                "memories",
+                self.vmctx_memories_begin(),
+                self.num_local_memories,
+                u32::from(self.size_of_vmmemory_definition()),
+            ),
+            region(
+                "globals",
+                self.vmctx_globals_begin(),
+                self.num_local_globals,
+                u32::from(self.size_of_vmglobal_local()),
+            ),
+            region(
+                "builtin functions",
+                self.vmctx_builtin_functions_begin(),
+                VMBuiltinFunctionIndex::builtin_functions_total_number(),
+                pointer_size,
+            ),
+            region(
+                "trap handler",
+                self.vmctx_trap_handler_begin(),
+                if self.has_trap_handlers { 1 } else { 0 },
+                pointer_size,
+            ),
+            region(
+                "gas counter pointer",
+                self.vmctx_gas_limiter_pointer(),
+                1,
+                pointer_size,
+            ),
+            region("stack limit", self.vmctx_stack_limit_begin(), 1, 4),
+            region(
+                "initial stack limit",
+                self.vmctx_stack_limit_initial_begin(),
+                1,
+                4,
+            ),
+            region(
+                "host call tracer pointer",
+                self.vmctx_host_call_tracer_pointer(),
+                1,
+                pointer_size,
+            ),
+            region(
+                "interrupt flag pointer",
+                self.vmctx_interrupt_flag_pointer(),
+                1,
+                pointer_size,
+            ),
+            region("call depth", self.vmctx_call_depth_begin(), 1, 4),
+            region(
+                "initial call depth",
+                self.vmctx_call_depth_initial_begin(),
+                1,
+                4,
+            ),
+        ]
+    }
+
+    /// Return a human readable report of [`VMOffsets::layout`], one region
+    /// per line, followed by the total size of the [`VMContext`].
+    ///
+    /// [`VMContext`]: crate::vmcontext::VMContext
+    pub fn debug_layout(&self) -> String {
+        let mut report = String::new();
+        for region in self.layout() {
+            report.push_str(&format!(
+                "{:#06x}..{:#06x} {:>5} bytes  {}\n",
+                region.offset,
+                region.offset + region.size,
+                region.size,
+                region.name
+            ));
+        }
+        report.push_str(&format!("total: {} bytes\n", self.size_of_vmctx()));
+        report
+    }
+}
+
 /// Target specific type for shared signature index.
 #[derive(Debug, Copy, Clone)]
 pub struct TargetSharedSignatureIndex(u32);
@@ -767,6 +947,11 @@ impl TargetSharedSignatureIndex {
 #[cfg(test)]
 mod tests {
     use crate::vmoffsets::align;
+    use crate::VMOffsets;
+    use wasmer_types::{
+        FunctionType, GlobalType, ImportCounts, MemoryType, ModuleInfo, Mutability, Pages,
+        TableType, Type,
+    };
 
     #[test]
     fn alignment() {
@@ -778,4 +963,50 @@ mod tests {
         assert!(is_aligned(align(33, 16)));
         assert!(is_aligned(align(31, 16)));
     }
+
+    #[test]
+    fn layout_is_consistent() {
+        let mut module = ModuleInfo::new();
+        module
+            .signatures
+            .push(FunctionType::new(vec![Type::I32], vec![]));
+        module.tables.push(TableType::new(Type::FuncRef, 1, None));
+        module.memories.push(MemoryType::new(Pages(1), None, false));
+        module.memories.push(MemoryType::new(Pages(1), None, false));
+        module
+            .globals
+            .push(GlobalType::new(Type::I64, Mutability::Var));
+        module.import_counts = ImportCounts {
+            functions: 2,
+            tables: 0,
+            memories: 1,
+            globals: 0,
+        };
+        let offsets = VMOffsets::for_host().with_module_info(&module);
+
+        let layout = offsets.layout();
+        let size = |name| layout.iter().find(|r| r.name == name).unwrap().size;
+        assert_eq!(
+            size("imported functions"),
+            2 * u32::from(offsets.size_of_vmfunction_import())
+        );
+        assert_eq!(
+            size("imported memories"),
+            u32::from(offsets.size_of_vmmemory_import())
+        );
+        assert_eq!(
+            size("memories"),
+            2 * u32::from(offsets.size_of_vmmemory_definition())
+        );
+        assert_eq!(size("imported tables"), 0);
+        for pair in layout.windows(2) {
+            assert!(pair[0].offset + pair[0].size <= pair[1].offset);
+        }
+        let last = layout.last().unwrap();
+        assert_eq!(last.offset + last.size, offsets.size_of_vmctx());
+
+        let report = offsets.debug_layout();
+        assert_eq!(report.lines().count(), layout.len() + 1);
+        assert!(report.contains("imported memories"));
+    }
 }
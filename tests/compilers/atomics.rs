@@ -0,0 +1,64 @@
+use wasmer::*;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_universal::Universal;
+use wasmer_vm::libcalls::wasmer_vm_atomic_load_u32;
+
+fn get_store() -> Store {
+    let mut features = Features::default();
+    features.threads(true).multi_value(false);
+    Store::new(
+        &Universal::new(Singlepass::default())
+            .features(features)
+            .engine(),
+    )
+}
+
+#[test]
+fn atomic_stores_to_shared_and_unshared_memories() {
+    for &shared in &[false, true] {
+        let wat = format!(
+            r#"
+            (module
+              (import "env" "memory" (memory 1 1 {}))
+              (func (export "store") (param i32 i32)
+                local.get 0
+                local.get 1
+                i32.atomic.store)
+              (func (export "store16") (param i32)
+                local.get 0
+                i32.const 0x7f
+                i32.atomic.store16)
+              (func (export "store64") (param i32 i64)
+                local.get 0
+                local.get 1
+                i64.atomic.store))
+            "#,
+            if shared { "shared" } else { "" }
+        );
+        let store = get_store();
+        let module = Module::new(&store, wat).unwrap();
+        let memory = Memory::new(&store, MemoryType::new(1, Some(1), shared)).unwrap();
+        let instance = Instance::new(
+            &module,
+            &imports! {
+                "env" => {
+                    "memory" => memory.clone(),
+                },
+            },
+        )
+        .unwrap();
+
+        let store32: NativeFunc<(i32, i32), ()> = instance.get_native_function("store").unwrap();
+        let store16: NativeFunc<i32, ()> = instance.get_native_function("store16").unwrap();
+        let store64: NativeFunc<(i32, i64), ()> = instance.get_native_function("store64").unwrap();
+        store32.call(8, 0x1234_5678).unwrap();
+        store16.call(12).unwrap();
+        store64.call(16, 0x0102_0304_0506_0708).unwrap();
+
+        let load = |offset| unsafe { wasmer_vm_atomic_load_u32(memory.data_ptr(), offset) };
+        assert_eq!(load(8), 0x1234_5678);
+        assert_eq!(load(12), 0x7f);
+        assert_eq!(load(16), 0x0506_0708);
+        assert_eq!(load(20), 0x0102_0304);
+    }
+}
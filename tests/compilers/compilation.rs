@@ -1,7 +1,11 @@
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use std::time::Duration;
 use wasmer::*;
 use wasmer_engine::{Engine, Executable};
 use wasmer_engine_universal::Universal;
-use wasmer_vm::Artifact;
+use wasmer_vm::{Artifact, TrapCode};
 
 fn slow_to_compile_contract(n_fns: usize, n_locals: usize) -> Vec<u8> {
     let fns = format!("(func (local {}))\n", "i32 ".repeat(n_locals)).repeat(n_fns);
@@ -100,9 +104,1056 @@ fn profiling() {
             assert_eq!("f1", info[1].0);
             assert_eq!("f2", info[2].0);
             assert_eq!("f3", info[3].0);
+            let names = artifact.function_names().collect::<Vec<_>>();
+            assert_eq!(names, [(FunctionIndex::from_u32(1), "f0")]);
         },
         Err(_) => {
             assert!(false)
         }
     }
+
+    let module = Module::new(&store, &wasm).unwrap();
+    let imports = imports! {
+        "env" => {
+            "impf" => Function::new_native(&store, || {}),
+        },
+    };
+    let instance = Instance::new(&module, &imports).unwrap();
+    let functions = instance.named_functions();
+    let exported = functions
+        .iter()
+        .filter_map(|f| Some((f.index.as_u32(), f.export_name.as_deref()?)))
+        .collect::<Vec<_>>();
+    assert_eq!(exported, [(2, "f1"), (3, "f2"), (4, "f3")]);
+    let f0 = functions.iter().find(|f| f.index.as_u32() == 1).unwrap();
+    assert_eq!(f0.export_name, None);
+    assert_eq!(f0.debug_name.as_deref(), Some("f0"));
+}
+
+#[test]
+fn trace_info() {
+    let wat = r#"
+       (func $f0 (param i32) (result i32)
+         local.get 0
+         i32.const 1
+         i32.add
+         i32.const 3
+         i32.mul)
+       (func (export "f1") (result i32)
+         i32.const 5
+         call $f0)
+    "#;
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let module_len = wasm.len();
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let executable = engine.compile_universal(&wasm, store.tunables()).unwrap();
+    let artifact = engine.load_universal_executable(&executable).unwrap();
+
+    let mut last_wasm_offset = 0;
+    for (idx, _) in artifact.functions().iter() {
+        let trace = artifact.trace_info(idx).unwrap();
+        let length = artifact.function_extent(idx).unwrap().length as u32;
+        assert!(!trace.entries.is_empty());
+        // The prologue precedes the first operator.
+        assert_eq!(trace.wasm_offset_at(0), None);
+        for pair in trace.entries.windows(2) {
+            assert!(pair[0].0 < pair[1].0);
+            assert!(pair[0].1 < pair[1].1);
+        }
+        for &(native, wasm) in &trace.entries {
+            assert!(native < length);
+            assert!(wasm > last_wasm_offset && (wasm as usize) < module_len);
+            assert_eq!(trace.wasm_offset_at(native), Some(wasm));
+        }
+        last_wasm_offset = trace.entries.last().unwrap().1;
+    }
+
+    // The trace info survives serialization.
+    let serialized = wasmer_engine::Executable::serialize(&executable).unwrap();
+    let executable =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized) }
+            .unwrap();
+    let loaded = engine.load_universal_executable_ref(&executable).unwrap();
+    for (idx, _) in artifact.functions().iter() {
+        assert_eq!(loaded.trace_info(idx), artifact.trace_info(idx));
+    }
+}
+
+#[test]
+fn function_traps() {
+    let wat = r#"
+       (memory 1)
+       (func (export "load") (param i32) (result i32)
+         local.get 0
+         i32.load)
+       (func (export "add") (param i32) (result i32)
+         local.get 0
+         i32.const 1
+         i32.add)
+       (func (export "abort")
+         unreachable)
+    "#;
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let executable = engine.compile_universal(&wasm, store.tunables()).unwrap();
+    let artifact = engine.load_universal_executable(&executable).unwrap();
+
+    let codes = |index: u32| {
+        let index = LocalFunctionIndex::from_u32(index);
+        let traps = artifact.function_traps(index);
+        let length = artifact.function_extent(index).unwrap().length as u32;
+        for pair in traps.windows(2) {
+            assert!(pair[0].code_offset < pair[1].code_offset);
+        }
+        assert!(traps.iter().all(|trap| trap.code_offset < length));
+        traps.iter().map(|trap| trap.trap_code).collect::<Vec<_>>()
+    };
+    assert!(codes(0).contains(&TrapCode::HeapAccessOutOfBounds));
+    assert!(!codes(1).contains(&TrapCode::HeapAccessOutOfBounds));
+    assert!(codes(1).contains(&TrapCode::WasmStackOverflow));
+    assert!(codes(2).contains(&TrapCode::UnreachableCodeReached));
+    assert!(!codes(2).contains(&TrapCode::HeapAccessOutOfBounds));
+
+    // The trap sites survive serialization.
+    let serialized = wasmer_engine::Executable::serialize(&executable).unwrap();
+    let executable =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized) }
+            .unwrap();
+    let loaded = engine.load_universal_executable_ref(&executable).unwrap();
+    for (index, _) in artifact.functions().iter() {
+        assert_eq!(loaded.function_traps(index), artifact.function_traps(index));
+    }
+}
+
+#[test]
+fn compile_from_translation() {
+    let wat = r#"
+       (import "host" "gas" (func (param i32)))
+       (memory 1)
+       (data (i32.const 8) "data")
+       (func (export "f") (param i32) (result i32)
+         local.get 0
+         i32.const 1
+         i32.add)
+    "#;
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+
+    let from_binary = engine.compile_universal(&wasm, store.tunables()).unwrap();
+    let translation = ModuleEnvironment::new().translate(&wasm).unwrap();
+    let from_translation = engine
+        .compile_from_translation(translation, store.tunables())
+        .unwrap();
+    assert_eq!(
+        from_translation.metering_info(),
+        from_binary.metering_info()
+    );
+    assert_eq!(
+        Executable::serialize(&from_translation).unwrap(),
+        Executable::serialize(&from_binary).unwrap()
+    );
+
+    let untranslated = ModuleEnvironment::new();
+    assert!(engine
+        .compile_from_translation(untranslated, store.tunables())
+        .is_err());
+}
+
+#[test]
+fn compile_function() {
+    let wat = r#"
+       (import "host" "gas" (func (param i32)))
+       (memory 1)
+       (func $double (param i32) (result i32)
+         local.get 0
+         i32.const 2
+         i32.mul)
+       (func (export "f") (param i32) (result i32)
+         (local i64)
+         local.get 0
+         call $double
+         i32.load)
+    "#;
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let translation = ModuleEnvironment::new().translate(&wasm).unwrap();
+    let mut features = Features::default();
+    features.multi_value(false);
+    let compile_info = wasmer_compiler::CompileModuleInfo {
+        features,
+        memory_styles: translation
+            .module
+            .memories
+            .values()
+            .map(|ty| store.tunables().memory_style(ty))
+            .collect(),
+        table_styles: Default::default(),
+        module: std::sync::Arc::new(translation.module),
+    };
+    let module_translation = translation.module_translation_state.unwrap();
+    let compiler = Box::new(Singlepass::default()).compiler();
+    let target = engine.target();
+
+    let bodies = translation.function_body_inputs;
+    assert_eq!(bodies.len(), 2);
+    let functions = bodies
+        .iter()
+        .map(|(index, body)| {
+            compiler
+                .compile_function(target, &compile_info, &module_translation, index, body)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let compilation = compiler
+        .compile_module(target, &compile_info, &module_translation, bodies)
+        .unwrap();
+    let module_bodies = compilation.get_function_bodies();
+    let module_frame_info = compilation.get_frame_info();
+    for (function, (body, frame_info)) in functions
+        .iter()
+        .zip(module_bodies.values().zip(module_frame_info.values()))
+    {
+        assert_eq!(&function.body, body);
+        assert_eq!(&function.frame_info, frame_info);
+    }
+}
+
+#[test]
+fn function_code() {
+    let wat = r#"
+       (import "env" "impf" (func))
+       (func $f0 nop)
+       (func $f1 (result i32) call $f0 i32.const 42)
+    "#;
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let executable = engine.compile_universal(&wasm, store.tunables()).unwrap();
+    let artifact = engine.load_universal_executable(&executable).unwrap();
+
+    for (index, function) in artifact.functions().iter() {
+        let code = artifact.function_code(index).unwrap();
+        assert!(!code.is_empty());
+        assert_eq!(code.len(), function.length as usize);
+        assert_eq!(code.as_ptr(), function.body.0 as *const u8);
+        let extent = artifact.function_extent(index).unwrap();
+        assert_eq!(code.len(), extent.length);
+    }
+    assert!(artifact
+        .function_code(LocalFunctionIndex::from_u32(2))
+        .is_none());
+}
+
+#[test]
+fn function_name_for_address() {
+    let wat = r#"
+       (import "env" "impf" (func))
+       (func $f0 nop)
+       (func $f1 nop nop)
+       (func (export "f2"))
+    "#;
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let translation = ModuleEnvironment::new().translate(&wasm).unwrap();
+    let module = translation.module.clone();
+    let executable = engine
+        .compile_from_translation(translation, store.tunables())
+        .unwrap();
+    let artifact = engine.load_universal_executable(&executable).unwrap();
+
+    let names = artifact
+        .functions()
+        .iter()
+        .map(|(idx, _)| {
+            let extent = artifact.function_extent(idx).unwrap();
+            let start = extent.address.0 as usize;
+            let end = start + extent.length;
+            (
+                artifact.function_name_for_address(&module, start),
+                artifact.function_name_for_address(&module, end - 1),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            (Some("f0"), Some("f0")),
+            (Some("f1"), Some("f1")),
+            (None, None)
+        ]
+    );
+    assert_eq!(artifact.function_name_for_address(&module, 0), None);
+}
+
+#[test]
+fn module_limits() {
+    let wat = r#"
+       (import "env" "f" (func))
+       (import "env" "g" (func))
+       (table 1 funcref)
+       (memory 1)
+       (func $f0)
+       (func $f1 call $f0)
+       (func (export "f2") call $f1)
+    "#;
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let compile = |limits: ModuleLimits| {
+        let engine = Universal::new(Singlepass::default())
+            .limits(limits)
+            .engine();
+        let store = Store::new(&engine);
+        engine.compile_universal(&wasm, store.tunables()).map(drop)
+    };
+
+    let within = ModuleLimits {
+        max_functions: Some(3),
+        max_imports: Some(2),
+        max_tables: Some(1),
+        max_memories: Some(1),
+        max_code_size: Some(1024),
+    };
+    assert!(compile(within).is_ok());
+    assert!(compile(ModuleLimits::default()).is_ok());
+
+    let err = compile(ModuleLimits {
+        max_functions: Some(2),
+        ..Default::default()
+    })
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        CompileError::LimitExceeded(ModuleLimit::Functions, 3, 2)
+    ));
+    assert_eq!(
+        err.to_string(),
+        "The module declares 3 functions, which exceeds the limit of 2"
+    );
+    assert!(matches!(
+        compile(ModuleLimits {
+            max_imports: Some(1),
+            ..Default::default()
+        }),
+        Err(CompileError::LimitExceeded(ModuleLimit::Imports, 2, 1))
+    ));
+    assert!(matches!(
+        compile(ModuleLimits {
+            max_tables: Some(0),
+            ..Default::default()
+        }),
+        Err(CompileError::LimitExceeded(ModuleLimit::Tables, 1, 0))
+    ));
+    assert!(matches!(
+        compile(ModuleLimits {
+            max_memories: Some(0),
+            ..Default::default()
+        }),
+        Err(CompileError::LimitExceeded(ModuleLimit::Memories, 1, 0))
+    ));
+    assert!(matches!(
+        compile(ModuleLimits {
+            max_code_size: Some(1),
+            ..Default::default()
+        }),
+        Err(CompileError::LimitExceeded(ModuleLimit::CodeSize, size, 1)) if size > 1
+    ));
+}
+
+#[test]
+fn import_allow_list() {
+    let compile = |wat: &str| {
+        let wasm = wat2wasm(wat.as_bytes()).unwrap();
+        let allow_list = ImportAllowList::new()
+            .allow(
+                "env",
+                "log",
+                ExternType::Function(FunctionType::new(vec![Type::I32], vec![])),
+            )
+            .allow(
+                "env",
+                "height",
+                ExternType::Global(GlobalType::new(Type::I64, Mutability::Const)),
+            )
+            .allow(
+                "env",
+                "memory",
+                ExternType::Memory(MemoryType::new(1, None, false)),
+            );
+        let engine = Universal::new(Singlepass::default())
+            .import_allow_list(allow_list)
+            .engine();
+        let store = Store::new(&engine);
+        engine.compile_universal(&wasm, store.tunables()).map(drop)
+    };
+    let error = |wat: &str| match compile(wat) {
+        Err(CompileError::Validate(message)) => message,
+        result => panic!("unexpected compilation result: {:?}", result),
+    };
+
+    assert!(compile(
+        r#"(module
+            (import "env" "log" (func (param i32)))
+            (import "env" "height" (global i64))
+            (import "env" "memory" (memory 2)))"#
+    )
+    .is_ok());
+    assert!(compile("(module)").is_ok());
+    assert_eq!(
+        error(
+            r#"(module
+                (import "env" "log" (func (param i32)))
+                (import "env" "random" (func (result i64))))"#
+        ),
+        "import `env`.`random` is not allowed"
+    );
+    assert_eq!(
+        error(r#"(module (import "env" "log" (func (param i64))))"#),
+        "import `env`.`log` has signature [I64] -> [] but is only allowed as [I32] -> []"
+    );
+    assert!(
+        error(r#"(module (import "env" "height" (global (mut i64))))"#)
+            .starts_with("import `env`.`height` has type")
+    );
+    assert_eq!(
+        error(r#"(module (import "env" "memory" (table 1 funcref)))"#),
+        "import `env`.`memory` is a table but is only allowed as a memory"
+    );
+}
+
+#[test]
+fn segment_bounds_checked_at_compile_time() {
+    let compile = |wat: &str, check: bool| {
+        let wasm = wat2wasm(wat.as_bytes()).unwrap();
+        let engine = Universal::new(Singlepass::default())
+            .check_segment_bounds(check)
+            .engine();
+        let store = Store::new(&engine);
+        engine.compile_universal(&wasm, store.tunables()).map(drop)
+    };
+
+    let past_memory = r#"(module
+        (memory 1)
+        (data (i32.const 65534) "abc"))"#;
+    assert!(compile(past_memory, false).is_ok());
+    assert!(matches!(
+        compile(past_memory, true),
+        Err(CompileError::Validate(message)) if message.contains("data segment 0")
+    ));
+    let past_table = r#"(module
+        (table 2 funcref)
+        (func)
+        (elem (i32.const 1) 0 0))"#;
+    assert!(compile(past_table, false).is_ok());
+    assert!(matches!(
+        compile(past_table, true),
+        Err(CompileError::Validate(message)) if message.contains("element segment 0")
+    ));
+
+    // Segments filling their memory or table exactly are fine, and the ones
+    // whose bounds depend on the imports are left to the instantiation.
+    let exact = r#"(module
+        (memory 1)
+        (table 2 funcref)
+        (func)
+        (data (i32.const 65533) "abc")
+        (elem (i32.const 0) 0 0))"#;
+    assert!(compile(exact, true).is_ok());
+    let imported_memory = r#"(module
+        (import "env" "memory" (memory 1))
+        (data (i32.const 65536) "abc"))"#;
+    assert!(compile(imported_memory, true).is_ok());
+    let global_offset = r#"(module
+        (import "env" "base" (global i32))
+        (memory 0)
+        (data (global.get 0) "abc"))"#;
+    assert!(compile(global_offset, true).is_ok());
+}
+
+#[test]
+fn zero_code_memory_on_drop() {
+    let wat = r#"
+       (func (export "add") (param i32 i32) (result i32)
+         local.get 0
+         local.get 1
+         i32.add)
+    "#;
+    let engine = Universal::new(Singlepass::default())
+        .zero_code_memory_on_drop(true)
+        .engine();
+    let store = Store::new(&engine);
+    let module = Module::new(&store, wat).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    let add: NativeFunc<(i32, i32), i32> = instance.get_native_function("add").unwrap();
+    assert_eq!(add.call(2, 3).unwrap(), 5);
+    drop((add, instance, module, store, engine));
+
+    let wasm = wat2wasm(wat.as_bytes()).unwrap();
+    let engine = Universal::new(Singlepass::default())
+        .zero_code_memory_on_drop(true)
+        .engine();
+    let store = Store::new(&engine);
+    let executable = engine.compile_universal(&wasm, store.tunables()).unwrap();
+    let artifact = engine.load_universal_executable(&executable).unwrap();
+    let code = artifact
+        .function_code(LocalFunctionIndex::from_u32(0))
+        .unwrap();
+    let (ptr, len) = (code.as_ptr(), code.len());
+    assert!(code.iter().any(|byte| *byte != 0));
+    unsafe { engine.zero_code_memory() };
+    let code = unsafe { std::slice::from_raw_parts(ptr, len) };
+    assert!(code.iter().all(|byte| *byte == 0));
+}
+
+#[test]
+fn code_memory_on_numa_node() {
+    let wat = r#"
+       (func (export "add") (param i32 i32) (result i32)
+         local.get 0
+         local.get 1
+         i32.add)
+    "#;
+    // Node 0 exists on every machine, and the hint is ignored where NUMA
+    // placement is not supported.
+    let engine = Universal::new(Singlepass::default()).numa_node(0).engine();
+    let store = Store::new(&engine);
+    let module = Module::new(&store, wat).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    let add: NativeFunc<(i32, i32), i32> = instance.get_native_function("add").unwrap();
+    assert_eq!(add.call(2, 3).unwrap(), 5);
+}
+
+#[test]
+fn compile_with_profile() {
+    let wasm = slow_to_compile_contract(3, 100);
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+
+    let (_, profile) = Engine::compile_with_profile(&engine, &wasm, store.tunables()).unwrap();
+    assert!(profile.validation > Duration::from_secs(0));
+    assert!(profile.translation > Duration::from_secs(0));
+    assert!(profile.function_compilation_min > Duration::from_secs(0));
+    assert!(profile.function_compilation_min <= profile.function_compilation_max);
+    assert!(profile.function_compilation_max <= profile.function_compilation_total);
+    assert!(profile.trampoline_generation > Duration::from_secs(0));
+    assert_eq!(profile.code_allocation, Duration::from_secs(0));
+    assert_eq!(profile.relocation, Duration::from_secs(0));
+
+    let (executable, mut profile) = engine
+        .compile_universal_with_profile(&wasm, store.tunables())
+        .unwrap();
+    engine
+        .load_universal_executable_with_profile(&executable, &mut profile)
+        .unwrap();
+    assert!(profile.function_compilation_total > Duration::from_secs(0));
+    assert!(profile.code_allocation > Duration::from_secs(0));
+
+    assert!(Engine::compile_with_profile(&engine, b"\0asm", store.tunables()).is_err());
+}
+
+#[test]
+fn instantiate_with_profile() {
+    let store = Store::new(&Universal::new(Singlepass::default()).engine());
+    let module = Module::new(
+        &store,
+        r#"
+        (import "host" "hit" (func $hit))
+        (memory 1)
+        (table 1 funcref)
+        (global (mut i32) (i32.const 0))
+        (data (i32.const 0) "hello")
+        (func $start
+          (local $i i32)
+          (loop $again
+            call $hit
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $again (i32.lt_u (local.get $i) (i32.const 1000)))))
+        (start $start)
+    "#,
+    )
+    .unwrap();
+    static HITS: AtomicUsize = AtomicUsize::new(0);
+    let hit = Function::new_native(&store, || {
+        HITS.fetch_add(1, SeqCst);
+    });
+    let imports = imports! { "host" => { "hit" => hit } };
+
+    let (_instance, profile) =
+        Instance::new_with_profile(&module, wasmer_types::InstanceConfig::default(), &imports)
+            .unwrap();
+    assert_eq!(HITS.load(SeqCst), 1000);
+    // Single phases can be too quick for the clock, but not all of them.
+    let total = profile.import_resolution
+        + profile.memory_creation
+        + profile.table_creation
+        + profile.global_creation
+        + profile.initialization
+        + profile.start_function;
+    assert!(total > Duration::from_secs(0));
+
+    // Nothing is timed unless the config asks for it.
+    let engine = Universal::new(Singlepass::default()).engine();
+    let wasm = wat2wasm(b"(module (memory 1))").unwrap();
+    let executable = engine.compile_universal(&wasm, store.tunables()).unwrap();
+    let artifact = Arc::new(engine.load_universal_executable(&executable).unwrap());
+    let mut profile = wasmer_vm::InstantiationProfile::default();
+    let handle = unsafe {
+        artifact.instantiate_with_profile(
+            store.tunables(),
+            &imports! {},
+            Box::new(()),
+            wasmer_types::InstanceConfig::default(),
+            &mut profile,
+        )
+    }
+    .unwrap();
+    unsafe { handle.finish_instantiation_with_profile(&mut profile) }.unwrap();
+    assert_eq!(profile, wasmer_vm::InstantiationProfile::default());
+}
+
+#[test]
+fn resolved_imports_of_another_module_are_rejected() {
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let load = |wat: &str| {
+        let wasm = wat2wasm(wat.as_bytes()).unwrap();
+        let executable = engine.compile_universal(&wasm, store.tunables()).unwrap();
+        Arc::new(engine.load_universal_executable(&executable).unwrap())
+    };
+    let imports = imports! { "env" => { "g" => Global::new(&store, Val::I32(1)) } };
+    let resolved = load(r#"(module (import "env" "g" (global i32)))"#)
+        .resolve_imports(&imports)
+        .unwrap();
+
+    for wat in [
+        r#"(module (import "env" "g" (global i64)))"#,
+        r#"(module (import "env" "g" (func)))"#,
+        r#"(module)"#,
+    ] {
+        let instance = unsafe {
+            load(wat).instantiate_resolved(
+                store.tunables(),
+                &resolved,
+                Box::new(()),
+                wasmer_types::InstanceConfig::default(),
+            )
+        };
+        assert!(matches!(
+            instance.err(),
+            Some(wasmer_engine::InstantiationError::Link(
+                wasmer_engine::LinkError::ResolvedForAnotherModule
+            ))
+        ));
+    }
+}
+
+#[test]
+fn passive_data_is_dropped_per_instance() {
+    let store = Store::new(&Universal::new(Singlepass::default()).engine());
+    let module = Module::new(
+        &store,
+        r#"
+        (memory 1)
+        (data "data")
+        (func (export "init") (param i32) (result i32)
+          (memory.init 0 (i32.const 0) (i32.const 0) (local.get 0))
+          (i32.load (i32.const 0)))
+        (func (export "drop") (data.drop 0))
+    "#,
+    )
+    .unwrap();
+    let init = |instance: &Instance, len: i32| {
+        instance
+            .lookup_function("init")
+            .unwrap()
+            .call(&[Val::I32(len)])
+            .map(|results| results[0].unwrap_i32())
+    };
+    let data = i32::from_le_bytes(*b"data");
+
+    let dropping = Instance::new(&module, &imports! {}).unwrap();
+    let other = Instance::new(&module, &imports! {}).unwrap();
+    assert_eq!(init(&dropping, 4).unwrap(), data);
+    dropping.lookup_function("drop").unwrap().call(&[]).unwrap();
+    // Only empty ranges of a dropped segment can be copied.
+    assert_eq!(init(&dropping, 0).unwrap(), data);
+    let trap = init(&dropping, 4).unwrap_err();
+    assert_eq!(trap.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    // The segment is still there for other instances, and new ones.
+    assert_eq!(init(&other, 4).unwrap(), data);
+    let new = Instance::new(&module, &imports! {}).unwrap();
+    assert_eq!(init(&new, 4).unwrap(), data);
+}
+
+#[test]
+fn evaluate_global_initializers() {
+    use wasmer_types::entity::{EntityRef, PrimaryMap};
+    use wasmer_types::{FunctionIndex, LocalGlobalIndex, Value};
+
+    let store = Store::new(&Universal::new(Singlepass::default()).engine());
+    let module = Module::new(
+        &store,
+        r#"
+        (import "env" "base" (global $base i64))
+        (global i32 (i32.const 42))
+        (global $copy i64 (global.get $base))
+        (global funcref (ref.func $f))
+        (global funcref (ref.null func))
+        (func $f)
+    "#,
+    )
+    .unwrap();
+
+    let base = Arc::new(wasmer_vm::Global::new(GlobalType::new(
+        Type::I64,
+        Mutability::Const,
+    )));
+    unsafe { base.set_unchecked(Val::I64(-7)) }.unwrap();
+    let mut globals = PrimaryMap::new();
+    globals.push(wasmer_vm::VMGlobalImport {
+        definition: base.vmglobal(),
+        from: Arc::clone(&base),
+    });
+    let imports = wasmer_vm::Imports::new(
+        PrimaryMap::new(),
+        PrimaryMap::new(),
+        PrimaryMap::new(),
+        PrimaryMap::new(),
+        globals,
+    );
+
+    let init = |index| unsafe {
+        module
+            .artifact()
+            .evaluate_global_init(LocalGlobalIndex::new(index), &imports)
+    };
+    assert!(matches!(init(0), Ok(Value::I32(42))));
+    assert!(matches!(init(1), Ok(Value::I64(-7))));
+    assert!(matches!(init(2), Ok(Value::FuncRef(Some(f))) if f == FunctionIndex::new(0)));
+    assert!(matches!(init(3), Ok(Value::FuncRef(None))));
+    assert!(matches!(
+        init(4),
+        Err(wasmer_vm::GlobalInitError::UnknownGlobal(_))
+    ));
+
+    // Imports that don't match the module are reported rather than read.
+    let missing = wasmer_vm::Imports::new(
+        PrimaryMap::new(),
+        PrimaryMap::new(),
+        PrimaryMap::new(),
+        PrimaryMap::new(),
+        PrimaryMap::new(),
+    );
+    let init = unsafe {
+        module
+            .artifact()
+            .evaluate_global_init(LocalGlobalIndex::new(1), &missing)
+    };
+    assert!(matches!(
+        init,
+        Err(wasmer_vm::GlobalInitError::MissingImport(_))
+    ));
+}
+
+#[test]
+fn stack_balanced_on_every_return() {
+    // Enough values are kept alive for some of them to be spilled to the
+    // stack, which debug builds check is unwound on every path to the end.
+    let wat = r#"
+       (func (export "nested") (param i32) (result i32)
+         local.get 0 local.get 0 local.get 0 local.get 0
+         local.get 0 local.get 0 local.get 0 local.get 0
+         (if (i32.eq (local.get 0) (i32.const 1)) (then (i32.const -1) (return)))
+         i32.add i32.add i32.add i32.add
+         i32.add i32.add i32.add)
+       (func (export "top") (param i32) (result i32)
+         local.get 0 local.get 0 local.get 0 local.get 0
+         local.get 0 local.get 0 local.get 0 local.get 0
+         return)
+    "#;
+    let store = Store::new(&Universal::new(Singlepass::default()).engine());
+    let module = Module::new(&store, wat).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    let nested: NativeFunc<i32, i32> = instance.get_native_function("nested").unwrap();
+    assert_eq!(nested.call(1).unwrap(), -1);
+    assert_eq!(nested.call(2).unwrap(), 16);
+    let top: NativeFunc<i32, i32> = instance.get_native_function("top").unwrap();
+    assert_eq!(top.call(3).unwrap(), 3);
+}
+
+#[test]
+fn required_features() {
+    let simd = wat2wasm(
+        br#"(func (export "zero") (result v128)
+             v128.const i64x2 0 0)"#,
+    )
+    .unwrap();
+    let mut no_simd = Features::new();
+    no_simd.simd(false);
+    assert!(matches!(
+        wasmer_compiler::validate_module(&no_simd, &simd),
+        Err(CompileError::Validate(e)) if e.contains("offset")
+    ));
+    wasmer_compiler::validate_module(&Features::new(), &simd).unwrap();
+    // As `wasmer validate --features` does.
+    let listed = Features::from_names("bulk_memory,multi_value".split(',')).unwrap();
+    assert!(wasmer_compiler::validate_module(&listed, &simd).is_err());
+    let listed = Features::from_names("simd".split(',')).unwrap();
+    wasmer_compiler::validate_module(&listed, &simd).unwrap();
+    let required = wasmer_compiler::required_features(&simd).unwrap();
+    assert_eq!(required.enabled(), vec!["simd"]);
+
+    let mvp = wat2wasm(br#"(func (export "one") (result i32) i32.const 1)"#).unwrap();
+    let required = wasmer_compiler::required_features(&mvp).unwrap();
+    assert!(required.enabled().is_empty());
+    assert!(wasmer_compiler::required_features(b"\0asm").is_err());
+}
+
+#[test]
+fn stable_engine_id() {
+    let first = Universal::new(Singlepass::default()).engine();
+    let second = Universal::new(Singlepass::default()).engine();
+    assert_ne!(first.id(), second.id());
+    assert_eq!(first.id().stable_id(), second.id().stable_id());
+    assert_eq!(
+        first.id().stable_id(),
+        wasmer_engine::EngineId::new(&Target::default(), Some("singlepass")).stable_id()
+    );
+    assert!(first
+        .id()
+        .stable_id()
+        .starts_with(&Triple::host().to_string()));
+    // Code compiled for other CPU features comes from another engine.
+    let other = Universal::new(Singlepass::default())
+        .target(Target::new(Triple::host(), CpuFeature::set()))
+        .engine();
+    assert_ne!(other.id().stable_id(), first.id().stable_id());
+    assert_eq!(
+        other.id().stable_id(),
+        format!("{}/singlepass", Triple::host())
+    );
+    let headless = Universal::headless().engine();
+    assert_eq!(
+        headless.id().stable_id(),
+        wasmer_engine::EngineId::new(&Target::default(), None).stable_id()
+    );
+}
+
+/// Hands out a binary a few bytes at a time, counting the bytes read.
+struct TrickleReader {
+    binary: Vec<u8>,
+    read: Arc<AtomicUsize>,
+}
+
+impl Read for TrickleReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let start = self.read.load(SeqCst);
+        let end = self.binary.len().min(start + 16).min(start + buf.len());
+        buf[..end - start].copy_from_slice(&self.binary[start..end]);
+        self.read.store(end, SeqCst);
+        Ok(end - start)
+    }
+}
+
+#[test]
+fn compile_stream() {
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let stream = |binary: Vec<u8>| {
+        let read = Arc::new(AtomicUsize::new(0));
+        let reader = TrickleReader {
+            binary,
+            read: read.clone(),
+        };
+        (engine.compile_stream(reader, store.tunables()), read)
+    };
+
+    let wasm = slow_to_compile_contract(3, 100);
+    let (streamed, read) = stream(wasm.clone());
+    assert_eq!(read.load(SeqCst), wasm.len());
+    let compiled = engine.compile(&wasm, store.tunables()).unwrap();
+    assert_eq!(
+        streamed.unwrap().serialize().unwrap(),
+        compiled.serialize().unwrap()
+    );
+
+    // The invalid first body is rejected before the next ones are read.
+    let mut wat = String::from("(module (func i32.add drop)");
+    for _ in 0..100 {
+        wat.push_str("(func (local i64)");
+        wat.push_str(&"i64.const 1 local.set 0 ".repeat(50));
+        wat.push(')');
+    }
+    wat.push(')');
+    let invalid = wat2wasm(wat.as_bytes()).unwrap().to_vec();
+    let (streamed, read) = stream(invalid.clone());
+    assert!(matches!(streamed, Err(CompileError::Validate(_))));
+    assert!(read.load(SeqCst) < invalid.len() / 10);
+
+    let (streamed, _) = stream(b"\0asm\x01\0\0\0\x01".to_vec());
+    assert!(streamed.is_err());
+}
+
+#[test]
+fn compile_for_another_target() {
+    let store = Store::new(&Universal::new(Singlepass::default()).engine());
+    let wat = r#"(func (export "add") (param i32 i32) (result i32)
+        local.get 0
+        local.get 1
+        i32.add)"#;
+    let features = CpuFeature::AVX | CpuFeature::SSE2;
+    // Target the calling convention the host doesn't use.
+    let triple = if cfg!(windows) {
+        "x86_64-unknown-linux-gnu"
+    } else {
+        "x86_64-pc-windows-msvc"
+    };
+    let target = Target::new(triple.parse().unwrap(), features);
+    let executable = Module::compile_for(&store, wat, &target).unwrap();
+    assert_eq!(executable.cpu_features(), features);
+    let serialized = executable.serialize().unwrap();
+    let deserialized =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized) }
+            .unwrap();
+    assert_eq!(deserialized.cpu_features(), features);
+
+    // The host uses another calling convention, so its code differs.
+    let host = Module::compile_for(&store, wat, store.engine().target()).unwrap();
+    assert_ne!(host.serialize().unwrap(), serialized);
+
+    let arm = Target::new("aarch64-unknown-linux-gnu".parse().unwrap(), features);
+    assert!(matches!(
+        Module::compile_for(&store, wat, &arm),
+        Err(CompileError::UnsupportedTarget(_))
+    ));
+    assert!(Module::compile_for(&store, "(func i32.add)", &target).is_err());
+}
+
+#[test]
+fn bit_counting() {
+    let wat = r#"
+        (func (export "i32.clz") (param i32) (result i32) (i32.clz (local.get 0)))
+        (func (export "i32.ctz") (param i32) (result i32) (i32.ctz (local.get 0)))
+        (func (export "i32.popcnt") (param i32) (result i32) (i32.popcnt (local.get 0)))
+        (func (export "i64.clz") (param i64) (result i64) (i64.clz (local.get 0)))
+        (func (export "i64.ctz") (param i64) (result i64) (i64.ctz (local.get 0)))
+        (func (export "i64.popcnt") (param i64) (result i64) (i64.popcnt (local.get 0)))
+    "#;
+    // Without LZCNT and BMI1, singlepass falls back to `bsr` and `bsf`.
+    let fallback = Target::new(
+        Target::default().triple().clone(),
+        CpuFeature::for_host() - CpuFeature::LZCNT - CpuFeature::BMI1,
+    );
+    for target in [Target::default(), fallback] {
+        let engine = Universal::new(Singlepass::default())
+            .target(target)
+            .engine();
+        let store = Store::new(&engine);
+        let module = Module::new(&store, wat).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let i32_op = |name| -> NativeFunc<i32, i32> { instance.get_native_function(name).unwrap() };
+        let i64_op = |name| -> NativeFunc<i64, i64> { instance.get_native_function(name).unwrap() };
+        for &x in &[0, 1, 0xf0, i32::MIN, -1] {
+            assert_eq!(i32_op("i32.clz").call(x).unwrap(), x.leading_zeros() as i32);
+            assert_eq!(
+                i32_op("i32.ctz").call(x).unwrap(),
+                x.trailing_zeros() as i32
+            );
+            assert_eq!(i32_op("i32.popcnt").call(x).unwrap(), x.count_ones() as i32);
+        }
+        for &x in &[0, 1, 0xf0, 1 << 40, i64::MIN, -1] {
+            assert_eq!(i64_op("i64.clz").call(x).unwrap(), x.leading_zeros() as i64);
+            assert_eq!(
+                i64_op("i64.ctz").call(x).unwrap(),
+                x.trailing_zeros() as i64
+            );
+            assert_eq!(i64_op("i64.popcnt").call(x).unwrap(), x.count_ones() as i64);
+        }
+    }
+}
+
+#[test]
+fn code_for_missing_cpu_features_is_rejected() {
+    let missing = [
+        CpuFeature::LZCNT,
+        CpuFeature::BMI1,
+        CpuFeature::AVX2,
+        CpuFeature::AVX512F,
+    ]
+    .iter()
+    .copied()
+    .find(|&feature| !CpuFeature::for_host().contains(feature));
+    let missing = match missing {
+        Some(missing) => missing,
+        // The host has all of them, so any code can be loaded.
+        None => return,
+    };
+    let target = Target::new(
+        Target::default().triple().clone(),
+        CpuFeature::for_host() | missing,
+    );
+    let engine = Universal::new(Singlepass::default())
+        .target(target)
+        .engine();
+    let wasm = wat2wasm(br#"(module (func (export "run")))"#).unwrap();
+    let executable = engine
+        .compile_universal(&wasm, &BaseTunables::for_target(engine.target()))
+        .unwrap();
+    match engine.load_universal_executable(&executable) {
+        Err(CompileError::UnsupportedTarget(message)) => {
+            assert!(message.contains(&format!("{:?}", missing)), "{}", message)
+        }
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("code for missing CPU features should not load"),
+    }
+}
+
+#[test]
+fn engine_with_features() {
+    let mut features = Features::default();
+    features.bulk_memory(true).multi_value(false);
+    let engine = Universal::new(Singlepass::default())
+        .with_features(features.clone())
+        .engine();
+    assert_eq!(engine.features(), features);
+    let store = Store::new(&engine);
+    let wat = r#"
+        (memory 1)
+        (data $hello "hello")
+        (func (export "run")
+            (memory.init $hello (i32.const 0) (i32.const 0) (i32.const 5))
+            (memory.copy (i32.const 8) (i32.const 0) (i32.const 5))
+            (memory.fill (i32.const 0) (i32.const 0) (i32.const 5))
+            (data.drop $hello))
+        (func (export "load") (param i32) (result i32)
+            (i32.load8_u (local.get 0)))
+    "#;
+    let module = Module::new(&store, wat).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    instance.lookup_function("run").unwrap().call(&[]).unwrap();
+    let load: NativeFunc<i32, i32> = instance.get_native_function("load").unwrap();
+    let contents: Vec<u8> = (0..13)
+        .map(|address| load.call(address).unwrap() as u8)
+        .collect();
+    assert_eq!(contents, b"\0\0\0\0\0\0\0\0hello");
+
+    // Without bulk memory, the module doesn't validate.
+    features.bulk_memory(false);
+    let engine = Universal::new(Singlepass::default())
+        .with_features(features.clone())
+        .engine();
+    assert!(Module::new(&Store::new(&engine), wat).is_err());
+
+    // Features the compiler doesn't support are rejected when compiling.
+    features.multi_value(true);
+    let engine = Universal::new(Singlepass::default())
+        .with_features(features)
+        .engine();
+    assert!(matches!(
+        Module::new(&Store::new(&engine), "(func)"),
+        Err(CompileError::UnsupportedFeature(_))
+    ));
 }
@@ -1,7 +1,11 @@
 use anyhow::Result;
-use wasmer::{wat2wasm, BaseTunables, Engine};
+use wasmer::{
+    imports, wat2wasm, BaseTunables, Engine, FromToNativeWasmType, Instance, Module, Store,
+    WasmTypeList,
+};
 use wasmer_compiler_singlepass::Singlepass;
 use wasmer_engine_universal::Universal;
+use wasmer_vm::TrapCode;
 
 fn compile_and_compare(wasm: &[u8]) -> Result<()> {
     let compiler = Singlepass::default();
@@ -46,3 +50,187 @@ fn deterministic_table() -> Result<()> {
 
     compile_and_compare(&wasm_bytes)
 }
+
+#[test]
+fn deterministic_register_pressure() -> Result<()> {
+    // Keeps many integer and float values live across calls, so that
+    // used registers are spilled and restored around each call site.
+    let wasm_bytes = wat2wasm(
+        br#"
+(module
+  (func $callee (param i64 f64) (result i64)
+    (i64.add (local.get 0) (i64.trunc_f64_s (local.get 1))))
+  (func (export "main") (param i64 i64 i64 f64 f64 f64) (result i64)
+    (i64.add
+      (i64.mul (local.get 0) (local.get 1))
+      (i64.add
+        (call $callee (local.get 2) (f64.mul (local.get 3) (local.get 4)))
+        (i64.add
+          (i64.sub (local.get 1) (local.get 2))
+          (i64.add
+            (call $callee
+              (i64.xor (local.get 0) (local.get 2))
+              (f64.add (f64.sub (local.get 5) (local.get 3)) (local.get 4)))
+            (i64.trunc_f64_s
+              (f64.div
+                (f64.add (local.get 3) (local.get 5))
+                (f64.mul (local.get 4) (local.get 5)))))))))
+  )
+"#,
+    )?;
+
+    compile_and_compare(&wasm_bytes)
+}
+
+/// Inputs around the edges of every integer range, in both float widths.
+fn conversion_inputs() -> Vec<f64> {
+    let edges: [f64; 13] = [
+        0.5,
+        0.99,
+        1.0,
+        2147483647.0,
+        2147483647.5,
+        2147483648.0,
+        2147483648.5,
+        2147483649.0,
+        4294967295.0,
+        4294967295.5,
+        4294967296.0,
+        9223372036854775807.0,
+        18446744073709551615.0,
+    ];
+    let mut inputs = vec![
+        f64::NAN,
+        -f64::NAN,
+        f64::from_bits(0x7ff4_0000_0000_0000),
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        0.0,
+        -0.0,
+    ];
+    for &edge in edges.iter() {
+        for &edge in [edge, -edge].iter() {
+            let bits = edge.to_bits();
+            inputs.push(edge);
+            inputs.push(f64::from_bits(bits + 1));
+            inputs.push(f64::from_bits(bits - 1));
+            let bits = (edge as f32).to_bits();
+            inputs.push(f32::from_bits(bits + 1).into());
+            inputs.push(f32::from_bits(bits - 1).into());
+        }
+    }
+    inputs
+}
+
+/// Calls the `op` export on every input and checks it against `expected`.
+fn check_conversion<A, R>(
+    instance: &Instance,
+    op: &str,
+    input: impl Fn(f64) -> A,
+    expected: impl Fn(A) -> Result<R, TrapCode>,
+) where
+    A: FromToNativeWasmType + WasmTypeList + Copy + std::fmt::Debug,
+    R: FromToNativeWasmType + WasmTypeList + PartialEq + std::fmt::Debug,
+{
+    let f = instance.get_native_function::<A, R>(op).unwrap();
+    for x in conversion_inputs().into_iter().map(input) {
+        let result = f.call(x).map_err(|e| e.to_trap().unwrap());
+        assert_eq!(result, expected(x), "{} {:?}", op, x);
+    }
+}
+
+/// The result of a trapping conversion: NaN is an invalid conversion, and
+/// anything whose truncation falls outside `min..2^bits` overflows.
+fn trunc<R>(x: f64, min: f64, bits: i32, convert: impl Fn(f64) -> R) -> Result<R, TrapCode> {
+    if x.is_nan() {
+        Err(TrapCode::BadConversionToInteger)
+    } else if x.trunc() < min || x.trunc() >= 2f64.powi(bits) {
+        Err(TrapCode::IntegerOverflow)
+    } else {
+        Ok(convert(x))
+    }
+}
+
+#[test]
+fn float_to_int_conversions() {
+    let mut wat = String::from("(module");
+    for (result, op, param) in [
+        ("i32", "i32.trunc_f32_s", "f32"),
+        ("i32", "i32.trunc_f32_u", "f32"),
+        ("i32", "i32.trunc_f64_s", "f64"),
+        ("i32", "i32.trunc_f64_u", "f64"),
+        ("i64", "i64.trunc_f32_s", "f32"),
+        ("i64", "i64.trunc_f32_u", "f32"),
+        ("i64", "i64.trunc_f64_s", "f64"),
+        ("i64", "i64.trunc_f64_u", "f64"),
+        ("i32", "i32.trunc_sat_f32_s", "f32"),
+        ("i32", "i32.trunc_sat_f32_u", "f32"),
+        ("i32", "i32.trunc_sat_f64_s", "f64"),
+        ("i32", "i32.trunc_sat_f64_u", "f64"),
+        ("i64", "i64.trunc_sat_f32_s", "f32"),
+        ("i64", "i64.trunc_sat_f32_u", "f32"),
+        ("i64", "i64.trunc_sat_f64_s", "f64"),
+        ("i64", "i64.trunc_sat_f64_u", "f64"),
+    ]
+    .iter()
+    {
+        wat.push_str(&format!(
+            r#"(func (export "{op}") (param {p}) (result {r}) local.get 0 {op})"#,
+            op = op,
+            p = param,
+            r = result,
+        ));
+    }
+    wat.push(')');
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let module = Module::new(&store, wat).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+
+    let f32_in = |x: f64| x as f32;
+    let f64_in = |x: f64| x;
+    let (i32_min, i64_min) = (-2147483648.0, -9223372036854775808.0);
+    check_conversion(&instance, "i32.trunc_f32_s", f32_in, |x| {
+        trunc(x.into(), i32_min, 31, |x| x as i32)
+    });
+    check_conversion(&instance, "i32.trunc_f32_u", f32_in, |x| {
+        trunc(x.into(), 0.0, 32, |x| x as u32 as i32)
+    });
+    check_conversion(&instance, "i32.trunc_f64_s", f64_in, |x| {
+        trunc(x, i32_min, 31, |x| x as i32)
+    });
+    check_conversion(&instance, "i32.trunc_f64_u", f64_in, |x| {
+        trunc(x, 0.0, 32, |x| x as u32 as i32)
+    });
+    check_conversion(&instance, "i64.trunc_f32_s", f32_in, |x| {
+        trunc(x.into(), i64_min, 63, |x| x as i64)
+    });
+    check_conversion(&instance, "i64.trunc_f32_u", f32_in, |x| {
+        trunc(x.into(), 0.0, 64, |x| x as u64 as i64)
+    });
+    check_conversion(&instance, "i64.trunc_f64_s", f64_in, |x| {
+        trunc(x, i64_min, 63, |x| x as i64)
+    });
+    check_conversion(&instance, "i64.trunc_f64_u", f64_in, |x| {
+        trunc(x, 0.0, 64, |x| x as u64 as i64)
+    });
+
+    // Rust's float to int casts saturate, and map NaN to zero, like the
+    // saturating WebAssembly conversions.
+    check_conversion(&instance, "i32.trunc_sat_f32_s", f32_in, |x| Ok(x as i32));
+    check_conversion(&instance, "i32.trunc_sat_f32_u", f32_in, |x| {
+        Ok(x as u32 as i32)
+    });
+    check_conversion(&instance, "i32.trunc_sat_f64_s", f64_in, |x| Ok(x as i32));
+    check_conversion(&instance, "i32.trunc_sat_f64_u", f64_in, |x| {
+        Ok(x as u32 as i32)
+    });
+    check_conversion(&instance, "i64.trunc_sat_f32_s", f32_in, |x| Ok(x as i64));
+    check_conversion(&instance, "i64.trunc_sat_f32_u", f32_in, |x| {
+        Ok(x as u64 as i64)
+    });
+    check_conversion(&instance, "i64.trunc_sat_f64_s", f64_in, |x| Ok(x as i64));
+    check_conversion(&instance, "i64.trunc_sat_f64_u", f64_in, |x| {
+        Ok(x as u64 as i64)
+    });
+}
@@ -1,6 +1,7 @@
 use std::ptr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
+use std::sync::{Arc, Mutex};
 use wasmer::*;
 use wasmer_compiler_singlepass::Singlepass;
 use wasmer_engine_universal::Universal;
@@ -124,7 +125,7 @@ fn test_gas_intrinsic_in_start() {
     assert!(result.is_err());
     match result {
         Err(InstantiationError::Start(runtime_error)) => {
-            assert_eq!(runtime_error.message(), "gas limit exceeded")
+            assert!(runtime_error.is_out_of_gas())
         }
         _ => assert!(false),
     }
@@ -192,6 +193,139 @@ fn test_gas_intrinsic_regular() {
     assert_eq!(gas_counter.burnt(), 10_000_000_000_000_726);
 }
 
+#[test]
+fn test_gas_intrinsic_cost_multiplier() {
+    let store = get_store();
+    let mut gas_counter = FastGasCounter::new(1000, 3);
+    let module = get_module(&store);
+    let instance = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_counter(ptr::addr_of_mut!(gas_counter)) },
+        &imports! {
+            "host" => {
+                "func" => Function::new_native(&store, || {}),
+                "has" => Function::new_native(&store, |_: i32| {}),
+                "gas" => Function::new_native(&store, |_: i32| {}),
+            },
+        },
+    )
+    .unwrap();
+    let bar_func = instance
+        .lookup_function("bar")
+        .expect("expected function bar");
+    bar_func.call(&[]).unwrap();
+    assert_eq!(gas_counter.burnt(), 300);
+    // The new cost applies to the already compiled code.
+    gas_counter.set_cost_multiplier(5);
+    bar_func.call(&[]).unwrap();
+    assert_eq!(gas_counter.burnt(), 800);
+    gas_counter.set_cost_multiplier(3);
+    let e = bar_func.call(&[]).unwrap_err();
+    assert!(e.is_out_of_gas());
+    assert_eq!(gas_counter.burnt(), 1100);
+}
+
+/// Charges `count` gas units at each cost of `costs` in turn, starting from
+/// 300 gas burnt out of 1000, and returns the outcome of each charge along
+/// with the gas burnt after it.
+fn charge_at_costs(
+    store: &Store,
+    count: u32,
+    costs: &[u64],
+) -> Vec<(Result<(), RuntimeError>, u64)> {
+    let module = Module::new(
+        store,
+        format!(
+            r#"
+        (import "host" "gas" (func (param i32)))
+        (func (export "charge")
+            i32.const {}
+            call 0
+        )
+    "#,
+            count
+        ),
+    )
+    .unwrap();
+    let mut gas_counter = FastGasCounter::new(1000, 1);
+    gas_counter.burnt_gas = 300;
+    let instance = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_counter(ptr::addr_of_mut!(gas_counter)) },
+        &imports! {
+            "host" => {
+                "gas" => Function::new_native(store, |_: i32| {}),
+            },
+        },
+    )
+    .unwrap();
+    let charge = instance.lookup_function("charge").unwrap();
+    costs
+        .iter()
+        .map(|&cost| {
+            gas_counter.set_cost_multiplier(cost);
+            let result = charge.call(&[]).map(drop);
+            (result, gas_counter.burnt())
+        })
+        .collect()
+}
+
+#[test]
+fn test_gas_intrinsic_overflow() {
+    let store = get_store();
+    // The charge traps when the counter overflows as a signed integer, and
+    // isn't recorded.
+    let charges = charge_at_costs(&store, 1, &[i64::MAX as u64]);
+    let e = charges[0].0.as_ref().unwrap_err();
+    assert_eq!(e.message(), "integer overflow");
+    assert!(!e.is_out_of_gas());
+    assert_eq!(charges[0].1, 300);
+    // A 2^64-1 fee doesn't overflow as a signed integer, and wraps around.
+    let charges = charge_at_costs(&store, 1, &[u64::MAX]);
+    assert!(charges[0].0.is_ok());
+    assert_eq!(charges[0].1, 299);
+}
+
+#[test]
+fn test_gas_intrinsic_saturating_overflow() {
+    let mut compiler = Singlepass::default();
+    compiler.saturate_gas(true);
+    let store = Store::new(&Universal::new(compiler).engine());
+    // A 2^64-1 fee clamps the counter to the limit, and the next charge
+    // runs out of gas.
+    let charges = charge_at_costs(&store, 1, &[u64::MAX, 1]);
+    assert!(charges[0].0.is_ok());
+    assert_eq!(charges[0].1, 1000);
+    assert!(charges[1].0.as_ref().unwrap_err().is_out_of_gas());
+    // So does a cost overflowing when multiplied by the count.
+    let charges = charge_at_costs(&store, 2, &[u64::MAX / 2 + 1, 0]);
+    assert!(charges[0].0.is_ok());
+    assert_eq!(charges[0].1, 1000);
+    assert!(charges[1].0.as_ref().unwrap_err().is_out_of_gas());
+    // Other charges are unchanged.
+    let charges = charge_at_costs(&store, 2, &[100, 300]);
+    assert!(charges[0].0.is_ok());
+    assert_eq!(charges[0].1, 500);
+    assert!(charges[1].0.as_ref().unwrap_err().is_out_of_gas());
+    assert_eq!(charges[1].1, 1100);
+}
+
+#[test]
+fn test_gas_intrinsic_saturation_is_recorded() {
+    let mut compiler = Singlepass::default();
+    compiler.saturate_gas(true);
+    let engine = Universal::new(compiler).engine();
+    assert!(engine
+        .id()
+        .stable_id()
+        .ends_with("/singlepass+saturate-gas"));
+    let store = Store::new(&engine);
+    let module = get_module(&store);
+    assert!(module.metering_info().saturate_gas);
+    let module = get_module(&get_store());
+    assert!(!module.metering_info().saturate_gas);
+}
+
 #[test]
 fn test_gas_intrinsic_default() {
     let store = get_store();
@@ -288,3 +422,243 @@ fn test_gas_intrinsic_tricky() {
     // Ensure "gas" was called.
     assert_eq!(HITS.load(SeqCst), 2);
 }
+
+#[test]
+fn test_gas_intrinsic_metering_info() {
+    let engine = Universal::new(Singlepass::default()).engine();
+    let store = Store::new(&engine);
+    let module = get_module(&store);
+    let expected = [("host".to_string(), "gas".to_string())];
+    assert!(module.metering_info().uses_gas_counter());
+    assert_eq!(module.metering_info().gas_intrinsic_imports, expected);
+
+    // The metering info survives serialization.
+    let wasm = wat::parse_str(r#"(import "host" "gas" (func (param i32)))"#).unwrap();
+    let executable = engine.compile_universal(&wasm, store.tunables()).unwrap();
+    assert_eq!(executable.metering_info().gas_intrinsic_imports, expected);
+    let serialized = wasmer_engine::Executable::serialize(&executable).unwrap();
+    let executable =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized) }
+            .unwrap();
+    assert_eq!(executable.metering_info().gas_intrinsic_imports, expected);
+    let artifact = engine.load_universal_executable_ref(&executable).unwrap();
+    assert_eq!(artifact.metering_info().gas_intrinsic_imports, expected);
+
+    // Imports with other names are regular calls.
+    let module = Module::new(&store, r#"(import "host" "has" (func (param i32)))"#).unwrap();
+    assert!(!module.metering_info().uses_gas_counter());
+}
+
+#[test]
+fn test_gas_intrinsic_module() {
+    // By default, `gas` is an intrinsic whatever the module it is imported
+    // from.
+    let store = get_store();
+    let wat = r#"
+        (import "env" "gas" (func $gas (param i32)))
+        (func (export "run")
+            i32.const 10
+            call $gas)
+    "#;
+    let module = Module::new(&store, wat).unwrap();
+    let expected = [("env".to_string(), "gas".to_string())];
+    assert_eq!(module.metering_info().gas_intrinsic_imports, expected);
+    let mut gas_counter = FastGasCounter::new(1000, 3);
+    let config = unsafe { InstanceConfig::default().with_counter(ptr::addr_of_mut!(gas_counter)) };
+    let imports = imports! {
+        "env" => {
+            "gas" => Function::new_native(&store, |_: i32| {
+                panic!("the call should have been intrinsified")
+            }),
+        },
+    };
+    let instance = Instance::new_with_config(&module, config, &imports).unwrap();
+    instance.lookup_function("run").unwrap().call(&[]).unwrap();
+    assert_eq!(gas_counter.burnt(), 30);
+
+    // It can be restricted to a single module, imports from other modules
+    // are then regular calls.
+    let mut compiler = Singlepass::default();
+    compiler.gas_intrinsic_module("host");
+    let engine = Universal::new(compiler).engine();
+    assert!(engine
+        .id()
+        .stable_id()
+        .ends_with("/singlepass+gas-module=host"));
+    let store = Store::new(&engine);
+    let module = Module::new(&store, wat).unwrap();
+    assert!(!module.metering_info().uses_gas_counter());
+    static HITS: AtomicUsize = AtomicUsize::new(0);
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "gas" => Function::new_native(&store, |_: i32| {
+                    HITS.fetch_add(1, SeqCst);
+                }),
+            },
+        },
+    )
+    .unwrap();
+    instance.lookup_function("run").unwrap().call(&[]).unwrap();
+    assert_eq!(HITS.load(SeqCst), 1);
+    let module = get_module(&store);
+    assert!(module.metering_info().uses_gas_counter());
+}
+
+#[test]
+fn test_gas_intrinsic_signature_mismatch() {
+    let store = get_store();
+    let err = Module::new(&store, r#"(import "host" "gas" (func (param i64)))"#).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("`host`.`gas`"), "{}", message);
+    assert!(message.contains("[I64] -> []"), "{}", message);
+    assert!(message.contains("[I32] -> []"), "{}", message);
+
+    let err = Module::new(&store, r#"(import "env" "gas" (func (param i64)))"#).unwrap_err();
+    assert!(err.to_string().contains("`env`.`gas`"), "{}", err);
+
+    // Imports from other modules than the one of the intrinsic are regular
+    // functions.
+    let mut compiler = Singlepass::default();
+    compiler.gas_intrinsic_module("host");
+    let store = Store::new(&Universal::new(compiler).engine());
+    let module = Module::new(&store, r#"(import "env" "gas" (func (param i64)))"#).unwrap();
+    assert!(!module.metering_info().uses_gas_counter());
+}
+
+#[test]
+fn test_gas_intrinsic_missing_counter() {
+    let store = get_store();
+    let module = get_module_with_start(&store);
+    let result = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_counter(ptr::null_mut()) },
+        &imports! {
+            "host" => {
+                "func" => Function::new_native(&store, || {}),
+                "gas" => Function::new_native(&store, |_: i32| {}),
+            },
+        },
+    );
+    match result {
+        Err(InstantiationError::HostEnvInitialization(HostEnvInitError::MissingGasCounter)) => {}
+        _ => panic!("expected a missing gas counter error"),
+    }
+
+    // Modules that don't meter gas don't need a counter.
+    let module = Module::new(&store, r#"(func (export "foo"))"#).unwrap();
+    let instance = Instance::new_with_config(
+        &module,
+        unsafe { InstanceConfig::default().with_counter(ptr::null_mut()) },
+        &imports! {},
+    )
+    .unwrap();
+    instance.lookup_function("foo").unwrap().call(&[]).unwrap();
+}
+
+#[test]
+fn test_gas_globals() {
+    let store = get_store();
+    let wat = r#"
+        (import "host" "gas" (func $gas (param i32)))
+        (import "host" "report" (func $report (param i64)))
+        (import "env" "burnt_gas" (global $burnt i64))
+        (import "env" "gas_limit" (global $limit i64))
+        (func $remaining (result i64)
+            global.get $limit
+            global.get $burnt
+            i64.sub)
+        (func (export "run")
+            i32.const 10
+            call $gas
+            call $remaining
+            call $report
+            i32.const 20
+            call $gas
+            call $remaining
+            call $report
+        )
+    "#;
+    let module = Module::new(&store, wat).unwrap();
+    #[derive(WasmerEnv, Clone, Default)]
+    struct Reports {
+        remaining: Arc<Mutex<Vec<i64>>>,
+    }
+    let reports = Reports::default();
+    // The instances don't keep the imported globals alive.
+    let placeholder = Global::new(&store, Value::I64(-1));
+    let imports = || {
+        imports! {
+            "host" => {
+                "gas" => Function::new_native(&store, |_: i32| {}),
+                "report" => Function::new_native_with_env(
+                    &store,
+                    reports.clone(),
+                    |reports: &Reports, remaining: i64| {
+                        reports.remaining.lock().unwrap().push(remaining)
+                    },
+                ),
+            },
+            "env" => {
+                "burnt_gas" => placeholder.clone(),
+                "gas_limit" => placeholder.clone(),
+            },
+        }
+    };
+
+    let mut gas_counter = FastGasCounter::new(1000, 3);
+    let config = unsafe {
+        InstanceConfig::default()
+            .with_counter(ptr::addr_of_mut!(gas_counter))
+            .with_gas_globals()
+    };
+    let instance = Instance::new_with_config(&module, config, &imports()).unwrap();
+    instance.lookup_function("run").unwrap().call(&[]).unwrap();
+    assert_eq!(*reports.remaining.lock().unwrap(), [970, 910]);
+    assert_eq!(gas_counter.burnt(), 90);
+    assert_eq!(
+        gas_counter.gas_limit - gas_counter.burnt(),
+        *reports.remaining.lock().unwrap().last().unwrap() as u64
+    );
+
+    // Without the option, the globals are the ones provided.
+    reports.remaining.lock().unwrap().clear();
+    let config = unsafe { InstanceConfig::default().with_counter(ptr::addr_of_mut!(gas_counter)) };
+    let instance = Instance::new_with_config(&module, config, &imports()).unwrap();
+    instance.lookup_function("run").unwrap().call(&[]).unwrap();
+    assert_eq!(*reports.remaining.lock().unwrap(), [0, 0]);
+
+    // With the gas intrinsic restricted to a module, so are the globals.
+    let mut compiler = Singlepass::default();
+    compiler.gas_intrinsic_module("host");
+    let module = Module::new(&Store::new(&Universal::new(compiler).engine()), wat).unwrap();
+    assert_eq!(module.metering_info().gas_module.as_deref(), Some("host"));
+    reports.remaining.lock().unwrap().clear();
+    let config = unsafe {
+        InstanceConfig::default()
+            .with_counter(ptr::addr_of_mut!(gas_counter))
+            .with_gas_globals()
+    };
+    let instance = Instance::new_with_config(&module, config, &imports()).unwrap();
+    instance.lookup_function("run").unwrap().call(&[]).unwrap();
+    assert_eq!(*reports.remaining.lock().unwrap(), [0, 0]);
+
+    // The counter can't be written to.
+    let module = Module::new(&store, r#"(import "env" "burnt_gas" (global (mut i64)))"#).unwrap();
+    let result = Instance::new_with_config(
+        &module,
+        InstanceConfig::default().with_gas_globals(),
+        &imports! {
+            "env" => {
+                "burnt_gas" => Global::new_mut(&store, Value::I64(0)),
+            },
+        },
+    );
+    match result {
+        Err(InstantiationError::Link(LinkError::Import(module, field, _))) => {
+            assert_eq!((&*module, &*field), ("env", "burnt_gas"))
+        }
+        _ => panic!("expected a link error"),
+    }
+}
@@ -0,0 +1,188 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use wasmer::*;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_universal::Universal;
+use wasmer_types::{FunctionIndex, HostCallTracer, InstanceConfig};
+
+fn get_store() -> Store {
+    Store::new(&Universal::new(Singlepass::default()).engine())
+}
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Enter(u32),
+    Exit(u32),
+}
+
+#[derive(Default)]
+struct RecordingTracer {
+    events: Mutex<Vec<Event>>,
+    last: Mutex<Option<Instant>>,
+}
+
+impl RecordingTracer {
+    fn record(&self, event: Event, timestamp: Instant) {
+        let mut last = self.last.lock().unwrap();
+        if let Some(last) = *last {
+            assert!(last <= timestamp);
+        }
+        *last = Some(timestamp);
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+impl HostCallTracer for RecordingTracer {
+    fn on_enter(&self, index: FunctionIndex, timestamp: Instant) {
+        self.record(Event::Enter(index.as_u32()), timestamp);
+    }
+
+    fn on_exit(&self, index: FunctionIndex, timestamp: Instant) {
+        self.record(Event::Exit(index.as_u32()), timestamp);
+    }
+}
+
+fn instantiate(
+    store: &Store,
+    wat: &str,
+    resolver: &dyn Resolver,
+    tracer: &Arc<RecordingTracer>,
+) -> Instance {
+    let module = Module::new(store, wat).unwrap();
+    Instance::new_with_config(
+        &module,
+        InstanceConfig::default().with_host_call_tracer(tracer.clone()),
+        resolver,
+    )
+    .unwrap()
+}
+
+#[test]
+fn host_call_tracer_counts_loop() {
+    let wat = r#"
+        (import "env" "nop" (func $nop))
+        (import "env" "inc" (func $inc (param i32) (result i32)))
+        (func (export "main") (param $n i32) (result i32)
+            (local $acc i32)
+            loop $L0
+                local.get $acc
+                call $inc
+                local.set $acc
+                local.get $n
+                i32.const 1
+                i32.sub
+                local.tee $n
+                br_if $L0
+            end
+            local.get $acc
+        )
+    "#;
+    let store = get_store();
+    let tracer = Arc::new(RecordingTracer::default());
+    let instance = instantiate(
+        &store,
+        wat,
+        &imports! {
+            "env" => {
+                "nop" => Function::new_native(&store, || {}),
+                "inc" => Function::new_native(&store, |x: i32| x + 1),
+            },
+        },
+        &tracer,
+    );
+    let main = instance.lookup_function("main").unwrap();
+    assert_eq!(main.call(&[Value::I32(100)]).unwrap()[0], Value::I32(100));
+
+    let events = tracer.events.lock().unwrap();
+    assert_eq!(events.len(), 200);
+    for pair in events.chunks(2) {
+        assert_eq!(pair, [Event::Enter(1), Event::Exit(1)]);
+    }
+}
+
+#[test]
+fn host_call_tracer_preserves_arguments() {
+    let wat = r#"
+        (import "env" "sum" (func $sum
+            (param i32 i64 f32 f64 i32 i64 i32 i64 f64 i32) (result f64)))
+        (func (export "main") (result f64)
+            (call $sum
+                (i32.const 1) (i64.const 2) (f32.const 3) (f64.const 4) (i32.const 5)
+                (i64.const 6) (i32.const 7) (i64.const 8) (f64.const 9) (i32.const 10))
+        )
+    "#;
+    let store = get_store();
+    let tracer = Arc::new(RecordingTracer::default());
+    let sum = Function::new_native(
+        &store,
+        |a: i32, b: i64, c: f32, d: f64, e: i32, f: i64, g: i32, h: i64, i: f64, j: i32| -> f64 {
+            a as f64
+                + b as f64
+                + c as f64
+                + d
+                + e as f64
+                + f as f64
+                + g as f64
+                + h as f64
+                + i
+                + j as f64 * 100.0
+        },
+    );
+    let instance = instantiate(
+        &store,
+        wat,
+        &imports! {
+            "env" => {
+                "sum" => sum,
+            },
+        },
+        &tracer,
+    );
+    let main = instance.lookup_function("main").unwrap();
+    assert_eq!(main.call(&[]).unwrap()[0], Value::F64(1045.0));
+    assert_eq!(
+        *tracer.events.lock().unwrap(),
+        [Event::Enter(0), Event::Exit(0)]
+    );
+}
+
+#[test]
+fn host_call_tracer_reports_exit_on_trap() {
+    let wat = r#"
+        (import "env" "ok" (func $ok))
+        (import "env" "fail" (func $fail))
+        (func (export "main")
+            call $ok
+            call $fail
+            call $ok
+        )
+    "#;
+    let store = get_store();
+    let tracer = Arc::new(RecordingTracer::default());
+    let fail = Function::new(&store, FunctionType::new(vec![], vec![]), |_| {
+        Err(RuntimeError::new("host failure"))
+    });
+    let instance = instantiate(
+        &store,
+        wat,
+        &imports! {
+            "env" => {
+                "ok" => Function::new_native(&store, || {}),
+                "fail" => fail,
+            },
+        },
+        &tracer,
+    );
+    let main = instance.lookup_function("main").unwrap();
+    let err = main.call(&[]).unwrap_err();
+    assert_eq!(err.message(), "host failure");
+    assert_eq!(
+        *tracer.events.lock().unwrap(),
+        [
+            Event::Enter(0),
+            Event::Exit(0),
+            Event::Enter(1),
+            Event::Exit(1)
+        ]
+    );
+}
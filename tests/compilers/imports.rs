@@ -333,6 +333,7 @@ fn dynamic_function_with_env_wasmer_env_init_works(config: crate::Config) -> Res
                 minimum: 0.into(),
                 maximum: None,
                 shared: false,
+                memory64: false,
             },
         )?,
     };
@@ -389,6 +390,104 @@ fn regression_import_trampolines(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[compiler_test(imports)]
+fn replace_function_import(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (type $t (func (param i32) (result i32)))
+        (import "host" "op" (func $op (type $t)))
+        (table 1 funcref)
+        (elem (i32.const 0) $op)
+        (func (export "direct") (param i32) (result i32)
+            local.get 0
+            call $op)
+        (func (export "indirect") (param i32) (result i32)
+            local.get 0
+            i32.const 0
+            call_indirect (type $t))
+    "#;
+    let module = Module::new(&store, wat)?;
+
+    #[derive(Clone)]
+    struct Env {
+        live: Arc<()>,
+        initialized: bool,
+    }
+    impl WasmerEnv for Env {
+        fn init_with_instance(&mut self, _: &Instance) -> Result<(), HostEnvInitError> {
+            self.initialized = true;
+            Ok(())
+        }
+    }
+    let live = Arc::new(());
+    let env = Env {
+        live: live.clone(),
+        initialized: false,
+    };
+    let add_one = Function::new_native_with_env(&store, env.clone(), |env: &Env, x: i32| {
+        assert!(env.initialized);
+        x + 1
+    });
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "host" => {
+                "op" => add_one,
+            },
+        },
+    )?;
+    let direct: NativeFunc<i32, i32> = instance.get_native_function("direct")?;
+    let indirect: NativeFunc<i32, i32> = instance.get_native_function("indirect")?;
+    assert_eq!(direct.call(3)?, 4);
+    assert_eq!(indirect.call(3)?, 4);
+    assert_eq!(Arc::strong_count(&live), 3);
+
+    // A dynamic function, with a host env of its own that replaces the
+    // previous one.
+    let double = Function::new_with_env(
+        &store,
+        FunctionType::new(vec![Type::I32], vec![Type::I32]),
+        env,
+        |env, args| {
+            assert!(env.initialized);
+            Ok(vec![Value::I32(args[0].unwrap_i32() * 2)])
+        },
+    );
+    unsafe { instance.replace_function_import("host", "op", &double)? };
+    assert_eq!(Arc::strong_count(&live), 3);
+    drop(double);
+    assert_eq!(direct.call(3)?, 6);
+    assert_eq!(indirect.call(3)?, 6);
+
+    let wrong_type = Function::new_native(&store, |x: i64| x);
+    let result = unsafe { instance.replace_function_import("host", "op", &wrong_type) };
+    assert!(matches!(
+        result,
+        Err(LinkError::Import(
+            _,
+            _,
+            wasmer_engine::ImportError::IncompatibleType(..)
+        ))
+    ));
+    let negate = Function::new_native(&store, |x: i32| -x);
+    let result = unsafe { instance.replace_function_import("host", "other", &negate) };
+    assert!(matches!(
+        result,
+        Err(LinkError::Import(
+            _,
+            _,
+            wasmer_engine::ImportError::UnknownImport(..)
+        ))
+    ));
+    assert_eq!(direct.call(3)?, 6);
+
+    unsafe { instance.replace_function_import("host", "op", &negate)? };
+    assert_eq!(Arc::strong_count(&live), 1);
+    assert_eq!(direct.call(3)?, -3);
+    assert_eq!(indirect.call(3)?, -3);
+    Ok(())
+}
+
 // TODO(0-copy): no longer possible to get references to exported entities other than functions
 //               (we don't need that functionality)
 // #[compiler_test(imports)]
@@ -477,3 +576,46 @@ fn regression_import_trampolines(config: crate::Config) -> Result<()> {
 //
 //     Ok(())
 // }
+
+#[compiler_test(imports)]
+fn imported_functions_resolution(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (import "host" "dynamic" (func (param i32)))
+        (import "host" "native" (func (param i32)))
+        (import "host" "last" (func))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let imports = imports! {
+        "host" => {
+            "dynamic" => Function::new(
+                &store,
+                FunctionType::new(vec![Type::I32], vec![]),
+                |_| Ok(vec![]),
+            ),
+            "native" => Function::new_native(&store, |_: i32| {}),
+            "last" => Function::new_native(&store, || {}),
+        },
+    };
+    let instance = Instance::new(&module, &imports)?;
+    let imported = instance.imported_functions();
+    let names = imported
+        .iter()
+        .map(|(module, field, _)| (&**module, &**field))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [("host", "dynamic"), ("host", "native"), ("host", "last")]
+    );
+
+    let artifact = module.artifact();
+    let trampoline = |index| {
+        *artifact
+            .dynamic_function_trampoline(FunctionIndex::from_u32(index))
+            .unwrap()
+    };
+    assert_eq!(imported[0].2, trampoline(0));
+    assert_ne!(imported[1].2, trampoline(1));
+    assert_ne!(imported[2].2, trampoline(2));
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use wasmer::*;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_universal::Universal;
+use wasmer_types::InstanceConfig;
+use wasmer_vm::TrapCode;
+
+fn get_store() -> Store {
+    let mut compiler = Singlepass::default();
+    compiler.check_interrupts(true);
+    Store::new(&Universal::new(compiler).engine())
+}
+
+const WAT: &str = r#"
+    (func (export "spin")
+        loop
+            br 0
+        end
+    )
+    (func (export "count") (param $n i32) (result i32)
+        (local $i i32)
+        loop
+            local.get $i
+            i32.const 1
+            i32.add
+            local.tee $i
+            local.get $n
+            i32.ne
+            br_if 0
+        end
+        local.get $i
+    )
+"#;
+
+fn get_instance(store: &Store, flag: &Arc<AtomicBool>) -> Instance {
+    let module = Module::new(store, WAT).unwrap();
+    assert!(module.metering_info().checks_interrupts);
+    Instance::new_with_config(
+        &module,
+        InstanceConfig::default().with_interrupt_flag(flag.clone()),
+        &imports! {},
+    )
+    .unwrap()
+}
+
+#[test]
+fn interrupt_infinite_loop() {
+    let store = get_store();
+    let flag = Arc::new(AtomicBool::new(false));
+    let instance = get_instance(&store, &flag);
+    let spin = instance.lookup_function("spin").unwrap();
+
+    let setter = {
+        let flag = flag.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            flag.store(true, Ordering::SeqCst);
+        })
+    };
+    let err = spin.call(&[]).unwrap_err();
+    setter.join().unwrap();
+    assert_eq!(err.to_trap(), Some(TrapCode::Interrupted));
+}
+
+#[test]
+fn interrupt_flag_unset() {
+    let store = get_store();
+    let flag = Arc::new(AtomicBool::new(false));
+    let instance = get_instance(&store, &flag);
+    let count = instance.lookup_function("count").unwrap();
+    assert_eq!(
+        count.call(&[Value::I32(1000)]).unwrap()[0],
+        Value::I32(1000)
+    );
+
+    // Once set, the next loop iteration traps.
+    flag.store(true, Ordering::SeqCst);
+    let err = count.call(&[Value::I32(1000)]).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::Interrupted));
+
+    // Instances without a flag can't be interrupted.
+    let module = instance.module();
+    let instance = Instance::new(module, &imports! {}).unwrap();
+    let count = instance.lookup_function("count").unwrap();
+    assert_eq!(
+        count.call(&[Value::I32(1000)]).unwrap()[0],
+        Value::I32(1000)
+    );
+}
+
+#[test]
+fn interrupt_flag_needs_checks() {
+    // Code compiled without the checks can't be interrupted.
+    let store = Store::new(&Universal::new(Singlepass::default()).engine());
+    let module = Module::new(&store, WAT).unwrap();
+    assert!(!module.metering_info().checks_interrupts);
+    let flag = Arc::new(AtomicBool::new(false));
+    let config = InstanceConfig::default().with_interrupt_flag(flag);
+    assert!(matches!(
+        Instance::new_with_config(&module, config, &imports! {}),
+        Err(InstantiationError::HostEnvInitialization(
+            HostEnvInitError::InterruptsNotChecked
+        ))
+    ));
+    assert!(Instance::new_with_config(&module, InstanceConfig::default(), &imports! {}).is_ok());
+}
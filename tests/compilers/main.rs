@@ -5,11 +5,15 @@
 #[macro_use]
 extern crate compiler_test_derive;
 
+mod atomics;
 mod config;
 mod deterministic;
 mod fast_gas_metering;
+mod host_call_tracer;
 mod imports;
+mod interrupt;
 mod issues;
+mod memory_allocator;
 // mod multi_value_imports;
 mod compilation;
 mod native_functions;
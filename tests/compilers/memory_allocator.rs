@@ -0,0 +1,212 @@
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+use wasmer::vm::{AllocatedMemory, MemoryAllocator, VMMemoryDefinition, VMTableDefinition};
+use wasmer::*;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_universal::Universal;
+use wasmer_vm::{Memory, MemoryStyle, Table, TableStyle, Tunables};
+
+/// Carves regions out of a single preallocated buffer, one after the other.
+struct Arena {
+    buffer: Mutex<Vec<u8>>,
+    next: Mutex<usize>,
+    released: Mutex<Vec<(usize, usize)>>,
+}
+
+impl Arena {
+    fn new(size: usize) -> Self {
+        Self {
+            buffer: Mutex::new(vec![0xff; size]),
+            next: Mutex::new(0),
+            released: Mutex::new(vec![]),
+        }
+    }
+
+    fn range(&self) -> std::ops::Range<usize> {
+        let buffer = self.buffer.lock().unwrap();
+        let start = buffer.as_ptr() as usize;
+        start..start + buffer.len()
+    }
+}
+
+impl MemoryAllocator for Arena {
+    fn allocate(&self, size: usize) -> Option<NonNull<u8>> {
+        let mut buffer = self.buffer.lock().unwrap();
+        let mut next = self.next.lock().unwrap();
+        if buffer.len() - *next < size {
+            return None;
+        }
+        let region = unsafe { buffer.as_mut_ptr().add(*next) };
+        *next += size;
+        NonNull::new(region)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, size: usize) {
+        self.released
+            .lock()
+            .unwrap()
+            .push((ptr.as_ptr() as usize, size));
+    }
+}
+
+/// Tunables placing every memory in the arena.
+struct ArenaTunables {
+    base: BaseTunables,
+    arena: Arc<Arena>,
+}
+
+impl Tunables for ArenaTunables {
+    fn memory_style(&self, _memory: &MemoryType) -> MemoryStyle {
+        MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+        }
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(Arc::new(AllocatedMemory::new(
+            ty,
+            style,
+            self.arena.clone(),
+        )?))
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(Arc::new(AllocatedMemory::from_definition(
+            ty,
+            style,
+            self.arena.clone(),
+            vm_definition_location,
+        )?))
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_vm_table(ty, style, vm_definition_location)
+    }
+}
+
+#[test]
+fn memories_carved_from_an_arena() {
+    let wat = r#"
+        (memory (export "memory") 1 2)
+        (func (export "store") (param i32 i32)
+            local.get 0
+            local.get 1
+            i32.store8)
+        (func (export "load") (param i32) (result i32)
+            local.get 0
+            i32.load8_u)
+        (func (export "grow") (param i32) (result i32)
+            local.get 0
+            memory.grow)
+    "#;
+    let engine = Universal::new(Singlepass::default()).engine();
+    let arena = Arc::new(Arena::new(4 * WASM_PAGE_SIZE));
+    let tunables = ArenaTunables {
+        base: BaseTunables::for_target(engine.target()),
+        arena: arena.clone(),
+    };
+    let store = Store::new_with_tunables(&engine, tunables);
+    let module = Module::new(&store, wat).unwrap();
+    let first = Instance::new(&module, &imports! {}).unwrap();
+    let second = Instance::new(&module, &imports! {}).unwrap();
+
+    let arena_range = arena.range();
+    for instance in &[&first, &second] {
+        let memory = instance.lookup("memory").unwrap();
+        let memory = match memory {
+            Export::Memory(memory) => memory,
+            _ => panic!("memory is not exported as a memory"),
+        };
+        let base = unsafe { memory.from.vmmemory().as_ref().base } as usize;
+        assert!(arena_range.contains(&base));
+        assert!(arena_range.contains(&(base + 2 * WASM_PAGE_SIZE - 1)));
+    }
+
+    let store_byte = |instance: &Instance, address: i32, value: i32| {
+        let store: NativeFunc<(i32, i32), ()> = instance.get_native_function("store").unwrap();
+        store.call(address, value)
+    };
+    let load_byte = |instance: &Instance, address: i32| {
+        let load: NativeFunc<i32, i32> = instance.get_native_function("load").unwrap();
+        load.call(address)
+    };
+    let grow = |instance: &Instance, delta: i32| {
+        let grow: NativeFunc<i32, i32> = instance.get_native_function("grow").unwrap();
+        grow.call(delta).unwrap()
+    };
+
+    // The regions start zeroed, whatever the arena held before.
+    assert_eq!(load_byte(&first, 0).unwrap(), 0);
+    assert_eq!(load_byte(&second, 0).unwrap(), 0);
+    store_byte(&first, 0, 42).unwrap();
+    assert_eq!(load_byte(&first, 0).unwrap(), 42);
+    assert_eq!(load_byte(&second, 0).unwrap(), 0);
+
+    // Growth stays within the carved maximum, and past it nothing is reachable.
+    let last = 2 * WASM_PAGE_SIZE as i32 - 1;
+    assert!(store_byte(&first, last, 7).is_err());
+    assert_eq!(grow(&first, 1), 1);
+    assert_eq!(grow(&first, 1), -1);
+    assert_eq!(load_byte(&first, last).unwrap(), 0);
+    store_byte(&first, last, 7).unwrap();
+    assert!(store_byte(&first, last + 1, 7).is_err());
+    assert_eq!(grow(&second, 1), 1);
+    assert_eq!(load_byte(&second, 0).unwrap(), 0);
+    assert_eq!(load_byte(&second, last).unwrap(), 0);
+
+    drop(first);
+    drop(second);
+    drop(module);
+    let released = arena.released.lock().unwrap();
+    assert_eq!(released.len(), 2);
+    assert!(released.iter().all(|&(_, size)| size == 2 * WASM_PAGE_SIZE));
+}
+
+#[test]
+fn allocated_memories_need_a_maximum() {
+    let style = MemoryStyle::Dynamic {
+        offset_guard_size: 0,
+    };
+    let arena = Arc::new(Arena::new(WASM_PAGE_SIZE));
+    assert!(matches!(
+        AllocatedMemory::new(&MemoryType::new(1, None, false), &style, arena.clone()),
+        Err(MemoryError::InvalidMemory { .. })
+    ));
+    let guarded = MemoryStyle::Dynamic {
+        offset_guard_size: 0x1_0000,
+    };
+    assert!(matches!(
+        AllocatedMemory::new(&MemoryType::new(1, Some(1), false), &guarded, arena.clone()),
+        Err(MemoryError::InvalidMemory { .. })
+    ));
+    assert!(matches!(
+        AllocatedMemory::new(&MemoryType::new(1, Some(2), false), &style, arena),
+        Err(MemoryError::Region(_))
+    ));
+}
@@ -440,3 +440,39 @@ fn dynamic_host_function_with_env(config: crate::Config) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[compiler_test(native_functions)]
+fn native_function_call_and_catch_trap(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let wat = r#"(module
+        (func $fail (import "env" "fail"))
+        (func (export "div") (param i32 i32) (result i32)
+           (i32.div_u (local.get 0) (local.get 1)))
+        (func (export "call_fail")
+           (call $fail))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let import_object = imports! {
+        "env" => {
+            "fail" => Function::new(&store, FunctionType::new(vec![], vec![]), |_| {
+                Err(RuntimeError::new("host failure"))
+            }),
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+
+    let div: NativeFunc<(i32, i32), i32> = instance.get_native_function("div")?;
+    assert_eq!(div.call_and_catch_trap(6, 3).unwrap(), 2);
+    match div.call_and_catch_trap(6, 0) {
+        Err(TrapOrHostError::Trap(code)) => assert_eq!(code, vm::TrapCode::IntegerDivisionByZero),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let call_fail: NativeFunc<(), ()> = instance.get_native_function("call_fail")?;
+    match call_fail.call_and_catch_trap() {
+        Err(TrapOrHostError::Host(err)) => assert_eq!(err.message(), "host failure"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    Ok(())
+}
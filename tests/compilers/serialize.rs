@@ -85,3 +85,165 @@ fn test_serialize(config: crate::Config) -> Result<()> {
 //     assert_eq!(result.to_vec(), vec![Value::I64(1500)]);
 //     Ok(())
 // }
+
+#[compiler_test(serialize)]
+fn module_hash(config: crate::Config) -> Result<()> {
+    let wasm = wat2wasm(br#"(module (func (export "run")))"#).unwrap();
+    let module = Module::new(&config.store(), &wasm)?;
+    let other_store = config.store();
+    assert_eq!(module.hash(), Module::new(&other_store, &wasm)?.hash());
+    let other_wasm = wat2wasm(br#"(module (func (export "walk")))"#).unwrap();
+    assert_ne!(
+        module.hash(),
+        Module::new(&other_store, &other_wasm)?.hash()
+    );
+
+    // The hash survives a serialization round trip.
+    let engine =
+        wasmer_engine_universal::Universal::new(wasmer_compiler_singlepass::Singlepass::default())
+            .engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    let executable = engine.compile_universal(&wasm, &tunables)?;
+    assert_eq!(executable.module_hash(), module.hash());
+    let serialized = wasmer_engine::Executable::serialize(&executable).unwrap();
+    let deserialized =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized)? };
+    assert_eq!(deserialized.module_hash(), module.hash());
+    let artifact = engine.load_universal_executable_ref(&deserialized)?;
+    assert_eq!(artifact.module_hash(), module.hash());
+    Ok(())
+}
+
+#[test]
+fn deterministic_code() -> Result<()> {
+    let wasm = wat2wasm(
+        br#"
+        (module
+          (import "env" "log" (func $log (param i32)))
+          (memory 1)
+          (func (export "run") (param i32) (result i32)
+            (call $log (local.get 0))
+            (i32.load (local.get 0))))
+    "#,
+    )
+    .unwrap();
+    let other_wasm = wat2wasm(br#"(module (func (export "run")))"#).unwrap();
+    let compile = |wasm: &[u8]| {
+        let engine = wasmer_engine_universal::Universal::new(
+            wasmer_compiler_singlepass::Singlepass::default(),
+        )
+        .engine();
+        let tunables = BaseTunables::for_target(engine.target());
+        engine.compile_universal(wasm, &tunables)
+    };
+    let executable = compile(&wasm)?;
+    assert!(executable.code_equals(&compile(&wasm)?));
+    assert!(!executable.code_equals(&compile(&other_wasm)?));
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn embedded_source_hash(config: crate::Config) -> Result<()> {
+    let wasm = wat2wasm(br#"(module (func (export "run")))"#).unwrap();
+    let engine =
+        wasmer_engine_universal::Universal::new(wasmer_compiler_singlepass::Singlepass::default())
+            .embed_source_hash(true)
+            .engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    let executable = engine.compile_universal(&wasm, &tunables)?;
+    let serialized = wasmer_engine::Executable::serialize(&executable).unwrap();
+    let deserialized =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized)? };
+
+    let features = wasmer_engine::Executable::features(&executable);
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(blake3::hash(&wasm).as_bytes());
+    for name in features.enabled() {
+        hasher.update(name.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.update(&engine.target().cpu_features().as_u64().to_le_bytes());
+    let expected = *hasher.finalize().as_bytes();
+    assert_eq!(deserialized.source_hash(), Some(expected));
+    assert_eq!(executable.source_hash(), Some(expected));
+    assert_eq!(
+        wasmer_engine_universal::source_hash(&wasm, &features, *engine.target().cpu_features()),
+        expected
+    );
+    // The executable loads as usual.
+    let artifact = engine.load_universal_executable_ref(&deserialized)?;
+    assert_eq!(artifact.module_hash(), *blake3::hash(&wasm).as_bytes());
+
+    // Executables compiled without it don't carry any.
+    let store = config.store();
+    let executable = store.engine().compile(&wasm, store.tunables())?;
+    let serialized = executable.serialize().unwrap();
+    let deserialized =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized)? };
+    assert_eq!(deserialized.source_hash(), None);
+    Ok(())
+}
+
+#[test]
+fn deserialized_executables_are_consistent() -> Result<()> {
+    let wasm = wat2wasm(
+        br#"
+        (module
+          (import "env" "log" (func $log (param i32)))
+          (memory 1)
+          (table 1 funcref)
+          (global (export "g") i32 (i32.const 7))
+          (data (i32.const 8) "data")
+          (func $run (export "run") (param i32) (result i32)
+            (call $log (local.get 0))
+            (i32.load (local.get 0)))
+          (start $start)
+          (func $start))
+    "#,
+    )
+    .unwrap();
+    let engine =
+        wasmer_engine_universal::Universal::new(wasmer_compiler_singlepass::Singlepass::default())
+            .engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    let executable = engine.compile_universal(&wasm, &tunables)?;
+    assert_eq!(executable.validate_invariants(), Ok(()));
+    let serialized = wasmer_engine::Executable::serialize(&executable).unwrap();
+    let deserialized =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized)? };
+    assert_eq!(deserialized.to_owned()?.validate_invariants(), Ok(()));
+    Ok(())
+}
+
+#[test]
+fn other_format_versions_are_rejected() -> Result<()> {
+    let wasm = wat2wasm(br#"(module (func (export "run")))"#).unwrap();
+    let engine =
+        wasmer_engine_universal::Universal::new(wasmer_compiler_singlepass::Singlepass::default())
+            .engine();
+    let tunables = BaseTunables::for_target(engine.target());
+    let executable = engine.compile_universal(&wasm, &tunables)?;
+    let mut serialized = wasmer_engine::Executable::serialize(&executable).unwrap();
+    // The format version follows the name in the header.
+    let version = b"\0wasmer-universal".len();
+    serialized[version] = serialized[version].wrapping_add(1);
+    let error =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized) }
+            .err()
+            .expect("the executable should be rejected");
+    assert!(
+        error.to_string().contains("incompatible version"),
+        "{}",
+        error
+    );
+
+    // So do the layout features, such as `compact-passive-data`.
+    serialized[version] = serialized[version].wrapping_sub(1);
+    serialized[version + 1] ^= 1;
+    let error =
+        unsafe { wasmer_engine_universal::UniversalExecutableRef::deserialize(&serialized) }
+            .err()
+            .expect("the executable should be rejected");
+    assert!(error.to_string().contains("layout features"), "{}", error);
+    Ok(())
+}
@@ -40,16 +40,13 @@ fn stack_limit_hit() {
     );
     assert!(instance.is_ok());
     let instance = instance.unwrap();
-    let main_func = instance
-        .lookup_function("main")
+    let main_func: NativeFunc<(), ()> = instance
+        .get_native_function("main")
         .expect("expected function main");
-    match main_func.call(&[]) {
-        Err(err) => {
-            let trap = err.to_trap().unwrap();
-            assert_eq!(trap, TrapCode::StackOverflow);
-        }
-        _ => assert!(false),
-    }
+    assert!(matches!(
+        main_func.call_and_catch_trap(),
+        Err(TrapOrHostError::Trap(TrapCode::WasmStackOverflow))
+    ));
 }
 
 #[test]
@@ -86,16 +83,13 @@ fn stack_limit_operand_stack() {
     );
     assert!(instance.is_ok());
     let instance = instance.unwrap();
-    let main_func = instance
-        .lookup_function("main")
+    let main_func: NativeFunc<(), ()> = instance
+        .get_native_function("main")
         .expect("expected function main");
-    match main_func.call(&[]) {
-        Err(err) => {
-            let trap = err.to_trap().unwrap();
-            assert_eq!(trap, TrapCode::StackOverflow);
-        }
-        _ => assert!(false),
-    }
+    assert!(matches!(
+        main_func.call_and_catch_trap(),
+        Err(TrapOrHostError::Trap(TrapCode::WasmStackOverflow))
+    ));
 }
 
 const OK_WAT: &str = r#"
@@ -182,16 +176,13 @@ fn stack_limit_no_args() {
     );
     assert!(instance.is_ok());
     let instance = instance.unwrap();
-    let main_func = instance
-        .lookup_function("main")
+    let main_func: NativeFunc<(), ()> = instance
+        .get_native_function("main")
         .expect("expected function main");
-    match main_func.call(&[]) {
-        Err(err) => {
-            let trap = err.to_trap().unwrap();
-            assert_eq!(trap, TrapCode::StackOverflow);
-        }
-        _ => assert!(false),
-    }
+    assert!(matches!(
+        main_func.call_and_catch_trap(),
+        Err(TrapOrHostError::Trap(TrapCode::WasmStackOverflow))
+    ));
 }
 
 #[test]
@@ -232,3 +223,85 @@ fn deep_but_sane() {
     let e = main_func.call(&[]);
     assert!(e.is_ok());
 }
+
+fn nested_operand_stack_wat(depth: usize) -> String {
+    format!(
+        r#"
+        (func (export "main") (result i32)
+            {push}
+            {add}
+        )
+    "#,
+        push = "i32.const 1\n".repeat(depth),
+        add = "i32.add\n".repeat(depth - 1)
+    )
+}
+
+#[test]
+fn operand_stack_depth_limit() {
+    let mut compiler = Singlepass::default();
+    compiler.max_operand_stack_depth(100);
+    let store = Store::new(&Universal::new(compiler).engine());
+
+    let module = Module::new(&store, nested_operand_stack_wat(100));
+    assert!(module.is_ok());
+
+    match Module::new(&store, nested_operand_stack_wat(10000)) {
+        Err(CompileError::Codegen(message)) => {
+            assert!(message.contains("exceeds the configured limit of 100"))
+        }
+        Err(error) => panic!("Unexpected error: {:?}", error),
+        Ok(_) => panic!("Expected the operand stack limit to be hit"),
+    }
+}
+
+#[test]
+fn max_function_call_depth() {
+    let wat = r#"
+        (func $rec (export "rec") (param $depth i32)
+            (if (local.get $depth)
+                (then (call $rec (i32.sub (local.get $depth) (i32.const 1))))))
+    "#;
+    let mut compiler = Singlepass::default();
+    compiler.limit_call_depth(true);
+    let store = Store::new(&Universal::new(compiler).engine());
+    let module = Module::new(&store, wat).unwrap();
+    assert!(module.metering_info().limits_call_depth);
+    let rec = |config: InstanceConfig| -> NativeFunc<i32, ()> {
+        let instance = Instance::new_with_config(&module, config, &imports! {}).unwrap();
+        instance.get_native_function("rec").unwrap()
+    };
+
+    // `rec(n)` runs `n + 1` nested frames, so that `rec(9)` is the deepest
+    // call allowed by a limit of 10.
+    let limited = rec(InstanceConfig::default().with_max_function_call_depth(10));
+    assert!(limited.call_and_catch_trap(9).is_ok());
+    assert!(matches!(
+        limited.call_and_catch_trap(10),
+        Err(TrapOrHostError::Trap(TrapCode::WasmStackOverflow))
+    ));
+    // The depth is counted from scratch again after a trap.
+    assert!(limited.call_and_catch_trap(9).is_ok());
+    assert!(limited.call_and_catch_trap(9).is_ok());
+
+    let unlimited = rec(InstanceConfig::default());
+    assert!(unlimited.call_and_catch_trap(1000).is_ok());
+
+    let none = rec(InstanceConfig::default().with_max_function_call_depth(0));
+    assert!(matches!(
+        none.call_and_catch_trap(0),
+        Err(TrapOrHostError::Trap(TrapCode::WasmStackOverflow))
+    ));
+
+    // Code compiled without the checks can't honor a maximum depth.
+    let module = Module::new(&get_store(), wat).unwrap();
+    assert!(!module.metering_info().limits_call_depth);
+    let config = InstanceConfig::default().with_max_function_call_depth(10);
+    assert!(matches!(
+        Instance::new_with_config(&module, config, &imports! {}),
+        Err(InstantiationError::HostEnvInitialization(
+            HostEnvInitError::CallDepthNotLimited
+        ))
+    ));
+    assert!(Instance::new_with_config(&module, InstanceConfig::default(), &imports! {}).is_ok());
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use std::panic::{self, AssertUnwindSafe};
 use wasmer::*;
+use wasmer_vm::TrapCode;
 
 #[compiler_test(traps)]
 fn test_trap_return(config: crate::Config) -> Result<()> {
@@ -71,6 +72,40 @@ fn test_trap_trace(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn test_trap_wasm_trace(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module $chain_mod
+            (func $a (export "run") (call $b))
+            (func $b (nop) (call $c))
+            (func $c (i32.const 1) (drop) (unreachable))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let run_func = instance
+        .lookup_function("run")
+        .expect("expected function export");
+
+    let e = run_func.call(&[]).unwrap_err();
+
+    let trace = e.wasm_trace();
+    let frames = trace
+        .iter()
+        .map(|frame| (frame.func_index(), frame.function_name()))
+        .collect::<Vec<_>>();
+    assert_eq!(frames, [(2, Some("c")), (1, Some("b")), (0, Some("a"))]);
+    for frame in &trace {
+        assert_eq!(frame.module_name(), "chain_mod");
+    }
+    assert!(trace[0].func_offset() > 0);
+
+    Ok(())
+}
+
 #[compiler_test(traps)]
 fn test_trap_trace_cb(config: crate::Config) -> Result<()> {
     let store = config.store();
@@ -465,3 +500,157 @@ fn present_after_module_drop(config: crate::Config) -> Result<()> {
         // assert_eq!(t.trace()[0].func_index(), 0);
     }
 }
+
+#[compiler_test(traps)]
+fn trap_in_builtin_is_annotated(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module $m
+            (memory 1)
+            (func (export "fill")
+                i32.const 65530
+                i32.const 0
+                i32.const 10
+                memory.fill)
+            (func (export "die") unreachable)
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let fill = instance.lookup_function("fill").unwrap();
+    let e = fill.call(&[]).unwrap_err();
+    assert_eq!(e.builtin_name(), Some("memory_fill"));
+    assert!(e
+        .to_string()
+        .starts_with("RuntimeError: out of bounds memory access\n    in builtin memory_fill"));
+
+    let die = instance.lookup_function("die").unwrap();
+    assert_eq!(die.call(&[]).unwrap_err().builtin_name(), None);
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn find_enclosing_artifact(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"(module (func $crash (export "crash") unreachable))"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let vmctx = instance.vmctx_ptr();
+    let artifact =
+        wasmer_vm::VMContext::find_enclosing_artifact(vmctx).expect("the instance is alive");
+    let crash = instance.get_function_address("crash").unwrap();
+    let index = artifact.function_for_address(crash).unwrap();
+    assert_eq!(artifact.function_name(index), Some("crash"));
+
+    drop(instance);
+    assert!(wasmer_vm::VMContext::find_enclosing_artifact(vmctx).is_none());
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn trace_after_artifact_drop(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let dropped = Module::new(
+        &store,
+        r#"(module $dropped (func $boom (export "run") unreachable))"#,
+    )?;
+    let dropped_instance = Instance::new(&dropped, &imports! {})?;
+    let live = Module::new(
+        &store,
+        r#"
+            (module $live
+                (func $inner unreachable)
+                (func $outer (export "run") call $inner))
+        "#,
+    )?;
+    let live_instance = Instance::new(&live, &imports! {})?;
+    let run = dropped_instance.lookup_function("run").unwrap();
+    assert_eq!(
+        run.call(&[]).unwrap_err().trace()[0].module_name(),
+        "dropped"
+    );
+    drop(run);
+    drop(dropped_instance);
+    drop(dropped);
+
+    // Compile another module, which may reuse whatever has been freed.
+    let other = Module::new(&store, r#"(module $other (func (export "run")))"#)?;
+    Instance::new(&other, &imports! {})?;
+
+    let run = live_instance.lookup_function("run").unwrap();
+    let err = run.call(&[]).unwrap_err();
+    let trace = err.trace();
+    assert_eq!(trace.len(), 2);
+    assert!(trace.iter().all(|frame| frame.module_name() == "live"));
+    assert_eq!(trace[0].function_name(), Some("inner"));
+    assert_eq!(trace[1].function_name(), Some("outer"));
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn integer_division_traps(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (func (export "i32.div_s") (param i32 i32) (result i32)
+                (i32.div_s (local.get 0) (local.get 1)))
+            (func (export "i32.rem_s") (param i32 i32) (result i32)
+                (i32.rem_s (local.get 0) (local.get 1)))
+            (func (export "i64.div_s") (param i64 i64) (result i64)
+                (i64.div_s (local.get 0) (local.get 1)))
+            (func (export "i64.rem_s") (param i64 i64) (result i64)
+                (i64.rem_s (local.get 0) (local.get 1)))
+            (func (export "i32.div_s_by_zero") (param i32) (result i32)
+                (i32.div_s (local.get 0) (i32.const 0)))
+            (func (export "i32.div_s_by_minus_one") (param i32) (result i32)
+                (i32.div_s (local.get 0) (i32.const -1)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    fn trap<T: std::fmt::Debug>(result: Result<T, RuntimeError>) -> Option<TrapCode> {
+        result.unwrap_err().to_trap()
+    }
+
+    let div: NativeFunc<(i32, i32), i32> = instance.get_native_function("i32.div_s")?;
+    let rem: NativeFunc<(i32, i32), i32> = instance.get_native_function("i32.rem_s")?;
+    assert_eq!(div.call(-7, 2)?, -3);
+    assert_eq!(trap(div.call(1, 0)), Some(TrapCode::IntegerDivisionByZero));
+    assert_eq!(
+        trap(div.call(i32::MIN, 0)),
+        Some(TrapCode::IntegerDivisionByZero)
+    );
+    assert_eq!(
+        trap(div.call(i32::MIN, -1)),
+        Some(TrapCode::IntegerOverflow)
+    );
+    assert_eq!(rem.call(-7, 2)?, -1);
+    assert_eq!(trap(rem.call(1, 0)), Some(TrapCode::IntegerDivisionByZero));
+    // The remainder is defined for this case, only the quotient overflows.
+    assert_eq!(rem.call(i32::MIN, -1)?, 0);
+
+    let div: NativeFunc<(i64, i64), i64> = instance.get_native_function("i64.div_s")?;
+    let rem: NativeFunc<(i64, i64), i64> = instance.get_native_function("i64.rem_s")?;
+    assert_eq!(div.call(-7, 2)?, -3);
+    assert_eq!(trap(div.call(1, 0)), Some(TrapCode::IntegerDivisionByZero));
+    assert_eq!(
+        trap(div.call(i64::MIN, -1)),
+        Some(TrapCode::IntegerOverflow)
+    );
+    assert_eq!(trap(rem.call(1, 0)), Some(TrapCode::IntegerDivisionByZero));
+    assert_eq!(rem.call(i64::MIN, -1)?, 0);
+
+    let by_zero: NativeFunc<i32, i32> = instance.get_native_function("i32.div_s_by_zero")?;
+    let by_minus_one: NativeFunc<i32, i32> =
+        instance.get_native_function("i32.div_s_by_minus_one")?;
+    assert_eq!(trap(by_zero.call(1)), Some(TrapCode::IntegerDivisionByZero));
+    assert_eq!(by_minus_one.call(7)?, -7);
+    assert_eq!(
+        trap(by_minus_one.call(i32::MIN)),
+        Some(TrapCode::IntegerOverflow)
+    );
+
+    Ok(())
+}